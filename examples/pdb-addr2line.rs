@@ -1,12 +1,14 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, Lines, StdinLock, Write};
+use std::io::{BufRead, BufReader, Lines, StdinLock, Write};
 use std::path::Path;
 
 use clap::{Arg, Command, Values};
 use msvc_demangler::DemangleFlags;
-use pdb_addr2line::pdb;
+use pdb_addr2line::{pdb, Rva32};
 
 fn parse_uint_from_hex_string(string: &str) -> u32 {
     if string.len() > 2 && string.starts_with("0x") {
@@ -16,9 +18,153 @@ fn parse_uint_from_hex_string(string: &str) -> u32 {
     }
 }
 
+fn parse_hex(string: &str) -> Option<u64> {
+    let string = string.trim();
+    let digits = string
+        .strip_prefix("0x")
+        .or_else(|| string.strip_prefix("0X"))
+        .unwrap_or(string);
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// One line of a trace file: an address, optionally with the name of the module that
+/// contains it.
+struct TraceLine<'a> {
+    module: Option<&'a str>,
+    rva: u32,
+}
+
+/// Parses a trace file line in one of these formats, where all numbers are hex:
+///
+///  - `module+offset`, e.g. `xul.dll+0x1234`
+///  - `module, base, address`, e.g. `xul.dll, 0x180000000, 0x180001234`
+///  - `base, address`, for the PDB given with `--exe`
+///  - `offset`, for the PDB given with `--exe`
+fn parse_trace_line(line: &str) -> Option<TraceLine<'_>> {
+    let line = line.trim();
+    let parts: Vec<&str> = line.split(',').map(str::trim).collect();
+    let (module, rva) = match parts.as_slice() {
+        [module, base, address] => (
+            Some(*module),
+            parse_hex(address)?.checked_sub(parse_hex(base)?)?,
+        ),
+        [base, address] => (None, parse_hex(address)?.checked_sub(parse_hex(base)?)?),
+        [single] => match single.rsplit_once('+') {
+            Some((module, offset)) => (Some(module.trim()), parse_hex(offset)?),
+            None => (None, parse_hex(single)?),
+        },
+        _ => return None,
+    };
+    Some(TraceLine {
+        module,
+        rva: u32::try_from(rva).ok()?,
+    })
+}
+
+/// Returns the path of the PDB for `module` in `pdb_dir`, e.g. `xul.pdb` for
+/// `C:\Program Files\Firefox\xul.dll`.
+fn pdb_path_for_module(pdb_dir: &Path, module: &str) -> Option<std::path::PathBuf> {
+    Some(pdb_dir.join(format!("{}.pdb", file_stem(module)?)))
+}
+
+/// Returns the file name of `path` without its extension, e.g. `xul` for
+/// `C:\Program Files\Firefox\xul.dll`.
+fn file_stem(path: &str) -> Option<&str> {
+    let file_name = path.rsplit(['\\', '/']).next()?;
+    Some(match file_name.rfind('.') {
+        Some(index) => &file_name[..index],
+        None => file_name,
+    })
+}
+
+/// Whether the trace's `module` is the one whose PDB was given with `--exe`, i.e.
+/// whether both have the same file name apart from the extension.
+fn is_exe_module(module: &str, exe_path: Option<&str>) -> bool {
+    match (file_stem(module), exe_path.and_then(file_stem)) {
+        (Some(module_stem), Some(exe_stem)) => module_stem.eq_ignore_ascii_case(exe_stem),
+        _ => false,
+    }
+}
+
+fn open_pdb(path: &Path) -> Option<pdb_addr2line::ContextPdbData<'static, 'static, Source>> {
+    let file = File::open(path).ok()?;
+    let map = unsafe { memmap2::MmapOptions::new().map(&file).ok()? };
+    pdb_addr2line::ContextPdbData::open(Source(map)).ok()
+}
+
+/// Symbolicate every line of a trace file, and print each address followed by its
+/// frames, from the innermost inlined function to the outer function. Lines which
+/// can't be parsed, such as blank lines between stacks, are printed unchanged.
+fn symbolize_trace(
+    trace_path: &str,
+    exe_path: Option<&str>,
+    pdb_dir: Option<&str>,
+    basenames: bool,
+    demangle: bool,
+) {
+    let trace = BufReader::new(File::open(trace_path).unwrap());
+    let lines: Vec<String> = trace.lines().map(Result::unwrap).collect();
+
+    // Open all PDBs up-front, because the contexts borrow from them.
+    let exe_data = exe_path.and_then(|path| open_pdb(Path::new(path)));
+    let mut module_data = HashMap::new();
+    if let Some(pdb_dir) = pdb_dir {
+        for line in &lines {
+            if let Some(TraceLine {
+                module: Some(module),
+                ..
+            }) = parse_trace_line(line)
+            {
+                module_data.entry(module).or_insert_with(|| {
+                    pdb_path_for_module(Path::new(pdb_dir), module).and_then(|path| open_pdb(&path))
+                });
+            }
+        }
+    }
+    let exe_context = exe_data.as_ref().and_then(|data| data.make_context().ok());
+    let module_contexts: HashMap<&str, _> = module_data
+        .iter()
+        .filter_map(|(module, data)| Some((*module, data.as_ref()?.make_context().ok()?)))
+        .collect();
+
+    for line in &lines {
+        println!("{}", line);
+        let trace_line = match parse_trace_line(line) {
+            Some(trace_line) => trace_line,
+            None => continue,
+        };
+        // Frames from modules without a PDB are printed as unknown, instead of
+        // looking up their addresses in an unrelated PDB.
+        let context = match trace_line.module {
+            Some(module) if is_exe_module(module, exe_path) => exe_context.as_ref(),
+            Some(module) => module_contexts.get(module),
+            None => exe_context.as_ref(),
+        };
+        let frames = context
+            .and_then(|context| context.find_frames_at(Rva32(trace_line.rva)).ok().flatten());
+        match frames {
+            Some(frames) => {
+                for (i, frame) in frames.frames.iter().enumerate() {
+                    print!("    ");
+                    if i != 0 {
+                        print!("(inlined by) ");
+                    }
+                    print_function(frame.function.as_deref().unwrap_or("??"), demangle);
+                    print!(" at ");
+                    print_loc(&frame.file, frame.line, basenames, false);
+                }
+            }
+            None => println!("    ?? at ??:?"),
+        }
+    }
+}
+
 enum Addrs<'a> {
     Args(Values<'a>),
     Stdin(Lines<StdinLock<'a>>),
+    /// Like `Stdin`, but with a prompt, and invalid input is reported instead of
+    /// ending the process. `quit` or `exit` ends the input.
+    Interactive(Lines<StdinLock<'a>>),
 }
 
 impl<'a> Iterator for Addrs<'a> {
@@ -28,6 +174,19 @@ impl<'a> Iterator for Addrs<'a> {
         let text = match *self {
             Addrs::Args(ref mut vals) => vals.next().map(Cow::from),
             Addrs::Stdin(ref mut lines) => lines.next().map(Result::unwrap).map(Cow::from),
+            Addrs::Interactive(ref mut lines) => loop {
+                eprint!("> ");
+                std::io::stderr().flush().unwrap();
+                let line = lines.next()?.ok()?;
+                match line.trim() {
+                    "" => continue,
+                    "quit" | "exit" => return None,
+                    text => match parse_hex(text).and_then(|addr| u32::try_from(addr).ok()) {
+                        Some(addr) => return Some(addr),
+                        None => eprintln!("Invalid address: {}", text),
+                    },
+                }
+            },
         };
         text.as_ref()
             .map(Cow::as_ref)
@@ -92,7 +251,7 @@ fn main() {
                 .help(
                     "Specify the name of the executable for which addresses should be translated.",
                 )
-                .required(true),
+                .required_unless_present("trace"),
             Arg::new("sup")
                 .long("sup")
                 .value_name("filename")
@@ -126,6 +285,34 @@ fn main() {
             Arg::new("llvm")
                 .long("llvm")
                 .help("Display output in the same format as llvm-symbolizer."),
+            Arg::new("trace").long("trace").value_name("filename").help(
+                "Symbolicate a file with one address per line, as `module+offset`, \
+                     `module, base, address`, or, for the PDB given with --exe, \
+                     `base, address` or `offset`. Each address is printed with its \
+                     inline frames.",
+            ),
+            Arg::new("pdb-dir")
+                .long("pdb-dir")
+                .value_name("directory")
+                .help(
+                    "The directory with the PDBs for the modules in the --trace file. \
+                     The PDB for `foo.dll` is expected at `<directory>/foo.pdb`.",
+                ),
+            Arg::new("format")
+                .long("format")
+                .value_name("template")
+                .help(
+                    "Print each frame on one line, formatted with a template such as \
+                     \"{function} @ {file}:{line} (+{offset:#x})\". The fields are \
+                     function, file, line, offset, rva, start_rva and end_rva. \
+                     Inlined frames are printed if -i is given.",
+                ),
+            Arg::new("interactive").long("interactive").help(
+                "Read addresses from stdin one at a time, with a prompt, and print \
+                     each result immediately. The PDB stays loaded between addresses, \
+                     so this is much faster than running the tool once per address. \
+                     Enter `quit` or end the input to exit.",
+            ),
             Arg::new("addrs")
                 .takes_value(true)
                 .multiple_occurrences(true)
@@ -140,23 +327,49 @@ fn main() {
     let basenames = matches.is_present("basenames");
     let demangle = matches.is_present("demangle");
     let llvm = matches.is_present("llvm");
+
+    if let Some(trace_path) = matches.value_of("trace") {
+        symbolize_trace(
+            trace_path,
+            matches.value_of("exe"),
+            matches.value_of("pdb-dir"),
+            basenames,
+            demangle,
+        );
+        return;
+    }
+
+    let template = matches
+        .value_of("format")
+        .map(|template| pdb_addr2line::FrameTemplate::parse(template).unwrap());
     let path = matches.value_of("exe").unwrap();
 
     let file = File::open(path).unwrap();
     let map = unsafe { memmap2::MmapOptions::new().map(&file).unwrap() };
     let source = Source(map);
 
-    let pdb = pdb::PDB::open(source).unwrap();
-    let context_data = pdb_addr2line::ContextPdbData::try_from_pdb(pdb).unwrap();
+    let context_data = pdb_addr2line::ContextPdbData::open(source).unwrap();
     let ctx = context_data.make_context().unwrap();
 
     let stdin = std::io::stdin();
-    let addrs = matches
-        .values_of("addrs")
-        .map(Addrs::Args)
-        .unwrap_or_else(|| Addrs::Stdin(stdin.lock().lines()));
+    let addrs = if matches.is_present("interactive") {
+        Addrs::Interactive(stdin.lock().lines())
+    } else {
+        matches
+            .values_of("addrs")
+            .map(Addrs::Args)
+            .unwrap_or_else(|| Addrs::Stdin(stdin.lock().lines()))
+    };
 
     for probe in addrs {
+        let frames = match ctx.find_frames_at(Rva32(probe)) {
+            Ok(frames) => frames,
+            Err(err) => {
+                eprintln!("Error looking up 0x{:x}: {}", probe, err);
+                None
+            }
+        };
+
         if print_addrs {
             if llvm {
                 print!("0x{:x}", probe);
@@ -170,9 +383,21 @@ fn main() {
             }
         }
 
-        if do_functions || do_inlines {
+        if let Some(template) = &template {
+            match &frames {
+                Some(frames) => {
+                    for frame in &frames.frames {
+                        println!("{}", template.format(frame));
+                        if !do_inlines {
+                            break;
+                        }
+                    }
+                }
+                None => println!("??"),
+            }
+        } else if do_functions || do_inlines {
             let mut printed_anything = false;
-            if let Some(frames) = ctx.find_frames(probe).unwrap() {
+            if let Some(frames) = frames {
                 for (i, frame) in frames.frames.iter().enumerate() {
                     if pretty && i != 0 {
                         print!(" (inlined by) ");
@@ -220,9 +445,13 @@ fn main() {
                 }
             }
         } else {
-            let frames = ctx.find_frames(probe).unwrap().unwrap();
-            let frame = &frames.frames[0];
-            print_loc(&frame.file, frame.line, basenames, llvm);
+            match frames {
+                Some(frames) => {
+                    let frame = &frames.frames[0];
+                    print_loc(&frame.file, frame.line, basenames, llvm);
+                }
+                None => print_loc(&None, None, basenames, llvm),
+            }
         }
 
         if llvm {
@@ -262,7 +491,7 @@ impl<'s> pdb::Source<'s> for Source {
         let mut bytes = Vec::with_capacity(len);
 
         for slice in slices {
-            bytes.extend_from_slice(&self.0[slice.offset as usize..][..slice.size as usize]);
+            bytes.extend_from_slice(&self.0[slice.offset as usize..][..slice.size]);
         }
 
         Ok(Box::new(ReadView { bytes }))
@@ -0,0 +1,83 @@
+use std::convert::TryFrom;
+
+use crate::{Context, Function, FunctionFrames, Result};
+
+/// A relative virtual address (rva): an address relative to the load address of
+/// the image. This is the kind of address which the lookup methods of [`Context`]
+/// take.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rva32(pub u32);
+
+/// A virtual address, e.g. an instruction pointer from a crash report, which
+/// includes the address at which the image was loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Va64(pub u64);
+
+/// An offset into the image file on disk. File offsets differ from rvas, because
+/// sections are aligned differently in the file and in memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileOffset(pub u32);
+
+impl Rva32 {
+    /// The virtual address of this rva in an image which was loaded at
+    /// `image_base`.
+    pub fn to_va(self, image_base: u64) -> Va64 {
+        Va64(image_base.wrapping_add(u64::from(self.0)))
+    }
+}
+
+impl Va64 {
+    /// The rva of this address in an image which was loaded at `image_base`.
+    /// Returns `None` if the address is below the image base or too far above it.
+    pub fn to_rva(self, image_base: u64) -> Option<Rva32> {
+        let rva = self.0.checked_sub(image_base)?;
+        u32::try_from(rva).ok().map(Rva32)
+    }
+}
+
+impl From<Rva32> for u32 {
+    fn from(rva: Rva32) -> u32 {
+        rva.0
+    }
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Like [`Context::find_frames_at`], but takes the rva as a plain `u32`.
+    #[deprecated(note = "use `Context::find_frames_at`, which takes an `Rva32`")]
+    pub fn find_frames(&self, probe: u32) -> Result<Option<FunctionFrames<'_>>> {
+        self.find_frames_at(Rva32(probe))
+    }
+
+    /// Like [`Context::find_function_at`], but takes the rva as a plain `u32`.
+    #[deprecated(note = "use `Context::find_function_at`, which takes an `Rva32`")]
+    pub fn find_function(&self, probe: u32) -> Result<Option<Function>> {
+        self.find_function_at(Rva32(probe))
+    }
+
+    /// Translate an offset in the image file to an rva, with the section headers
+    /// in the PDB. Returns `None` if the offset isn't in the raw data of a section,
+    /// e.g. if it is in the headers.
+    pub fn rva_for_file_offset(&self, file_offset: FileOffset) -> Option<Rva32> {
+        let section = self.sections.iter().find(|section| {
+            file_offset.0 >= section.pointer_to_raw_data
+                && file_offset.0 - section.pointer_to_raw_data < section.size_of_raw_data
+        })?;
+        let offset = file_offset.0 - section.pointer_to_raw_data;
+        section.virtual_address.checked_add(offset).map(Rva32)
+    }
+
+    /// Translate an rva to an offset in the image file, with the section headers in
+    /// the PDB. Returns `None` if the rva isn't in a section, or if it is in the
+    /// part of a section which isn't stored in the file, e.g. uninitialized data.
+    pub fn file_offset_for_rva(&self, rva: Rva32) -> Option<FileOffset> {
+        let section = self.sections.iter().find(|section| {
+            rva.0 >= section.virtual_address
+                && rva.0 - section.virtual_address < section.size_of_raw_data
+        })?;
+        let offset = rva.0 - section.virtual_address;
+        section
+            .pointer_to_raw_data
+            .checked_add(offset)
+            .map(FileOffset)
+    }
+}
@@ -0,0 +1,335 @@
+use pdb::{FileIndex, IdIndex, PdbInternalSectionOffset, SymbolIndex, TypeIndex};
+use uuid::Uuid;
+
+use crate::{CachedLineInfo, Error, InlineRange};
+
+const MAGIC: &[u8; 8] = b"PA2LSNAP";
+const VERSION: u32 = 3;
+
+/// The derived information which a [`Context`](crate::Context) has computed so far,
+/// from [`Context::export_cache`](crate::Context::export_cache). This can be stored
+/// with [`CacheSnapshot::to_bytes`] and given to
+/// [`Context::import_cache`](crate::Context::import_cache) in a later run over the
+/// same PDB, so that the procedure lists of the modules, the formatted function
+/// names, line tables and inline ranges don't have to be computed again.
+///
+/// The snapshot records the GUID and age of the PDB and the flags which affect the
+/// cached information, and is only accepted by contexts which match all of them.
+#[derive(Clone, Debug)]
+pub struct CacheSnapshot {
+    pub(crate) guid: Uuid,
+    pub(crate) age: u32,
+    pub(crate) context_flags: u32,
+    pub(crate) formatter_flags: u32,
+    pub(crate) names: Vec<(PdbInternalSectionOffset, Option<String>)>,
+    pub(crate) lines: Vec<(PdbInternalSectionOffset, Vec<CachedLineInfo>)>,
+    pub(crate) inline_ranges: Vec<(PdbInternalSectionOffset, Vec<InlineRange>)>,
+    pub(crate) procedures: Vec<(usize, ModuleProcedures)>,
+}
+
+/// The procedures of a module, which are otherwise collected by walking the module's
+/// whole symbol stream.
+#[derive(Clone, Debug)]
+pub(crate) struct ModuleProcedures {
+    pub(crate) symbols_size: usize,
+    pub(crate) procedures: Vec<SnapshotProcedure>,
+}
+
+/// A procedure without its name. The name is read from the symbol record at
+/// `name_symbol_index`, which is the procedure's own record, or the record of the
+/// parent procedure for separated code.
+#[derive(Clone, Debug)]
+pub(crate) struct SnapshotProcedure {
+    pub(crate) offset: PdbInternalSectionOffset,
+    pub(crate) len: u32,
+    pub(crate) symbol_index: SymbolIndex,
+    pub(crate) end_symbol_index: SymbolIndex,
+    pub(crate) name_symbol_index: SymbolIndex,
+    pub(crate) type_index: TypeIndex,
+    pub(crate) managed_token: Option<u32>,
+    pub(crate) is_thunk: bool,
+}
+
+impl CacheSnapshot {
+    /// The GUID of the PDB that this snapshot was made from.
+    pub fn guid(&self) -> Uuid {
+        self.guid
+    }
+
+    /// The age of the PDB that this snapshot was made from.
+    pub fn age(&self) -> u32 {
+        self.age
+    }
+
+    /// The number of cached entries, counting module procedure lists, names, line
+    /// tables and inline ranges separately.
+    pub fn len(&self) -> usize {
+        self.procedures.len() + self.names.len() + self.lines.len() + self.inline_ranges.len()
+    }
+
+    /// Whether the snapshot has no cached information.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Serialize the snapshot into a compact binary format, which can be read back
+    /// with [`CacheSnapshot::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer(Vec::new());
+        w.0.extend_from_slice(MAGIC);
+        w.u32(VERSION);
+        w.0.extend_from_slice(self.guid.as_bytes());
+        w.u32(self.age);
+        w.u32(self.context_flags);
+        w.u32(self.formatter_flags);
+
+        w.len(self.names.len());
+        for (offset, name) in &self.names {
+            w.offset(*offset);
+            match name {
+                Some(name) => {
+                    w.u8(1);
+                    w.len(name.len());
+                    w.0.extend_from_slice(name.as_bytes());
+                }
+                None => w.u8(0),
+            }
+        }
+
+        w.len(self.lines.len());
+        for (offset, lines) in &self.lines {
+            w.offset(*offset);
+            w.len(lines.len());
+            for line in lines {
+                w.u32(line.start_offset);
+                w.u32(line.file_index.0);
+                w.u32(line.line_start);
+                w.u32(line.line_end);
+                w.optional_u32(line.column_start);
+                w.optional_u32(line.column_end);
+            }
+        }
+
+        w.len(self.inline_ranges.len());
+        for (offset, ranges) in &self.inline_ranges {
+            w.offset(*offset);
+            w.len(ranges.len());
+            for range in ranges {
+                w.u32(range.start_offset);
+                w.u32(range.end_offset);
+                w.u16(range.call_depth);
+                w.u32(range.inlinee.0);
+                w.optional_u32(range.file_index.map(|file_index| file_index.0));
+                w.optional_u32(range.line_start);
+            }
+        }
+
+        w.len(self.procedures.len());
+        for (module_index, module) in &self.procedures {
+            w.len(*module_index);
+            w.len(module.symbols_size);
+            w.len(module.procedures.len());
+            for proc in &module.procedures {
+                w.offset(proc.offset);
+                w.u32(proc.len);
+                w.u32(proc.symbol_index.0);
+                w.u32(proc.end_symbol_index.0);
+                w.u32(proc.name_symbol_index.0);
+                w.u32(proc.type_index.0);
+                w.optional_u32(proc.managed_token);
+                w.u8(proc.is_thunk as u8);
+            }
+        }
+        w.0
+    }
+
+    /// Read a snapshot which was serialized with [`CacheSnapshot::to_bytes`]. This
+    /// only checks the format; whether the snapshot fits a context is checked by
+    /// [`Context::import_cache`](crate::Context::import_cache).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut r = Reader(bytes);
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err(Error::InvalidCacheSnapshot("not a cache snapshot"));
+        }
+        if r.u32()? != VERSION {
+            return Err(Error::InvalidCacheSnapshot("unsupported version"));
+        }
+        let mut guid = [0; 16];
+        guid.copy_from_slice(r.take(16)?);
+        let guid = Uuid::from_bytes(guid);
+        let age = r.u32()?;
+        let context_flags = r.u32()?;
+        let formatter_flags = r.u32()?;
+
+        let names = (0..r.u32()?)
+            .map(|_| {
+                let offset = r.offset()?;
+                let name = match r.u8()? {
+                    0 => None,
+                    _ => {
+                        let len = r.u32()? as usize;
+                        let name = std::str::from_utf8(r.take(len)?)
+                            .map_err(|_| Error::InvalidCacheSnapshot("invalid UTF-8 in a name"))?;
+                        Some(name.to_string())
+                    }
+                };
+                Ok((offset, name))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let lines = (0..r.u32()?)
+            .map(|_| {
+                let offset = r.offset()?;
+                let lines = (0..r.u32()?)
+                    .map(|_| {
+                        Ok(CachedLineInfo {
+                            start_offset: r.u32()?,
+                            file_index: FileIndex(r.u32()?),
+                            line_start: r.u32()?,
+                            line_end: r.u32()?,
+                            column_start: r.optional_u32()?,
+                            column_end: r.optional_u32()?,
+                        })
+                    })
+                    .collect::<Result<_, Error>>()?;
+                Ok((offset, lines))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let inline_ranges = (0..r.u32()?)
+            .map(|_| {
+                let offset = r.offset()?;
+                let ranges = (0..r.u32()?)
+                    .map(|_| {
+                        Ok(InlineRange {
+                            start_offset: r.u32()?,
+                            end_offset: r.u32()?,
+                            call_depth: r.u16()?,
+                            inlinee: IdIndex(r.u32()?),
+                            file_index: r.optional_u32()?.map(FileIndex),
+                            line_start: r.optional_u32()?,
+                        })
+                    })
+                    .collect::<Result<_, Error>>()?;
+                Ok((offset, ranges))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let procedures = (0..r.u32()?)
+            .map(|_| {
+                let module_index = r.u32()? as usize;
+                let symbols_size = r.u32()? as usize;
+                let procedures = (0..r.u32()?)
+                    .map(|_| {
+                        Ok(SnapshotProcedure {
+                            offset: r.offset()?,
+                            len: r.u32()?,
+                            symbol_index: SymbolIndex(r.u32()?),
+                            end_symbol_index: SymbolIndex(r.u32()?),
+                            name_symbol_index: SymbolIndex(r.u32()?),
+                            type_index: TypeIndex(r.u32()?),
+                            managed_token: r.optional_u32()?,
+                            is_thunk: r.u8()? != 0,
+                        })
+                    })
+                    .collect::<Result<_, Error>>()?;
+                Ok((
+                    module_index,
+                    ModuleProcedures {
+                        symbols_size,
+                        procedures,
+                    },
+                ))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        if !r.0.is_empty() {
+            return Err(Error::InvalidCacheSnapshot("trailing data"));
+        }
+        Ok(Self {
+            guid,
+            age,
+            context_flags,
+            formatter_flags,
+            names,
+            lines,
+            inline_ranges,
+            procedures,
+        })
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+
+    fn offset(&mut self, offset: PdbInternalSectionOffset) {
+        self.u16(offset.section);
+        self.u32(offset.offset);
+    }
+
+    fn optional_u32(&mut self, value: Option<u32>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.u32(value);
+            }
+            None => self.u8(0),
+        }
+    }
+}
+
+struct Reader<'b>(&'b [u8]);
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, len: usize) -> Result<&'b [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::InvalidCacheSnapshot("unexpected end of data"));
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn offset(&mut self) -> Result<PdbInternalSectionOffset, Error> {
+        let section = self.u16()?;
+        let offset = self.u32()?;
+        Ok(PdbInternalSectionOffset { offset, section })
+    }
+
+    fn optional_u32(&mut self) -> Result<Option<u32>, Error> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.u32()?)),
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::BTreeMap;
+use std::mem;
+use std::ops::Range;
+
+use pdb::{
+    FallibleIterator, FileChecksum, FileIndex, IdIndex, Inlinee, LineInfo, LineInfoKind,
+    LineProgram, ModuleInfo, PdbInternalSectionOffset, RawString, StringRef, StringTable,
+    SymbolIndex, TypeIndex,
+};
+
+use crate::{
+    compute_module_language, compute_parameter_names, compute_procedure_frame_info,
+    compute_procedure_inline_ranges, BasicModuleInfo, InlineRange, Language, ProcedureFrameInfo,
+    ProcedureSymbolFunction,
+};
+
+/// The information which lookups need from a module, extracted from the module's
+/// stream so that the stream doesn't have to stay in memory. See
+/// [`ContextFlags::COMPACT_MODULES`](crate::ContextFlags::COMPACT_MODULES).
+///
+/// This has the module's procedures with their names, parameter names, frame
+/// information and inline ranges, the module's lines, and the files which the
+/// lines refer to. The other symbol records, e.g. the local variables, are dropped.
+pub struct CompactModule {
+    /// Sorted by section and offset.
+    procedures: Vec<CompactProcedure>,
+    /// The procedure names and parameter names, one after the other.
+    strings: Vec<u8>,
+    /// The ranges in `strings` of the parameter names of all procedures.
+    parameter_names: Vec<Range<u32>>,
+    /// The line blocks of the line program, sorted by section and offset.
+    line_blocks: Vec<CompactLineBlock>,
+    /// The lines of all line blocks, one block after the other.
+    lines: Vec<CompactLine>,
+    /// Sorted by file index.
+    files: Vec<CompactFile>,
+    /// The checksums of the files, one after the other.
+    checksums: Vec<u8>,
+    language: Option<Language>,
+}
+
+struct CompactProcedure {
+    offset: PdbInternalSectionOffset,
+    len: u32,
+    /// The range of the name in `strings`.
+    name: Range<u32>,
+    symbol_index: SymbolIndex,
+    end_symbol_index: SymbolIndex,
+    /// See `BasicModuleInfo::name_symbol_index`.
+    name_symbol_index: SymbolIndex,
+    type_index: TypeIndex,
+    managed_token: Option<u32>,
+    is_thunk: bool,
+    /// The range of the procedure's entries in `parameter_names`, if they could be
+    /// read. Only procedures with a type have parameter names.
+    parameter_names: Option<Range<u32>>,
+    frame_info: Option<ProcedureFrameInfo>,
+    inline_ranges: Vec<InlineRange>,
+}
+
+/// The lines of one lines subsection of the line program, which usually has the
+/// lines of one function.
+struct CompactLineBlock {
+    /// The start of the subsection, or the offset of its first line if that is
+    /// lower.
+    offset: PdbInternalSectionOffset,
+    /// The range of the block's entries in `lines`.
+    lines: Range<u32>,
+}
+
+struct CompactLine {
+    offset: PdbInternalSectionOffset,
+    length: Option<u32>,
+    kind: LineInfoKind,
+    file_index: FileIndex,
+    line_start: u32,
+    line_end: u32,
+    column_start: Option<u32>,
+    column_end: Option<u32>,
+}
+
+struct CompactFile {
+    index: FileIndex,
+    name: StringRef,
+    checksum_kind: ChecksumKind,
+    /// The range of the checksum in `checksums`.
+    checksum: Range<u32>,
+}
+
+#[derive(Clone, Copy)]
+enum ChecksumKind {
+    None,
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl CompactModule {
+    /// Extract the information which lookups need from `module_info`. Returns `None`
+    /// if the module can't be compacted: if any of its symbols, its line program,
+    /// its inlinees or the inline ranges of a procedure can't be read, or if it has
+    /// line information in the legacy C11 format. Such modules are looked up in their
+    /// stream, which reports the problems as diagnostics.
+    pub fn new(module_info: &ModuleInfo) -> Option<Self> {
+        let line_program = module_info.line_program().ok()?;
+        let inlinees: BTreeMap<IdIndex, Inlinee> = module_info
+            .inlinees()
+            .and_then(|inlinees| inlinees.map(|i| Ok((i.index(), i))).collect())
+            .ok()?;
+        let mut malformed_symbols = Vec::new();
+        let basic_module_info =
+            BasicModuleInfo::try_from_module_info(module_info, &mut malformed_symbols, || None)
+                .ok()?;
+        if !malformed_symbols.is_empty() {
+            return None;
+        }
+
+        let mut strings = Vec::new();
+        let mut parameter_names = Vec::new();
+        let mut file_indexes = Vec::new();
+        let mut missing_inlinees = Vec::new();
+        let procedures = basic_module_info
+            .procedures
+            .iter()
+            .map(|proc| {
+                let proc_parameter_names = match proc.type_index {
+                    TypeIndex(0) => None,
+                    _ => compute_parameter_names(module_info, proc)
+                        .ok()
+                        .map(|names| {
+                            let start = parameter_names.len() as u32;
+                            for name in names {
+                                parameter_names.push(push_bytes(&mut strings, name.as_bytes()));
+                            }
+                            start..parameter_names.len() as u32
+                        }),
+                };
+                let inline_ranges = compute_procedure_inline_ranges(
+                    module_info,
+                    proc,
+                    &inlinees,
+                    &mut missing_inlinees,
+                )
+                .ok()?;
+                file_indexes.extend(inline_ranges.iter().filter_map(|range| range.file_index));
+                Some(CompactProcedure {
+                    offset: proc.offset,
+                    len: proc.len,
+                    name: push_bytes(&mut strings, proc.name.as_bytes()),
+                    symbol_index: proc.symbol_index,
+                    end_symbol_index: proc.end_symbol_index,
+                    name_symbol_index: basic_module_info.name_symbol_index(proc),
+                    type_index: proc.type_index,
+                    managed_token: proc.managed_token,
+                    is_thunk: proc.is_thunk,
+                    parameter_names: proc_parameter_names,
+                    frame_info: compute_procedure_frame_info(module_info, proc).ok(),
+                    inline_ranges,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if !missing_inlinees.is_empty() {
+            return None;
+        }
+
+        let line_blocks = read_line_blocks(&line_program, &procedures)?;
+        let mut lines = Vec::new();
+        let line_blocks: Vec<CompactLineBlock> = line_blocks
+            .into_iter()
+            .map(|((section, offset), block_lines)| {
+                let start = lines.len() as u32;
+                for line in block_lines {
+                    file_indexes.push(line.file_index);
+                    lines.push(CompactLine {
+                        offset: line.offset,
+                        length: line.length,
+                        kind: line.kind,
+                        file_index: line.file_index,
+                        line_start: line.line_start,
+                        line_end: line.line_end,
+                        column_start: line.column_start,
+                        column_end: line.column_end,
+                    });
+                }
+                CompactLineBlock {
+                    offset: PdbInternalSectionOffset { section, offset },
+                    lines: start..lines.len() as u32,
+                }
+            })
+            .collect();
+        file_indexes.sort_unstable();
+        file_indexes.dedup();
+
+        let mut checksums = Vec::new();
+        let files = file_indexes
+            .into_iter()
+            .filter_map(|index| {
+                let file_info = line_program.get_file_info(index).ok()?;
+                let (checksum_kind, bytes) = match file_info.checksum {
+                    FileChecksum::None => (ChecksumKind::None, &[][..]),
+                    FileChecksum::Md5(bytes) => (ChecksumKind::Md5, bytes),
+                    FileChecksum::Sha1(bytes) => (ChecksumKind::Sha1, bytes),
+                    FileChecksum::Sha256(bytes) => (ChecksumKind::Sha256, bytes),
+                };
+                Some(CompactFile {
+                    index,
+                    name: file_info.name,
+                    checksum_kind,
+                    checksum: push_bytes(&mut checksums, bytes),
+                })
+            })
+            .collect();
+
+        strings.shrink_to_fit();
+        parameter_names.shrink_to_fit();
+        lines.shrink_to_fit();
+        Some(Self {
+            procedures,
+            strings,
+            parameter_names,
+            line_blocks,
+            lines,
+            files,
+            checksums,
+            language: compute_module_language(module_info).ok().flatten(),
+        })
+    }
+
+    /// The procedures of the module, as they are cached by the context.
+    pub(crate) fn procedures(&self) -> Vec<ProcedureSymbolFunction<'_>> {
+        self.procedures
+            .iter()
+            .map(|proc| ProcedureSymbolFunction {
+                offset: proc.offset,
+                len: proc.len,
+                name: RawString::from(self.string(&proc.name)),
+                symbol_index: proc.symbol_index,
+                end_symbol_index: proc.end_symbol_index,
+                type_index: proc.type_index,
+                managed_token: proc.managed_token,
+                is_thunk: proc.is_thunk,
+            })
+            .collect()
+    }
+
+    fn procedure(&self, proc: &ProcedureSymbolFunction) -> Option<&CompactProcedure> {
+        let index = self
+            .procedures
+            .binary_search_by_key(&(proc.offset.section, proc.offset.offset), |p| {
+                (p.offset.section, p.offset.offset)
+            })
+            .ok()?;
+        Some(&self.procedures[index])
+    }
+
+    fn string(&self, range: &Range<u32>) -> &[u8] {
+        &self.strings[range.start as usize..range.end as usize]
+    }
+
+    /// See `compute_parameter_names`.
+    pub(crate) fn parameter_names(&self, proc: &ProcedureSymbolFunction) -> Option<Vec<String>> {
+        let range = self.procedure(proc)?.parameter_names.clone()?;
+        let names = &self.parameter_names[range.start as usize..range.end as usize];
+        Some(
+            names
+                .iter()
+                .map(|name| String::from_utf8_lossy(self.string(name)).into_owned())
+                .collect(),
+        )
+    }
+
+    /// See `BasicModuleInfo::name_symbol_index`.
+    pub(crate) fn name_symbol_index(&self, proc: &ProcedureSymbolFunction) -> SymbolIndex {
+        self.procedure(proc)
+            .map_or(proc.symbol_index, |p| p.name_symbol_index)
+    }
+
+    pub(crate) fn frame_info(&self, proc: &ProcedureSymbolFunction) -> Option<ProcedureFrameInfo> {
+        self.procedure(proc)?.frame_info
+    }
+
+    pub(crate) fn inline_ranges(&self, proc: &ProcedureSymbolFunction) -> &[InlineRange] {
+        self.procedure(proc)
+            .map_or(&[], |proc| proc.inline_ranges.as_slice())
+    }
+
+    /// The lines of the line block which contains `offset`, like
+    /// `LineProgram::lines_for_symbol`.
+    pub(crate) fn lines_for_symbol(&self, offset: PdbInternalSectionOffset) -> Vec<LineInfo> {
+        let key = (offset.section, offset.offset);
+        let block = match self
+            .line_blocks
+            .binary_search_by_key(&key, |block| (block.offset.section, block.offset.offset))
+        {
+            Ok(i) => &self.line_blocks[i],
+            Err(0) => return Vec::new(),
+            Err(i) => &self.line_blocks[i - 1],
+        };
+        if block.offset.section != offset.section {
+            return Vec::new();
+        }
+        self.lines[block.lines.start as usize..block.lines.end as usize]
+            .iter()
+            .map(|line| LineInfo {
+                offset: line.offset,
+                length: line.length,
+                file_index: line.file_index,
+                line_start: line.line_start,
+                line_end: line.line_end,
+                column_start: line.column_start,
+                column_end: line.column_end,
+                kind: line.kind,
+            })
+            .collect()
+    }
+
+    fn file(&self, file_index: FileIndex) -> Option<&CompactFile> {
+        let index = self
+            .files
+            .binary_search_by_key(&file_index, |file| file.index)
+            .ok()?;
+        Some(&self.files[index])
+    }
+
+    pub(crate) fn file_name<'t>(
+        &self,
+        file_index: FileIndex,
+        string_table: Option<&'t StringTable<'_>>,
+    ) -> Option<RawString<'t>> {
+        self.file(file_index)?
+            .name
+            .to_raw_string(string_table?)
+            .ok()
+    }
+
+    pub(crate) fn file_checksum(&self, file_index: FileIndex) -> Option<FileChecksum<'_>> {
+        let file = self.file(file_index)?;
+        let bytes = &self.checksums[file.checksum.start as usize..file.checksum.end as usize];
+        Some(match file.checksum_kind {
+            ChecksumKind::None => FileChecksum::None,
+            ChecksumKind::Md5 => FileChecksum::Md5(bytes),
+            ChecksumKind::Sha1 => FileChecksum::Sha1(bytes),
+            ChecksumKind::Sha256 => FileChecksum::Sha256(bytes),
+        })
+    }
+
+    pub(crate) fn language(&self) -> Option<Language> {
+        self.language
+    }
+
+    /// The heap memory which this module uses.
+    pub(crate) fn heap_size(&self) -> usize {
+        let inline_ranges: usize = self
+            .procedures
+            .iter()
+            .map(|proc| proc.inline_ranges.capacity() * mem::size_of::<InlineRange>())
+            .sum();
+        self.procedures.capacity() * mem::size_of::<CompactProcedure>()
+            + self.line_blocks.capacity() * mem::size_of::<CompactLineBlock>()
+            + self.strings.capacity()
+            + self.parameter_names.capacity() * mem::size_of::<Range<u32>>()
+            + self.lines.capacity() * mem::size_of::<CompactLine>()
+            + self.files.capacity() * mem::size_of::<CompactFile>()
+            + self.checksums.capacity()
+            + inline_ranges
+    }
+}
+
+/// Read the lines subsections of the line program, by their start offset.
+///
+/// The pdb crate only finds the subsection which contains a given offset, and
+/// reading all lines at once fails for line programs with several sections. So
+/// this starts at each procedure and at the end of each section with procedures,
+/// and walks back from the start of each subsection to the one before it, until
+/// it reaches a subsection which it already has. This finds the subsections of
+/// the functions between the procedures too, e.g. of functions which only have a
+/// public symbol. Returns `None` if lines can't be read, or if the module has
+/// files but no procedures, whose lines can't be found this way.
+fn read_line_blocks(
+    line_program: &LineProgram,
+    procedures: &[CompactProcedure],
+) -> Option<BTreeMap<(u16, u32), Vec<LineInfo>>> {
+    if procedures.is_empty() {
+        return match line_program.files().next().ok()? {
+            Some(_) => None,
+            None => Some(BTreeMap::new()),
+        };
+    }
+    let section_end = |section| PdbInternalSectionOffset {
+        section,
+        offset: u32::MAX,
+    };
+    let mut starts: Vec<PdbInternalSectionOffset> = Vec::new();
+    for (i, proc) in procedures.iter().enumerate() {
+        starts.push(proc.offset);
+        match procedures.get(i + 1) {
+            Some(next) if next.offset.section == proc.offset.section => {}
+            _ => starts.push(section_end(proc.offset.section)),
+        }
+    }
+
+    let mut blocks = BTreeMap::new();
+    for mut offset in starts {
+        loop {
+            let lines: Vec<LineInfo> = line_program.lines_for_symbol(offset).collect().ok()?;
+            let first_offset = match lines.iter().map(|line| line.offset.offset).min() {
+                Some(first_offset) => first_offset.min(offset.offset),
+                None => {
+                    // The subsection at the offset has no lines, or there is none.
+                    // Its start is unknown, so it only covers the offset itself.
+                    blocks.entry((offset.section, offset.offset)).or_default();
+                    break;
+                }
+            };
+            if blocks
+                .insert((offset.section, first_offset), lines)
+                .is_some()
+                || first_offset == 0
+            {
+                break;
+            }
+            offset.offset = first_offset - 1;
+        }
+    }
+    Some(blocks)
+}
+
+/// Append `bytes` to `buffer` and return their range.
+fn push_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) -> Range<u32> {
+    let start = buffer.len() as u32;
+    buffer.extend_from_slice(bytes);
+    start..buffer.len() as u32
+}
@@ -16,3 +16,17 @@ pub const S_THUNK32: u16 = 0x1102; // Thunk Start
 pub const S_SEPCODE: u16 = 0x1132;
 pub const S_INLINESITE: u16 = 0x114d; // inlined function callsite.
 pub const S_INLINESITE2: u16 = 0x115d; // extended inline site information
+pub const S_BLOCK32_ST: u16 = 0x0207; // block start
+pub const S_BLOCK32: u16 = 0x1103; // block start
+pub const S_BPREL32: u16 = 0x110b; // BP-relative
+pub const S_REGREL32: u16 = 0x1111; // register relative address
+pub const S_LOCAL: u16 = 0x113e; // defines a local symbol in optimized code
+pub const S_COMPILE: u16 = 0x0001; // Compile flags symbol
+pub const S_COMPILE2_ST: u16 = 0x1013; // extended compile flags and info
+pub const S_COMPILE2: u16 = 0x1116; // extended compile flags and info
+pub const S_COMPILE3: u16 = 0x113c; // extended compile flags and info
+pub const S_FRAMEPROC: u16 = 0x1012; // extra frame and proc information
+pub const S_GMANPROC: u16 = 0x112a; // Global proc for managed code
+pub const S_LMANPROC: u16 = 0x112b; // Local proc for managed code
+pub const S_COFFGROUP: u16 = 0x1137; // A COFF group
+pub const CV_CALL_THISCALL: u8 = 0x0b; // this in ecx, arguments pushed right to left
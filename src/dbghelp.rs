@@ -0,0 +1,145 @@
+use pdb::Rva;
+
+use crate::{Context, ContextCache, Result, Rva32};
+
+/// The symbol which contains an address, as returned by
+/// [`Context::symbol_from_address`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolFromAddress {
+    /// The function name, formatted like in [`Context::find_function_at`]. `None` if
+    /// there was an error during stringification.
+    pub name: Option<String>,
+    /// The start address of the function.
+    pub address: u32,
+    /// The size of the function in bytes, if known.
+    pub size: Option<u32>,
+    /// The offset of the looked-up address from `address`.
+    pub displacement: u32,
+}
+
+/// The source line which contains an address, as returned by
+/// [`Context::line_from_address`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineFromAddress {
+    /// The file name.
+    pub file: String,
+    /// The line number.
+    pub line: u32,
+    /// The start address of the instructions of this line which contain the
+    /// looked-up address.
+    pub address: u32,
+    /// The offset of the looked-up address from `address`.
+    pub displacement: u32,
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Find the function which contains the provided address, with the same
+    /// semantics as dbghelp's `SymFromAddr`: the result has the start address of the
+    /// function and the displacement of `probe` from it.
+    ///
+    /// Incremental linking thunks are not resolved, even if
+    /// [`ContextFlags::RESOLVE_INCREMENTAL_LINKING_THUNKS`](crate::ContextFlags::RESOLVE_INCREMENTAL_LINKING_THUNKS)
+    /// is set, because the displacement would be meaningless.
+    pub fn symbol_from_address(&self, probe: Rva32) -> Result<Option<SymbolFromAddress>> {
+        let probe = probe.0;
+        let function = match self.find_function_at_rva(probe, false)? {
+            Some(function) => function,
+            None => return Ok(None),
+        };
+        let displacement = match probe.checked_sub(function.start_rva) {
+            Some(displacement) => displacement,
+            None => return Ok(None),
+        };
+        let size = function
+            .end_rva
+            .map(|end_rva| end_rva.saturating_sub(function.start_rva));
+        Ok(Some(SymbolFromAddress {
+            name: function.name,
+            address: function.start_rva,
+            size,
+            displacement,
+        }))
+    }
+
+    /// Find the source line which contains the provided address, with the same
+    /// semantics as dbghelp's `SymGetLineFromAddr64`: inlined functions are ignored,
+    /// and the line is the one in the outermost function. The result has the start
+    /// address of the line's instructions and the displacement of `probe` from it.
+    ///
+    /// Returns `Ok(None)` if the address has no file or line information.
+    pub fn line_from_address(&self, probe: Rva32) -> Result<Option<LineFromAddress>> {
+        let probe = probe.0;
+        let function_frames = match self.find_frames_at(Rva32(probe))? {
+            Some(function_frames) => function_frames,
+            None => return Ok(None),
+        };
+        let outer = match function_frames.frames.last() {
+            Some(outer) => outer,
+            None => return Ok(None),
+        };
+        let (file, line) = match (&outer.file, outer.line) {
+            (Some(file), Some(line)) => (file.to_string(), line),
+            _ => return Ok(None),
+        };
+        let address = self
+            .line_start_rva(probe)?
+            .unwrap_or(function_frames.start_rva);
+        let displacement = match probe.checked_sub(address) {
+            Some(displacement) => displacement,
+            None => return Ok(None),
+        };
+        Ok(Some(LineFromAddress {
+            file,
+            line,
+            address,
+            displacement,
+        }))
+    }
+
+    /// The start address of the line record of the outermost function which contains
+    /// `probe`.
+    fn line_start_rva(&self, probe: u32) -> Result<Option<u32>> {
+        let handle = match self.lookup_function_handle(Rva32(probe)) {
+            Some(handle) => handle,
+            None => return Ok(None),
+        };
+        let offset = match Rva(probe).to_internal_offset(self.address_map) {
+            Some(offset) if handle.contains(offset) => offset,
+            _ => return Ok(None),
+        };
+
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            function_line_cache,
+            extended_module_cache,
+            ..
+        } = &mut *cache;
+        let module_info = match module_cache
+            .get_basic_module_info(self.type_formatter.modules(), handle.module_index)
+        {
+            Some(basic_module_info) => basic_module_info.module_info,
+            None => return Ok(None),
+        };
+        let extended_module_info =
+            self.get_extended_module_info(extended_module_cache, handle.module_index, module_info)?;
+        let size = handle
+            .end_offset
+            .map(|end_offset| end_offset - handle.start_offset.offset);
+        let lines = function_line_cache
+            .entry(handle.start_offset)
+            .or_default()
+            .get_lines(handle.start_offset, size, &extended_module_info.lines)
+            .map_err(|e| {
+                self.module_error(handle.module_index, "reading the procedure's lines", e)
+            })?;
+        let index = match lines.binary_search_by_key(&offset.offset, |line| line.start_offset) {
+            Err(0) => return Ok(None),
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        Ok(Some(
+            handle.start_rva + (lines[index].start_offset - handle.start_offset.offset),
+        ))
+    }
+}
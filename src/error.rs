@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
 pub enum Error {
@@ -7,6 +9,27 @@ pub enum Error {
     #[error("PDB error: {0}")]
     PdbError(#[source] pdb::Error),
 
+    #[error("PDB error while reading the {stream} stream: {source}")]
+    Stream {
+        /// The name of the stream, e.g. "DBI" or "TPI", or the name of a named stream
+        /// like "/LinkInfo".
+        stream: Cow<'static, str>,
+        #[source]
+        source: pdb::Error,
+    },
+
+    #[error("Error while {operation} for module {module_index} ({module_name}): {source}")]
+    Module {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The name of the module, usually the path to an object file.
+        module_name: String,
+        /// What was being done when the error occurred, e.g. "reading the line program".
+        operation: &'static str,
+        #[source]
+        source: Box<Error>,
+    },
+
     #[error("Unexpected type for argument list")]
     ArgumentTypeNotArgumentList,
 
@@ -22,6 +45,9 @@ pub enum Error {
     #[error("Overlapping section contributions in section {0} from modules {1} and {2}")]
     OverlappingSectionContributions(u16, usize, usize),
 
+    #[error("The PDB has {0} section headers, but section indexes are 16-bit")]
+    TooManySections(usize),
+
     #[error("Getting the procedure lines was unsuccessful")]
     ProcedureLinesUnsuccessful,
 
@@ -51,6 +77,47 @@ pub enum Error {
 
     #[error("Could not get the ModuleInfo for module index {0}")]
     ModuleInfoNotFound(usize),
+
+    #[error("The PDB's GUID {actual} does not match the expected GUID {expected}")]
+    GuidMismatch {
+        expected: uuid::Uuid,
+        actual: uuid::Uuid,
+    },
+
+    #[error("The PDB's age {actual} does not match the expected age {expected}")]
+    AgeMismatch { expected: u32, actual: u32 },
+
+    #[error("Invalid UTF-8 in {0}: {1}")]
+    InvalidUtf8(&'static str, String),
+
+    #[error("Invalid template {0:?}: {1}")]
+    InvalidTemplate(String, String),
+
+    #[error("The GUID and age of the PDB are unknown, see Context::set_pdb_identity")]
+    UnknownPdbIdentity,
+
+    #[error("The cache snapshot was made from a different PDB or with different flags")]
+    StaleCacheSnapshot,
+
+    #[error("Invalid cache snapshot: {0}")]
+    InvalidCacheSnapshot(&'static str),
+
+    #[error("The operation was cancelled")]
+    Cancelled,
+
+    #[error("This is a Portable PDB file (used for .NET assemblies), which is not supported")]
+    PortablePdb,
+
+    #[cfg(feature = "pe")]
+    #[error("Could not read the PE export table: {0}")]
+    PeExports(#[source] object::Error),
+}
+
+impl Error {
+    pub(crate) fn stream(stream: impl Into<Cow<'static, str>>) -> impl FnOnce(pdb::Error) -> Self {
+        let stream = stream.into();
+        move |source| Self::Stream { stream, source }
+    }
 }
 
 impl From<pdb::Error> for Error {
@@ -0,0 +1,48 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use crate::Frame;
+
+impl<'a, S> Frame<'a, S> {
+    /// The file name as a string: [`Frame::file`], or if that was left empty with
+    /// [`ContextFlags::RAW_FILE_NAMES`](crate::ContextFlags::RAW_FILE_NAMES), the
+    /// bytes of [`Frame::raw_file`] converted now. Invalid UTF-8 sequences are
+    /// replaced with U+FFFD REPLACEMENT CHARACTER.
+    pub fn file_name(&self) -> Option<Cow<'a, str>> {
+        self.file
+            .clone()
+            .or_else(|| self.raw_file.map(|raw_file| raw_file.to_string()))
+    }
+
+    /// The file name as a `PathBuf`, made from the bytes of [`Frame::raw_file`]
+    /// instead of from the UTF-8 string in [`Frame::file`], so that file names which
+    /// aren't valid UTF-8 keep their bytes.
+    ///
+    /// PDBs don't record the encoding of their strings. MSVC writes UTF-8, but some
+    /// toolchains write paths in the encoding of the current locale. On Unix, the
+    /// bytes are used unchanged. On other platforms, where paths can't be made from
+    /// arbitrary bytes, this falls back to [`Frame::file`] if the bytes aren't UTF-8,
+    /// see [`Context::set_non_utf8_handling`](crate::Context::set_non_utf8_handling).
+    ///
+    /// The path is interpreted with the host's path semantics: on Windows, drive
+    /// letters and UNC prefixes are recognized, on other hosts a path like
+    /// `C:\src\a.cpp` is a single component.
+    pub fn file_path(&self) -> Option<PathBuf> {
+        self.raw_file
+            .and_then(|raw_file| path_from_bytes(raw_file.as_bytes()))
+            .or_else(|| self.file.as_deref().map(PathBuf::from))
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> Option<PathBuf> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    Some(PathBuf::from(OsStr::from_bytes(bytes)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> Option<PathBuf> {
+    std::str::from_utf8(bytes).ok().map(PathBuf::from)
+}
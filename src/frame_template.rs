@@ -0,0 +1,213 @@
+use std::fmt::Write;
+
+use crate::{Error, Frame};
+
+/// A template for formatting a [`Frame`] as a line of text, e.g.
+/// `"{function} @ {file}:{line} (+{offset:#x})"`.
+///
+/// The template is literal text with fields in braces. Use `{{` and `}}` for
+/// literal braces. These fields are supported:
+///
+///  - `{function}`: The function name.
+///  - `{file}`: The file name.
+///  - `{line}`: The line number.
+///  - `{offset}`: The offset of the looked-up address from the start of the function.
+///  - `{rva}`: The looked-up address.
+///  - `{start_rva}`, `{end_rva}`: The address range of the function.
+///
+/// Values which are not known are written as `??`.
+///
+/// A field can have a format spec after a colon, with a subset of the syntax of
+/// Rust's format strings: `{field:[#][0][width][x|X]}`. `#` adds a `0x` prefix,
+/// `x` and `X` format numbers as hex, `0` pads with zeros instead of spaces, and
+/// `width` is the minimum width. Numbers are right-aligned and strings are
+/// left-aligned. For example, `{rva:#010x}` gives `0x00001234`.
+#[derive(Clone, Debug)]
+pub struct FrameTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Clone, Debug)]
+enum TemplatePart {
+    Literal(String),
+    Field(TemplateField, FormatSpec),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TemplateField {
+    Function,
+    File,
+    Line,
+    Offset,
+    Rva,
+    StartRva,
+    EndRva,
+}
+
+impl TemplateField {
+    fn is_numeric(self) -> bool {
+        !matches!(self, TemplateField::Function | TemplateField::File)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct FormatSpec {
+    alternate: bool,
+    zero_pad: bool,
+    width: usize,
+    radix: Radix,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Radix {
+    #[default]
+    Decimal,
+    LowerHex,
+    UpperHex,
+}
+
+impl FrameTemplate {
+    /// Parse a template. Returns [`Error::InvalidTemplate`] for unknown fields,
+    /// invalid format specs and unbalanced braces.
+    pub fn parse(template: &str) -> Result<Self, Error> {
+        let invalid = |message: &str| Error::InvalidTemplate(template.to_string(), message.into());
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(invalid("unmatched `}`")),
+                '{' => {
+                    let mut field = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => field.push(c),
+                            None => return Err(invalid("unclosed `{`")),
+                        }
+                    }
+                    let (name, spec) = match field.split_once(':') {
+                        Some((name, spec)) => (name, spec),
+                        None => (field.as_str(), ""),
+                    };
+                    let field = match name.trim() {
+                        "function" => TemplateField::Function,
+                        "file" => TemplateField::File,
+                        "line" => TemplateField::Line,
+                        "offset" => TemplateField::Offset,
+                        "rva" => TemplateField::Rva,
+                        "start_rva" => TemplateField::StartRva,
+                        "end_rva" => TemplateField::EndRva,
+                        _ => return Err(invalid(&format!("unknown field `{}`", name))),
+                    };
+                    let spec = parse_format_spec(spec)
+                        .ok_or_else(|| invalid(&format!("invalid format spec `{}`", spec)))?;
+                    if !field.is_numeric() && (spec.alternate || spec.radix != Radix::Decimal) {
+                        return Err(invalid(&format!("`{}` is not a number", name)));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(TemplatePart::Field(field, spec));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Format `frame` according to this template.
+    pub fn format<S: AsRef<str>>(&self, frame: &Frame<'_, S>) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(literal) => output.push_str(literal),
+                TemplatePart::Field(field, spec) => {
+                    let number = match field {
+                        TemplateField::Function => {
+                            write_str(&mut output, frame.function.as_ref().map(S::as_ref), spec);
+                            continue;
+                        }
+                        TemplateField::File => {
+                            write_str(&mut output, frame.file.as_deref(), spec);
+                            continue;
+                        }
+                        TemplateField::Line => frame.line,
+                        TemplateField::Offset => Some(frame.offset),
+                        TemplateField::Rva => frame.start_rva.checked_add(frame.offset),
+                        TemplateField::StartRva => Some(frame.start_rva),
+                        TemplateField::EndRva => frame.end_rva,
+                    };
+                    match number {
+                        Some(number) => write_number(&mut output, number, spec),
+                        None => write_str(&mut output, None, spec),
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Parse `[#][0][width][x|X]`.
+fn parse_format_spec(spec: &str) -> Option<FormatSpec> {
+    let mut spec = spec;
+    let mut result = FormatSpec::default();
+    if let Some(rest) = spec.strip_prefix('#') {
+        result.alternate = true;
+        spec = rest;
+    }
+    if let Some(rest) = spec.strip_prefix('0') {
+        result.zero_pad = true;
+        spec = rest;
+    }
+    if let Some(rest) = spec.strip_suffix('x') {
+        result.radix = Radix::LowerHex;
+        spec = rest;
+    } else if let Some(rest) = spec.strip_suffix('X') {
+        result.radix = Radix::UpperHex;
+        spec = rest;
+    }
+    if !spec.is_empty() {
+        result.width = spec.parse().ok()?;
+    }
+    Some(result)
+}
+
+fn write_str(output: &mut String, value: Option<&str>, spec: &FormatSpec) {
+    let value = value.unwrap_or("??");
+    let _ = write!(output, "{:<width$}", value, width = spec.width);
+}
+
+fn write_number(output: &mut String, value: u32, spec: &FormatSpec) {
+    let digits = match spec.radix {
+        Radix::Decimal => value.to_string(),
+        Radix::LowerHex => format!("{:x}", value),
+        Radix::UpperHex => format!("{:X}", value),
+    };
+    let prefix = if spec.alternate && spec.radix != Radix::Decimal {
+        "0x"
+    } else {
+        ""
+    };
+    if spec.zero_pad {
+        let zeros = spec.width.saturating_sub(prefix.len() + digits.len());
+        output.push_str(prefix);
+        output.push_str(&"0".repeat(zeros));
+        output.push_str(&digits);
+    } else {
+        let number = format!("{}{}", prefix, digits);
+        let _ = write!(output, "{:>width$}", number, width = spec.width);
+    }
+}
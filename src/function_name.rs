@@ -0,0 +1,351 @@
+use std::ops::Range;
+
+use crate::Frame;
+
+/// The parts of a formatted function name, e.g. of
+/// `` void `anonymous namespace'::A<B::C>::f(int) const ``. Returned by
+/// [`parse_function_name`] and [`Frame::function_name_parts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionNameParts<'n> {
+    /// The return type and calling convention in front of the name, if the name
+    /// was formatted with them, e.g. `void`.
+    pub return_type: Option<&'n str>,
+    /// The namespaces and classes which contain the function, outermost first, e.g.
+    /// ``["`anonymous namespace'", "A<B::C>"]``.
+    pub scope: Vec<&'n str>,
+    /// The unqualified function name, including template arguments, e.g. `f`.
+    pub name: &'n str,
+    /// The argument list in parentheses, with the qualifiers after it, e.g.
+    /// `(int) const`. `None` if the name has no argument list.
+    pub arguments: Option<&'n str>,
+}
+
+impl<'n> FunctionNameParts<'n> {
+    /// The scope segments followed by the unqualified name.
+    pub fn path(&self) -> impl Iterator<Item = &'n str> + '_ {
+        self.scope.iter().copied().chain(std::iter::once(self.name))
+    }
+}
+
+/// The positions of the parts of a function name, which the
+/// [`TypeFormatter`](crate::TypeFormatter) records while it writes the name from the
+/// type and id records. See [`Frame::function_name_layout`].
+///
+/// The scope comes from a single string in the PDB, e.g. the name of the class, so it
+/// is split into its segments at the `::` separators outside of template arguments,
+/// argument lists and quotes. The boundaries of the return type, the unqualified name
+/// and the argument list are exact.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionNameLayout {
+    return_type: Option<Range<usize>>,
+    scope: Vec<Range<usize>>,
+    name: Range<usize>,
+    arguments: Option<Range<usize>>,
+}
+
+impl FunctionNameLayout {
+    /// Create the layout of `full_name`. `qualified` is the range of the scope and the
+    /// unqualified name. `name_start` is where the name string of the function record
+    /// starts, if its scope was written separately, followed by `::`. The name string
+    /// itself can still be qualified, so the unqualified name is the last segment of
+    /// it. Empty ranges and trailing spaces of the return type are dropped.
+    pub(crate) fn new(
+        full_name: &str,
+        return_type: Option<Range<usize>>,
+        qualified: Range<usize>,
+        name_start: Option<usize>,
+        arguments: Option<Range<usize>>,
+    ) -> Self {
+        let return_type = return_type
+            .map(|range| range.start..range.start + full_name[range].trim_end().len())
+            .filter(|range| !range.is_empty());
+        let offset =
+            |start: usize| move |range: Range<usize>| start + range.start..start + range.end;
+        let name_start = name_start.unwrap_or(qualified.start);
+        let mut segments = Vec::new();
+        if name_start >= qualified.start + 2 {
+            let scope = &full_name[qualified.start..name_start - 2];
+            segments.extend(
+                split_qualified_name(scope)
+                    .into_iter()
+                    .map(offset(qualified.start)),
+            );
+        }
+        let name = &full_name[name_start..qualified.end];
+        segments.extend(
+            split_qualified_name(name)
+                .into_iter()
+                .map(offset(name_start)),
+        );
+        let name = segments.pop().unwrap_or(qualified);
+        Self {
+            return_type,
+            scope: segments,
+            name,
+            arguments: arguments.filter(|range| !range.is_empty()),
+        }
+    }
+
+    /// Split `full_name`, the name which this layout was recorded for, into its parts.
+    /// Returns `None` if the layout doesn't fit the name.
+    pub fn parts<'n>(&self, full_name: &'n str) -> Option<FunctionNameParts<'n>> {
+        let part = |range: &Range<usize>| full_name.get(range.clone());
+        Some(FunctionNameParts {
+            return_type: match &self.return_type {
+                Some(range) => Some(part(range)?),
+                None => None,
+            },
+            scope: self.scope.iter().map(part).collect::<Option<_>>()?,
+            name: part(&self.name)?,
+            arguments: match &self.arguments {
+                Some(range) => Some(part(range)?),
+                None => None,
+            },
+        })
+    }
+
+    /// The start and end positions of all parts.
+    pub(crate) fn boundaries(&self) -> Vec<usize> {
+        self.return_type
+            .iter()
+            .chain(&self.scope)
+            .chain(std::iter::once(&self.name))
+            .chain(&self.arguments)
+            .flat_map(|range| [range.start, range.end])
+            .collect()
+    }
+
+    /// Move all positions with `map_position`, e.g. after the name was rewritten.
+    pub(crate) fn map_positions(&self, map_position: impl Fn(usize) -> usize) -> Self {
+        let map_range = |range: &Range<usize>| map_position(range.start)..map_position(range.end);
+        Self {
+            return_type: self.return_type.as_ref().map(map_range),
+            scope: self.scope.iter().map(map_range).collect(),
+            name: map_range(&self.name),
+            arguments: self.arguments.as_ref().map(map_range),
+        }
+    }
+
+    /// The heap memory which this layout uses.
+    pub(crate) fn heap_size(&self) -> usize {
+        self.scope.capacity() * std::mem::size_of::<Range<usize>>()
+    }
+}
+
+impl<'a, S: AsRef<str>> Frame<'a, S> {
+    /// Split the function name of this frame into its scope segments, the
+    /// unqualified name, and the argument list. This uses
+    /// [`Frame::function_name_layout`] if the name was formatted from the type
+    /// records, and falls back to [`parse_function_name`] otherwise, e.g. for the
+    /// names of public symbols. `None` if the frame has no function name.
+    pub fn function_name_parts(&self) -> Option<FunctionNameParts<'_>> {
+        let function = self.function.as_ref()?.as_ref();
+        let parts = self
+            .function_name_layout
+            .as_ref()
+            .and_then(|layout| layout.parts(function));
+        Some(parts.unwrap_or_else(|| parse_function_name(function)))
+    }
+}
+
+/// Split a function name, as formatted by this crate, into its parts. The `::`
+/// separators inside template arguments, argument lists and quoted names like
+/// `` `anonymous namespace' `` don't split the name, and neither do the characters
+/// of operator names like `operator<`.
+///
+/// This only looks at the punctuation, so it gives useful results for all names in
+/// the style of MSVC's undecorated names, including the ones of public symbols
+/// which were demangled by the consumer.
+pub fn parse_function_name(full_name: &str) -> FunctionNameParts<'_> {
+    let bytes = full_name.as_bytes();
+    let mut depth = 0usize;
+    let mut segment_start = 0;
+    let mut separators = Vec::new();
+    let mut last_space = None;
+    let mut arguments_start = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        if depth == 0 && i == segment_start && is_operator_name(&full_name[i..]) {
+            // Skip the operator symbol, which can contain any kind of bracket, up to
+            // the argument list.
+            i += "operator".len();
+            if full_name[i..].starts_with("()") {
+                i += 2;
+            }
+            while i < bytes.len() && bytes[i] != b'(' {
+                i += 1;
+            }
+            continue;
+        }
+        match bytes[i] {
+            b'`' => {
+                i = quote_end(bytes, i);
+                continue;
+            }
+            b'<' | b'[' => depth += 1,
+            b'>' | b']' => depth = depth.saturating_sub(1),
+            b'(' if depth == 0 => {
+                arguments_start = Some(i);
+                break;
+            }
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') => {
+                separators.push(i);
+                i += 2;
+                segment_start = i;
+                continue;
+            }
+            b' ' if depth == 0 => {
+                last_space = Some(i);
+                separators.clear();
+                segment_start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let qualified_end = arguments_start.unwrap_or(bytes.len());
+    let qualified_start = last_space.map_or(0, |space| space + 1);
+    let mut scope = Vec::with_capacity(separators.len());
+    let mut start = qualified_start;
+    for separator in separators {
+        scope.push(&full_name[start..separator]);
+        start = separator + 2;
+    }
+    FunctionNameParts {
+        return_type: last_space.map(|space| full_name[..space].trim_end()),
+        scope,
+        name: &full_name[start..qualified_end],
+        arguments: arguments_start.map(|start| &full_name[start..]),
+    }
+}
+
+/// Split a qualified name without return type and argument list, e.g. a class name,
+/// into the ranges of its segments, like [`parse_function_name`] does.
+fn split_qualified_name(name: &str) -> Vec<Range<usize>> {
+    let bytes = name.as_bytes();
+    let mut depth = 0usize;
+    let mut segments = Vec::new();
+    let mut segment_start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if depth == 0 && i == segment_start && is_operator_name(&name[i..]) {
+            // The operator symbol can contain any character, and is always the last
+            // segment.
+            break;
+        }
+        match bytes[i] {
+            b'`' => {
+                i = quote_end(bytes, i);
+                continue;
+            }
+            b'<' | b'[' | b'(' => depth += 1,
+            b'>' | b']' | b')' => depth = depth.saturating_sub(1),
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') => {
+                segments.push(segment_start..i);
+                i += 2;
+                segment_start = i;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    segments.push(segment_start..bytes.len());
+    segments
+}
+
+/// The position after the quote which starts with the backtick at `start`. Quotes
+/// end with an apostrophe. Names inside quotes are quoted with apostrophes, which
+/// can be closed twice, like in MSVC's `` `dynamic initializer for 'x'' ``, or once,
+/// like in this crate's `` `dynamic initializer for 'x'() ``.
+fn quote_end(bytes: &[u8], start: usize) -> usize {
+    let mut quote_depth = 1usize;
+    let mut i = start + 1;
+    while i < bytes.len() && quote_depth > 0 {
+        match bytes[i] {
+            b'`' => quote_depth += 1,
+            b'\'' if bytes[i - 1] == b' ' && is_doubly_closed(&bytes[i + 1..]) => quote_depth += 1,
+            b'\'' if bytes[i - 1] == b' ' => {}
+            b'\'' => quote_depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+/// Whether `name` starts with the keyword `operator`, and not with an identifier like
+/// `operator_id`.
+fn is_operator_name(name: &str) -> bool {
+    match name.strip_prefix("operator") {
+        Some(rest) => !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+        None => false,
+    }
+}
+
+/// Whether the next apostrophe in `rest` is directly followed by a second one.
+fn is_doubly_closed(rest: &[u8]) -> bool {
+    match rest.iter().position(|b| *b == b'\'') {
+        Some(pos) => rest.get(pos + 1) == Some(&b'\''),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_keeps_brackets_in_template_arguments() {
+        let full_name = "void (__cdecl*)(int) A::B<C::D>::E<C::F(int)>(int)";
+        let qualified_start = "void (__cdecl*)(int) ".len();
+        let arguments_start = full_name.len() - "(int)".len();
+        let layout = FunctionNameLayout::new(
+            full_name,
+            Some(0..qualified_start),
+            qualified_start..arguments_start,
+            None,
+            Some(arguments_start..full_name.len()),
+        );
+        assert_eq!(
+            layout.parts(full_name),
+            Some(FunctionNameParts {
+                return_type: Some("void (__cdecl*)(int)"),
+                scope: vec!["A", "B<C::D>"],
+                name: "E<C::F(int)>",
+                arguments: Some("(int)"),
+            })
+        );
+        // Without the layout, the first parenthesis is taken as the argument list.
+        assert_ne!(parse_function_name(full_name).name, "E<C::F(int)>");
+    }
+
+    #[test]
+    fn layout_splits_qualified_record_names() {
+        // The scope of an id record is written separately, but the name of the
+        // function can be qualified itself.
+        let full_name = "`anonymous namespace'::ns::`dynamic initializer for 'x''()";
+        let name_start = "`anonymous namespace'::".len();
+        let arguments_start = full_name.len() - 2;
+        let layout = FunctionNameLayout::new(
+            full_name,
+            None,
+            0..arguments_start,
+            Some(name_start),
+            Some(arguments_start..full_name.len()),
+        );
+        let parts = layout.parts(full_name).unwrap();
+        assert_eq!(
+            parts.path().collect::<Vec<_>>(),
+            vec![
+                "`anonymous namespace'",
+                "ns",
+                "`dynamic initializer for 'x''"
+            ]
+        );
+        assert_eq!(parts.arguments, Some("()"));
+    }
+}
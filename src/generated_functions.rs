@@ -0,0 +1,80 @@
+/// A kind of function which the compiler generates without a corresponding function
+/// in the source code, see [`Function::generated`](crate::Function::generated).
+///
+/// Size reports can use this to separate the user's code from compiler glue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GeneratedFunctionKind {
+    /// A scalar or vector deleting destructor, which calls the destructor and then
+    /// frees the memory, e.g. `` Foo::`scalar deleting destructor'(unsigned int) ``.
+    DeletingDestructor,
+    /// An exception handling function, e.g. `__ehhandler$?f@@YAXXZ`, or a catch,
+    /// unwind or filter funclet like `` `f'::`1'::catch$0 ``.
+    ExceptionHandler,
+    /// A function which initializes a global variable at startup, e.g.
+    /// `` `dynamic initializer for 'x'' ``, or clang's `_GLOBAL__sub_I_file.cpp`.
+    DynamicInitializer,
+    /// A function which destroys a global variable at exit, e.g.
+    /// `` `dynamic atexit destructor for 'x'' ``.
+    AtexitDestructor,
+    /// A thunk which adjusts the `this` pointer or dispatches a virtual call, e.g.
+    /// `` [thunk]:Foo::f`adjustor{8}' `` or `` [thunk]:Foo::`vcall'{8,{flat}}' ``.
+    Thunk,
+}
+
+impl GeneratedFunctionKind {
+    /// Classify a function by its name, which can be an undecorated name or the
+    /// decorated name of a public symbol. Returns `None` for functions which have a
+    /// counterpart in the source code.
+    pub fn from_function_name(name: &str) -> Option<Self> {
+        if name.starts_with('?') {
+            return Self::from_decorated_name(name);
+        }
+        if name.starts_with("[thunk]:")
+            || name.contains("`vcall'")
+            || name.contains("`adjustor{")
+            || name.contains("`vtordisp{")
+        {
+            return Some(Self::Thunk);
+        }
+        if name.contains("`scalar deleting destructor'")
+            || name.contains("`vector deleting destructor'")
+        {
+            return Some(Self::DeletingDestructor);
+        }
+        if name.contains("`dynamic initializer for '") || name.starts_with("_GLOBAL__sub_I_") {
+            return Some(Self::DynamicInitializer);
+        }
+        if name.contains("`dynamic atexit destructor for '") {
+            return Some(Self::AtexitDestructor);
+        }
+
+        const EXCEPTION_HANDLER_PREFIXES: &[&str] = &["ehhandler$", "unwindfunclet$", "catch$"];
+        const FUNCLET_MARKERS: &[&str] = &["'::catch$", "'::dtor$", "'::fin$", "'::filt$"];
+        let undecorated = name.trim_start_matches('_');
+        if EXCEPTION_HANDLER_PREFIXES
+            .iter()
+            .any(|prefix| undecorated.starts_with(prefix))
+            || FUNCLET_MARKERS.iter().any(|marker| name.contains(marker))
+        {
+            return Some(Self::ExceptionHandler);
+        }
+        None
+    }
+
+    /// Classify a decorated name by its special name code, e.g. `??_G` for scalar
+    /// deleting destructors.
+    fn from_decorated_name(name: &str) -> Option<Self> {
+        if name.starts_with("??_G") || name.starts_with("??_E") {
+            Some(Self::DeletingDestructor)
+        } else if name.starts_with("??__E") {
+            Some(Self::DynamicInitializer)
+        } else if name.starts_with("??__F") {
+            Some(Self::AtexitDestructor)
+        } else if name.starts_with("??_9") {
+            Some(Self::Thunk)
+        } else {
+            None
+        }
+    }
+}
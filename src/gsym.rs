@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+
+use crate::{Context, FunctionFrames, Result, Rva32};
+
+const MAGIC: u32 = 0x4753_594d; // "GSYM"
+const VERSION: u16 = 1;
+const HEADER_UUID_SIZE: usize = 20;
+/// The offset of the string table offset field in the header.
+const HEADER_STRTAB_OFFSET: usize = 20;
+
+// The types of the data chunks of a function.
+const INFO_END_OF_LIST: u32 = 0;
+const INFO_LINE_TABLE: u32 = 1;
+const INFO_INLINE: u32 = 2;
+
+// The line table opcodes. Opcodes from FIRST_SPECIAL on advance both the address and
+// the line.
+const OP_END_SEQUENCE: u8 = 0;
+const OP_SET_FILE: u8 = 1;
+const OP_ADVANCE_PC: u8 = 2;
+const OP_ADVANCE_LINE: u8 = 3;
+const OP_FIRST_SPECIAL: u8 = 4;
+
+/// The range of line deltas which are encoded in the special opcodes. Other deltas
+/// need a separate `OP_ADVANCE_LINE`.
+const MIN_LINE_DELTA: i64 = -4;
+const MAX_LINE_DELTA: i64 = 10;
+
+impl<'a, 's> Context<'a, 's> {
+    /// Convert the functions of this PDB, with their line tables and inlined
+    /// functions, into the GSYM format, which is used by the LLVM symbolication
+    /// tools. Function addresses are `image_base` plus the function's rva. The UUID
+    /// in the header is the GUID of the PDB, if it is known.
+    ///
+    /// The function names are the same as the ones returned by
+    /// [`Context::functions`]. This looks up every function, so it is slow for large
+    /// PDBs.
+    pub fn export_gsym(&self, image_base: u64) -> Result<Vec<u8>> {
+        let mut strings = StringTable::default();
+        let mut files = FileTable::default();
+        let mut functions: Vec<(u64, Vec<u8>)> = Vec::new();
+        for function in self.functions() {
+            let address = image_base + u64::from(function.start_rva);
+            if functions.last().is_some_and(|(last, _)| *last == address) {
+                continue;
+            }
+            let size = function
+                .end_rva
+                .map_or(0, |end_rva| end_rva.saturating_sub(function.start_rva));
+            let name = strings.insert(function.name.as_deref().unwrap_or(""));
+
+            let mut data = Vec::new();
+            put_u32(&mut data, size);
+            put_u32(&mut data, name);
+            if size != 0 {
+                let (rows, inline_root) = self.gsym_function_info(
+                    function.start_rva,
+                    size,
+                    name,
+                    &mut strings,
+                    &mut files,
+                )?;
+                if !rows.is_empty() {
+                    put_u32(&mut data, INFO_LINE_TABLE);
+                    let chunk = encode_line_table(&rows, function.start_rva);
+                    put_u32(&mut data, chunk.len() as u32);
+                    data.extend_from_slice(&chunk);
+                }
+                if !inline_root.children.is_empty() {
+                    put_u32(&mut data, INFO_INLINE);
+                    let mut chunk = Vec::new();
+                    inline_root.encode(&mut chunk, function.start_rva);
+                    put_u32(&mut data, chunk.len() as u32);
+                    data.extend_from_slice(&chunk);
+                }
+            }
+            put_u32(&mut data, INFO_END_OF_LIST);
+            put_u32(&mut data, 0);
+            functions.push((address, data));
+        }
+
+        let base_address = functions.first().map_or(0, |(address, _)| *address);
+        let max_offset = functions
+            .last()
+            .map_or(0, |(address, _)| address - base_address);
+        let address_offset_size: u8 = match max_offset {
+            0..=0xff => 1,
+            0x100..=0xffff => 2,
+            0x1_0000..=0xffff_ffff => 4,
+            _ => 8,
+        };
+        let guid_bytes = self.pdb_identity.map(|(guid, _)| *guid.as_bytes());
+        let uuid = guid_bytes.as_ref().map_or(&[][..], |bytes| &bytes[..]);
+
+        let mut out = Vec::new();
+        put_u32(&mut out, MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.push(address_offset_size);
+        out.push(uuid.len() as u8);
+        out.extend_from_slice(&base_address.to_le_bytes());
+        put_u32(&mut out, functions.len() as u32);
+        // The string table offset and size are filled in below.
+        put_u32(&mut out, 0);
+        put_u32(&mut out, 0);
+        let mut uuid_field = [0; HEADER_UUID_SIZE];
+        uuid_field[..uuid.len()].copy_from_slice(uuid);
+        out.extend_from_slice(&uuid_field);
+
+        align(&mut out, address_offset_size as usize);
+        for (address, _) in &functions {
+            let offset = address - base_address;
+            out.extend_from_slice(&offset.to_le_bytes()[..address_offset_size as usize]);
+        }
+
+        // The offsets of the function infos are filled in below.
+        align(&mut out, 4);
+        let info_offsets_start = out.len();
+        out.resize(info_offsets_start + functions.len() * 4, 0);
+
+        align(&mut out, 4);
+        put_u32(&mut out, files.entries.len() as u32);
+        for (dir, base) in &files.entries {
+            put_u32(&mut out, *dir);
+            put_u32(&mut out, *base);
+        }
+
+        let strtab_offset = out.len() as u32;
+        out.extend_from_slice(&strings.data);
+        out[HEADER_STRTAB_OFFSET..HEADER_STRTAB_OFFSET + 4]
+            .copy_from_slice(&strtab_offset.to_le_bytes());
+        out[HEADER_STRTAB_OFFSET + 4..HEADER_STRTAB_OFFSET + 8]
+            .copy_from_slice(&(strings.data.len() as u32).to_le_bytes());
+
+        for (index, (_, data)) in functions.iter().enumerate() {
+            align(&mut out, 4);
+            let info_offset = info_offsets_start + index * 4;
+            let data_offset = out.len() as u32;
+            out[info_offset..info_offset + 4].copy_from_slice(&data_offset.to_le_bytes());
+            out.extend_from_slice(data);
+        }
+        Ok(out)
+    }
+
+    /// The line table rows and the tree of inlined functions of the function at
+    /// `start_rva`. The rows describe the innermost function at each address.
+    fn gsym_function_info(
+        &self,
+        start_rva: u32,
+        size: u32,
+        name: u32,
+        strings: &mut StringTable,
+        files: &mut FileTable,
+    ) -> Result<(Vec<LineRow>, InlineNode)> {
+        let end_rva = start_rva.saturating_add(size);
+        let mut rows: Vec<LineRow> = Vec::new();
+        let mut root = InlineNode {
+            name,
+            call_file: 0,
+            call_line: 0,
+            ranges: vec![(start_rva, end_rva)],
+            children: Vec::new(),
+        };
+        for rva in self.frame_boundaries(start_rva, size)? {
+            let frames = match self.find_frames_at(Rva32(rva))? {
+                Some(FunctionFrames {
+                    start_rva: frames_start_rva,
+                    frames,
+                    ..
+                }) if frames_start_rva == start_rva => frames,
+                _ => continue,
+            };
+
+            if let Some(innermost) = frames.first() {
+                if let Some(line) = innermost.line {
+                    let file = files.insert(innermost.file.as_deref(), strings);
+                    if !matches!(rows.last(), Some(row) if row.file == file && row.line == line) {
+                        rows.push(LineRow { rva, file, line });
+                    }
+                }
+            }
+
+            // The frames are ordered from inside to outside; the last one is the
+            // function itself. The call site of each inlined frame is the location in
+            // the frame outside of it.
+            let mut node = &mut root;
+            for pair in frames.windows(2).rev() {
+                let (inlined, caller) = (&pair[0], &pair[1]);
+                let call_file = files.insert(caller.file.as_deref(), strings);
+                let call_line = caller.line.unwrap_or(0);
+                let name = strings.insert(inlined.function.as_deref().unwrap_or(""));
+                let index = match node.children.iter().position(|child| {
+                    (child.name, child.call_file, child.call_line) == (name, call_file, call_line)
+                }) {
+                    Some(index) => index,
+                    None => {
+                        node.children.push(InlineNode {
+                            name,
+                            call_file,
+                            call_line,
+                            ranges: Vec::new(),
+                            children: Vec::new(),
+                        });
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[index];
+                let range_end = inlined.end_rva.unwrap_or(end_rva).min(end_rva);
+                node.ranges
+                    .push((inlined.start_rva.max(start_rva), range_end));
+            }
+        }
+        root.normalize();
+        Ok((rows, root))
+    }
+}
+
+/// A row of a GSYM line table.
+struct LineRow {
+    rva: u32,
+    file: u32,
+    line: u32,
+}
+
+/// An inlined function in the GSYM inline tree. The root node is the function itself.
+struct InlineNode {
+    name: u32,
+    call_file: u32,
+    call_line: u32,
+    /// `(start_rva, end_rva)` pairs.
+    ranges: Vec<(u32, u32)>,
+    children: Vec<InlineNode>,
+}
+
+impl InlineNode {
+    /// Sort the ranges and merge the adjacent and overlapping ones, recursively.
+    fn normalize(&mut self) {
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(self.ranges.len());
+        for &(start, end) in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        self.ranges = merged;
+        for child in &mut self.children {
+            child.normalize();
+        }
+        self.children
+            .sort_by_key(|child| child.ranges.first().copied());
+    }
+
+    /// Encode this node. The ranges are relative to `base_rva`, and the ranges of the
+    /// children are relative to the start of this node's first range.
+    fn encode(&self, out: &mut Vec<u8>, base_rva: u32) {
+        put_uleb(out, self.ranges.len() as u64);
+        for (start, end) in &self.ranges {
+            put_uleb(out, u64::from(start - base_rva));
+            put_uleb(out, u64::from(end - start));
+        }
+        out.push(!self.children.is_empty() as u8);
+        put_u32(out, self.name);
+        put_uleb(out, u64::from(self.call_file));
+        put_uleb(out, u64::from(self.call_line));
+        if let Some((child_base_rva, _)) = self.ranges.first() {
+            if !self.children.is_empty() {
+                for child in &self.children {
+                    child.encode(out, *child_base_rva);
+                }
+                // A child with zero ranges ends the list.
+                put_uleb(out, 0);
+            }
+        }
+    }
+}
+
+/// Encode the line table of the function at `start_rva`.
+fn encode_line_table(rows: &[LineRow], start_rva: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_sleb(&mut out, MIN_LINE_DELTA);
+    put_sleb(&mut out, MAX_LINE_DELTA);
+    put_uleb(&mut out, u64::from(rows[0].line));
+    let line_range = MAX_LINE_DELTA - MIN_LINE_DELTA + 1;
+    let (mut prev_rva, mut prev_file, mut prev_line) = (start_rva, 1, rows[0].line);
+    for row in rows {
+        if row.file != prev_file {
+            out.push(OP_SET_FILE);
+            put_uleb(&mut out, u64::from(row.file));
+        }
+        let line_delta = i64::from(row.line) - i64::from(prev_line);
+        let address_delta = i64::from(row.rva - prev_rva);
+        let special = (line_delta - MIN_LINE_DELTA)
+            + address_delta * line_range
+            + i64::from(OP_FIRST_SPECIAL);
+        if (MIN_LINE_DELTA..=MAX_LINE_DELTA).contains(&line_delta) && special <= 255 {
+            out.push(special as u8);
+        } else {
+            if line_delta != 0 {
+                out.push(OP_ADVANCE_LINE);
+                put_sleb(&mut out, line_delta);
+            }
+            out.push(OP_ADVANCE_PC);
+            put_uleb(&mut out, address_delta as u64);
+        }
+        (prev_rva, prev_file, prev_line) = (row.rva, row.file, row.line);
+    }
+    out.push(OP_END_SEQUENCE);
+    out
+}
+
+/// The GSYM string table. The first string is the empty string, at offset 0.
+struct StringTable {
+    data: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        Self {
+            data: vec![0],
+            offsets: HashMap::from([(String::new(), 0)]),
+        }
+    }
+}
+
+impl StringTable {
+    fn insert(&mut self, string: &str) -> u32 {
+        if let Some(offset) = self.offsets.get(string) {
+            return *offset;
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(string.as_bytes());
+        self.data.push(0);
+        self.offsets.insert(string.to_string(), offset);
+        offset
+    }
+}
+
+/// The GSYM file table, with the directory and the base name of each file as string
+/// table offsets. The first entry stands for "no file".
+struct FileTable {
+    entries: Vec<(u32, u32)>,
+    indexes: HashMap<(u32, u32), u32>,
+}
+
+impl Default for FileTable {
+    fn default() -> Self {
+        Self {
+            entries: vec![(0, 0)],
+            indexes: HashMap::from([((0, 0), 0)]),
+        }
+    }
+}
+
+impl FileTable {
+    fn insert(&mut self, path: Option<&str>, strings: &mut StringTable) -> u32 {
+        let path = match path {
+            Some(path) => path,
+            None => return 0,
+        };
+        let entry = match path.rfind(['\\', '/']) {
+            Some(pos) => (
+                strings.insert(&path[..pos]),
+                strings.insert(&path[pos + 1..]),
+            ),
+            None => (0, strings.insert(path)),
+        };
+        let entries = &mut self.entries;
+        *self.indexes.entry(entry).or_insert_with(|| {
+            entries.push(entry);
+            entries.len() as u32 - 1
+        })
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_uleb(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn put_sleb(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Pad `out` with zeros to a multiple of `alignment`.
+fn align(out: &mut Vec<u8>, alignment: usize) {
+    let len = out.len().next_multiple_of(alignment);
+    out.resize(len, 0);
+}
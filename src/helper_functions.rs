@@ -0,0 +1,114 @@
+use crate::{Context, Result, Rva32};
+
+/// A well-known compiler or C runtime helper function, see
+/// [`Context::helper_function_kind`].
+///
+/// Samples in these functions are usually attributed to the caller by profilers,
+/// because they are called implicitly by compiler-generated code, or because they
+/// are small leaf functions that would otherwise show up as hot functions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HelperFunctionKind {
+    /// A Control Flow Guard check or dispatch function, e.g.
+    /// `__guard_dispatch_icall_fptr` or `_guard_check_icall_nop`.
+    ControlFlowGuard,
+    /// A stack buffer overrun check, e.g. `__security_check_cookie` or
+    /// `__GSHandlerCheck`.
+    SecurityCheck,
+    /// A stack probe which touches the pages of a large stack frame, e.g.
+    /// `__chkstk` or `_alloca_probe_16`.
+    StackProbe,
+    /// A memory copy, move, fill or compare routine, including the internal helpers
+    /// that their fast paths jump to, e.g. `memcpy`, `memset_repmovs` or
+    /// `MoveSmall`.
+    MemoryRoutine,
+}
+
+impl HelperFunctionKind {
+    /// Classify a function by its name. The name can be the undecorated name, with
+    /// or without an argument list, or the decorated name of a C function, e.g.
+    /// `@__security_check_cookie@4`. C++ names with a namespace are never helpers.
+    pub fn from_function_name(name: &str) -> Option<Self> {
+        let name = match name.find('(') {
+            Some(pos) => &name[..pos],
+            None => name,
+        };
+        let name = name.trim_start_matches(['_', '@']);
+        // Remove the argument size suffix of __stdcall and __fastcall names.
+        let name = match name.rfind('@') {
+            Some(pos) if name[pos + 1..].bytes().all(|b| b.is_ascii_digit()) => &name[..pos],
+            _ => name,
+        };
+
+        const CONTROL_FLOW_GUARD_PREFIXES: &[&str] = &[
+            "guard_dispatch_icall",
+            "guard_check_icall",
+            "guard_xfg_dispatch_icall",
+            "guard_xfg_check_icall",
+            "guard_xfg_table_dispatch_icall",
+        ];
+        const SECURITY_CHECK_PREFIXES: &[&str] = &[
+            "security_check_cookie",
+            "GSHandlerCheck",
+            "report_gsfailure",
+            "report_rangecheckfailure",
+        ];
+        const STACK_PROBES: &[&str] = &[
+            "chkstk",
+            "chkstk_ms",
+            "alloca_probe",
+            "alloca_probe_8",
+            "alloca_probe_16",
+        ];
+        const MEMORY_ROUTINES: &[&str] = &[
+            "memcpy",
+            "memmove",
+            "memset",
+            "memcmp",
+            "memchr",
+            "memcpy_repmovs",
+            "memmove_repmovs",
+            "memset_repmovs",
+            "MoveSmall",
+            "MoveSmallReverse",
+            "FillSmall",
+            "RtlCopyMemory",
+            "RtlMoveMemory",
+            "RtlFillMemory",
+            "RtlZeroMemory",
+        ];
+
+        if CONTROL_FLOW_GUARD_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            Some(Self::ControlFlowGuard)
+        } else if SECURITY_CHECK_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            Some(Self::SecurityCheck)
+        } else if STACK_PROBES.contains(&name) {
+            Some(Self::StackProbe)
+        } else if MEMORY_ROUTINES.contains(&name) {
+            Some(Self::MemoryRoutine)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Classify the function which contains the provided address as a well-known
+    /// compiler or C runtime helper, based on its name. Returns `Ok(None)` if the
+    /// function isn't a helper or if no function contains the address.
+    ///
+    /// Profilers can use this to fold these functions into their callers, or to tag
+    /// them, instead of showing them as hot leaf functions.
+    pub fn helper_function_kind(&self, probe: Rva32) -> Result<Option<HelperFunctionKind>> {
+        Ok(self
+            .find_function_at(probe)?
+            .and_then(|function| function.name)
+            .and_then(|name| HelperFunctionKind::from_function_name(&name)))
+    }
+}
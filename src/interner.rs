@@ -0,0 +1,76 @@
+use pdb::Rva;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::{Context, FunctionFrames, LookupOptions, Result, Rva32};
+
+/// A string interner which can be shared by the contexts of many PDBs, see
+/// [`Context::find_frames_interned`].
+///
+/// Services which symbolicate with hundreds of PDBs at once see the same names
+/// over and over, e.g. the functions of the C runtime and the standard library.
+/// With a shared interner, each distinct name is stored once per process instead
+/// of once per PDB or per frame. Clones share the same strings, and the interner
+/// can be used from several threads.
+///
+/// Strings are never removed, so the interner grows with the number of distinct
+/// names; drop it and create a new one to release them.
+#[derive(Clone, Debug, Default)]
+pub struct StringInterner {
+    strings: Arc<Mutex<HashSet<Arc<str>>>>,
+}
+
+impl StringInterner {
+    /// Create an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared copy of `string`, adding it if it isn't in the interner
+    /// yet. Interning a string which is already in the interner doesn't allocate.
+    ///
+    /// Frames borrow their file names from the PDB, so consumers which keep frames
+    /// after the PDB is closed can use this to store the file names once, too.
+    pub fn intern(&self, string: &str) -> Arc<str> {
+        let mut strings = self.strings.lock().unwrap();
+        if let Some(stored) = strings.get(string) {
+            return stored.clone();
+        }
+        let stored: Arc<str> = Arc::from(string);
+        strings.insert(stored.clone());
+        stored
+    }
+
+    /// The number of distinct strings in the interner.
+    pub fn len(&self) -> usize {
+        self.strings.lock().unwrap().len()
+    }
+
+    /// Whether the interner is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Like [`Context::find_frames_at`], but the function names in the result are
+    /// shared copies from `interner`. Unlike a [`StringArena`](crate::StringArena),
+    /// the interner isn't tied to this context, so the same interner can be used
+    /// with the contexts of many PDBs, and the names outlive them.
+    pub fn find_frames_interned(
+        &self,
+        probe: Rva32,
+        interner: &StringInterner,
+    ) -> Result<Option<FunctionFrames<'_, Arc<str>>>> {
+        let offset = match Rva(probe.0).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let offset = self.resolve_incremental_linking_thunk_if_requested(offset);
+        let result = self.find_frames_impl(offset, None, &LookupOptions::default(), &mut |name| {
+            interner.intern(name)
+        });
+        self.enforce_memory_budget();
+        self.apply_non_utf8_handling_to_frames(result?)
+    }
+}
@@ -0,0 +1,182 @@
+use pdb::{FileIndex, LineInfo, LineInfoKind, PdbInternalSectionOffset, RawString};
+
+/// The line information of a module in the legacy C11 format, which was written by
+/// compilers up to Visual C++ 7. The pdb crate only reads the current C13 format.
+///
+/// The data is a table of source files. Each file has one block of line records
+/// per section that it has code in. A line record is the section offset at which
+/// the code for the line starts, and the line number.
+#[derive(Default)]
+pub(crate) struct LegacyLineProgram<'a> {
+    file_names: Vec<RawString<'a>>,
+    /// Sorted by section and offset.
+    lines: Vec<LineInfo>,
+}
+
+impl<'a> LegacyLineProgram<'a> {
+    /// Parse the C11 line data of a module, which is stored in the module's stream
+    /// right after the symbols.
+    pub fn parse(data: &'a [u8]) -> Result<Self, pdb::Error> {
+        // The layout is:
+        // file count: u16, section count: u16, file offsets: [u32; file count],
+        // followed by the section ranges, which we don't need.
+        // Each file, at its offset from the start of the data:
+        // block count: u16, padding: u16, block offsets: [u32; block count],
+        // block ranges: [(start: u32, end: u32); block count], name length: u8,
+        // name: [u8; name length]
+        // Each block, at its offset from the start of the data:
+        // section: u16, line count: u16, offsets: [u32; line count],
+        // line numbers: [u16; line count]
+        let file_count = u16_at(data, 0)?;
+        let mut file_names = Vec::with_capacity(file_count as usize);
+        let mut lines = Vec::new();
+        for file_index in 0..file_count as usize {
+            let file_start = u32_at(data, 4 + file_index * 4)? as usize;
+            let block_count = u16_at(data, file_start)? as usize;
+            let block_offsets_start = file_start + 4;
+            let block_ranges_start = block_offsets_start + block_count * 4;
+            let name_start = block_ranges_start + block_count * 8;
+            let name_len = *data.get(name_start).ok_or(pdb::Error::UnexpectedEof)? as usize;
+            let name = data
+                .get(name_start + 1..name_start + 1 + name_len)
+                .ok_or(pdb::Error::UnexpectedEof)?;
+            file_names.push(RawString::from(name));
+
+            for block_index in 0..block_count {
+                let block_start = u32_at(data, block_offsets_start + block_index * 4)? as usize;
+                // The end of the block's range is the offset of its last byte.
+                let block_end = u32_at(data, block_ranges_start + block_index * 8 + 4)?;
+                let section = u16_at(data, block_start)?;
+                let line_count = u16_at(data, block_start + 2)? as usize;
+                let offsets_start = block_start + 4;
+                let line_numbers_start = offsets_start + line_count * 4;
+                let first_line = lines.len();
+                for line_index in 0..line_count {
+                    let offset = u32_at(data, offsets_start + line_index * 4)?;
+                    let line = u16_at(data, line_numbers_start + line_index * 2)? as u32;
+                    lines.push(LineInfo {
+                        offset: PdbInternalSectionOffset { offset, section },
+                        length: None,
+                        file_index: FileIndex(file_index as u32),
+                        line_start: line,
+                        line_end: line,
+                        column_start: None,
+                        column_end: None,
+                        kind: LineInfoKind::Statement,
+                    });
+                }
+                // Each line extends up to the next line of the block, and the last
+                // line to the end of the block.
+                let block_lines = &mut lines[first_line..];
+                block_lines.sort_by_key(|line| line.offset.offset);
+                let mut next_start = block_end.saturating_add(1);
+                for line in block_lines.iter_mut().rev() {
+                    line.length = Some(next_start.saturating_sub(line.offset.offset));
+                    next_start = line.offset.offset;
+                }
+            }
+        }
+        lines.sort_by_key(|line| (line.offset.section, line.offset.offset));
+        Ok(Self { file_names, lines })
+    }
+
+    /// The lines of the function which starts at `offset` and has the length `len`.
+    /// If the length is not known, this returns the lines up to the end of the
+    /// section.
+    pub fn lines_for_symbol(
+        &self,
+        offset: PdbInternalSectionOffset,
+        len: Option<u32>,
+    ) -> Vec<LineInfo> {
+        let end = len.map_or(u32::MAX, |len| offset.offset.saturating_add(len));
+        let start_index = self.lines.partition_point(|line| {
+            (line.offset.section, line.offset.offset) < (offset.section, offset.offset)
+        });
+        self.lines[start_index..]
+            .iter()
+            .take_while(|line| line.offset.section == offset.section && line.offset.offset < end)
+            .cloned()
+            .collect()
+    }
+
+    /// The name of the file with the index `file_index`.
+    pub fn file_name(&self, file_index: FileIndex) -> Option<RawString<'a>> {
+        self.file_names.get(file_index.0 as usize).copied()
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Result<u16, pdb::Error> {
+    let bytes = data.get(pos..pos + 2).ok_or(pdb::Error::UnexpectedEof)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Result<u32, pdb::Error> {
+    let bytes = data.get(pos..pos + 4).ok_or(pdb::Error::UnexpectedEof)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build the C11 line data for one file with one block of `(offset, line)`
+    /// records in section 1, which ends at `block_end`.
+    fn line_data(name: &[u8], lines: &[(u32, u16)], block_end: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        // Header: one file, one section, the offset of the file.
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&8u32.to_le_bytes());
+        // File: one block, padding, the block's offset and range, the name.
+        let block_start = 8 + 4 + 4 + 8 + 1 + name.len() as u32;
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&block_start.to_le_bytes());
+        data.extend_from_slice(&lines[0].0.to_le_bytes());
+        data.extend_from_slice(&block_end.to_le_bytes());
+        data.push(name.len() as u8);
+        data.extend_from_slice(name);
+        // Block: section, line count, offsets, line numbers.
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&(lines.len() as u16).to_le_bytes());
+        for (offset, _) in lines {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        for (_, line) in lines {
+            data.extend_from_slice(&line.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn parse_length_prefixed_file_name() {
+        let data = line_data(b"c:\\src\\main.c", &[(0x10, 5), (0x20, 7)], 0x2f);
+        let program = LegacyLineProgram::parse(&data).unwrap();
+        assert_eq!(
+            program.file_name(FileIndex(0)).unwrap().as_bytes(),
+            b"c:\\src\\main.c"
+        );
+        assert!(program.file_name(FileIndex(1)).is_none());
+
+        let start = PdbInternalSectionOffset {
+            section: 1,
+            offset: 0x10,
+        };
+        let lines = program.lines_for_symbol(start, None);
+        let lines: Vec<_> = lines
+            .iter()
+            .map(|line| (line.offset.offset, line.length, line.line_start))
+            .collect();
+        assert_eq!(lines, [(0x10, Some(0x10), 5), (0x20, Some(0x10), 7)]);
+        assert_eq!(program.lines_for_symbol(start, Some(0x10)).len(), 1);
+    }
+
+    #[test]
+    fn parse_truncated_file_name() {
+        let mut data = line_data(b"main.c", &[(0x10, 5)], 0x1f);
+        // The name length byte claims more bytes than the data has.
+        let name_len_pos = 8 + 4 + 4 + 8;
+        data[name_len_pos] = 0xff;
+        assert!(LegacyLineProgram::parse(&data).is_err());
+    }
+}
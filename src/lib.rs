@@ -14,16 +14,16 @@
 //! # Example
 //!
 //! ```
-//! use pdb_addr2line::pdb; // (this is a re-export of the pdb crate)
+//! use pdb_addr2line::{pdb, Rva32}; // (pdb is a re-export of the pdb crate)
 //!
-//! fn look_up_addresses<'s, S: pdb::Source<'s> + 's>(stream: S, addresses: &[u32]) -> std::result::Result<(), pdb_addr2line::Error> {
+//! fn look_up_addresses<'s, S: pdb::Source<'s> + 's>(stream: S, addresses: &[Rva32]) -> std::result::Result<(), pdb_addr2line::Error> {
 //!     let pdb = pdb::PDB::open(stream)?;
 //!     let context_data = pdb_addr2line::ContextPdbData::try_from_pdb(pdb)?;
 //!     let context = context_data.make_context()?;
 //!
 //!     for address in addresses {
-//!         if let Some(procedure_frames) = context.find_frames(*address)? {
-//!             eprintln!("0x{:x} - {} frames:", address, procedure_frames.frames.len());
+//!         if let Some(procedure_frames) = context.find_frames_at(*address)? {
+//!             eprintln!("0x{:x} - {} frames:", address.0, procedure_frames.frames.len());
 //!             for frame in procedure_frames.frames {
 //!                 let line_str = frame.line.map(|l| format!("{}", l));
 //!                 eprintln!(
@@ -34,7 +34,7 @@
 //!                 )
 //!             }
 //!         } else {
-//!             eprintln!("{:x} - no frames found", address);
+//!             eprintln!("{:x} - no frames found", address.0);
 //!         }
 //!     }
 //!     Ok(())
@@ -42,32 +42,100 @@
 //! ```
 
 pub use maybe_owned;
+#[cfg(feature = "demangle")]
+pub use msvc_demangler;
 pub use pdb;
+pub use uuid;
 
+mod address;
+mod cache_snapshot;
+mod compact_module;
 mod constants;
+mod dbghelp;
 mod error;
+mod file_path;
+mod frame_template;
+mod function_name;
+mod generated_functions;
+mod gsym;
+mod helper_functions;
+mod interner;
+mod legacy_lines;
+mod map_file;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "pe")]
+mod pe_exports;
+mod profiler_symbols;
+mod prologue;
+#[cfg(feature = "range-source")]
+mod range_source;
+mod raw_streams;
+mod source_files;
+mod stack;
+mod symbol_path;
+mod trampolines;
 mod type_formatter;
+mod unwind_info;
 
+pub use address::{FileOffset, Rva32, Va64};
+pub use cache_snapshot::CacheSnapshot;
+pub use compact_module::CompactModule;
+pub use dbghelp::{LineFromAddress, SymbolFromAddress};
 pub use error::Error;
+pub use frame_template::FrameTemplate;
+pub use function_name::{parse_function_name, FunctionNameLayout, FunctionNameParts};
+pub use generated_functions::GeneratedFunctionKind;
+pub use helper_functions::HelperFunctionKind;
+pub use interner::StringInterner;
+#[cfg(feature = "rayon")]
+pub use parallel::par_functions;
+#[cfg(feature = "pe")]
+pub use pe_exports::PeExports;
+pub use profiler_symbols::CompactSymbolTable;
+#[cfg(feature = "range-source")]
+pub use range_source::{RangeReader, RangeSource};
+pub use source_files::FunctionSourceFile;
+pub use stack::StackFrame;
+pub use symbol_path::{SymbolLocation, SymbolPath, SymbolPathEntry};
+pub use trampolines::{Trampoline, TrampolineKind};
 pub use type_formatter::*;
+pub use unwind_info::{RuntimeFunction, UnwindIssue};
 
+use bitflags::bitflags;
+use cache_snapshot::{ModuleProcedures, SnapshotProcedure};
 use constants::*;
 use elsa::FrozenMap;
+use legacy_lines::LegacyLineProgram;
 use maybe_owned::{MaybeOwned, MaybeOwnedMut};
 use pdb::{
-    AddressMap, DebugInformation, FallibleIterator, FileIndex, IdIndex, IdInformation,
-    ImageSectionHeader, InlineSiteSymbol, Inlinee, LineProgram, Module, ModuleInfo,
-    PdbInternalSectionOffset, PublicSymbol, RawString, Rva, Source, StringTable, SymbolData,
-    SymbolIndex, SymbolIter, SymbolTable, TypeIndex, TypeInformation, PDB,
+    AddressMap, BinaryAnnotation, DebugInformation, FallibleIterator, FileChecksum, FileIndex,
+    IdIndex, IdInformation, ImageSectionHeader, InlineSiteSymbol, Inlinee, LineInfo, LineProgram,
+    Module, ModuleInfo, PdbInternalRva, PdbInternalSectionOffset, PublicSymbol, RawString, Rva,
+    Source, SourceSlice, StreamIndex, StringTable, SymbolData, SymbolIndex, SymbolIter,
+    SymbolTable, TypeIndex, TypeInformation, PDB,
 };
 use range_collections::{AbstractRangeSet, RangeSet, RangeSet2};
+use raw_streams::{
+    dbi_module_entries, dbi_version, pdb_info_features, pdb_info_version, DBI_STREAM_INDEX,
+    FEATURE_MINIMAL_DEBUG_INFO, PDB_INFO_STREAM_INDEX,
+};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::LowerHex;
 use std::mem;
-use std::ops::Bound;
+use std::ops::{Bound, Range};
 use std::rc::Rc;
-use std::{borrow::Cow, cell::RefCell, collections::BTreeMap};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+};
+use trampolines::{find_trampoline, trampoline_records, TrampolineRecord};
+use uuid::Uuid;
 
 type Result<V> = std::result::Result<V, Error>;
 
@@ -97,6 +165,16 @@ pub struct ContextPdbData<'p, 's, S: Source<'s> + 's> {
     /// Context can internally store objects which have a lifetime dependency on
     /// ModuleInfo, such as Inlinees, LinePrograms, and RawStrings from modules.
     module_infos: FrozenMap<usize, Box<ModuleInfo<'s>>>,
+    /// The C11 line data of the modules which have it, see
+    /// [`ModuleProvider::get_legacy_line_data`].
+    legacy_line_data: FrozenMap<usize, Vec<u8>>,
+    /// The modules which were extracted for [`ContextFlags::COMPACT_MODULES`], or
+    /// `None` for modules which can't be compacted. See
+    /// [`ModuleProvider::get_compact_module`].
+    compact_modules: FrozenMap<usize, Box<Option<CompactModule>>>,
+    /// Which debug information each module has, read from the DBI stream on first
+    /// use. See [`ModuleProvider::module_debug_info`].
+    modules_with_debug_info: RefCell<Option<Vec<ModuleDebugInfo>>>,
 
     address_map: AddressMap<'s>,
     string_table: Option<StringTable<'s>>,
@@ -104,9 +182,115 @@ pub struct ContextPdbData<'p, 's, S: Source<'s> + 's> {
     debug_info: DebugInformation<'s>,
     type_info: TypeInformation<'s>,
     id_info: IdInformation<'s>,
+    debug_id_match: DebugIdMatch,
+    /// The GUID and the age of the PDB, if the PDB information stream could be read.
+    pdb_identity: Option<(Uuid, u32)>,
+    stream_parse_time: Duration,
+}
+
+/// The result of comparing the identity of a PDB with the identity that the image
+/// expects, see [`ContextPdbData::verify_debug_id`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugIdMatch {
+    /// The PDB's identity was not checked.
+    Unchecked,
+    /// The GUID and the age match.
+    Verified,
+    /// The GUID matches but the age doesn't. This is only accepted with
+    /// [`AgeMismatchPolicy::BestEffort`]. Results from such a PDB are usually correct
+    /// but are not guaranteed to match the image.
+    AgeMismatch {
+        /// The age that the image expects.
+        expected: u32,
+        /// The age of the PDB.
+        actual: u32,
+    },
+}
+
+impl DebugIdMatch {
+    /// Whether the PDB was accepted despite not matching the image exactly.
+    pub fn is_unverified(&self) -> bool {
+        matches!(self, DebugIdMatch::AgeMismatch { .. })
+    }
+}
+
+/// What [`ContextPdbData::verify_debug_id`] should do if the PDB's GUID matches but
+/// its age doesn't.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AgeMismatchPolicy {
+    /// Return [`Error::AgeMismatch`].
+    Reject,
+    /// Accept the PDB, and mark the contexts created from it, and their results, as
+    /// unverified. PDBs which were rebuilt without code changes often only differ in
+    /// their age, and this is sometimes the only PDB that is available.
+    BestEffort,
+}
+
+/// The parsed line programs and inlinee tables of modules, shared between the
+/// [`Context`]s which are created with [`SharedModuleCache::make_context`]. Without
+/// this, each context parses this information again, e.g. when contexts with
+/// different [`TypeFormatterFlags`] are used side by side.
+///
+/// The cache is made by [`ContextPdbData::make_shared_module_cache`] and borrows
+/// the [`ContextPdbData`], so all of its contexts are created from the same PDB.
+///
+/// The shared information is kept until the cache is dropped; it is not affected
+/// by [`Context::set_memory_budget`] or [`Context::release_module`].
+pub struct SharedModuleCache<'a, 's> {
+    /// The [`ContextPdbData`] which made this cache.
+    data: &'a dyn MakeContext<'s>,
+    modules: RefCell<HashMap<usize, Rc<ExtendedModuleInfo<'a, 's>>>>,
+}
+
+/// Creates the contexts of a [`SharedModuleCache`], without knowing the type of the
+/// [`ContextPdbData`]'s source.
+trait MakeContext<'s> {
+    fn make_context_with_flags(
+        &self,
+        formatter_flags: TypeFormatterFlags,
+        context_flags: ContextFlags,
+    ) -> Result<Context<'_, 's>>;
+}
+
+impl<'a, 's> SharedModuleCache<'a, 's> {
+    /// Create a [`Context`] like [`ContextPdbData::make_context_with_flags`], which
+    /// shares the parsed module information in this cache with the other contexts
+    /// that were created from it.
+    pub fn make_context(
+        &'a self,
+        formatter_flags: TypeFormatterFlags,
+        context_flags: ContextFlags,
+    ) -> Result<Context<'a, 's>> {
+        let data = self.data;
+        let mut context = data.make_context_with_flags(formatter_flags, context_flags)?;
+        context.shared_module_cache = Some(self);
+        Ok(context)
+    }
+
+    /// The number of modules whose information is in the cache.
+    pub fn len(&self) -> usize {
+        self.modules.borrow().len()
+    }
+
+    /// Whether the cache has no module information yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl<'p, 's, S: Source<'s> + 's> ContextPdbData<'p, 's, S> {
+    /// Open the PDB file in `source` and create a [`ContextPdbData`] from it.
+    ///
+    /// Unlike [`pdb::PDB::open`], this recognizes Portable PDB files, which are used
+    /// for .NET assemblies and which this crate doesn't support, and returns
+    /// [`Error::PortablePdb`] for them instead of an unspecific format error.
+    pub fn open(mut source: S) -> Result<Self> {
+        if is_portable_pdb(&mut source) {
+            return Err(Error::PortablePdb);
+        }
+        Self::try_from_pdb(PDB::open(source)?)
+    }
+
     /// Create a [`ContextPdbData`] from a [`PDB`](pdb::PDB). This parses many of the PDB
     /// streams and stores them in the [`ContextPdbData`].
     /// This creator function takes ownership of the pdb object and never gives it back.
@@ -123,25 +307,186 @@ impl<'p, 's, S: Source<'s> + 's> ContextPdbData<'p, 's, S> {
     }
 
     fn try_from_maybe_owned(mut pdb: MaybeOwnedMut<'p, PDB<'s, S>>) -> Result<Self> {
-        let global_symbols = pdb.global_symbols()?;
-        let debug_info = pdb.debug_information()?;
-        let type_info = pdb.type_information()?;
-        let id_info = pdb.id_information()?;
-        let address_map = pdb.address_map()?;
+        let start = Instant::now();
+        let global_symbols = pdb
+            .global_symbols()
+            .map_err(Error::stream("global symbols"))?;
+        let debug_info = pdb.debug_information().map_err(Error::stream("DBI"))?;
+        let type_info = pdb.type_information().map_err(Error::stream("TPI"))?;
+        let id_info = pdb.id_information().map_err(Error::stream("IPI"))?;
+        let address_map = pdb.address_map().map_err(Error::stream("address map"))?;
         let string_table = pdb.string_table().ok();
+        // Use the same age as verify_debug_id.
+        let pdb_identity = pdb
+            .pdb_information()
+            .ok()
+            .map(|info| (info.guid, debug_info.age().unwrap_or(info.age)));
 
         Ok(Self {
             pdb: RefCell::new(pdb),
             module_infos: FrozenMap::new(),
+            legacy_line_data: FrozenMap::new(),
+            compact_modules: FrozenMap::new(),
+            modules_with_debug_info: RefCell::new(None),
             global_symbols,
             debug_info,
             type_info,
             id_info,
             address_map,
             string_table,
+            debug_id_match: DebugIdMatch::Unchecked,
+            pdb_identity,
+            stream_parse_time: start.elapsed(),
+        })
+    }
+
+    /// The time spent so far in each phase of reading the PDB. Only
+    /// [`Timings::stream_parse`] is filled in here; the other phases happen in the
+    /// [`Context`], see [`Context::timings`].
+    pub fn timings(&self) -> Timings {
+        Timings {
+            stream_parse: self.stream_parse_time,
+            ..Default::default()
+        }
+    }
+
+    /// The names of the named streams in this PDB, in the order in which they are
+    /// listed in the PDB information stream. Besides the standard streams like
+    /// `/names` and `/LinkInfo`, this includes custom streams which were added by
+    /// build tooling.
+    pub fn stream_names(&self) -> Result<Vec<String>> {
+        let pdb_information = self
+            .pdb
+            .borrow_mut()
+            .pdb_information()
+            .map_err(Error::stream("PDB information"))?;
+        let stream_names = pdb_information
+            .stream_names()
+            .map_err(Error::stream("PDB information"))?;
+        Ok(stream_names
+            .iter()
+            .map(|stream_name| stream_name.name.to_string().into_owned())
+            .collect())
+    }
+
+    /// Read the contents of the named stream `name`, see
+    /// [`ContextPdbData::stream_names`]. Returns `None` if the PDB has no stream with
+    /// this name.
+    pub fn named_stream(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match self.pdb.borrow_mut().named_stream(name.as_bytes()) {
+            Ok(stream) => Ok(Some(stream.as_slice().to_vec())),
+            Err(pdb::Error::StreamNameNotFound) => Ok(None),
+            Err(e) => Err(Error::stream(name.to_owned())(e)),
+        }
+    }
+
+    /// Summarize the format and the contents of this PDB, so that consumers can
+    /// decide whether it is worth processing. This only reads the stream headers and
+    /// the module list, not the module streams.
+    pub fn capabilities(&self) -> Result<Capabilities> {
+        let mut pdb = self.pdb.borrow_mut();
+        let pdb_information = pdb
+            .pdb_information()
+            .map_err(Error::stream("PDB information"))?;
+        let info = pdb
+            .raw_stream(StreamIndex(PDB_INFO_STREAM_INDEX))
+            .map_err(Error::stream("PDB information"))?;
+        let info = info.as_ref().map(|info| info.as_slice()).unwrap_or(&[]);
+        let names_end = pdb_information.names_offset + pdb_information.names_size;
+        let features = pdb_info_features(info, names_end).unwrap_or_default();
+        let dbi = pdb
+            .raw_stream(StreamIndex(DBI_STREAM_INDEX))
+            .map_err(Error::stream("DBI"))?;
+        let dbi = dbi.as_ref().map(|dbi| dbi.as_slice()).unwrap_or(&[]);
+        let modules = dbi_module_entries(dbi).unwrap_or_default();
+        // The symbols of a module start with a 4 byte signature.
+        let modules_with_symbols = modules
+            .iter()
+            .filter(|module| module.stream().is_some() && module.symbols_size > 4);
+
+        Ok(Capabilities {
+            pdb_version: pdb_info_version(info).unwrap_or(0),
+            dbi_version: dbi_version(dbi).unwrap_or(0),
+            has_line_info: modules.iter().any(|module| {
+                module.stream().is_some() && (module.lines_size != 0 || module.c13_lines_size != 0)
+            }),
+            has_ipi: !self.id_info.is_empty(),
+            publics_only: modules_with_symbols.count() == 0,
+            is_fastlink: features.contains(&FEATURE_MINIMAL_DEBUG_INFO),
         })
     }
 
+    /// Check this PDB for problems which make lookups return less information, e.g.
+    /// to reject broken PDBs in a symbol pipeline before they are published. Unlike
+    /// [`ContextPdbData::capabilities`], this reads all module streams, so it is
+    /// slow for large PDBs.
+    ///
+    /// Errors are only returned if the PDB can't be read at all; everything else is
+    /// reported in the [`ValidationReport`].
+    pub fn validate(&self) -> Result<ValidationReport> {
+        let mut issues = Vec::new();
+        if self.pdb_identity.is_none() {
+            issues.push(ValidationIssue::MissingStream {
+                name: "PDB information",
+            });
+        }
+        if self.string_table.is_none() {
+            issues.push(ValidationIssue::MissingStream { name: "/names" });
+        }
+        if !matches!(self.pdb.borrow_mut().sections(), Ok(Some(_))) {
+            issues.push(ValidationIssue::MissingStream {
+                name: "section headers",
+            });
+        }
+        let context = self.make_context()?;
+        context.collect_validation_issues(&mut issues)?;
+        Ok(ValidationReport { issues })
+    }
+
+    /// Check that this PDB belongs to the image which references it with
+    /// `expected_guid` and `expected_age`, e.g. from the image's CodeView debug
+    /// directory entry.
+    ///
+    /// A GUID mismatch is always an error. An age mismatch is handled according to
+    /// `age_mismatch_policy`. The outcome is remembered and passed on to the contexts
+    /// which are created afterwards, see [`Context::debug_id_match`].
+    pub fn verify_debug_id(
+        &mut self,
+        expected_guid: Uuid,
+        expected_age: u32,
+        age_mismatch_policy: AgeMismatchPolicy,
+    ) -> Result<DebugIdMatch> {
+        let pdb_information = self
+            .pdb
+            .get_mut()
+            .pdb_information()
+            .map_err(Error::stream("PDB information"))?;
+        if pdb_information.guid != expected_guid {
+            return Err(Error::GuidMismatch {
+                expected: expected_guid,
+                actual: pdb_information.guid,
+            });
+        }
+        // The age in the DBI stream is the one which matches the image; the age in the
+        // PDB information stream is incremented each time the PDB is written.
+        let actual_age = self.debug_info.age().unwrap_or(pdb_information.age);
+        let debug_id_match = if actual_age == expected_age {
+            DebugIdMatch::Verified
+        } else if age_mismatch_policy == AgeMismatchPolicy::BestEffort {
+            DebugIdMatch::AgeMismatch {
+                expected: expected_age,
+                actual: actual_age,
+            }
+        } else {
+            return Err(Error::AgeMismatch {
+                expected: expected_age,
+                actual: actual_age,
+            });
+        };
+        self.debug_id_match = debug_id_match;
+        Ok(debug_id_match)
+    }
+
     /// Create a [`TypeFormatter`]. This uses the default [`TypeFormatter`] settings.
     pub fn make_type_formatter(&self) -> Result<TypeFormatter<'_, 's>> {
         self.make_type_formatter_with_flags(Default::default())
@@ -157,7 +502,11 @@ impl<'p, 's, S: Source<'s> + 's> ContextPdbData<'p, 's, S> {
         // that's when the actual module stream is read. We use the list of modules so
         // that we can call pdb.module_info with the right module, which we look up based
         // on its module_index.
-        let modules = self.debug_info.modules()?.collect::<Vec<_>>()?;
+        let modules = self
+            .debug_info
+            .modules()
+            .and_then(|modules| modules.collect::<Vec<_>>())
+            .map_err(Error::stream("DBI module list"))?;
 
         Ok(TypeFormatter::new_from_parts(
             self,
@@ -180,10 +529,23 @@ impl<'p, 's, S: Source<'s> + 's> ContextPdbData<'p, 's, S> {
         &self,
         flags: TypeFormatterFlags,
     ) -> Result<Context<'_, 's>> {
-        let type_formatter = self.make_type_formatter_with_flags(flags)?;
-        let sections = self.pdb.borrow_mut().sections()?;
+        self.make_context_with_flags(flags, Default::default())
+    }
+
+    /// Create a [`Context`], using the specified [`TypeFormatterFlags`] and [`ContextFlags`].
+    pub fn make_context_with_flags(
+        &self,
+        formatter_flags: TypeFormatterFlags,
+        context_flags: ContextFlags,
+    ) -> Result<Context<'_, 's>> {
+        let type_formatter = self.make_type_formatter_with_flags(formatter_flags)?;
+        let sections = self
+            .pdb
+            .borrow_mut()
+            .sections()
+            .map_err(Error::stream("section headers"))?;
 
-        Context::new_from_parts(
+        let mut context = Context::new_from_parts(
             self,
             sections.as_deref().unwrap_or(&[]),
             &self.address_map,
@@ -191,7 +553,33 @@ impl<'p, 's, S: Source<'s> + 's> ContextPdbData<'p, 's, S> {
             self.string_table.as_ref(),
             &self.debug_info,
             MaybeOwned::Owned(type_formatter),
-        )
+        )?;
+        context.set_flags(context_flags);
+        context.set_debug_id_match(self.debug_id_match);
+        if let Some((guid, age)) = self.pdb_identity {
+            context.set_pdb_identity(guid, age);
+        }
+        context.timings.get_mut().stream_parse = self.stream_parse_time;
+        Ok(context)
+    }
+
+    /// Create an empty [`SharedModuleCache`] for the contexts of this PDB, see
+    /// [`SharedModuleCache::make_context`].
+    pub fn make_shared_module_cache(&self) -> SharedModuleCache<'_, 's> {
+        SharedModuleCache {
+            data: self,
+            modules: Default::default(),
+        }
+    }
+}
+
+impl<'p, 's, S: Source<'s> + 's> MakeContext<'s> for ContextPdbData<'p, 's, S> {
+    fn make_context_with_flags(
+        &self,
+        formatter_flags: TypeFormatterFlags,
+        context_flags: ContextFlags,
+    ) -> Result<Context<'_, 's>> {
+        ContextPdbData::make_context_with_flags(self, formatter_flags, context_flags)
     }
 }
 
@@ -211,6 +599,129 @@ impl<'p, 's, S: Source<'s> + 's> ModuleProvider<'s> for ContextPdbData<'p, 's, S
                 .insert(module_index, Box::new(module_info))
         }))
     }
+
+    fn get_compact_module(&self, module_index: usize, module: &Module) -> Option<&CompactModule> {
+        if let Some(compact_module) = self.compact_modules.get(&module_index) {
+            return compact_module.as_ref();
+        }
+        // If the stream was already read for something else, it stays anyway.
+        // Otherwise, read it only for the extraction.
+        let compact_module = match self.module_infos.get(&module_index) {
+            Some(module_info) => CompactModule::new(module_info),
+            None => {
+                let module_info = self.pdb.borrow_mut().module_info(module);
+                module_info
+                    .ok()
+                    .flatten()
+                    .and_then(|module_info| CompactModule::new(&module_info))
+            }
+        };
+        self.compact_modules
+            .insert(module_index, Box::new(compact_module))
+            .as_ref()
+    }
+
+    fn get_legacy_line_data(
+        &self,
+        module_index: usize,
+        _module: &Module,
+    ) -> std::result::Result<Option<&[u8]>, pdb::Error> {
+        if let Some(data) = self.legacy_line_data.get(&module_index) {
+            return Ok(Some(data));
+        }
+
+        // The pdb crate doesn't expose the module's stream or the size of the C11
+        // line data, so read them from the module's entry in the DBI stream.
+        let mut pdb = self.pdb.borrow_mut();
+        let dbi = match pdb.raw_stream(StreamIndex(DBI_STREAM_INDEX))? {
+            Some(dbi) => dbi,
+            None => return Ok(None),
+        };
+        let entries = dbi_module_entries(dbi.as_slice()).ok_or(pdb::Error::UnexpectedEof)?;
+        let entry = entries.get(module_index).ok_or(pdb::Error::UnexpectedEof)?;
+        let stream_index = match entry.stream() {
+            Some(stream_index) if entry.lines_size != 0 => stream_index,
+            _ => return Ok(None),
+        };
+        let stream = match pdb.raw_stream(StreamIndex(stream_index))? {
+            Some(stream) => stream,
+            None => return Ok(None),
+        };
+        let (start, len) = (entry.symbols_size, entry.lines_size);
+        let data = stream
+            .as_slice()
+            .get(start..start + len)
+            .ok_or(pdb::Error::UnexpectedEof)?;
+        Ok(Some(
+            self.legacy_line_data.insert(module_index, data.to_vec()),
+        ))
+    }
+
+    fn has_debug_info(
+        &self,
+        module_index: usize,
+        module: &Module,
+    ) -> std::result::Result<bool, pdb::Error> {
+        let debug_info = self.module_debug_info(module_index, module)?;
+        Ok(debug_info.has_symbols || debug_info.has_lines)
+    }
+
+    fn module_debug_info(
+        &self,
+        module_index: usize,
+        _module: &Module,
+    ) -> std::result::Result<ModuleDebugInfo, pdb::Error> {
+        let mut modules_with_debug_info = self.modules_with_debug_info.borrow_mut();
+        if modules_with_debug_info.is_none() {
+            let mut pdb = self.pdb.borrow_mut();
+            let entries = match pdb.raw_stream(StreamIndex(DBI_STREAM_INDEX))? {
+                Some(dbi) => dbi_module_entries(dbi.as_slice()).ok_or(pdb::Error::UnexpectedEof)?,
+                None => Vec::new(),
+            };
+            // The symbols of a module start with a 4 byte signature.
+            *modules_with_debug_info = Some(
+                entries
+                    .iter()
+                    .map(|entry| ModuleDebugInfo {
+                        has_symbols: entry.stream().is_some() && entry.symbols_size > 4,
+                        has_lines: entry.stream().is_some()
+                            && (entry.lines_size != 0 || entry.c13_lines_size != 0),
+                    })
+                    .collect(),
+            );
+        }
+        Ok(modules_with_debug_info
+            .as_ref()
+            .and_then(|modules| modules.get(module_index))
+            .copied()
+            .unwrap_or_default())
+    }
+}
+
+/// A summary of the format and the contents of a PDB, from
+/// [`ContextPdbData::capabilities`].
+///
+/// Portable PDBs, which this crate doesn't support, are detected by
+/// [`ContextPdbData::open`] and [`is_portable_pdb`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The version from the header of the PDB information stream, e.g. 20000404.
+    pub pdb_version: u32,
+    /// The version from the header of the DBI stream, e.g. 19990903.
+    pub dbi_version: u32,
+    /// Whether any module has line information, so that lookups can return file
+    /// names and line numbers.
+    pub has_line_info: bool,
+    /// Whether the PDB has an IPI stream with items, which is needed for the names
+    /// of inlined functions.
+    pub has_ipi: bool,
+    /// Whether no module has symbols, so that lookups can only use the public
+    /// symbols. This is the case for stripped PDBs.
+    pub publics_only: bool,
+    /// Whether the PDB was linked with `/DEBUG:FASTLINK`. Such PDBs leave most of
+    /// the debug information in the object files, so lookups mostly return public
+    /// symbols.
+    pub is_fastlink: bool,
 }
 
 /// Basic information about a function.
@@ -220,170 +731,3466 @@ pub struct Function {
     pub start_rva: u32,
     /// The end address of the function, if known.
     pub end_rva: Option<u32>,
+    /// `end_rva` if it is known. Otherwise, for the functions from
+    /// [`Context::functions`], an estimate of the end address: the start of the next
+    /// function in the same section, or the end of the section. This happens for
+    /// functions from public symbols at the end of a section. The estimate can
+    /// include padding, or code which belongs to a function without a symbol.
+    pub estimated_end_rva: Option<u32>,
     /// The function name. `None` if there was an error during stringification.
     /// If this function is based on a public symbol, the consumer may need to demangle
     /// ("undecorate") the name. This can be detected based on a leading '?' byte.
     pub name: Option<String>,
+    /// What kind of symbol the function was found in, see [`Provenance`].
+    pub provenance: Provenance,
+    /// The number of other functions which the linker folded into this one because
+    /// their code was identical, i.e. the number of other public symbols at this
+    /// function's start address. Only computed if
+    /// [`ContextFlags::FOLDED_FUNCTION_COUNTS`] is set, 0 otherwise. The names can be
+    /// listed with [`Context::folded_function_names`].
+    pub other_folded_functions: usize,
+    /// Whether this result comes from a PDB which doesn't exactly match the image, see
+    /// [`Context::debug_id_match`].
+    pub unverified: bool,
+    /// The kind of compiler-generated function this is, based on its name, or `None`
+    /// for ordinary functions.
+    pub generated: Option<GeneratedFunctionKind>,
+    /// A small number which identifies the function within this context, for
+    /// aggregating results per function without hashing names. All lookups which
+    /// find the function at the same start address return the same id. The ids are
+    /// assigned in the order in which the functions are first found, starting at 0,
+    /// so they differ between contexts.
+    pub id: u32,
+    /// The start address of the function as a section offset, as used by the PDB's
+    /// symbols. This is the same address as `start_rva`, for tools which key
+    /// functions by section offset. `None` for PE exports outside the PDB's sections.
+    pub start_offset: Option<PdbInternalSectionOffset>,
+}
+
+/// The kind of symbol that a [`Function`] or a [`FunctionFrames`] result was found
+/// in, from the most to the least trustworthy. Crash reporting pipelines can use this
+/// to show the confidence in a frame, and to decide whether to use it for grouping
+/// crashes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Provenance {
+    /// A procedure symbol with line information. Only used for frames.
+    ProcedureWithLines,
+    /// A procedure symbol in a module's symbol stream. These have the most accurate
+    /// names and sizes. Used for [`Function`]s, whose lookups don't read the line
+    /// information; frames use `ProcedureWithLines` or `ProcedureWithoutLines`.
+    Procedure,
+    /// A procedure symbol without line information, e.g. from code which was
+    /// compiled without debug information or written in assembly. Only used for
+    /// frames.
+    ProcedureWithoutLines,
+    /// A thunk symbol, e.g. a jump to an imported function. Thunks have no line
+    /// information.
+    Thunk,
+    /// A trampoline record of the linker, e.g. a jump thunk of the incremental linking
+    /// table or a branch island. Trampolines have no line information; the target
+    /// can be found with [`Context::trampoline_at`].
+    Trampoline,
+    /// A public symbol. The function's size is inferred from the next known
+    /// function, so addresses near the end can belong to a function without a
+    /// symbol.
+    PublicSymbol,
+    /// An entry of the PE export table, used as a last resort for addresses which
+    /// aren't covered by the PDB. See [`Context::set_pe_exports`].
+    PeExport,
+    /// The nearest preceding symbol isn't known to be a function: it is an unnamed
+    /// placeholder for a section contribution without symbols, or a public symbol
+    /// which isn't marked as code. The function name is probably wrong.
+    NearestSymbol,
 }
 
 /// One frame of the inline stack at the looked-up address.
+///
+/// The function name is a `String`, a `&str` from a [`StringArena`] for lookups
+/// with [`Context::find_frames_in`], or an `Arc<str>` from a [`StringInterner`] for
+/// lookups with [`Context::find_frames_interned`].
 #[derive(Clone)]
-pub struct Frame<'a> {
+pub struct Frame<'a, S = String> {
     /// The function name. `None` if there was an error during stringification.
-    pub function: Option<String>,
-    /// The file name, if known.
+    pub function: Option<S>,
+    /// The symbol name of the function as it is stored in the PDB, without the
+    /// conversion to UTF-8 and without the argument list. For inlined functions, this
+    /// is the name from the inlinee's id record, which doesn't include the scope.
+    pub raw_function: Option<RawString<'a>>,
+    /// The positions of the parts of [`Frame::function`], if the name was formatted
+    /// from the type and id records. This is used by [`Frame::function_name_parts`].
+    pub function_name_layout: Option<FunctionNameLayout>,
+    /// The file name, if known. `None` if the context was created with
+    /// [`ContextFlags::RAW_FILE_NAMES`], see [`Frame::file_name`].
     pub file: Option<Cow<'a, str>>,
+    /// The index of the file in the line program of the module which contains
+    /// this function, if known. File indexes are only unique within a module.
+    pub file_index: Option<FileIndex>,
+    /// The file name as it is stored in the PDB string table, if known. This is the
+    /// same string as `file`, but without the conversion to UTF-8.
+    pub raw_file: Option<RawString<'a>>,
+    /// The checksum of the file's contents at compile time, if known. The variant
+    /// of [`FileChecksum`] indicates the hash algorithm.
+    /// This is only populated if the context was created with
+    /// [`ContextFlags::FILE_CHECKSUMS`].
+    pub file_checksum: Option<FileChecksum<'a>>,
     /// The start address of the function which contained the looked-up address.
+    /// For inlined frames, this is the start of the inline range which contains
+    /// the looked-up address.
     pub start_rva: u32,
     /// The end address of the function which contained the looked-up address, if known.
+    /// For inlined frames, this is the end of the inline range which contains
+    /// the looked-up address.
     pub end_rva: Option<u32>,
+    /// The offset of the looked-up address from `start_rva`, in bytes.
+    pub offset: u32,
     /// The line number, if known. This is the source line inside this function
     /// that is associated with the instruction at the looked-up address.
     pub line: Option<u32>,
+    /// The last line of the statement which starts at `line`, for statements which
+    /// span several lines; the same as `line` otherwise. Only available for the
+    /// outermost frame, because the line information of inlined functions has no end
+    /// lines.
+    pub line_end: Option<u32>,
+    /// The column of the start of the statement, if the PDB has column information.
+    /// Most compilers don't emit columns by default. Only available for the
+    /// outermost frame.
+    pub column: Option<u32>,
+    /// The column after the end of the statement, on `line_end`, if the PDB has
+    /// column information. Only available for the outermost frame.
+    pub column_end: Option<u32>,
+    /// The distance in bytes of the looked-up address from the start of the line
+    /// record which `line` comes from. Only available for the outermost frame.
+    ///
+    /// A line record covers the addresses up to the next record, so an address in
+    /// code without line information, e.g. in padding or in code from an object
+    /// file without debug information, gets the line of the closest record before
+    /// it. Statements rarely take more than a few dozen bytes, so a large distance
+    /// means that the line is likely wrong.
+    pub line_distance: Option<u32>,
+    /// The source language of the module which contains the function, from the
+    /// module's compile flags record. Only available for the outermost frame; `None`
+    /// for inlined functions and if the module has no such record.
+    pub language: Option<Language>,
 }
 
-/// The main API of this crate. Resolves addresses to function information.
-pub struct Context<'a, 's> {
-    address_map: &'a AddressMap<'s>,
-    section_contributions: Vec<ModuleSectionContribution>,
-    string_table: Option<&'a StringTable<'s>>,
-    type_formatter: MaybeOwned<'a, TypeFormatter<'a, 's>>,
-    /// Contains an entry for hopefully every function in an executable section.
-    /// The entries come from the public function symbols, and from the section
-    /// contributions: We create an unnamed "placeholder" entry for each section
-    /// contribution.
-    global_functions: Vec<PublicSymbolFunctionOrPlaceholder<'a>>,
-    cache: RefCell<ContextCache<'a, 's>>,
+/// The source language of a module, see [`Frame::language`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Language {
+    C,
+    Cpp,
+    Fortran,
+    /// Microsoft Macro Assembler.
+    Masm,
+    Pascal,
+    Basic,
+    Cobol,
+    /// A module which was generated by the linker.
+    Link,
+    /// A resource module which was converted with CVTRES.
+    Cvtres,
+    /// A module which was generated with CVTPGD for profile-guided optimization.
+    Cvtpgd,
+    CSharp,
+    VisualBasic,
+    /// Common Intermediate Language assembly.
+    ILAsm,
+    Java,
+    JScript,
+    /// Microsoft Intermediate Language of unknown source, e.g. from link-time code
+    /// generation.
+    Msil,
+    /// High Level Shader Language.
+    Hlsl,
+    ObjC,
+    ObjCpp,
+    Swift,
+    /// An alias object module.
+    AliasObj,
+    Rust,
+    Go,
+    D,
+    /// A language code which this crate doesn't know.
+    Unknown(u8),
 }
 
-impl<'a, 's> Context<'a, 's> {
-    /// Create a [`Context`] manually. Most consumers will want to use
-    /// [`ContextPdbData::make_context`] instead.
-    ///
-    /// However, if you interact with a PDB directly and parse some of its contents
-    /// for other uses, you may want to call this method in order to avoid overhead
-    /// from repeatedly parsing the same streams.
-    #[allow(clippy::too_many_arguments)]
-    pub fn new_from_parts(
-        module_info_provider: &'a dyn ModuleProvider<'s>,
-        sections: &[ImageSectionHeader],
-        address_map: &'a AddressMap<'s>,
-        global_symbols: &'a SymbolTable<'s>,
-        string_table: Option<&'a StringTable<'s>>,
-        debug_info: &'a DebugInformation,
-        type_formatter: MaybeOwned<'a, TypeFormatter<'a, 's>>,
-    ) -> Result<Self> {
-        let mut global_functions = Vec::new();
-
-        // Start with the public function symbols.
-        let mut symbol_iter = global_symbols.iter();
-        while let Some(symbol) = symbol_iter.next()? {
-            if let S_PUB32 | S_PUB32_ST = symbol.raw_kind() {
-                if let Ok(SymbolData::Public(PublicSymbol { name, offset, .. })) = symbol.parse() {
-                    if is_executable_section(offset.section, sections) {
-                        global_functions.push(PublicSymbolFunctionOrPlaceholder {
-                            start_offset: offset,
-                            name: Some(name),
-                        });
-                    }
-                }
-            }
+impl Language {
+    fn from_code(code: u8) -> Self {
+        match code {
+            0x00 => Language::C,
+            0x01 => Language::Cpp,
+            0x02 => Language::Fortran,
+            0x03 => Language::Masm,
+            0x04 => Language::Pascal,
+            0x05 => Language::Basic,
+            0x06 => Language::Cobol,
+            0x07 => Language::Link,
+            0x08 => Language::Cvtres,
+            0x09 => Language::Cvtpgd,
+            0x0a => Language::CSharp,
+            0x0b => Language::VisualBasic,
+            0x0c => Language::ILAsm,
+            0x0d => Language::Java,
+            0x0e => Language::JScript,
+            0x0f => Language::Msil,
+            0x10 => Language::Hlsl,
+            0x11 => Language::ObjC,
+            0x12 => Language::ObjCpp,
+            0x13 => Language::Swift,
+            0x14 => Language::AliasObj,
+            0x15 => Language::Rust,
+            0x16 => Language::Go,
+            // The DMD compiler uses the letter 'D'.
+            0x44 => Language::D,
+            code => Language::Unknown(code),
         }
+    }
+}
 
-        // Read the section contributions. This will let us find the right module
-        // based on the PdbSectionInternalOffset that corresponds to the looked-up
-        // address. This allows reading module info on demand.
-        // The section contributions also give us more function start addresses. We
-        // create placeholder symbols for them so we don't account missing functions to
-        // the nearest public function, and so that we can find line information for
-        // those missing functions if present.
-        let section_contributions =
-            compute_section_contributions(debug_info, sections, &mut global_functions)?;
+/// What to do with symbol names and file names which are not valid UTF-8, see
+/// [`Context::set_non_utf8_handling`]. Some toolchains write paths in the encoding
+/// of the current locale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NonUtf8Handling {
+    /// Replace invalid sequences with U+FFFD REPLACEMENT CHARACTER.
+    #[default]
+    Lossy,
+    /// Fail the lookup with [`Error::InvalidUtf8`].
+    Error,
+    /// Leave the string as `None`, so that the consumer can decode the raw bytes
+    /// from [`Frame::raw_function`] or [`Frame::raw_file`] itself.
+    Raw,
+}
 
-        // Add a few more placeholder entries for the end addresses of executable sections.
-        // These act as terminator addresses for the last function in a section.
-        for (section_index_zero_based, section) in sections.iter().enumerate() {
-            let section_index = (section_index_zero_based + 1) as u16;
-            if !is_executable_section(section_index, sections) {
-                continue;
-            }
-            let size = section.virtual_size;
-            let section_end_offset = PdbInternalSectionOffset::new(section_index, size);
-            global_functions.push(PublicSymbolFunctionOrPlaceholder {
-                start_offset: section_end_offset,
-                name: None,
-            });
-        }
+bitflags! {
+    /// Flags for [`Context`].
+    #[derive(Default)]
+    pub struct ContextFlags: u32 {
+        /// Populate [`Frame::file_checksum`]. This is off by default because most
+        /// consumers don't need the checksums.
+        const FILE_CHECKSUMS = 0b1;
 
-        // Sort and de-duplicate, so that we can use binary search during lookup.
-        // If we have both a public symbol and a placeholder symbol at the same offset,
-        // make it so that the symbol with name comes first, so that we keep it during
-        // the deduplication.
-        global_functions.sort_unstable_by_key(|p| {
-            (
-                p.start_offset.section,
-                p.start_offset.offset,
-                p.name.is_none(),
-            )
-        });
-        global_functions.dedup_by_key(|p| p.start_offset);
+        /// Include parameter names in the signatures of procedures, e.g.
+        /// `foo(int count, char* name)` instead of `foo(int, char*)`. The names are
+        /// taken from the procedure's variable records. This does not affect the
+        /// names of inlined functions, whose parameter names are not in the PDB.
+        const PARAMETER_NAMES = 0b10;
 
-        Ok(Self {
-            address_map,
-            section_contributions,
-            string_table,
-            type_formatter,
-            global_functions,
-            cache: RefCell::new(ContextCache {
-                module_cache: BasicModuleInfoCache {
-                    cache: Default::default(),
-                    module_info_provider,
-                },
-                function_line_cache: Default::default(),
-                procedure_cache: Default::default(),
-                extended_module_cache: Default::default(),
-                inline_name_cache: Default::default(),
-                full_rva_list: Default::default(),
-            }),
-        })
+        /// When a looked-up address is inside a jump thunk of the incremental linking
+        /// table (ILT), look up the thunk's target function instead. Without this flag,
+        /// such addresses resolve to the thunk, whose name is usually something like
+        /// `@ILT+1234(?foo@@YAXXZ)`. See [`Context::incremental_linking_thunk_target`].
+        const RESOLVE_INCREMENTAL_LINKING_THUNKS = 0b100;
+
+        /// Populate [`Function::other_folded_functions`] and
+        /// [`FunctionFrames::other_folded_functions`].
+        const FOLDED_FUNCTION_COUNTS = 0b1000;
+
+        /// Measure the time spent in the phases of each lookup, see
+        /// [`Context::timings`]. The phases which happen when the context is created
+        /// are always measured.
+        const TIMINGS = 0b10000;
+
+        /// Check each lookup which resolves to a procedure against the public
+        /// symbols, and report a [`Diagnostic::PublicSymbolMismatch`] if the public
+        /// symbol at that address has a different start address or name. This is
+        /// meant for validating the crate and PDBs, and makes lookups slower.
+        const CROSS_CHECK_PUBLICS = 0b100000;
+
+        /// Only attribute addresses to public symbols which the PDB marks as code or
+        /// as a function. Public symbols outside executable sections are always
+        /// ignored, but some PDBs also have data symbols in executable sections, e.g.
+        /// for jump tables. With this flag, the addresses up to the next function
+        /// after such a symbol aren't attributed to any function, instead of to the
+        /// data symbol. Note that linkers don't mark all code either, e.g. the
+        /// `__imp_load_*` thunks for delay-loaded imports.
+        const FUNCTION_PUBLICS_ONLY = 0b1000000;
+
+        /// Always name procedures from their own symbol record. By default, a
+        /// procedure without type information is named after a decorated public
+        /// symbol at the same address if there is one, because the public's name
+        /// usually includes the arguments. This flag keeps the undecorated names
+        /// stable, e.g. for grouping. It takes precedence over
+        /// [`ContextFlags::PREFER_DECORATED_PUBLIC_NAMES`].
+        const PREFER_PROCEDURE_NAMES = 0b10000000;
+
+        /// Name procedures after a decorated public symbol (one whose name starts
+        /// with `?`) at the same address whenever there is one, even if the procedure
+        /// has type information. The decorated name is used as it is.
+        const PREFER_DECORATED_PUBLIC_NAMES = 0b100000000;
+
+        /// Report line number 0 in [`Frame::line`] as it is. Compilers use line 0 for
+        /// code which they can't attribute to a source line, e.g. for code that
+        /// was merged from several lines, so by default such lines are reported as
+        /// `None`. This applies to the outer frame and to inlined frames.
+        const KEEP_LINE_ZERO = 0b1000000000;
+
+        /// Correct the end addresses of procedures with the section contributions
+        /// and the neighbouring functions. The length in a procedure's symbol
+        /// excludes the alignment padding after it, and sometimes includes data like
+        /// jump tables. With this flag, a procedure ends where the next procedure or
+        /// public symbol starts, but never after the end of its module's section
+        /// contribution, so that the functions of a module cover its code without
+        /// gaps or overlaps. Addresses in the padding are then attributed to the
+        /// procedure before them.
+        const CORRECT_PROCEDURE_ENDS = 0b10000000000;
+
+        /// Leave [`Frame::file`] empty and only fill in [`Frame::raw_file`] and
+        /// [`Frame::file_index`]. This saves converting the file name of every frame
+        /// to a string, for consumers which key their caches on the file index or
+        /// handle the raw paths themselves. [`Frame::file_name`] converts the name
+        /// when it's needed.
+        const RAW_FILE_NAMES = 0b100000000000;
+
+        /// Extract the procedures, lines and inline ranges of each module into a
+        /// [`CompactModule`] when a lookup first needs the module, and drop the
+        /// module's stream right away. Without this flag, the whole stream stays in
+        /// memory until the [`ContextPdbData`] is dropped, which for huge PDBs, e.g.
+        /// from unity builds, is most of the PDB. The compact modules are kept by the
+        /// [`ContextPdbData`] and shared by all of its contexts which use this flag.
+        ///
+        /// The first lookup in a module is slower, because the information of all
+        /// the module's procedures is extracted at once. Modules with C11 lines, and
+        /// modules with records that can't be read, are looked up in their stream as
+        /// usual. Methods which walk the records of a module, e.g.
+        /// [`Context::line_program`] and [`Context::all_inlinees`], still read the
+        /// stream and keep it. Modules which the context looked up before this flag
+        /// was set stay as they are.
+        const COMPACT_MODULES = 0b1000000000000;
     }
+}
 
-    /// The number of functions found in public symbols.
-    pub fn function_count(&self) -> usize {
-        self.global_functions.len()
+bitflags! {
+    /// Properties of a procedure's stack frame and code generation, from the
+    /// procedure's `S_FRAMEPROC` record. See [`FunctionFrames::frame_flags`].
+    #[derive(Default)]
+    pub struct FrameFlags: u32 {
+        /// The procedure calls `alloca`.
+        const HAS_ALLOCA = 1 << 0;
+        /// The procedure calls `setjmp`.
+        const HAS_SETJMP = 1 << 1;
+        /// The procedure calls `longjmp`.
+        const HAS_LONGJMP = 1 << 2;
+        /// The procedure contains inline assembly.
+        const HAS_INLINE_ASM = 1 << 3;
+        /// The procedure has C++ exception handling.
+        const HAS_EH = 1 << 4;
+        /// The procedure was declared `inline`.
+        const MARKED_INLINE = 1 << 5;
+        /// The procedure has structured exception handling.
+        const HAS_SEH = 1 << 6;
+        /// The procedure is `__declspec(naked)`.
+        const NAKED = 1 << 7;
+        /// The procedure has buffer security checks (`/GS`).
+        const SECURITY_CHECKS = 1 << 8;
+        /// The procedure was compiled with asynchronous exception handling (`/EHa`).
+        const ASYNC_EH = 1 << 9;
+        /// The stack ordering for the `/GS` checks could not be done.
+        const GS_NO_STACK_ORDERING = 1 << 10;
+        /// The procedure was inlined somewhere.
+        const WAS_INLINED = 1 << 11;
+        /// The procedure has a `/GS` security cookie check.
+        const GS_CHECK = 1 << 12;
+        /// The procedure is `__declspec(safebuffers)`.
+        const SAFE_BUFFERS = 1 << 13;
+        /// Profile-guided optimization was used for the procedure.
+        const PGO = 1 << 18;
+        /// There are valid profile counts for the procedure.
+        const VALID_PGO_COUNTS = 1 << 19;
+        /// The procedure was optimized for speed.
+        const OPTIMIZED_FOR_SPEED = 1 << 20;
+        /// The procedure contains control flow guard checks.
+        const GUARD_CF = 1 << 21;
+        /// The procedure contains control flow guard write checks.
+        const GUARD_CFW = 1 << 22;
     }
+}
 
-    /// Iterate over all functions in the modules.
-    pub fn functions(&self) -> FunctionIter<'_, 'a, 's> {
-        let mut cache = self.cache.borrow_mut();
-        let ContextCache {
-            full_rva_list,
-            module_cache,
-            ..
-        } = &mut *cache;
-        let full_rva_list = full_rva_list
-            .get_or_insert_with(|| Rc::new(self.compute_full_rva_list(module_cache)))
-            .clone();
-        FunctionIter {
-            context: self,
-            full_rva_list,
-            cur_index: 0,
+/// The layout of a procedure's stack frame, from the procedure's `S_FRAMEPROC`
+/// record. Returned by [`Context::frame_layout`].
+///
+/// Stack walkers can use this to reconstruct frames when there is no FPO or unwind
+/// data for a function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameLayout {
+    /// The size of the local variables, in bytes.
+    pub frame_size: u32,
+    /// The size of the padding in the frame, in bytes.
+    pub padding_size: u32,
+    /// The offset of the padding from the frame pointer.
+    pub padding_offset: u32,
+    /// The size of the callee-saved registers, in bytes.
+    pub saved_registers_size: u32,
+    /// The address of the exception handler, if the procedure has one.
+    pub exception_handler_rva: Option<u32>,
+    /// The register through which the local variables are addressed.
+    pub local_base_register: Option<FrameBaseRegister>,
+    /// The register through which the parameters are addressed.
+    pub parameter_base_register: Option<FrameBaseRegister>,
+    /// The flags of the record, see [`FunctionFrames::frame_flags`].
+    pub flags: FrameFlags,
+}
+
+/// A register which is used to address locals or parameters, see [`FrameLayout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameBaseRegister {
+    /// The stack pointer, `esp` or `rsp`.
+    StackPointer,
+    /// The frame pointer, `ebp` or `rbp`.
+    FramePointer,
+    /// A separate base register for frames which are realigned: `ebx` on x86, `r13`
+    /// on x64.
+    AlignedFramePointer,
+}
+
+impl FrameBaseRegister {
+    /// Decode the two-bit register field of the `S_FRAMEPROC` flags.
+    fn from_encoded(encoded: u32) -> Option<Self> {
+        match encoded & 0b11 {
+            1 => Some(Self::StackPointer),
+            2 => Some(Self::FramePointer),
+            3 => Some(Self::AlignedFramePointer),
+            _ => None,
         }
     }
+}
 
-    /// Find the function whose code contains the provided address.
-    /// The return value only contains the function name and the rva range, but
-    /// no file or line information.
-    pub fn find_function(&self, probe: u32) -> Result<Option<Function>> {
-        let offset = match Rva(probe).to_internal_offset(self.address_map) {
-            Some(offset) => offset,
-            None => return Ok(None),
-        };
+/// The result of an address lookup from [`Context::find_frames_at`].
+#[derive(Clone)]
+pub struct FunctionFrames<'a, S = String> {
+    /// The start address of the function which contained the looked-up address.
+    pub start_rva: u32,
+    /// The end address of the function which contained the looked-up address, if known.
+    pub end_rva: Option<u32>,
+    /// The inline stack at the looked-up address, ordered from inside to outside.
+    /// Always contains at least one entry: the last element is always the function
+    /// which contains the looked-up address.
+    pub frames: Vec<Frame<'a, S>>,
+    /// Whether the function which contained the looked-up address was compiled with
+    /// optimizations, if known. In optimized code, line information can be imprecise
+    /// and variable values may be unavailable. `None` if the function was found
+    /// only in the public symbols, or if its symbols have no information about it.
+    pub is_optimized: Option<bool>,
+    /// The flags from the `S_FRAMEPROC` record of the function which contained the
+    /// looked-up address, if present.
+    pub frame_flags: Option<FrameFlags>,
+    /// The number of other functions which were folded into the function which
+    /// contained the looked-up address, see [`Function::other_folded_functions`].
+    pub other_folded_functions: usize,
+    /// Whether this result comes from a PDB which doesn't exactly match the image, see
+    /// [`Context::debug_id_match`].
+    pub unverified: bool,
+    /// What kind of symbol the function was found in, which says how much the result
+    /// can be trusted.
+    pub provenance: Provenance,
+    /// The id of the function, the same as [`Function::id`].
+    pub function_id: u32,
+    /// The start address of the function as a section offset, the same as
+    /// [`Function::start_offset`].
+    pub start_offset: Option<PdbInternalSectionOffset>,
+}
 
-        let mut cache = self.cache.borrow_mut();
-        let ContextCache {
-            module_cache,
+/// How much code was generated for a function by inlining it, summed up over all
+/// the places it was inlined into. Returned by [`Context::all_inlinees`].
+#[derive(Clone, Debug)]
+pub struct InlineeSummary {
+    /// The function name. `None` if there was an error during stringification.
+    pub name: Option<String>,
+    /// The total number of bytes of code which were generated for the inlined calls,
+    /// including the code of functions which were inlined into them in turn.
+    pub inlined_bytes: u64,
+    /// The number of places that the function was inlined into.
+    pub call_sites: usize,
+}
+
+/// The code size of a function, split into the function's own code and the code of
+/// the functions which were inlined into it. Returned by
+/// [`Context::function_size_metrics`].
+#[derive(Clone, Debug)]
+pub struct FunctionSizeMetrics {
+    /// The function name. `None` if there was an error during stringification.
+    pub name: Option<String>,
+    /// The start address of the function, as a relative address (rva).
+    pub start_rva: u32,
+    /// The size of the function's code, in bytes.
+    pub total_size: u32,
+    /// The number of bytes which aren't attributed to any inlined function.
+    pub self_size: u32,
+    /// The code of each function which was inlined into this one, directly or
+    /// indirectly, sorted by size, largest first.
+    pub inlinees: Vec<InlinedCodeSize>,
+}
+
+/// The amount of code that a function generated where it was inlined, see
+/// [`FunctionSizeMetrics::inlinees`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlinedCodeSize {
+    /// The function name. `None` if there was an error during stringification.
+    pub name: Option<String>,
+    /// The number of bytes of the outer function which are attributed to this
+    /// inlined function, including the code of functions which were inlined into it
+    /// in turn.
+    pub bytes: u32,
+}
+
+/// A range of code which comes from an inlined function, as returned by
+/// [`Context::inline_ranges_for_function`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlinedRange {
+    /// The start address of the range.
+    pub start_rva: u32,
+    /// The end address of the range, exclusive.
+    pub end_rva: u32,
+    /// The nesting depth of the inline site: 0 for functions which were inlined
+    /// directly into the outer function, 1 for functions which were inlined into
+    /// those, and so on.
+    pub depth: u16,
+    /// The name of the inlined function. `None` if there was an error during
+    /// stringification.
+    pub function: Option<String>,
+    /// The file of the inlined function's code in this range, if known.
+    pub file: Option<String>,
+    /// The line of the inlined function's code at the start of this range, if known.
+    pub line: Option<u32>,
+}
+
+/// The binary annotations of an inline site record (`S_INLINESITE`), as returned by
+/// [`Context::inline_site_annotations`].
+#[derive(Clone, Debug)]
+pub struct InlineSiteAnnotations {
+    /// The item id of the inlined function.
+    pub inlinee: IdIndex,
+    /// The name of the inlined function. `None` if there was an error during
+    /// stringification.
+    pub function: Option<String>,
+    /// The nesting depth of the inline site: 0 for functions which were inlined
+    /// directly into the procedure, 1 for functions which were inlined into those,
+    /// and so on.
+    pub depth: u16,
+    /// The decoded annotations, in the order in which they are stored. Their code
+    /// offsets are relative to the start of the procedure.
+    pub annotations: Vec<BinaryAnnotation>,
+}
+
+/// How much of the code in the executable sections each function and each module
+/// accounts for. Returned by [`Context::size_breakdown`].
+#[derive(Clone, Debug, Default)]
+pub struct SizeBreakdown {
+    /// The sizes of all functions, sorted by address.
+    pub functions: Vec<FunctionSize>,
+    /// The sizes of all modules with code, sorted by size, largest first.
+    pub modules: Vec<ModuleSize>,
+    /// The number of bytes in the executable sections which aren't covered by the
+    /// section contributions of any module, e.g. padding between object files and
+    /// code that was added by the linker.
+    pub unattributed_bytes: u64,
+}
+
+/// The size of a function, see [`SizeBreakdown`].
+#[derive(Clone, Debug)]
+pub struct FunctionSize {
+    /// The function name. `None` if there was an error during stringification.
+    /// Names from public symbols are not demangled.
+    pub name: Option<String>,
+    /// The start address of the function, as a relative address (rva).
+    pub start_rva: u32,
+    /// The index of the module whose section contribution contains the function.
+    pub module_index: Option<usize>,
+    /// The size of the function's code, in bytes.
+    pub size: u32,
+    /// The number of bytes between the end of the function and the next function
+    /// or the end of the module's section contribution, usually alignment.
+    pub padding: u32,
+}
+
+/// The size of the code of a module (compiland), see [`SizeBreakdown`].
+#[derive(Clone, Debug)]
+pub struct ModuleSize {
+    /// The index of the module.
+    pub module_index: usize,
+    /// The module name, usually the path of an object file.
+    pub name: String,
+    /// The size of the module's section contributions to executable sections, in
+    /// bytes.
+    pub size: u64,
+    /// The part of `size` which is covered by the code of functions. The padding
+    /// after the functions is counted in `padding_bytes` instead.
+    pub function_bytes: u64,
+    /// The part of `size` which isn't covered by any function, i.e. the total size
+    /// of the module's [`PaddingGap`]s.
+    pub padding_bytes: u64,
+}
+
+/// A range of bytes in a module's section contribution which isn't covered by any
+/// function, usually alignment between functions or at the end of the
+/// contribution. Returned by [`Context::padding_gaps`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PaddingGap {
+    /// The index of the module whose section contribution contains the gap.
+    pub module_index: usize,
+    /// The start address of the gap, as a relative address (rva).
+    pub start_rva: u32,
+    /// The size of the gap, in bytes.
+    pub size: u32,
+}
+
+/// The static initializers of an image. Returned by
+/// [`Context::static_initializers`].
+#[derive(Clone, Debug, Default)]
+pub struct StaticInitializers {
+    /// The modules' contributions to the tables of initializer function pointers
+    /// in the `.CRT$XC*` sections, which the C runtime calls at startup. Sorted by
+    /// address.
+    pub table_contributions: Vec<InitializerTableContribution>,
+    /// The compiler-generated functions which construct or destroy global
+    /// variables, sorted by address.
+    pub functions: Vec<InitializerFunction>,
+}
+
+/// A module's part of an initializer table, see [`StaticInitializers`].
+#[derive(Clone, Debug)]
+pub struct InitializerTableContribution {
+    /// The name of the grouped section, e.g. `.CRT$XCU`.
+    pub section_name: String,
+    /// The start address of the contribution, as a relative address (rva).
+    pub start_rva: u32,
+    /// The size of the contribution, in bytes. This is a multiple of the pointer
+    /// size.
+    pub size: u32,
+    /// The index of the contributing module.
+    pub module_index: usize,
+    /// The module name, usually the path of an object file.
+    pub module_name: String,
+}
+
+/// A function which constructs or destroys a global variable, see
+/// [`StaticInitializers`].
+#[derive(Clone, Debug)]
+pub struct InitializerFunction {
+    /// Whether the function constructs or destroys the variable.
+    pub kind: InitializerKind,
+    /// The name of the variable, including its namespace.
+    pub variable: String,
+    /// The function name, e.g. `` `dynamic initializer for 'gFoo'' ``.
+    pub name: String,
+    /// The start address of the function, as a relative address (rva).
+    pub start_rva: u32,
+    /// The index of the module which contains the function.
+    pub module_index: usize,
+    /// The module name, usually the path of an object file.
+    pub module_name: String,
+}
+
+/// The kind of an [`InitializerFunction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InitializerKind {
+    /// A `` `dynamic initializer for 'x'' `` function, which is called at startup
+    /// through an initializer table.
+    DynamicInitializer,
+    /// A `` `dynamic atexit destructor for 'x'' `` function, which the initializer
+    /// registers with `atexit`.
+    AtexitDestructor,
+}
+
+/// A public symbol, of code or data. Returned by [`Context::public_symbols`].
+#[derive(Clone, Debug)]
+pub struct PublicSymbolEntry {
+    /// The symbol name. This is the mangled ("decorated") name, see
+    /// [`PublicSymbolEntry::demangled_name`].
+    pub name: String,
+    /// The address of the symbol, as a relative address (rva). `None` for absolute
+    /// symbols, see [`Context::absolute_symbols`], and for addresses which can't be
+    /// mapped.
+    pub rva: Option<u32>,
+    /// The symbol refers to code, according to the PDB.
+    pub code: bool,
+    /// The symbol is a function, according to the PDB.
+    pub function: bool,
+    /// The symbol is in managed code, either native or IL.
+    pub managed: bool,
+    /// The symbol is managed IL code.
+    pub msil: bool,
+    /// The symbol is in an executable section, according to the section headers.
+    /// This is more reliable than `code` and `function`, which some linkers also set
+    /// for data.
+    pub in_executable_section: bool,
+}
+
+impl PublicSymbolEntry {
+    /// The demangled ("undecorated") name, or `None` if the name isn't a mangled
+    /// C++ name or can't be demangled. Names of C functions with a calling convention
+    /// suffix, e.g. `_foo@8`, are not demangled.
+    #[cfg(feature = "demangle")]
+    pub fn demangled_name(&self, flags: msvc_demangler::DemangleFlags) -> Option<String> {
+        if !self.name.starts_with('?') {
+            return None;
+        }
+        msvc_demangler::demangle(&self.name, flags).ok()
+    }
+}
+
+/// A module (compiland) of the PDB. Returned by [`Context::modules`].
+#[derive(Clone, Debug)]
+pub struct ModuleSummary {
+    /// The index of the module, as used by [`Context::module_for_address`] and
+    /// [`Context::release_module`].
+    pub module_index: usize,
+    /// The module name, usually the path of an object file.
+    pub name: String,
+    /// The name of the library that the module was linked from, or the same as
+    /// `name` if it wasn't linked from a library.
+    pub object_file_name: String,
+    /// Whether the module has symbols or line information. Modules without debug
+    /// information only contribute public symbols.
+    pub has_debug_info: bool,
+    /// Whether the module has symbol records. Addresses in modules without them
+    /// resolve to public symbols, without argument types.
+    pub has_symbols: bool,
+    /// Whether the module has line information. Frames in modules without it have
+    /// no file and line.
+    pub has_lines: bool,
+}
+
+/// How many modules of the PDB have which kind of debug information. Returned by
+/// [`Context::debug_info_summary`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DebugInfoSummary {
+    /// The number of modules.
+    pub module_count: usize,
+    /// The number of modules with symbol records.
+    pub modules_with_symbols: usize,
+    /// The number of modules with line information.
+    pub modules_with_lines: usize,
+    /// The number of modules without symbols and without line information. Their
+    /// code can only be resolved to public symbols, e.g. because the object files
+    /// were compiled without `/Zi` or the module is a stripped library.
+    pub stripped_modules: usize,
+}
+
+/// The result of [`ContextPdbData::validate`].
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    /// The problems that were found, grouped by kind: first the stream problems,
+    /// then the section contribution problems, then the module problems in module
+    /// order.
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Whether no problems were found.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A problem found by [`ContextPdbData::validate`]. Lookups still work around most
+/// of these, but return less information.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationIssue {
+    /// An optional stream is missing or couldn't be read.
+    MissingStream {
+        /// A description of the stream, e.g. `"/names"`.
+        name: &'static str,
+    },
+    /// The DBI section contributions aren't sorted by address. Only the first such
+    /// contribution is reported.
+    UnorderedContributions {
+        /// The 1-based section index of the contribution.
+        section: u16,
+        /// The start offset of the contribution within the section.
+        offset: u32,
+        /// The index of the contributing module.
+        module_index: usize,
+    },
+    /// A section contribution starts before the end of the previous one.
+    OverlappingContributions {
+        /// The 1-based section index of the contribution.
+        section: u16,
+        /// The start offset of the contribution within the section.
+        offset: u32,
+        /// The index of the contributing module.
+        module_index: usize,
+        /// The index of the module of the previous contribution.
+        other_module_index: usize,
+    },
+    /// A section contribution is in a section which has no section header, e.g.
+    /// because the section header stream only describes some of the sections of a
+    /// very large binary. Addresses in it can't be translated to rvas. Only the
+    /// first such contribution of each section is reported.
+    ContributionOutsideSections {
+        /// The 1-based section index of the contribution.
+        section: u16,
+        /// The start offset of the contribution within the section.
+        offset: u32,
+        /// The index of the contributing module.
+        module_index: usize,
+    },
+    /// A module's symbols, line information or inline information couldn't be read.
+    UnreadableModule {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The name of the module, usually the path to an object file.
+        module_name: String,
+        /// The error from reading the module.
+        error: String,
+    },
+    /// A symbol record of a module could not be parsed, see
+    /// [`Diagnostic::MalformedSymbol`].
+    MalformedSymbol {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The offset of the record in the module's symbol stream.
+        symbol_offset: u32,
+        /// The error from parsing the record.
+        error: String,
+    },
+    /// A procedure has no line records.
+    ProcedureWithoutLines {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The name of the procedure, as stored in the procedure record.
+        name: String,
+        /// The start rva of the procedure, if it could be translated.
+        start_rva: Option<u32>,
+    },
+    /// An inline site has no code ranges of its own. Lookups derive its ranges from
+    /// the functions which were inlined into it, see
+    /// [`Diagnostic::MissingInlineRanges`].
+    InlineSiteWithoutRanges {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The start rva of the procedure which contains the inline site, if it could
+        /// be translated.
+        procedure_start_rva: Option<u32>,
+        /// The item id of the inlinee.
+        inlinee: u32,
+    },
+}
+
+/// A public symbol whose value is a constant instead of an address in a section,
+/// e.g. `___safe_se_handler_count`. Returned by [`Context::absolute_symbols`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbsoluteSymbol {
+    /// The symbol name. This is the mangled ("decorated") name.
+    pub name: String,
+    /// The value of the symbol.
+    pub value: u32,
+}
+
+/// A function with line records in a particular source file. Returned by
+/// [`Context::functions_in_file`].
+#[derive(Clone, Debug)]
+pub struct SourceFileFunction {
+    /// The function name. `None` if there was an error during stringification.
+    pub name: Option<String>,
+    /// The start address of the function, as a relative address (rva).
+    pub start_rva: u32,
+    /// The end address of the function.
+    pub end_rva: u32,
+    /// The parts of the function's code whose line records refer to the file, as
+    /// `(start_rva, end_rva)` pairs. Sorted, with adjacent ranges merged.
+    pub ranges: Vec<(u32, u32)>,
+}
+
+/// An approximate breakdown of the memory used by a [`Context`], in bytes.
+/// Returned by [`Context::memory_usage`].
+#[derive(Clone, Debug, Default)]
+pub struct MemoryUsage {
+    /// The symbol data of the module streams which have been read so far. These
+    /// are owned by the [`ContextPdbData`] and shared between all contexts that were
+    /// created from it.
+    pub module_streams: usize,
+    /// The modules which were extracted for [`ContextFlags::COMPACT_MODULES`]. Like
+    /// the module streams, these are owned by the [`ContextPdbData`] and shared.
+    pub compact_modules: usize,
+    /// The per-module lists of procedures.
+    pub procedures: usize,
+    /// The cached per-procedure line tables.
+    pub line_tables: usize,
+    /// The cached formatted function names, of both procedures and inlinees.
+    pub names: usize,
+    /// The cached inline ranges and per-module inlinee tables.
+    pub inline_ranges: usize,
+    /// The lookup structures which are created up-front: the list of public
+    /// symbols, the section contributions, and the list of function addresses.
+    pub lookup_tables: usize,
+}
+
+impl MemoryUsage {
+    /// The sum of all the parts.
+    pub fn total(&self) -> usize {
+        self.module_streams
+            + self.compact_modules
+            + self.procedures
+            + self.line_tables
+            + self.names
+            + self.inline_ranges
+            + self.lookup_tables
+    }
+}
+
+/// A problem with the debug information which a [`Context`] worked around, reported
+/// to the callback that was set with [`Context::set_diagnostics_callback`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Diagnostic {
+    /// The symbols of a module could not be read. Addresses in this module are
+    /// resolved with the public symbols, without file and line information.
+    UnreadableModule {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The name of the module, usually the path to an object file.
+        module_name: String,
+        /// The error from reading the module.
+        error: String,
+    },
+    /// A symbol record of a module could not be parsed and was skipped. If the
+    /// record's length was broken, the symbols after it were skipped as well,
+    /// because the start of the next record is unknown.
+    MalformedSymbol {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The name of the module, usually the path to an object file.
+        module_name: String,
+        /// The offset of the record in the module's symbol stream.
+        symbol_offset: u32,
+        /// The error from parsing the record.
+        error: String,
+    },
+    /// The line information of an inlinee is missing, or doesn't cover the code of
+    /// the functions which were inlined into it. The missing address ranges were
+    /// derived from those functions, and have no line numbers.
+    MissingInlineRanges {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The item id of the inlinee.
+        inlinee: u32,
+    },
+    /// A section offset could not be translated to an rva with the address map, so
+    /// the lookup returned no result.
+    UnmappableOffset {
+        /// The 1-based section index.
+        section: u16,
+        /// The offset within the section.
+        offset: u32,
+    },
+    /// The file of a line record could not be found in the module's file table or
+    /// in the string table, so the frame has no file name. Each file is only
+    /// reported once.
+    UnresolvedFileName {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The file index from the line record.
+        file_index: u32,
+    },
+    /// With [`ContextFlags::CROSS_CHECK_PUBLICS`]: The procedure which contains an
+    /// address disagrees with the public symbols about the function's start address
+    /// or name. Procedures which have no public symbol, such as static functions,
+    /// are not reported.
+    PublicSymbolMismatch {
+        /// The rva that was looked up.
+        rva: u32,
+        /// The name of the procedure, as stored in the procedure record.
+        procedure_name: String,
+        /// The start rva of the procedure.
+        procedure_start_rva: u32,
+        /// The mangled name of the public symbol.
+        public_name: String,
+        /// The start rva of the public symbol.
+        public_start_rva: u32,
+    },
+    /// Type or id records which a function name refers to could not be found, e.g.
+    /// because they are in a type server or in the object files of a
+    /// `/DEBUG:FASTLINK` PDB. The name has placeholders for them, see
+    /// [`TypeFormatter::take_unresolved_indexes`].
+    UnresolvedTypes {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The indexes of the missing records.
+        indexes: Vec<u32>,
+    },
+    /// Part of a module's debug information, e.g. its line information or its
+    /// inlinee records, could not be read. Lookups in this module still return the
+    /// function, but without the information which depends on the unreadable part.
+    /// The errors are cached, so this is only reported by the first lookup which
+    /// runs into it, for each module or procedure.
+    DegradedModule {
+        /// The index of the module in the DBI module list.
+        module_index: usize,
+        /// The name of the module, usually the path to an object file.
+        module_name: String,
+        /// What was being read, e.g. "reading the procedure's lines".
+        operation: &'static str,
+        /// The error from reading it.
+        error: String,
+    },
+    /// The PDB has no string table (the `/names` stream), or it could not be read,
+    /// so frames have no file names. This is only reported once per context.
+    MissingStringTable,
+}
+
+/// Storage for the function names of lookups with [`Context::find_frames_in`], so
+/// that the results can borrow the names instead of owning a copy.
+///
+/// Each distinct name is only stored once, so symbolicating many addresses in the
+/// same functions, e.g. the samples of a profile, doesn't allocate a new string per
+/// frame. The names stay alive until the arena is dropped.
+#[derive(Default)]
+pub struct StringArena {
+    strings: FrozenMap<Rc<str>, Rc<str>>,
+}
+
+impl StringArena {
+    /// Create an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of `string` which lives as long as the arena. Storing a string
+    /// which is already in the arena doesn't allocate.
+    pub fn alloc(&self, string: &str) -> &str {
+        if let Some(stored) = self.strings.get(string) {
+            return stored;
+        }
+        let stored: Rc<str> = Rc::from(string);
+        self.strings.insert(stored.clone(), stored)
+    }
+
+    /// The number of distinct strings in the arena.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Whether the arena is empty.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// A callback for [`Context::set_diagnostics_callback`].
+pub type DiagnosticsCallback = Box<dyn Fn(&Diagnostic)>;
+
+/// A callback for [`Context::set_progress_callback`].
+pub type ProgressCallback = Box<dyn Fn(Progress)>;
+
+/// How far an operation over all modules has got, see
+/// [`Context::set_progress_callback`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of modules which have been processed so far.
+    pub modules_processed: usize,
+    /// The total number of modules.
+    pub module_count: usize,
+}
+
+/// Cancels the operations of a [`Context`] which go over all modules, see
+/// [`Context::set_cancellation_token`]. Clones share the same state, and can be
+/// sent to other threads, e.g. to cancel from a UI thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token which isn't cancelled yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the cancellation of the running and all future operations which
+    /// check this token.
+    pub fn cancel(&self) {
+        self.0.store(true, atomic::Ordering::Relaxed);
+    }
+
+    /// Whether [`CancellationToken::cancel`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(atomic::Ordering::Relaxed)
+    }
+}
+
+/// Options for a single lookup with [`Context::find_frames_with_options`]. Unlike
+/// [`ContextFlags`], these can be different for each call.
+#[derive(Clone, Debug)]
+pub struct LookupOptions {
+    /// Format the name of the outer function from its type information. If false, the
+    /// outer frame has the raw symbol name from the PDB instead, which is the mangled
+    /// name for functions that are only known from public symbols. This also skips the
+    /// formatting work. The default is true.
+    pub demangle: bool,
+    /// Include the frames of inlined functions. The default is true.
+    pub inline_frames: bool,
+    /// The maximum number of inlined frames, counted from the outer function. `None`,
+    /// the default, means no limit other than the one from
+    /// [`Context::set_max_inline_depth`].
+    pub max_inline_depth: Option<usize>,
+    /// The address is a return address, as found in stack walks, so look up the
+    /// previous byte instead: The call instruction which belongs to the return address
+    /// can be at the end of a function or of an inlined range, and the return address
+    /// then already belongs to the next one. The default is false.
+    pub return_address: bool,
+    /// If no procedure covers the address, fall back to the nearest preceding public
+    /// symbol, like [`Context::find_frames_at`] does. The default is true.
+    pub nearest_symbol_fallback: bool,
+    /// If the address is exactly the end address of a function, look up the last byte
+    /// of that function instead of the next function, or of nothing. This is common
+    /// for return addresses after calls to `noreturn` functions at the end of a
+    /// function. Unlike `return_address`, other addresses are looked up as they are.
+    /// The default is false.
+    pub inclusive_end: bool,
+}
+
+impl Default for LookupOptions {
+    fn default() -> Self {
+        Self {
+            demangle: true,
+            inline_frames: true,
+            max_inline_depth: None,
+            return_address: false,
+            nearest_symbol_fallback: true,
+            inclusive_end: false,
+        }
+    }
+}
+
+impl LookupOptions {
+    fn max_inline_depth(&self) -> usize {
+        if self.inline_frames {
+            self.max_inline_depth.unwrap_or(usize::MAX)
+        } else {
+            0
+        }
+    }
+}
+
+/// Restricts the functions returned by [`Context::functions_matching`] to the ones
+/// from certain modules. An empty list means no restriction.
+#[derive(Clone, Debug, Default)]
+pub struct FunctionFilter {
+    /// Only include functions from modules whose name, or the name of the library
+    /// they were linked from, matches one of these patterns. The patterns can
+    /// contain the wildcards `*` and `?`. The comparison is case-insensitive and
+    /// treats `/` and `\` as the same character, e.g. `*\myproject\*`.
+    pub module_patterns: Vec<String>,
+    /// Only include functions from modules with one of these source languages, see
+    /// [`Frame::language`].
+    pub languages: Vec<Language>,
+    /// Only include the shard `(index, count)` of the functions: the ones from
+    /// every `count`-th module, starting at module `index`. Functions which can't be
+    /// attributed to a module are in shard 0. [`Context::functions_matching`] panics
+    /// if `index` is not less than `count`.
+    ///
+    /// This splits a full dump into parts which can be processed by separate
+    /// processes or threads, each with its own [`ContextPdbData`], which then only
+    /// reads the module streams of its own shard. The shards are only balanced if
+    /// the functions are spread over many modules; a large module ends up in a
+    /// single shard. With the `rayon` feature, `par_functions` balances the work
+    /// across threads module by module instead.
+    pub shard: Option<(usize, usize)>,
+}
+
+impl FunctionFilter {
+    fn matches_module(
+        &self,
+        module_index: usize,
+        module: &Module,
+        language: impl FnOnce() -> Option<Language>,
+    ) -> bool {
+        if let Some((shard_index, shard_count)) = self.shard {
+            if module_index % shard_count != shard_index {
+                return false;
+            }
+        }
+        if !self.module_patterns.is_empty() {
+            let module_name = module.module_name();
+            let object_file_name = module.object_file_name();
+            let matches_pattern = self.module_patterns.iter().any(|pattern| {
+                glob_matches(pattern.as_bytes(), module_name.as_bytes())
+                    || glob_matches(pattern.as_bytes(), object_file_name.as_bytes())
+            });
+            if !matches_pattern {
+                return false;
+            }
+        }
+        if !self.languages.is_empty() {
+            match language() {
+                Some(language) if self.languages.contains(&language) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Match `text` against a pattern with the wildcards `*` and `?`, for
+/// [`FunctionFilter::module_patterns`].
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    fn normalize(c: u8) -> u8 {
+        match c {
+            b'/' => b'\\',
+            c => c.to_ascii_lowercase(),
+        }
+    }
+    let (mut p, mut t) = (0, 0);
+    // The position after the last `*` in the pattern, and the position in the text
+    // which that `*` is currently matched up to.
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == b'?' || normalize(c) == normalize(text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// A function which was found by [`Context::lookup_function_handle`]. Pass it to
+/// [`Context::frames_for`] to look up addresses inside this function without
+/// searching for the function again.
+///
+/// A handle is only meaningful for the [`Context`] which created it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FunctionHandle {
+    module_index: usize,
+    symbol: FunctionHandleSymbol,
+    start_offset: PdbInternalSectionOffset,
+    /// The end offset in the section of `start_offset`, exclusive.
+    end_offset: Option<u32>,
+    start_rva: u32,
+    end_rva: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FunctionHandleSymbol {
+    /// An index into `Context::global_functions`.
+    Public(usize),
+    /// An index into the procedures of the module.
+    Procedure(usize),
+}
+
+impl FunctionHandle {
+    /// The start address of the function.
+    pub fn start_rva(&self) -> u32 {
+        self.start_rva
+    }
+
+    /// The end address of the function, if known.
+    pub fn end_rva(&self) -> Option<u32> {
+        self.end_rva
+    }
+
+    fn contains(&self, offset: PdbInternalSectionOffset) -> bool {
+        offset.section == self.start_offset.section
+            && offset.offset >= self.start_offset.offset
+            && !matches!(self.end_offset, Some(end) if offset.offset >= end)
+    }
+}
+
+/// The time spent in each phase of reading a PDB, to find out which phase is slow for
+/// a particular PDB. Returned by [`ContextPdbData::timings`] and [`Context::timings`].
+///
+/// The phases which happen lazily during lookups are only measured if
+/// [`ContextFlags::TIMINGS`] is set; the times are accumulated over all lookups.
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    /// Reading the global streams (global symbols, DBI, TPI, IPI, address map and
+    /// string table) in [`ContextPdbData`].
+    pub stream_parse: Duration,
+    /// Scanning the public symbols and the section contributions when creating the
+    /// [`Context`].
+    pub publics_scan: Duration,
+    /// Reading module streams and collecting their procedures.
+    pub module_symbol_walk: Duration,
+    /// Reading the line programs of modules and the lines of procedures.
+    pub line_program_parse: Duration,
+    /// Computing the inline ranges of procedures.
+    pub inline_computation: Duration,
+    /// Formatting function names, of both procedures and inlinees.
+    pub formatting: Duration,
+}
+
+impl Timings {
+    /// The sum of all the phases.
+    pub fn total(&self) -> Duration {
+        self.stream_parse
+            + self.publics_scan
+            + self.module_symbol_walk
+            + self.line_program_parse
+            + self.inline_computation
+            + self.formatting
+    }
+}
+
+/// Normalize a path for the comparison in [`Context::functions_in_file`].
+fn normalize_path_for_comparison(path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split(['/', '\\']) {
+        match component {
+            "." => {}
+            "" if !components.is_empty() => {}
+            ".." if components
+                .last()
+                .is_some_and(|c| !c.is_empty() && *c != "..") =>
+            {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    components.join("\\").to_ascii_lowercase()
+}
+
+/// Whether `source` is a Portable PDB file, which is used for .NET assemblies and
+/// which this crate doesn't support. Portable PDBs are ECMA-335 metadata files,
+/// which start with the metadata signature "BSJB".
+pub fn is_portable_pdb<'s, S: Source<'s>>(source: &mut S) -> bool {
+    let slice = SourceSlice { offset: 0, size: 4 };
+    match source.view(&[slice]) {
+        Ok(view) => view.as_slice() == b"BSJB",
+        Err(_) => false,
+    }
+}
+
+fn vec_size<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * mem::size_of::<T>()
+}
+
+fn hash_map_size<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * mem::size_of::<(K, V)>()
+}
+
+fn btree_map_size<K, V>(map: &BTreeMap<K, V>) -> usize {
+    map.len() * mem::size_of::<(K, V)>()
+}
+
+/// The main API of this crate. Resolves addresses to function information.
+pub struct Context<'a, 's> {
+    module_info_provider: &'a dyn ModuleProvider<'s>,
+    address_map: &'a AddressMap<'s>,
+    global_symbols: &'a SymbolTable<'s>,
+    debug_info: &'a DebugInformation<'s>,
+    section_contributions: Vec<ModuleSectionContribution>,
+    string_table: Option<&'a StringTable<'s>>,
+    type_formatter: MaybeOwned<'a, TypeFormatter<'a, 's>>,
+    /// Contains an entry for hopefully every function in an executable section.
+    /// The entries come from the public function symbols, and from the section
+    /// contributions: We create an unnamed "placeholder" entry for each section
+    /// contribution.
+    global_functions: Vec<PublicSymbolFunctionOrPlaceholder<'a>>,
+    /// The public symbols which were removed from `global_functions` because another
+    /// public symbol has the same address. Sorted by address.
+    folded_functions: Vec<PublicSymbolFunctionOrPlaceholder<'a>>,
+    /// The public symbols in section 0, which have no address.
+    absolute_symbols: Vec<AbsoluteSymbol>,
+    sections: Vec<ImageSectionHeader>,
+    flags: ContextFlags,
+    symbol_name_handling: NonUtf8Handling,
+    file_name_handling: NonUtf8Handling,
+    max_inline_depth: Option<usize>,
+    max_public_function_size: Option<u32>,
+    debug_id_match: DebugIdMatch,
+    pdb_identity: Option<(Uuid, u32)>,
+    #[cfg(feature = "pe")]
+    pe_exports: Option<PeExports>,
+    timings: RefCell<Timings>,
+    diagnostics_callback: Option<DiagnosticsCallback>,
+    progress_callback: Option<ProgressCallback>,
+    cancellation_token: Option<CancellationToken>,
+    shared_module_cache: Option<&'a SharedModuleCache<'a, 's>>,
+    /// The ids of the functions which were returned so far, by start address. This
+    /// is not part of the cache, because the ids have to stay the same.
+    function_ids: RefCell<HashMap<u32, u32>>,
+    /// Whether [`Diagnostic::MissingStringTable`] was reported.
+    missing_string_table_reported: Cell<bool>,
+    /// The `(module_index, file_index)` pairs for which
+    /// [`Diagnostic::UnresolvedFileName`] was reported.
+    unresolved_file_names_reported: RefCell<HashSet<(usize, u32)>>,
+    cache: RefCell<ContextCache<'a, 's>>,
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Create a [`Context`] manually. Most consumers will want to use
+    /// [`ContextPdbData::make_context`] instead.
+    ///
+    /// However, if you interact with a PDB directly and parse some of its contents
+    /// for other uses, you may want to call this method in order to avoid overhead
+    /// from repeatedly parsing the same streams.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_from_parts(
+        module_info_provider: &'a dyn ModuleProvider<'s>,
+        sections: &[ImageSectionHeader],
+        address_map: &'a AddressMap<'s>,
+        global_symbols: &'a SymbolTable<'s>,
+        string_table: Option<&'a StringTable<'s>>,
+        debug_info: &'a DebugInformation<'s>,
+        type_formatter: MaybeOwned<'a, TypeFormatter<'a, 's>>,
+    ) -> Result<Self> {
+        let start = Instant::now();
+        // Section indexes are 16-bit and 1-based in all PDB records, so more headers
+        // than that can't be addressed. PE images can't have that many sections.
+        if sections.len() > usize::from(u16::MAX) {
+            return Err(Error::TooManySections(sections.len()));
+        }
+        let mut global_functions = Vec::new();
+        let mut absolute_symbols = Vec::new();
+
+        // Start with the public function symbols. Symbols in section 0 are absolute:
+        // their offset is a value, not an address, so they must stay out of the
+        // address-based lookups.
+        let mut symbol_iter = global_symbols.iter();
+        while let Some(symbol) = symbol_iter
+            .next()
+            .map_err(Error::stream("global symbols"))?
+        {
+            if let S_PUB32 | S_PUB32_ST = symbol.raw_kind() {
+                if let Ok(SymbolData::Public(PublicSymbol {
+                    name,
+                    offset,
+                    code,
+                    function,
+                    ..
+                })) = symbol.parse()
+                {
+                    if offset.section == 0 {
+                        absolute_symbols.push(AbsoluteSymbol {
+                            name: name.to_string().into_owned(),
+                            value: offset.offset,
+                        });
+                    } else if is_executable_section(offset.section, sections) {
+                        global_functions.push(PublicSymbolFunctionOrPlaceholder {
+                            start_offset: offset,
+                            name: Some(name),
+                            is_code: code || function,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Read the section contributions. This will let us find the right module
+        // based on the PdbSectionInternalOffset that corresponds to the looked-up
+        // address. This allows reading module info on demand.
+        // The section contributions also give us more function start addresses. We
+        // create placeholder symbols for them so we don't account missing functions to
+        // the nearest public function, and so that we can find line information for
+        // those missing functions if present.
+        let section_contributions =
+            compute_section_contributions(debug_info, sections, &mut global_functions)?;
+
+        // Add a few more placeholder entries for the end addresses of executable sections.
+        // These act as terminator addresses for the last function in a section.
+        for (section_index, section) in (1..=u16::MAX).zip(sections) {
+            if !is_executable_section(section_index, sections) {
+                continue;
+            }
+            let size = section.virtual_size;
+            let section_end_offset = PdbInternalSectionOffset::new(section_index, size);
+            global_functions.push(PublicSymbolFunctionOrPlaceholder {
+                start_offset: section_end_offset,
+                name: None,
+                is_code: true,
+            });
+        }
+
+        // Sort and de-duplicate, so that we can use binary search during lookup.
+        // If we have both a public symbol and a placeholder symbol at the same offset,
+        // make it so that the symbol with name comes first, so that we keep it during
+        // the deduplication. Among public symbols, prefer the ones marked as code.
+        global_functions.sort_unstable_by_key(|p| {
+            (
+                p.start_offset.section,
+                p.start_offset.offset,
+                p.name.is_none(),
+                !p.is_code,
+            )
+        });
+        // Identical code folding (ICF) can leave multiple public symbols at the same
+        // address. Keep the names of the ones we remove, so that they can be listed by
+        // `folded_function_names`.
+        let mut folded_functions = Vec::new();
+        global_functions.dedup_by(|entry, kept| {
+            if entry.start_offset != kept.start_offset {
+                return false;
+            }
+            if entry.name.is_some() {
+                folded_functions.push(entry.clone());
+            }
+            true
+        });
+
+        Ok(Self {
+            module_info_provider,
+            address_map,
+            global_symbols,
+            debug_info,
+            section_contributions,
+            string_table,
+            type_formatter,
+            global_functions,
+            folded_functions,
+            absolute_symbols,
+            sections: sections.to_vec(),
+            flags: ContextFlags::default(),
+            symbol_name_handling: NonUtf8Handling::default(),
+            file_name_handling: NonUtf8Handling::default(),
+            max_inline_depth: None,
+            max_public_function_size: None,
+            debug_id_match: DebugIdMatch::Unchecked,
+            pdb_identity: None,
+            #[cfg(feature = "pe")]
+            pe_exports: None,
+            timings: RefCell::new(Timings {
+                publics_scan: start.elapsed(),
+                ..Default::default()
+            }),
+            diagnostics_callback: None,
+            progress_callback: None,
+            cancellation_token: None,
+            shared_module_cache: None,
+            function_ids: Default::default(),
+            missing_string_table_reported: Cell::new(false),
+            unresolved_file_names_reported: Default::default(),
+            cache: RefCell::new(ContextCache {
+                module_cache: BasicModuleInfoCache {
+                    cache: Default::default(),
+                    unreadable_modules: Default::default(),
+                    malformed_symbols: Default::default(),
+                    imported_procedures: Default::default(),
+                    compact_modules: false,
+                    module_info_provider,
+                },
+                function_line_cache: Default::default(),
+                procedure_cache: Default::default(),
+                extended_module_cache: Default::default(),
+                inline_name_cache: Default::default(),
+                full_rva_list: Default::default(),
+                frames_cache: Default::default(),
+                memory_budget: Default::default(),
+                trampolines: Default::default(),
+            }),
+        })
+    }
+
+    /// Change the flags of this context. This should be done before the first lookup,
+    /// because some of the cached information depends on the flags.
+    pub fn set_flags(&mut self, flags: ContextFlags) {
+        self.flags = flags;
+        let cache = self.cache.get_mut();
+        cache.module_cache.compact_modules = flags.contains(ContextFlags::COMPACT_MODULES);
+        cache.frames_cache.clear();
+    }
+
+    /// The flags of this context.
+    pub fn flags(&self) -> ContextFlags {
+        self.flags
+    }
+
+    /// Choose how symbol names and file names which are not valid UTF-8 are handled
+    /// in lookup results. The default is [`NonUtf8Handling::Lossy`] for both.
+    ///
+    /// A symbol name is invalid if the bytes of the function's name in its symbol
+    /// record, or in its id record for inlined functions, are not valid UTF-8. Type
+    /// names in the signature aren't checked. With [`NonUtf8Handling::Raw`],
+    /// [`Function::name`] is `None` for such names; the raw bytes are only available
+    /// in [`Frame::raw_function`].
+    pub fn set_non_utf8_handling(
+        &mut self,
+        symbol_names: NonUtf8Handling,
+        file_names: NonUtf8Handling,
+    ) {
+        self.symbol_name_handling = symbol_names;
+        self.file_name_handling = file_names;
+        self.cache.get_mut().frames_cache.clear();
+    }
+
+    /// Limit the number of inlined frames of all lookups, counted from the outer
+    /// function. Inline ranges deeper than this aren't looked at, and their names
+    /// aren't formatted. [`LookupOptions::max_inline_depth`] can lower the limit for a
+    /// single lookup. `None`, the default, means no limit.
+    pub fn set_max_inline_depth(&mut self, max_inline_depth: Option<usize>) {
+        self.max_inline_depth = max_inline_depth;
+        self.cache.get_mut().frames_cache.clear();
+    }
+
+    /// Limit the size of functions which are only known from public symbols. The
+    /// size of such a function is estimated from the address of the next public
+    /// symbol, which can be megabytes away in a PDB without private symbols, e.g.
+    /// if a stripped function or data follows it. Addresses further than
+    /// `max_size` bytes from the start of the public symbol aren't attributed to
+    /// it, and lookups for them find no function.
+    ///
+    /// `None`, the default, means no limit. Procedures aren't affected.
+    pub fn set_max_public_function_size(&mut self, max_size: Option<u32>) {
+        self.max_public_function_size = max_size;
+        self.cache.get_mut().frames_cache.clear();
+    }
+
+    /// Limit the length of the formatted names of procedures and inlined functions,
+    /// see [`TypeFormatter::set_max_name_length`]. Names which were already
+    /// formatted are dropped from the cache, so later lookups use the new limit.
+    ///
+    /// If the context uses a borrowed [`TypeFormatter`], the limit applies to all
+    /// users of that formatter.
+    pub fn set_max_name_length(&mut self, max_name_length: Option<usize>) {
+        self.type_formatter.set_max_name_length(max_name_length);
+        let cache = self.cache.get_mut();
+        for procedure_info in cache.procedure_cache.values_mut() {
+            procedure_info.name = None;
+        }
+        cache.inline_name_cache.clear();
+        cache.frames_cache.clear();
+    }
+
+    /// Remember the results of up to `capacity` calls to [`Context::find_frames_at`],
+    /// keyed by the probe address, so that repeated lookups of the same addresses,
+    /// e.g. of the hot addresses in a sampled profile, return a copy of the earlier
+    /// result instead of searching again. The least recently used results are
+    /// dropped first. A capacity of 0, the default, turns this off.
+    ///
+    /// Lookups which fail are not remembered. The results are not covered by
+    /// [`Context::set_memory_budget`].
+    pub fn set_frames_cache_capacity(&mut self, capacity: usize) {
+        let frames_cache = &mut self.cache.get_mut().frames_cache;
+        frames_cache.capacity = capacity;
+        frames_cache.clear();
+    }
+
+    /// Set a callback which is called whenever a lookup works around a problem with
+    /// the debug information, for example to collect telemetry about broken PDBs.
+    /// The lookups still succeed as well as they can. The same problem can be
+    /// reported more than once.
+    ///
+    /// The callback is called during the lookup, so it must not use this context.
+    pub fn set_diagnostics_callback(&mut self, callback: Option<DiagnosticsCallback>) {
+        self.diagnostics_callback = callback;
+    }
+
+    /// Set a callback which is called with the progress of the operations that go
+    /// over all modules, such as [`Context::all_inlinees`] and the first call to
+    /// [`Context::functions`]. It is called before each module is processed, and
+    /// once at the end.
+    ///
+    /// The callback is called during the operation, so it must not use this
+    /// context.
+    pub fn set_progress_callback(&mut self, callback: Option<ProgressCallback>) {
+        self.progress_callback = callback;
+    }
+
+    /// Set a token which cancels the operations that go over all modules. The token
+    /// is checked before each module, and a cancelled operation returns
+    /// [`Error::Cancelled`]. The information which was cached up to that point
+    /// stays valid.
+    ///
+    /// [`Context::functions`] and [`Context::size_breakdown`] can't fail, so they
+    /// can't be cancelled.
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.cancellation_token = token;
+    }
+
+    fn report_progress(&self, modules_processed: usize) {
+        if let Some(callback) = &self.progress_callback {
+            callback(Progress {
+                modules_processed,
+                module_count: self.type_formatter.modules().len(),
+            });
+        }
+    }
+
+    /// Report the progress before processing the module with the index
+    /// `module_index`, and check whether the operation was cancelled.
+    fn start_module(&self, module_index: usize) -> Result<()> {
+        self.report_progress(module_index);
+        match &self.cancellation_token {
+            Some(token) if token.is_cancelled() => Err(Error::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    fn report(&self, diagnostic: impl FnOnce() -> Diagnostic) {
+        if let Some(callback) = &self.diagnostics_callback {
+            callback(&diagnostic());
+        }
+    }
+
+    /// Report the records which the type formatter couldn't find while formatting
+    /// names, with one diagnostic per module.
+    fn report_unresolved_types(&self) {
+        let mut unresolved = self.type_formatter.take_unresolved_indexes();
+        while let Some(&(module_index, _)) = unresolved.first() {
+            let (module_indexes, other_indexes): (Vec<_>, Vec<_>) = unresolved
+                .into_iter()
+                .partition(|(other_module_index, _)| *other_module_index == module_index);
+            unresolved = other_indexes;
+            self.report(|| Diagnostic::UnresolvedTypes {
+                module_index,
+                indexes: module_indexes.into_iter().map(|(_, index)| index).collect(),
+            });
+        }
+    }
+
+    /// Translate `offset` to an rva, and report a diagnostic if that fails.
+    fn rva_or_report(&self, offset: PdbInternalSectionOffset) -> Option<u32> {
+        let rva = offset.to_rva(self.address_map).map(|rva| rva.0);
+        if rva.is_none() {
+            self.report(|| Diagnostic::UnmappableOffset {
+                section: offset.section,
+                offset: offset.offset,
+            });
+        }
+        rva
+    }
+
+    fn report_module_problems(&self, module_cache: &mut BasicModuleInfoCache<'a, 's>) {
+        let module_name = |module_index: usize| {
+            self.type_formatter
+                .modules()
+                .get(module_index)
+                .map(|module| module.module_name().into_owned())
+                .unwrap_or_default()
+        };
+        for (module_index, error) in module_cache.unreadable_modules.drain(..) {
+            self.report(|| Diagnostic::UnreadableModule {
+                module_index,
+                module_name: module_name(module_index),
+                error: error.to_string(),
+            });
+        }
+        for (module_index, symbol_index, error) in module_cache.malformed_symbols.drain(..) {
+            self.report(|| Diagnostic::MalformedSymbol {
+                module_index,
+                module_name: module_name(module_index),
+                symbol_offset: symbol_index.0,
+                error: error.to_string(),
+            });
+        }
+    }
+
+    /// The time spent so far in each phase of reading the PDB. The lookup phases
+    /// are only measured if [`ContextFlags::TIMINGS`] is set.
+    pub fn timings(&self) -> Timings {
+        self.timings.borrow().clone()
+    }
+
+    /// Returns the start time of a lookup phase, if timings are being measured.
+    fn start_phase(&self) -> Option<Instant> {
+        if self.flags.contains(ContextFlags::TIMINGS) {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    /// Add the time since `start` to the phase which is selected by `phase`.
+    fn end_phase(&self, start: Option<Instant>, phase: fn(&mut Timings) -> &mut Duration) {
+        if let Some(start) = start {
+            *phase(&mut self.timings.borrow_mut()) += start.elapsed();
+        }
+    }
+
+    /// Set whether the PDB was verified to match the image. Contexts created with
+    /// [`ContextPdbData`] get this from [`ContextPdbData::verify_debug_id`].
+    pub fn set_debug_id_match(&mut self, debug_id_match: DebugIdMatch) {
+        self.debug_id_match = debug_id_match;
+        self.cache.get_mut().frames_cache.clear();
+    }
+
+    /// Whether the PDB was verified to match the image. If the PDB was accepted
+    /// despite an age mismatch, all results from this context are marked as
+    /// unverified.
+    pub fn debug_id_match(&self) -> DebugIdMatch {
+        self.debug_id_match
+    }
+
+    /// Set the GUID and the age of the PDB, which identify the PDB in cache snapshots,
+    /// see [`Context::export_cache`]. Contexts created with [`ContextPdbData`] get
+    /// these from the PDB.
+    pub fn set_pdb_identity(&mut self, guid: Uuid, age: u32) {
+        self.pdb_identity = Some((guid, age));
+    }
+
+    /// Save the information that this context has computed so far, i.e. the
+    /// procedure lists of the modules that were read, and the formatted names, the
+    /// line tables and the inline ranges of the functions that were looked up, so that a later run over the same PDB can skip computing it
+    /// again, see [`Context::import_cache`].
+    ///
+    /// Returns [`Error::UnknownPdbIdentity`] if the GUID and age of the PDB are not
+    /// known.
+    pub fn export_cache(&self) -> Result<CacheSnapshot> {
+        let (guid, age) = self.pdb_identity.ok_or(Error::UnknownPdbIdentity)?;
+        let cache = self.cache.borrow();
+        let mut names: Vec<_> = cache
+            .procedure_cache
+            .iter()
+            .filter_map(|(offset, info)| {
+                Some((
+                    *offset,
+                    info.name.as_ref()?.as_ref().map(|(name, _)| name.clone()),
+                ))
+            })
+            .collect();
+        let mut lines: Vec<_> = cache
+            .function_line_cache
+            .iter()
+            .filter_map(|(offset, info)| match &info.lines {
+                Some(Ok(lines)) => Some((*offset, lines.clone())),
+                _ => None,
+            })
+            .collect();
+        let mut inline_ranges: Vec<_> = cache
+            .procedure_cache
+            .iter()
+            .filter_map(|(offset, info)| match &info.inline_ranges {
+                Some(Ok(ranges)) => Some((*offset, ranges.clone())),
+                _ => None,
+            })
+            .collect();
+        // Sort the entries so that the same cache contents give the same bytes.
+        names.sort_unstable_by_key(|(offset, _)| (offset.section, offset.offset));
+        lines.sort_unstable_by_key(|(offset, _)| (offset.section, offset.offset));
+        inline_ranges.sort_unstable_by_key(|(offset, _)| (offset.section, offset.offset));
+        let module_cache = &cache.module_cache;
+        let mut procedures: Vec<_> = module_cache
+            .cache
+            .iter()
+            .filter_map(|(module_index, info)| {
+                Some((*module_index, info.as_ref()?.to_module_procedures()))
+            })
+            .collect();
+        // Keep the imported lists of the modules which haven't been read since.
+        procedures.extend(
+            module_cache
+                .imported_procedures
+                .iter()
+                .filter(|(module_index, _)| !module_cache.cache.contains_key(module_index))
+                .map(|(module_index, imported)| (*module_index, imported.clone())),
+        );
+        procedures.sort_unstable_by_key(|(module_index, _)| *module_index);
+        Ok(CacheSnapshot {
+            guid,
+            age,
+            context_flags: self.flags.bits(),
+            formatter_flags: self.type_formatter.flags().bits(),
+            names,
+            lines,
+            inline_ranges,
+            procedures,
+        })
+    }
+
+    /// Fill the caches of this context from a snapshot which was made with
+    /// [`Context::export_cache`], usually in an earlier run. Information which this
+    /// context has already computed is kept.
+    ///
+    /// Returns [`Error::StaleCacheSnapshot`] if the snapshot was made from a PDB with
+    /// a different GUID or age, or with different [`ContextFlags`] or
+    /// [`TypeFormatterFlags`], because the cached information would not match.
+    pub fn import_cache(&self, snapshot: &CacheSnapshot) -> Result<()> {
+        let (guid, age) = self.pdb_identity.ok_or(Error::UnknownPdbIdentity)?;
+        if snapshot.guid != guid
+            || snapshot.age != age
+            || snapshot.context_flags != self.flags.bits()
+            || snapshot.formatter_flags != self.type_formatter.flags().bits()
+        {
+            return Err(Error::StaleCacheSnapshot);
+        }
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            function_line_cache,
+            procedure_cache,
+            ..
+        } = &mut *cache;
+        for (module_index, imported) in &snapshot.procedures {
+            module_cache
+                .imported_procedures
+                .entry(*module_index)
+                .or_insert_with(|| imported.clone());
+        }
+        for (offset, name) in &snapshot.names {
+            let info = procedure_cache.entry(*offset).or_default();
+            info.name
+                .get_or_insert_with(|| name.clone().map(|name| (name, None)));
+        }
+        for (offset, lines) in &snapshot.lines {
+            let info = function_line_cache.entry(*offset).or_default();
+            info.lines.get_or_insert_with(|| Ok(lines.clone()));
+        }
+        for (offset, ranges) in &snapshot.inline_ranges {
+            let info = procedure_cache.entry(*offset).or_default();
+            info.inline_ranges.get_or_insert_with(|| Ok(ranges.clone()));
+        }
+        Ok(())
+    }
+
+    /// The address map of the PDB, which translates between the addresses in the
+    /// final image and the addresses used in the PDB's symbols. Use it to convert
+    /// data which was read with the pdb crate without loading a second copy of the
+    /// map. For the common conversions, see [`Context::offset_for_rva`] and
+    /// [`Context::rva_for_offset`].
+    pub fn address_map(&self) -> &'a AddressMap<'s> {
+        self.address_map
+    }
+
+    /// Translate an rva in the final image to the corresponding "original" rva, i.e.
+    /// the address before the image was rearranged by a post-link optimizer such as
+    /// BBT. Such images have OMAP tables in their PDB; for all other images this
+    /// returns the rva unchanged.
+    ///
+    /// Returns `None` if the address has no counterpart in the original image.
+    pub fn original_rva_for_rva(&self, rva: Rva32) -> Option<u32> {
+        Rva(rva.0)
+            .to_internal_rva(self.address_map)
+            .map(|original_rva| original_rva.0)
+    }
+
+    /// Translate an "original" rva, from before the image was rearranged by a
+    /// post-link optimizer, to the rva in the final image. This is the inverse of
+    /// [`Context::original_rva_for_rva`].
+    ///
+    /// Returns `None` if the address has no counterpart in the final image.
+    pub fn rva_for_original_rva(&self, original_rva: u32) -> Option<Rva32> {
+        PdbInternalRva(original_rva)
+            .to_rva(self.address_map)
+            .map(|rva| Rva32(rva.0))
+    }
+
+    /// Translate an rva in the final image to the section offset used by the PDB's
+    /// symbols, see [`Context::find_frames_at_offset`].
+    pub fn offset_for_rva(&self, rva: Rva32) -> Option<PdbInternalSectionOffset> {
+        Rva(rva.0).to_internal_offset(self.address_map)
+    }
+
+    /// Translate a section offset from the PDB's symbols to an rva in the final image.
+    pub fn rva_for_offset(&self, offset: PdbInternalSectionOffset) -> Option<Rva32> {
+        offset.to_rva(self.address_map).map(|rva| Rva32(rva.0))
+    }
+
+    /// Use the export table of the PE image as a last resort in
+    /// [`Context::find_function_at`] and [`Context::find_frames_at`], for addresses which
+    /// aren't covered by any public symbol, procedure or section contribution in the
+    /// PDB. Functions and frames found this way have the provenance
+    /// [`Provenance::PeExport`].
+    #[cfg(feature = "pe")]
+    pub fn set_pe_exports(&mut self, pe_exports: Option<PeExports>) {
+        self.pe_exports = pe_exports;
+        self.cache.get_mut().frames_cache.clear();
+    }
+
+    /// Limit the memory used by this context's caches to roughly `budget` bytes, or
+    /// remove the limit with `None`.
+    ///
+    /// When the cached information exceeds the budget after a lookup, the caches of
+    /// the least-recently-used modules are dropped: their procedure lists, line
+    /// programs and inlinee tables, and the line tables, names and inline ranges of
+    /// their procedures. Dropped information is recomputed if it is needed again.
+    ///
+    /// The caches of the module that was used by the most recent lookup are always
+    /// kept, so a single large module can exceed the budget. The module streams
+    /// themselves are owned by the [`ContextPdbData`] and are not covered by the
+    /// budget. Information that was cached before a budget was set is not
+    /// accounted, so this should be called before the first lookup.
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) {
+        let cache = self.cache.get_mut();
+        cache.memory_budget.limit = budget;
+        self.enforce_memory_budget();
+    }
+
+    /// The memory budget of this context, see [`Context::set_memory_budget`].
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.cache.borrow().memory_budget.limit
+    }
+
+    /// Drop the procedure list, inlinee table and line program that this context
+    /// has parsed from the module with the index `module_index`, and return whether
+    /// anything was cached for it.
+    ///
+    /// The names, line tables and inline ranges which were already computed for
+    /// the module's functions are kept, so repeated lookups of those functions stay
+    /// cheap. Lookups of other functions in the module parse the module again.
+    ///
+    /// The module stream itself is owned by the [`ContextPdbData`] and stays in
+    /// memory until it is dropped. To avoid keeping the streams, use
+    /// [`ContextFlags::COMPACT_MODULES`].
+    pub fn release_module(&self, module_index: usize) -> bool {
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            extended_module_cache,
+            memory_budget,
+            ..
+        } = &mut *cache;
+        let had_module_info = module_cache.cache.remove(&module_index).is_some();
+        let had_extended_info = extended_module_cache.remove(&module_index).is_some();
+        if let Some(module_usage) = memory_budget.modules.remove(&module_index) {
+            memory_budget.total_size -= module_usage.size;
+        }
+        had_module_info || had_extended_info
+    }
+
+    /// Returns an approximate breakdown of the memory used by this context's caches,
+    /// and by the module streams that were read on its behalf.
+    ///
+    /// The numbers are estimates based on the sizes and capacities of the cached
+    /// collections; allocator overhead is not included.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let cache = self.cache.borrow();
+        let mut usage = MemoryUsage {
+            lookup_tables: vec_size(&self.global_functions)
+                + vec_size(&self.absolute_symbols)
+                + vec_size(&self.section_contributions)
+                + cache.full_rva_list.as_deref().map_or(0, vec_size),
+            procedures: hash_map_size(&cache.module_cache.cache),
+            line_tables: hash_map_size(&cache.function_line_cache),
+            names: btree_map_size(&cache.inline_name_cache),
+            inline_ranges: hash_map_size(&cache.procedure_cache),
+            ..Default::default()
+        };
+        for module in cache.module_cache.cache.values().flatten() {
+            usage.module_streams += module.symbols_size;
+            if let ModuleData::Compact(compact_module) = module.module_info {
+                usage.compact_modules += compact_module.heap_size();
+            }
+            usage.procedures += vec_size(&module.procedures);
+        }
+        for function_line_info in cache.function_line_cache.values() {
+            if let Some(Ok(lines)) = &function_line_info.lines {
+                usage.line_tables += vec_size(lines);
+            }
+        }
+        for procedure_info in cache.procedure_cache.values() {
+            if let Some(Some((name, layout))) = &procedure_info.name {
+                usage.names +=
+                    name.capacity() + layout.as_ref().map_or(0, FunctionNameLayout::heap_size);
+            }
+            if let Some(Ok(inline_ranges)) = &procedure_info.inline_ranges {
+                usage.inline_ranges += vec_size(inline_ranges);
+            }
+        }
+        for (name, layout) in cache.inline_name_cache.values().flatten() {
+            usage.names += name.capacity() + layout.as_ref().map_or(0, |l| l.heap_size());
+        }
+        for extended_module_info in cache.extended_module_cache.values().flatten() {
+            usage.inline_ranges += btree_map_size(&extended_module_info.inlinees);
+        }
+        usage
+    }
+
+    /// Format the name of the inlined function `id_index`, in the same way as the
+    /// names of inlined frames in [`Context::find_frames_at`], and using the same cache.
+    /// `module_index` is the index of the module whose inline site refers to the
+    /// function; it is needed to resolve cross-module references.
+    ///
+    /// This is useful for tools which walk the inline sites of a module themselves,
+    /// with the pdb crate.
+    pub fn inlinee_name(&self, module_index: usize, id_index: IdIndex) -> Result<String> {
+        let mut cache = self.cache.borrow_mut();
+        let start = self.start_phase();
+        let name = cache.inline_name_cache.entry(id_index).or_insert_with(|| {
+            self.type_formatter
+                .format_id_with_layout(module_index, id_index)
+        });
+        self.end_phase(start, |t| &mut t.formatting);
+        self.report_unresolved_types();
+        match name {
+            Ok((name, _)) => Ok(name.clone()),
+            // Errors can't be cloned, so format the name again to get a fresh one.
+            Err(_) => self.type_formatter.format_id(module_index, id_index),
+        }
+    }
+
+    /// Returns the line program of the module, from the same cache that
+    /// [`Context::find_frames_at`] uses, so that the module stream isn't parsed again.
+    /// This is useful for tools which run their own queries against the line
+    /// information, with the pdb crate.
+    ///
+    /// Returns `Ok(None)` if the module doesn't exist or if it only has line
+    /// information in the legacy C11 format, which the pdb crate can't represent.
+    pub fn line_program(&self, module_index: usize) -> Result<Option<ModuleLineProgram<'a, 's>>> {
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            extended_module_cache,
+            ..
+        } = &mut *cache;
+        let module_info =
+            match module_cache.get_basic_module_info(self.type_formatter.modules(), module_index) {
+                Some(basic_module_info) => basic_module_info.module_info,
+                None => return Ok(None),
+            };
+        let info = self.get_stream_module_info(extended_module_cache, module_index, module_info)?;
+        Ok(match info.lines {
+            ModuleLines::C13(_) => Some(ModuleLineProgram { info }),
+            _ => None,
+        })
+    }
+
+    /// Returns every distinct inlined function, with the number of places it was
+    /// inlined into and the amount of code this produced, aggregated across all
+    /// modules. Functions are identified by their formatted name. The list is
+    /// sorted by the number of inlined bytes, largest first.
+    ///
+    /// This reads the symbols of all modules, so it is slow for large PDBs.
+    pub fn all_inlinees(&self) -> Result<Vec<InlineeSummary>> {
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            extended_module_cache,
+            ..
+        } = &mut *cache;
+
+        let mut summaries: HashMap<Option<String>, InlineeSummary> = HashMap::new();
+        let modules = self.type_formatter.modules();
+        for module_index in 0..modules.len() {
+            self.start_module(module_index)?;
+            let BasicModuleInfo {
+                procedures,
+                module_info,
+                ..
+            } = match module_cache.get_basic_module_info(modules, module_index) {
+                Some(basic_module_info) => basic_module_info,
+                None => continue,
+            };
+            let info =
+                self.get_stream_module_info(extended_module_cache, module_index, *module_info)?;
+            let module_info = self.module_stream(module_index, info.module_info)?;
+            let inlinees = &info.inlinees;
+
+            // Inlinee ids are only unique within a module, so aggregate per module
+            // first, and then by name.
+            let mut module_sites: HashMap<IdIndex, (u64, usize)> = HashMap::new();
+            for proc in procedures {
+                add_inline_site_sizes(module_info, proc, inlinees, &mut module_sites).map_err(
+                    |e| self.module_error(module_index, "reading the procedure's inline sites", e),
+                )?;
+            }
+            for (inlinee, (inlined_bytes, call_sites)) in module_sites {
+                let name = self.type_formatter.format_id(module_index, inlinee).ok();
+                let summary = summaries
+                    .entry(name.clone())
+                    .or_insert_with(|| InlineeSummary {
+                        name,
+                        inlined_bytes: 0,
+                        call_sites: 0,
+                    });
+                summary.inlined_bytes += inlined_bytes;
+                summary.call_sites += call_sites;
+            }
+        }
+        self.report_progress(modules.len());
+
+        let mut summaries: Vec<InlineeSummary> = summaries.into_values().collect();
+        summaries.sort_by(|a, b| {
+            b.inlined_bytes
+                .cmp(&a.inlined_bytes)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Ok(summaries)
+    }
+
+    /// Returns the size of the function which contains the provided address, how
+    /// many of its bytes are its own code, and how many bytes each inlined function
+    /// accounts for. This is computed from the inline ranges which are also used by
+    /// [`Context::find_frames_at`].
+    ///
+    /// Functions which are only known from public symbols have no inline
+    /// information, so all of their code counts as their own.
+    pub fn function_size_metrics(&self, probe: Rva32) -> Result<Option<FunctionSizeMetrics>> {
+        let probe = probe.0;
+        let function = match self.find_function_at_rva(probe, false)? {
+            Some(function) => function,
+            None => return Ok(None),
+        };
+        let total_size = function
+            .end_rva
+            .map_or(0, |end_rva| end_rva.saturating_sub(function.start_rva));
+        let mut metrics = FunctionSizeMetrics {
+            name: function.name,
+            start_rva: function.start_rva,
+            total_size,
+            self_size: total_size,
+            inlinees: Vec::new(),
+        };
+        let offset = match Rva(function.start_rva).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(Some(metrics)),
+        };
+
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            procedure_cache,
+            extended_module_cache,
+            inline_name_cache,
+            ..
+        } = &mut *cache;
+        let (module_index, module_info, proc, proc_end_offset) =
+            match self.lookup_function(offset, module_cache) {
+                Some(PublicOrProcedureSymbol::Procedure(
+                    module_index,
+                    module_info,
+                    _,
+                    proc,
+                    end_offset,
+                )) => (module_index, module_info, proc, end_offset),
+                _ => return Ok(Some(metrics)),
+            };
+        let ExtendedModuleInfo { inlinees, .. } =
+            self.get_extended_module_info(extended_module_cache, module_index, module_info)?;
+        let inline_ranges = procedure_cache
+            .entry(proc.offset)
+            .or_default()
+            .get_inline_ranges(module_info, proc, inlinees, &mut Vec::new())
+            .map_err(|e| {
+                self.module_error(module_index, "computing the procedure's inline ranges", e)
+            })?;
+
+        // Ranges of nested and recursive inlinees overlap, so merge them before
+        // counting the bytes.
+        let mut outermost_ranges = Vec::new();
+        let mut ranges_by_inlinee: BTreeMap<IdIndex, Vec<(u32, u32)>> = BTreeMap::new();
+        for range in inline_ranges {
+            let start_offset = range.start_offset.max(proc.offset.offset);
+            let end_offset = range.end_offset.min(proc_end_offset);
+            if start_offset >= end_offset {
+                continue;
+            }
+            if range.call_depth == 0 {
+                outermost_ranges.push((start_offset, end_offset));
+            }
+            ranges_by_inlinee
+                .entry(range.inlinee)
+                .or_default()
+                .push((start_offset, end_offset));
+        }
+        metrics.self_size = total_size.saturating_sub(merged_range_length(outermost_ranges));
+
+        let mut sizes: HashMap<Option<String>, u32> = HashMap::new();
+        for (inlinee, ranges) in ranges_by_inlinee {
+            let name = inline_name_cache
+                .entry(inlinee)
+                .or_insert_with(|| {
+                    self.type_formatter
+                        .format_id_with_layout(module_index, inlinee)
+                })
+                .as_ref()
+                .ok()
+                .map(|(name, _)| name.clone());
+            *sizes.entry(name).or_default() += merged_range_length(ranges);
+        }
+        metrics.inlinees = sizes
+            .into_iter()
+            .map(|(name, bytes)| InlinedCodeSize { name, bytes })
+            .collect();
+        metrics
+            .inlinees
+            .sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+        Ok(Some(metrics))
+    }
+
+    /// Returns the ranges of code which come from inlined functions, in the function
+    /// which contains the provided address, sorted by start address and then by
+    /// depth. Coverage and disassembly tools can use this to annotate inlined code
+    /// without looking up every address. An inline site whose line changes within
+    /// the site is split into one range per line.
+    ///
+    /// Returns an empty list if the address isn't in a procedure, e.g. if the
+    /// function is only known from a public symbol.
+    pub fn inline_ranges_for_function(&self, probe: Rva32) -> Result<Vec<InlinedRange>> {
+        let offset = match Rva(probe.0).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            procedure_cache,
+            extended_module_cache,
+            inline_name_cache,
+            ..
+        } = &mut *cache;
+        let (module_index, module_info, proc) = match self.lookup_function(offset, module_cache) {
+            Some(PublicOrProcedureSymbol::Procedure(module_index, module_info, _, proc, _)) => {
+                (module_index, module_info, proc)
+            }
+            _ => return Ok(Vec::new()),
+        };
+        let ExtendedModuleInfo {
+            inlinees, lines, ..
+        } = self.get_extended_module_info(extended_module_cache, module_index, module_info)?;
+        let inline_ranges = procedure_cache
+            .entry(proc.offset)
+            .or_default()
+            .get_inline_ranges(module_info, proc, inlinees, &mut Vec::new())
+            .map_err(|e| {
+                self.module_error(module_index, "computing the procedure's inline ranges", e)
+            })?;
+
+        let mut ranges = Vec::with_capacity(inline_ranges.len());
+        for range in inline_ranges {
+            // Malformed annotations can give ranges which end before they start.
+            let len = match range.end_offset.checked_sub(range.start_offset) {
+                Some(len) => len,
+                None => continue,
+            };
+            let start_rva = self
+                .rva_for_offset(PdbInternalSectionOffset {
+                    section: proc.offset.section,
+                    offset: range.start_offset,
+                })
+                .map(u32::from);
+            let (start_rva, end_rva) = match start_rva {
+                Some(start_rva) => (start_rva, start_rva.saturating_add(len)),
+                None => continue,
+            };
+            let function = inline_name_cache
+                .entry(range.inlinee)
+                .or_insert_with(|| {
+                    self.type_formatter
+                        .format_id_with_layout(module_index, range.inlinee)
+                })
+                .as_ref()
+                .ok()
+                .map(|(name, _)| name.clone());
+            let file = range
+                .file_index
+                .and_then(|file_index| self.resolve_raw_filename(module_index, lines, file_index))
+                .map(|name| name.to_string().into_owned());
+            ranges.push(InlinedRange {
+                start_rva,
+                end_rva,
+                depth: range.call_depth,
+                function,
+                file,
+                line: range.line_start.and_then(|line| self.line_number(line)),
+            });
+        }
+        self.report_unresolved_types();
+        ranges.sort_by_key(|range| (range.start_rva, range.depth));
+        Ok(ranges)
+    }
+
+    /// Returns the undecoded line information of the inline sites in the procedure
+    /// which contains the provided address: the binary annotations of each
+    /// `S_INLINESITE` record, in the order of the records, i.e. each site is
+    /// followed by the sites which are nested in it. This is meant for tools which
+    /// want to verify or re-derive the ranges from
+    /// [`Context::inline_ranges_for_function`].
+    ///
+    /// Returns an empty list if the address isn't in a procedure, e.g. if the
+    /// function is only known from a public symbol.
+    pub fn inline_site_annotations(&self, probe: Rva32) -> Result<Vec<InlineSiteAnnotations>> {
+        let offset = match Rva(probe.0).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut cache = self.cache.borrow_mut();
+        let (module_index, module_info, proc) =
+            match self.lookup_function(offset, &mut cache.module_cache) {
+                Some(PublicOrProcedureSymbol::Procedure(module_index, module_info, _, proc, _)) => {
+                    (module_index, module_info, proc)
+                }
+                _ => return Ok(Vec::new()),
+            };
+        let module_info = self.module_stream(module_index, module_info)?;
+        let read_error =
+            |e: pdb::Error| self.module_error(module_index, "reading the inline sites", e.into());
+        let mut sites = Vec::new();
+        // The end symbol indexes of the inline sites which contain the current symbol.
+        let mut site_ends: Vec<SymbolIndex> = Vec::new();
+        let mut symbols_iter = module_info
+            .symbols_at(proc.symbol_index)
+            .map_err(read_error)?;
+        let _proc_sym = symbols_iter.next().map_err(read_error)?;
+        while let Some(symbol) = symbols_iter.next().map_err(read_error)? {
+            if symbol.index() >= proc.end_symbol_index {
+                break;
+            }
+            while site_ends.last().is_some_and(|end| symbol.index() >= *end) {
+                site_ends.pop();
+            }
+            match symbol.parse() {
+                Ok(SymbolData::Procedure(p)) => {
+                    // This is a nested procedure. Skip it.
+                    symbols_iter.skip_to(p.end).map_err(read_error)?;
+                }
+                Ok(SymbolData::InlineSite(site)) => {
+                    let annotations = site
+                        .annotations
+                        .iter()
+                        .collect::<Vec<_>>()
+                        .map_err(read_error)?;
+                    sites.push((site.inlinee, site_ends.len() as u16, annotations));
+                    site_ends.push(site.end);
+                }
+                _ => {}
+            }
+        }
+        drop(cache);
+
+        Ok(sites
+            .into_iter()
+            .map(|(inlinee, depth, annotations)| InlineSiteAnnotations {
+                inlinee,
+                function: self.inlinee_name(module_index, inlinee).ok(),
+                depth,
+                annotations,
+            })
+            .collect())
+    }
+
+    /// Returns how many bytes of code each function and each module accounts for,
+    /// and how much of the executable sections isn't covered by any module, as a
+    /// size profile of the image.
+    ///
+    /// Procedures have known sizes; the space between a procedure's end and the next
+    /// function is counted as its padding. Functions from public symbols extend up
+    /// to the next function or to the end of their module's section contribution.
+    ///
+    /// This looks up every function, so it reads the symbols of all modules.
+    pub fn size_breakdown(&self) -> SizeBreakdown {
+        let modules = self.type_formatter.modules();
+        let mut module_sizes: HashMap<usize, ModuleSize> = HashMap::new();
+        let mut executable_bytes: u64 = self
+            .sections
+            .iter()
+            .filter(|section| section.characteristics.execute())
+            .map(|section| u64::from(section.virtual_size))
+            .sum();
+        for sc in &self.section_contributions {
+            let size = u64::from(sc.end_offset - sc.start_offset);
+            executable_bytes = executable_bytes.saturating_sub(size);
+            module_sizes
+                .entry(sc.module_index)
+                .or_insert_with(|| ModuleSize {
+                    module_index: sc.module_index,
+                    name: modules
+                        .get(sc.module_index)
+                        .map(|module| module.module_name().into_owned())
+                        .unwrap_or_default(),
+                    size: 0,
+                    function_bytes: 0,
+                    padding_bytes: 0,
+                })
+                .size += size;
+        }
+
+        let functions: Vec<Function> = self.functions().collect();
+        for gap in self.padding_gaps_between(&functions) {
+            if let Some(module_size) = module_sizes.get_mut(&gap.module_index) {
+                module_size.padding_bytes += u64::from(gap.size);
+            }
+        }
+        let mut function_sizes = Vec::with_capacity(functions.len());
+        for (index, function) in functions.iter().enumerate() {
+            let sc = self
+                .offset_for_rva(Rva32(function.start_rva))
+                .and_then(|offset| self.lookup_section_contribution(offset));
+            let sc_end_rva = sc.and_then(|sc| {
+                self.rva_for_offset(PdbInternalSectionOffset {
+                    offset: sc.end_offset,
+                    section: sc.section_index,
+                })
+                .map(u32::from)
+            });
+            let next_start_rva = functions.get(index + 1).map(|next| next.start_rva);
+            let boundary = next_start_rva.into_iter().chain(sc_end_rva).min();
+            let end_rva = function.end_rva.or(boundary).unwrap_or(function.start_rva);
+            let size = end_rva.saturating_sub(function.start_rva);
+            let padding = boundary.map_or(0, |boundary| boundary.saturating_sub(end_rva));
+            if let Some(module_size) = sc.and_then(|sc| module_sizes.get_mut(&sc.module_index)) {
+                // Code which overlaps the next function or leaves the contribution is
+                // only counted once.
+                let code_end_rva = boundary.map_or(end_rva, |boundary| end_rva.min(boundary));
+                module_size.function_bytes +=
+                    u64::from(code_end_rva.saturating_sub(function.start_rva));
+            }
+            function_sizes.push(FunctionSize {
+                name: function.name.clone(),
+                start_rva: function.start_rva,
+                module_index: sc.map(|sc| sc.module_index),
+                size,
+                padding,
+            });
+        }
+
+        let mut module_sizes: Vec<ModuleSize> = module_sizes.into_values().collect();
+        module_sizes.sort_by(|a, b| {
+            b.size
+                .cmp(&a.size)
+                .then_with(|| a.module_index.cmp(&b.module_index))
+        });
+        SizeBreakdown {
+            functions: function_sizes,
+            modules: module_sizes,
+            unattributed_bytes: executable_bytes,
+        }
+    }
+
+    /// Returns the ranges of the modules' section contributions to executable
+    /// sections which aren't covered by any function, sorted by address. These are
+    /// usually alignment padding between the functions of an object file, which
+    /// size-tracking tools can attribute to the module instead of leaving it
+    /// unaccounted for. [`ModuleSize::padding_bytes`] has the total per module.
+    ///
+    /// Procedures have known sizes, so the gaps are the bytes after their ends.
+    /// Functions from public symbols are assumed to extend up to the next function,
+    /// so they have no gaps after them.
+    ///
+    /// This looks up every function, so it reads the symbols of all modules.
+    pub fn padding_gaps(&self) -> Vec<PaddingGap> {
+        let functions: Vec<Function> = self.functions().collect();
+        self.padding_gaps_between(&functions)
+    }
+
+    /// The gaps between `functions`, which must be sorted by start address, within
+    /// each section contribution.
+    fn padding_gaps_between(&self, functions: &[Function]) -> Vec<PaddingGap> {
+        let mut gaps = Vec::new();
+        for sc in &self.section_contributions {
+            let sc_start_rva = match self
+                .rva_for_offset(PdbInternalSectionOffset {
+                    offset: sc.start_offset,
+                    section: sc.section_index,
+                })
+                .map(u32::from)
+            {
+                Some(rva) => rva,
+                None => continue,
+            };
+            let sc_end_rva = sc_start_rva + (sc.end_offset - sc.start_offset);
+            let first = functions.partition_point(|function| function.start_rva < sc_start_rva);
+            // A function which starts before the contribution can extend into it.
+            let mut covered_end_rva = first
+                .checked_sub(1)
+                .and_then(|index| functions[index].end_rva)
+                .map_or(sc_start_rva, |end_rva| end_rva.max(sc_start_rva));
+            let mut add_gap = |start_rva: u32, end_rva: u32| {
+                if start_rva < end_rva {
+                    gaps.push(PaddingGap {
+                        module_index: sc.module_index,
+                        start_rva,
+                        size: end_rva - start_rva,
+                    });
+                }
+            };
+            for (index, function) in functions.iter().enumerate().skip(first) {
+                if function.start_rva >= sc_end_rva {
+                    break;
+                }
+                add_gap(covered_end_rva, function.start_rva);
+                let next_start_rva = functions.get(index + 1).map(|next| next.start_rva);
+                let end_rva = function
+                    .end_rva
+                    .or(next_start_rva)
+                    .unwrap_or(sc_end_rva)
+                    .min(sc_end_rva);
+                covered_end_rva = covered_end_rva.max(end_rva);
+            }
+            add_gap(covered_end_rva, sc_end_rva);
+        }
+        gaps.sort_unstable_by_key(|gap| gap.start_rva);
+        gaps
+    }
+
+    /// Returns the modules which contribute to the static initializer tables, and
+    /// the functions which construct and destroy global variables, to find out
+    /// which object files run code at startup.
+    ///
+    /// The tables are found through the grouped sections that the linker records
+    /// in the `* Linker *` module. The start and end markers of the table,
+    /// `.CRT$XCA` and `.CRT$XCZ`, are not listed. The functions are found by the
+    /// names that MSVC gives them, so they are only listed if the PDB has
+    /// procedure symbols.
+    ///
+    /// This reads the symbols of all modules, so it is slow for large PDBs.
+    pub fn static_initializers(&self) -> Result<StaticInitializers> {
+        let mut cache = self.cache.borrow_mut();
+        let module_cache = &mut cache.module_cache;
+        let modules = self.type_formatter.modules();
+        let module_name = |module_index: usize| {
+            modules
+                .get(module_index)
+                .map(|module| module.module_name().into_owned())
+                .unwrap_or_default()
+        };
+
+        let mut table_groups = Vec::new();
+        let linker_module_index = modules
+            .iter()
+            .position(|module| module.module_name() == "* Linker *");
+        if let Some(linker_module_index) = linker_module_index {
+            if let Some(BasicModuleInfo { module_info, .. }) =
+                module_cache.get_basic_module_info(modules, linker_module_index)
+            {
+                let module_info = self.module_stream(linker_module_index, *module_info)?;
+                let mut symbols_iter = module_info.symbols().map_err(|e| {
+                    self.module_error(linker_module_index, "reading the symbols", e.into())
+                })?;
+                while let Some(symbol) = symbols_iter.next().map_err(|e| {
+                    self.module_error(linker_module_index, "reading the symbols", e.into())
+                })? {
+                    if symbol.raw_kind() != S_COFFGROUP {
+                        continue;
+                    }
+                    if let Some(group) = parse_coff_group(symbol.raw_bytes()) {
+                        if is_initializer_table(&group.name) {
+                            table_groups.push(group);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut table_contributions = Vec::new();
+        if !table_groups.is_empty() {
+            let mut section_contribution_iter = self
+                .debug_info
+                .section_contributions()
+                .map_err(Error::stream("DBI section contributions"))?;
+            while let Some(sc) = section_contribution_iter
+                .next()
+                .map_err(Error::stream("DBI section contributions"))?
+            {
+                let group = table_groups.iter().find(|group| {
+                    group.offset.section == sc.offset.section
+                        && group.offset.offset <= sc.offset.offset
+                        && sc.offset.offset < group.offset.offset + group.size
+                });
+                let (group, start_rva) =
+                    match (group, self.rva_for_offset(sc.offset).map(u32::from)) {
+                        (Some(group), Some(start_rva)) if sc.size != 0 => (group, start_rva),
+                        _ => continue,
+                    };
+                table_contributions.push(InitializerTableContribution {
+                    section_name: group.name.clone(),
+                    start_rva,
+                    size: sc.size,
+                    module_index: sc.module,
+                    module_name: module_name(sc.module),
+                });
+            }
+            table_contributions.sort_by_key(|contribution| contribution.start_rva);
+        }
+
+        let mut functions = Vec::new();
+        for module_index in 0..modules.len() {
+            self.start_module(module_index)?;
+            let procedures = match module_cache.get_basic_module_info(modules, module_index) {
+                Some(basic_module_info) => &basic_module_info.procedures,
+                None => continue,
+            };
+            for proc in procedures {
+                let name = proc.name.to_string();
+                let (kind, variable) = match parse_initializer_name(&name) {
+                    Some(initializer) => initializer,
+                    None => continue,
+                };
+                let start_rva = match self.rva_for_offset(proc.offset).map(u32::from) {
+                    Some(start_rva) => start_rva,
+                    None => continue,
+                };
+                functions.push(InitializerFunction {
+                    kind,
+                    variable,
+                    name: name.into_owned(),
+                    start_rva,
+                    module_index,
+                    module_name: module_name(module_index),
+                });
+            }
+        }
+        self.report_progress(modules.len());
+        functions.sort_by_key(|function| (function.start_rva, function.module_index));
+
+        Ok(StaticInitializers {
+            table_contributions,
+            functions,
+        })
+    }
+
+    /// Returns all procedures which have line records in the source file at `path`,
+    /// with the address ranges of the code that comes from this file. The list is
+    /// sorted by address.
+    ///
+    /// Paths are compared after normalization: `/` and `\` are treated as the same
+    /// separator, `.` and `..` components are resolved, and ASCII case is ignored,
+    /// because the paths in PDBs are usually Windows paths.
+    ///
+    /// Only the procedures' own line records are considered, so code from this file
+    /// which was inlined into functions from other files is not listed. This reads
+    /// the line programs of all modules, so it is slow for large PDBs.
+    pub fn functions_in_file(&self, path: &str) -> Result<Vec<SourceFileFunction>> {
+        let string_table = self.string_table;
+        let wanted_path = normalize_path_for_comparison(path);
+
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            procedure_cache,
+            extended_module_cache,
+            ..
+        } = &mut *cache;
+
+        let mut functions = Vec::new();
+        let modules = self.type_formatter.modules();
+        for module_index in 0..modules.len() {
+            self.start_module(module_index)?;
+            let BasicModuleInfo {
+                procedures,
+                module_info,
+                ..
+            } = match module_cache.get_basic_module_info(modules, module_index) {
+                Some(basic_module_info) => basic_module_info,
+                None => continue,
+            };
+            let ExtendedModuleInfo { lines, .. } =
+                self.get_extended_module_info(extended_module_cache, module_index, *module_info)?;
+
+            let mut file_matches: HashMap<FileIndex, bool> = HashMap::new();
+            for (procedure_index, proc) in procedures.iter().enumerate() {
+                let mut ranges: Vec<(u32, u32)> = Vec::new();
+                let proc_lines = lines
+                    .lines_for_symbol(proc.offset, Some(proc.len))
+                    .map_err(|e| {
+                        self.module_error(module_index, "reading the procedure's lines", e)
+                    })?;
+                for line_info in proc_lines {
+                    let is_match = *file_matches.entry(line_info.file_index).or_insert_with(|| {
+                        lines
+                            .file_name(line_info.file_index, string_table)
+                            .is_some_and(|name| {
+                                normalize_path_for_comparison(&name.to_string()) == wanted_path
+                            })
+                    });
+                    let length = line_info.length.unwrap_or(0);
+                    if !is_match || length == 0 {
+                        continue;
+                    }
+                    let start_offset = PdbInternalSectionOffset {
+                        section: proc.offset.section,
+                        offset: line_info.offset.offset,
+                    };
+                    if let Some(start_rva) = start_offset.to_rva(self.address_map) {
+                        ranges.push((start_rva.0, start_rva.0.saturating_add(length)));
+                    }
+                }
+                if ranges.is_empty() {
+                    continue;
+                }
+                let start_rva = match proc.offset.to_rva(self.address_map) {
+                    Some(rva) => rva.0,
+                    None => continue,
+                };
+                let end_offset = self.procedure_end_offset(procedures, procedure_index);
+
+                ranges.sort_unstable();
+                let mut merged_ranges: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+                for (start, end) in ranges {
+                    match merged_ranges.last_mut() {
+                        Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                        _ => merged_ranges.push((start, end)),
+                    }
+                }
+
+                let name = procedure_cache
+                    .entry(proc.offset)
+                    .or_default()
+                    .get_name(
+                        proc,
+                        &self.type_formatter,
+                        &self.global_functions,
+                        module_index,
+                        *module_info,
+                        self.flags,
+                    )
+                    .map(String::from);
+                functions.push(SourceFileFunction {
+                    name,
+                    start_rva,
+                    end_rva: start_rva.saturating_add(end_offset - proc.offset.offset),
+                    ranges: merged_ranges,
+                });
+            }
+        }
+        self.report_progress(modules.len());
+        functions.sort_by_key(|function| function.start_rva);
+        Ok(functions)
+    }
+
+    /// Returns the index and name of the module (compiland) whose section
+    /// contribution contains the address. The name is usually the path of an object
+    /// file.
+    ///
+    /// This only searches the section contributions, which are read when the context
+    /// is created, so it's cheap and doesn't read any module streams.
+    pub fn module_for_address(&self, probe: Rva32) -> Option<(usize, Cow<'a, str>)> {
+        let offset = Rva(probe.0).to_internal_offset(self.address_map)?;
+        let sc = self.lookup_section_contribution(offset)?;
+        let module = self.type_formatter.modules().get(sc.module_index)?;
+        Some((sc.module_index, module.module_name()))
+    }
+
+    /// Iterate over all public symbols, of both code and data, in the order of the
+    /// global symbol stream.
+    ///
+    /// This reads the global symbol stream again, so it doesn't need any module
+    /// streams.
+    pub fn public_symbols(&self) -> PublicSymbolIter<'_, 'a, 's> {
+        PublicSymbolIter {
+            context: self,
+            symbol_iter: Some(self.global_symbols.iter()),
+        }
+    }
+
+    /// Returns all modules (compilands) of the PDB, in module index order.
+    ///
+    /// Whether a module has debug information is found out through the
+    /// [`ModuleProvider`]. [`ContextPdbData`] reads it from the DBI stream, without
+    /// reading the module streams.
+    pub fn modules(&self) -> Result<Vec<ModuleSummary>> {
+        self.type_formatter
+            .modules()
+            .iter()
+            .enumerate()
+            .map(|(module_index, module)| {
+                let debug_info = self
+                    .module_info_provider
+                    .module_debug_info(module_index, module)
+                    .map_err(|e| {
+                        self.module_error(module_index, "checking for debug info", e.into())
+                    })?;
+                Ok(ModuleSummary {
+                    module_index,
+                    name: module.module_name().into_owned(),
+                    object_file_name: module.object_file_name().into_owned(),
+                    has_debug_info: debug_info.has_symbols || debug_info.has_lines,
+                    has_symbols: debug_info.has_symbols,
+                    has_lines: debug_info.has_lines,
+                })
+            })
+            .collect()
+    }
+
+    /// Count the modules with symbols, with line information, and without either.
+    /// This explains why some addresses only resolve to public symbols; the flags
+    /// of each module are in [`Context::modules`].
+    pub fn debug_info_summary(&self) -> Result<DebugInfoSummary> {
+        let modules = self.modules()?;
+        Ok(DebugInfoSummary {
+            module_count: modules.len(),
+            modules_with_symbols: modules.iter().filter(|module| module.has_symbols).count(),
+            modules_with_lines: modules.iter().filter(|module| module.has_lines).count(),
+            stripped_modules: modules
+                .iter()
+                .filter(|module| !module.has_debug_info)
+                .count(),
+        })
+    }
+
+    /// The number of functions found in public symbols.
+    pub fn function_count(&self) -> usize {
+        self.global_functions.len()
+    }
+
+    /// Iterate over all functions in the modules.
+    pub fn functions(&self) -> FunctionIter<'_, 'a, 's> {
+        let full_rva_list = self.full_rva_list();
+        let end_index = full_rva_list.len();
+        FunctionIter {
+            context: self,
+            full_rva_list,
+            cur_index: 0,
+            end_index,
+        }
+    }
+
+    /// Like [`Context::functions`], but only iterates over the functions from the
+    /// modules which match `filter`. Functions which can't be attributed to a module
+    /// are skipped if the filter has any restrictions.
+    ///
+    /// This reads the symbols of the matching modules. Filtering by language also
+    /// needs to read the symbols of every module whose name matches.
+    ///
+    /// Panics if the index of [`FunctionFilter::shard`] is out of range.
+    pub fn functions_matching(&self, filter: &FunctionFilter) -> FunctionIter<'_, 'a, 's> {
+        if let Some((shard_index, shard_count)) = filter.shard {
+            assert!(
+                shard_index < shard_count,
+                "shard index {} is out of range for {} shards",
+                shard_index,
+                shard_count
+            );
+        }
+        let modules = self.type_formatter.modules();
+        let matching_modules: Vec<bool> = {
+            let mut cache = self.cache.borrow_mut();
+            modules
+                .iter()
+                .enumerate()
+                .map(|(module_index, module)| {
+                    filter.matches_module(module_index, module, || {
+                        let basic_module_info = cache
+                            .module_cache
+                            .get_basic_module_info(modules, module_index)?;
+                        basic_module_info.module_info.language()
+                    })
+                })
+                .collect()
+        };
+        // Only read the procedures of the matching modules, so that a shard doesn't
+        // read the module streams of the other shards.
+        let mut candidate_rvas: Vec<u32> = self
+            .global_functions
+            .iter()
+            .filter_map(|func| Some(func.start_offset.to_rva(self.address_map)?.0))
+            .collect();
+        {
+            let mut cache = self.cache.borrow_mut();
+            for module_index in (0..modules.len()).filter(|&i| matching_modules[i]) {
+                if let Some(BasicModuleInfo { procedures, .. }) = cache
+                    .module_cache
+                    .get_basic_module_info(modules, module_index)
+                {
+                    candidate_rvas.extend(
+                        procedures
+                            .iter()
+                            .filter_map(|proc| Some(proc.offset.to_rva(self.address_map)?.0)),
+                    );
+                }
+            }
+        }
+        candidate_rvas.sort_unstable();
+        candidate_rvas.dedup();
+
+        let has_restrictions = !filter.module_patterns.is_empty() || !filter.languages.is_empty();
+        let full_rva_list: Vec<u32> = candidate_rvas
+            .into_iter()
+            .filter(|&rva| {
+                let module_index = self
+                    .offset_for_rva(Rva32(rva))
+                    .and_then(|offset| self.lookup_section_contribution(offset))
+                    .map(|sc| sc.module_index);
+                match module_index {
+                    Some(module_index) => matching_modules.get(module_index) == Some(&true),
+                    None => {
+                        !has_restrictions
+                            && !matches!(filter.shard, Some((shard_index, _)) if shard_index != 0)
+                    }
+                }
+            })
+            .collect();
+        let end_index = full_rva_list.len();
+        FunctionIter {
+            context: self,
+            full_rva_list: Rc::new(full_rva_list),
+            cur_index: 0,
+            end_index,
+        }
+    }
+
+    fn full_rva_list(&self) -> Rc<Vec<u32>> {
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            full_rva_list,
+            module_cache,
+            ..
+        } = &mut *cache;
+        full_rva_list
+            .get_or_insert_with(|| Rc::new(self.compute_full_rva_list(module_cache)))
+            .clone()
+    }
+
+    /// Find the function whose code contains the provided address.
+    /// The return value only contains the function name and the rva range, but
+    /// no file or line information.
+    pub fn find_function_at(&self, probe: Rva32) -> Result<Option<Function>> {
+        let resolve_thunks = self
+            .flags
+            .contains(ContextFlags::RESOLVE_INCREMENTAL_LINKING_THUNKS);
+        self.find_function_at_rva(probe.0, resolve_thunks)
+    }
+
+    fn find_function_at_rva(&self, probe: u32, resolve_thunks: bool) -> Result<Option<Function>> {
+        let result = match Rva(probe).to_internal_offset(self.address_map) {
+            Some(offset) => {
+                let offset = match resolve_thunks {
+                    true => self.resolve_incremental_linking_thunk(offset),
+                    false => offset,
+                };
+                let result = self.find_function_impl(offset);
+                self.enforce_memory_budget();
+                self.apply_non_utf8_handling_to_function(result?)
+            }
+            None => Ok(None),
+        };
+        #[cfg(feature = "pe")]
+        if let Ok(None) = result {
+            return Ok(self.find_pe_export(probe));
+        }
+        result
+    }
+
+    /// Get the number of bytes that the arguments of the procedure which contains the
+    /// provided address take up on the stack, see
+    /// [`TypeFormatter::stack_parameter_size`]. This can be used to synthesize the
+    /// `_name@N` decoration of `__stdcall` functions on x86.
+    ///
+    /// Returns `None` if the address isn't in a procedure with type information, e.g.
+    /// if it is only covered by a public symbol.
+    pub fn stack_parameter_size(&self, probe: Rva32) -> Result<Option<u64>> {
+        let offset = match Rva(probe.0).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let mut cache = self.cache.borrow_mut();
+        let (module_index, type_index) = match self.lookup_function(offset, &mut cache.module_cache)
+        {
+            Some(PublicOrProcedureSymbol::Procedure(module_index, _, _, proc, _)) => {
+                (module_index, proc.type_index)
+            }
+            _ => return Ok(None),
+        };
+        drop(cache);
+        if type_index == TypeIndex(0) {
+            return Ok(None);
+        }
+        self.type_formatter
+            .stack_parameter_size(module_index, type_index)
+    }
+
+    /// Get the stack frame layout of the procedure which contains the provided
+    /// address, from its `S_FRAMEPROC` record.
+    ///
+    /// Returns `None` if the address isn't in a procedure, e.g. if it is only covered
+    /// by a public symbol, or if the procedure has no such record.
+    pub fn frame_layout(&self, probe: Rva32) -> Result<Option<FrameLayout>> {
+        let offset = match Rva(probe.0).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            procedure_cache,
+            ..
+        } = &mut *cache;
+        let record = match self.lookup_function(offset, module_cache) {
+            Some(PublicOrProcedureSymbol::Procedure(_, module_info, _, proc, _)) => procedure_cache
+                .entry(proc.offset)
+                .or_default()
+                .get_frame_info(module_info, proc)
+                .and_then(|info| info.frame_proc),
+            _ => None,
+        };
+        Ok(record.map(|record| FrameLayout {
+            frame_size: record.frame_size,
+            padding_size: record.padding_size,
+            padding_offset: record.padding_offset,
+            saved_registers_size: record.saved_registers_size,
+            exception_handler_rva: match record.exception_handler.section {
+                0 => None,
+                _ => record
+                    .exception_handler
+                    .to_rva(self.address_map)
+                    .map(|rva| rva.0),
+            },
+            local_base_register: FrameBaseRegister::from_encoded(record.flags >> 14),
+            parameter_base_register: FrameBaseRegister::from_encoded(record.flags >> 16),
+            flags: FrameFlags::from_bits_truncate(record.flags),
+        }))
+    }
+
+    /// Like [`Context::find_function_at`], but takes a section offset instead of an rva,
+    /// for callers which already have one, for example from iterating over symbols.
+    /// This skips the translation through the address map.
+    pub fn find_function_at_offset(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> Result<Option<Function>> {
+        let offset = self.resolve_incremental_linking_thunk_if_requested(offset);
+        let result = self.find_function_impl(offset);
+        self.enforce_memory_budget();
+        self.apply_non_utf8_handling_to_function(result?)
+    }
+
+    fn apply_non_utf8_handling_to_function(
+        &self,
+        function: Option<Function>,
+    ) -> Result<Option<Function>> {
+        let mut function = match function {
+            Some(function) => function,
+            None => return Ok(None),
+        };
+        if self.symbol_name_handling != NonUtf8Handling::Lossy {
+            let raw_name = self.raw_function_name(&function);
+            self.apply_non_utf8_handling_to_name(&mut function.name, raw_name)?;
+        }
+        Ok(Some(function))
+    }
+
+    fn apply_non_utf8_handling_to_frames<'f, S: AsRef<str>>(
+        &self,
+        frames: Option<FunctionFrames<'f, S>>,
+    ) -> Result<Option<FunctionFrames<'f, S>>> {
+        let mut frames = match frames {
+            Some(frames) => frames,
+            None => return Ok(None),
+        };
+        for frame in &mut frames.frames {
+            self.apply_non_utf8_handling_to_name(&mut frame.function, frame.raw_function)?;
+            let is_invalid = frame
+                .raw_file
+                .is_some_and(|raw_file| std::str::from_utf8(raw_file.as_bytes()).is_err());
+            if is_invalid {
+                match self.file_name_handling {
+                    NonUtf8Handling::Lossy => {}
+                    NonUtf8Handling::Error => {
+                        return Err(Error::InvalidUtf8(
+                            "file name",
+                            frame
+                                .raw_file
+                                .map(|raw_file| raw_file.to_string().into_owned())
+                                .unwrap_or_default(),
+                        ))
+                    }
+                    NonUtf8Handling::Raw => frame.file = None,
+                }
+            }
+        }
+        Ok(Some(frames))
+    }
+
+    fn apply_non_utf8_handling_to_name<S: AsRef<str>>(
+        &self,
+        name: &mut Option<S>,
+        raw_name: Option<RawString>,
+    ) -> Result<()> {
+        let is_invalid =
+            raw_name.is_some_and(|raw_name| std::str::from_utf8(raw_name.as_bytes()).is_err());
+        if is_invalid {
+            match self.symbol_name_handling {
+                NonUtf8Handling::Lossy => {}
+                NonUtf8Handling::Error => {
+                    return Err(Error::InvalidUtf8(
+                        "symbol name",
+                        name.take()
+                            .map(|name| name.as_ref().to_string())
+                            .unwrap_or_default(),
+                    ))
+                }
+                NonUtf8Handling::Raw => *name = None,
+            }
+        }
+        Ok(())
+    }
+
+    /// The name of the symbol which `function` was made from, as it is stored in the
+    /// PDB.
+    fn raw_function_name(&self, function: &Function) -> Option<RawString<'a>> {
+        let offset = function.start_offset?;
+        match function.provenance {
+            Provenance::PeExport => None,
+            Provenance::Trampoline => self.public_name_at(offset),
+            _ => {
+                let mut cache = self.cache.borrow_mut();
+                match self.lookup_function(offset, &mut cache.module_cache)? {
+                    PublicOrProcedureSymbol::Public(_, _, global_function_index) => {
+                        self.global_functions[global_function_index].name
+                    }
+                    PublicOrProcedureSymbol::Procedure(_, _, _, proc, _) => Some(proc.name),
+                }
+            }
+        }
+    }
+
+    /// The public symbols which have a constant value instead of an address, in the
+    /// order of the symbol table. These are never returned by lookups.
+    pub fn absolute_symbols(&self) -> &[AbsoluteSymbol] {
+        &self.absolute_symbols
+    }
+
+    /// Returns the names of all public symbols at the start address of the function
+    /// which contains `probe`.
+    ///
+    /// When the linker folds functions with identical code (identical COMDAT folding),
+    /// all but one of them are removed, and the public symbols of the removed functions
+    /// point at the remaining one. Lookups only return one of these names; this method
+    /// returns all of them. The name that lookups of public symbols return comes
+    /// first. The names are mangled ("decorated").
+    pub fn folded_function_names(&self, probe: Rva32) -> Result<Vec<String>> {
+        let start_rva = match self.find_function_at(probe)? {
+            Some(function) => function.start_rva,
+            None => return Ok(Vec::new()),
+        };
+        let offset = match Rva(start_rva).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(Vec::new()),
+        };
+        Ok(self
+            .public_names_at_offset(offset)
+            .map(|name| name.to_string().into_owned())
+            .collect())
+    }
+
+    /// The id of the function which starts at `start_rva`, see [`Function::id`].
+    fn function_id(&self, start_rva: u32) -> u32 {
+        let mut function_ids = self.function_ids.borrow_mut();
+        let next_id = function_ids.len() as u32;
+        *function_ids.entry(start_rva).or_insert(next_id)
+    }
+
+    fn count_other_folded_functions(&self, offset: PdbInternalSectionOffset) -> usize {
+        if !self.flags.contains(ContextFlags::FOLDED_FUNCTION_COUNTS) {
+            return 0;
+        }
+        self.public_names_at_offset(offset)
+            .count()
+            .saturating_sub(1)
+    }
+
+    fn public_names_at_offset(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> impl Iterator<Item = RawString<'a>> + '_ {
+        let key = (offset.section, offset.offset);
+        let sort_key =
+            |f: &PublicSymbolFunctionOrPlaceholder| (f.start_offset.section, f.start_offset.offset);
+        let kept = self
+            .global_functions
+            .binary_search_by_key(&key, sort_key)
+            .ok()
+            .and_then(|index| self.global_functions[index].name);
+        let folded_start = self.folded_functions.partition_point(|f| sort_key(f) < key);
+        let folded = self.folded_functions[folded_start..]
+            .iter()
+            .take_while(move |f| sort_key(f) == key)
+            .filter_map(|f| f.name);
+        kept.into_iter().chain(folded)
+    }
+
+    /// If `probe` is inside a jump thunk of the incremental linking table (ILT),
+    /// returns the rva of the function that the thunk jumps to.
+    ///
+    /// Incrementally linked binaries route calls through these thunks, so return
+    /// addresses and call targets often point into the ILT. The thunks are found in
+    /// the trampoline records of the linker's module.
+    pub fn incremental_linking_thunk_target(&self, probe: Rva32) -> Option<Rva32> {
+        let offset = Rva(probe.0).to_internal_offset(self.address_map)?;
+        let target = self.lookup_incremental_linking_thunk(offset)?;
+        target.to_rva(self.address_map).map(|rva| Rva32(rva.0))
+    }
+
+    fn resolve_incremental_linking_thunk_if_requested(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> PdbInternalSectionOffset {
+        if self
+            .flags
+            .contains(ContextFlags::RESOLVE_INCREMENTAL_LINKING_THUNKS)
+        {
+            self.resolve_incremental_linking_thunk(offset)
+        } else {
+            offset
+        }
+    }
+
+    fn resolve_incremental_linking_thunk(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> PdbInternalSectionOffset {
+        self.lookup_incremental_linking_thunk(offset)
+            .unwrap_or(offset)
+    }
+
+    /// Returns the target of the incremental linking thunk which contains `offset`.
+    fn lookup_incremental_linking_thunk(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> Option<PdbInternalSectionOffset> {
+        self.lookup_trampoline(offset)
+            .filter(|trampoline| trampoline.kind == TrampolineKind::IncrementalLinking)
+            .map(|trampoline| trampoline.target)
+    }
+
+    /// Returns the trampoline record which contains `offset`.
+    fn lookup_trampoline(&self, offset: PdbInternalSectionOffset) -> Option<TrampolineRecord> {
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            trampolines,
+            ..
+        } = &mut *cache;
+        let trampolines = trampolines.get_or_insert_with(|| self.compute_trampolines(module_cache));
+        find_trampoline(trampolines, offset)
+    }
+
+    /// Like `lookup_trampoline`, but only reads the trampolines if `offset` is in a
+    /// section contribution of the linker's module, or in none, so that lookups of
+    /// ordinary code don't need to read the linker's module.
+    fn lookup_linker_trampoline(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> Option<TrampolineRecord> {
+        if let Some(sc) = self.lookup_section_contribution(offset) {
+            let module = self.type_formatter.modules().get(sc.module_index)?;
+            if module.module_name() != "* Linker *" {
+                return None;
+            }
+        }
+        self.lookup_trampoline(offset)
+    }
+
+    fn compute_trampolines(
+        &self,
+        module_cache: &BasicModuleInfoCache<'a, 's>,
+    ) -> Vec<TrampolineRecord> {
+        let mut symbols = Vec::new();
+        for (module_index, module) in self.type_formatter.modules().iter().enumerate() {
+            if module.module_name() != "* Linker *" {
+                continue;
+            }
+            let module_info = match module_cache
+                .module_info_provider
+                .get_module_info(module_index, module)
+            {
+                Ok(Some(module_info)) => module_info,
+                _ => continue,
+            };
+            let mut symbols_iter = match module_info.symbols() {
+                Ok(symbols_iter) => symbols_iter,
+                Err(_) => continue,
+            };
+            while let Ok(Some(symbol)) = symbols_iter.next() {
+                if let Ok(SymbolData::Trampoline(trampoline)) = symbol.parse() {
+                    symbols.push(trampoline);
+                }
+            }
+        }
+        trampoline_records(symbols)
+    }
+
+    /// The function for an address inside a trampoline. Trampolines are preferred
+    /// over the public symbols which cover them, because the public symbols would
+    /// give them the size up to the next public symbol. The name is the one of the
+    /// public symbol at the start of the trampoline, if there is one.
+    fn trampoline_function(&self, trampoline: &TrampolineRecord) -> Option<Function> {
+        let start_rva = self.rva_or_report(trampoline.start_offset)?;
+        let name = self
+            .public_name_at(trampoline.start_offset)
+            .map(|name| name.to_string().into_owned());
+        let end_rva = Some(start_rva + u32::from(trampoline.size));
+        Some(Function {
+            start_rva,
+            end_rva,
+            estimated_end_rva: end_rva,
+            generated: name
+                .as_deref()
+                .and_then(GeneratedFunctionKind::from_function_name),
+            name,
+            provenance: Provenance::Trampoline,
+            other_folded_functions: 0,
+            unverified: self.debug_id_match.is_unverified(),
+            id: self.function_id(start_rva),
+            start_offset: Some(trampoline.start_offset),
+        })
+    }
+
+    /// The name of the public symbol which starts at `offset`, if any.
+    fn public_name_at(&self, offset: PdbInternalSectionOffset) -> Option<RawString<'a>> {
+        let index = self
+            .global_functions
+            .binary_search_by_key(&(offset.section, offset.offset), |p| {
+                (p.start_offset.section, p.start_offset.offset)
+            })
+            .ok()?;
+        self.global_functions[index].name
+    }
+
+    /// The frames for the address `offset` inside a trampoline, see
+    /// [`Context::trampoline_function`].
+    fn trampoline_frames<S>(
+        &self,
+        trampoline: &TrampolineRecord,
+        offset: PdbInternalSectionOffset,
+        make_name: &mut dyn FnMut(&str) -> S,
+    ) -> Option<FunctionFrames<'a, S>> {
+        let function = self.trampoline_function(trampoline)?;
+        let probe = self.rva_or_report(offset)?;
+        let frame = Frame {
+            function: function.name.as_deref().map(make_name),
+            raw_function: self.public_name_at(trampoline.start_offset),
+            function_name_layout: None,
+            file: None,
+            file_index: None,
+            raw_file: None,
+            file_checksum: None,
+            start_rva: function.start_rva,
+            end_rva: function.end_rva,
+            offset: probe - function.start_rva,
+            line: None,
+            line_end: None,
+            column: None,
+            column_end: None,
+            line_distance: None,
+            language: None,
+        };
+        Some(FunctionFrames {
+            start_rva: function.start_rva,
+            end_rva: function.end_rva,
+            frames: vec![frame],
+            is_optimized: None,
+            frame_flags: None,
+            other_folded_functions: 0,
+            unverified: function.unverified,
+            provenance: Provenance::Trampoline,
+            function_id: function.id,
+            start_offset: function.start_offset,
+        })
+    }
+
+    #[cfg(feature = "pe")]
+    fn find_pe_export(&self, probe: u32) -> Option<Function> {
+        let (name, start_rva, end_rva) = self.pe_exports.as_ref()?.lookup(probe)?;
+        Some(Function {
+            start_rva,
+            end_rva,
+            estimated_end_rva: end_rva,
+            name: Some(name.to_string()),
+            provenance: Provenance::PeExport,
+            other_folded_functions: 0,
+            unverified: self.debug_id_match.is_unverified(),
+            generated: GeneratedFunctionKind::from_function_name(name),
+            id: self.function_id(start_rva),
+            start_offset: self.offset_for_rva(Rva32(start_rva)),
+        })
+    }
+
+    fn find_function_impl(&self, offset: PdbInternalSectionOffset) -> Result<Option<Function>> {
+        if let Some(trampoline) = self.lookup_linker_trampoline(offset) {
+            return Ok(self.trampoline_function(&trampoline));
+        }
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
             procedure_cache,
+            memory_budget,
             ..
         } = &mut *cache;
 
@@ -393,11 +4200,12 @@ impl<'a, 's> Context<'a, 's> {
         };
 
         match func {
-            PublicOrProcedureSymbol::Public(_, _, global_function_index) => {
+            PublicOrProcedureSymbol::Public(module_index, _, global_function_index) => {
                 let func = &self.global_functions[global_function_index];
+                memory_budget.touch(module_index, func.start_offset);
                 let name = func.name.map(|name| name.to_string().to_string());
-                let start_rva = match func.start_offset.to_rva(self.address_map) {
-                    Some(rva) => rva.0,
+                let start_rva = match self.rva_or_report(func.start_offset) {
+                    Some(rva) => rva,
                     None => return Ok(None),
                 };
                 // Get the end address from the address of the next entry in the global function list.
@@ -405,38 +4213,68 @@ impl<'a, 's> Context<'a, 's> {
                     Some(next_entry)
                         if next_entry.start_offset.section == func.start_offset.section =>
                     {
-                        match next_entry.start_offset.to_rva(self.address_map) {
-                            Some(rva) => Some(rva.0),
+                        match self.rva_or_report(next_entry.start_offset) {
+                            Some(rva) => Some(rva),
                             None => return Ok(None),
                         }
                     }
                     _ => None,
                 };
+                let end_rva = self
+                    .cap_public_function_size(end_rva.map(|end| end.saturating_sub(start_rva)))
+                    .map(|size| start_rva.saturating_add(size));
                 Ok(Some(Function {
                     start_rva,
                     end_rva,
+                    estimated_end_rva: end_rva,
+                    generated: name
+                        .as_deref()
+                        .and_then(GeneratedFunctionKind::from_function_name),
                     name,
+                    provenance: func.provenance(),
+                    other_folded_functions: self.count_other_folded_functions(func.start_offset),
+                    unverified: self.debug_id_match.is_unverified(),
+                    id: self.function_id(start_rva),
+                    start_offset: Some(func.start_offset),
                 }))
             }
-            PublicOrProcedureSymbol::Procedure(module_index, _, func) => {
+            PublicOrProcedureSymbol::Procedure(module_index, module_info, _, func, end_offset) => {
+                memory_budget.touch(module_index, func.offset);
                 let extended_info = procedure_cache.entry(func.offset).or_default();
+                let start = self.start_phase();
                 let name = extended_info
                     .get_name(
                         func,
                         &self.type_formatter,
                         &self.global_functions,
                         module_index,
+                        module_info,
+                        self.flags,
                     )
                     .map(String::from);
-                let start_rva = match func.offset.to_rva(self.address_map) {
-                    Some(rva) => rva.0,
+                self.end_phase(start, |t| &mut t.formatting);
+                self.report_unresolved_types();
+                let start_rva = match self.rva_or_report(func.offset) {
+                    Some(rva) => rva,
                     None => return Ok(None),
                 };
-                let end_rva = start_rva + func.len;
+                let end_rva = start_rva + (end_offset - func.offset.offset);
                 Ok(Some(Function {
                     start_rva,
                     end_rva: Some(end_rva),
+                    estimated_end_rva: Some(end_rva),
+                    generated: name
+                        .as_deref()
+                        .and_then(GeneratedFunctionKind::from_function_name),
                     name,
+                    provenance: match func.is_thunk {
+                        true => Provenance::Thunk,
+                        false => Provenance::Procedure,
+                    },
+                    other_folded_functions: self.count_other_folded_functions(func.offset),
+                    unverified: self.debug_id_match.is_unverified(),
+                    id: self.function_id(start_rva),
+                    start_offset: Some(func.offset),
                 }))
             }
         }
@@ -448,12 +4286,399 @@ impl<'a, 's> Context<'a, 's> {
     /// into the procedure by the compiler, at that address.
     ///
     /// A lot of information is cached so that repeated calls are fast.
-    pub fn find_frames(&self, probe: u32) -> Result<Option<Vec<Frame>>> {
+    pub fn find_frames_at(&self, probe: Rva32) -> Result<Option<FunctionFrames<'_>>> {
+        let probe = probe.0;
+        if let Some(frames) = self.cache.borrow_mut().frames_cache.get(probe) {
+            return Ok(frames);
+        }
+        let frames = self.find_frames_uncached(probe)?;
+        let frames_cache = &mut self.cache.borrow_mut().frames_cache;
+        if frames_cache.capacity != 0 {
+            frames_cache.insert(probe, frames.clone());
+        }
+        Ok(frames)
+    }
+
+    fn find_frames_uncached(&self, probe: u32) -> Result<Option<FunctionFrames<'a>>> {
+        let result = match Rva(probe).to_internal_offset(self.address_map) {
+            Some(offset) => self.find_frames_at_offset_impl(offset),
+            None => Ok(None),
+        };
+        #[cfg(feature = "pe")]
+        if let Ok(None) = result {
+            return Ok(self.find_pe_export(probe).map(|function| {
+                let frame = Frame {
+                    function: function.name,
+                    raw_function: None,
+                    function_name_layout: None,
+                    file: None,
+                    file_index: None,
+                    raw_file: None,
+                    file_checksum: None,
+                    start_rva: function.start_rva,
+                    end_rva: function.end_rva,
+                    offset: probe - function.start_rva,
+                    line: None,
+                    line_end: None,
+                    column: None,
+                    column_end: None,
+                    line_distance: None,
+                    language: None,
+                };
+                FunctionFrames {
+                    start_rva: function.start_rva,
+                    end_rva: function.end_rva,
+                    frames: vec![frame],
+                    is_optimized: None,
+                    frame_flags: None,
+                    other_folded_functions: 0,
+                    unverified: function.unverified,
+                    provenance: Provenance::PeExport,
+                    function_id: function.id,
+                    start_offset: function.start_offset,
+                }
+            }));
+        }
+        result
+    }
+
+    /// Like [`Context::find_frames_at`], but takes a section offset instead of an rva,
+    /// for callers which already have one, for example from iterating over symbols.
+    /// This skips the translation through the address map.
+    pub fn find_frames_at_offset(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> Result<Option<FunctionFrames<'_>>> {
+        self.find_frames_at_offset_impl(offset)
+    }
+
+    fn find_frames_at_offset_impl(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> Result<Option<FunctionFrames<'a>>> {
+        let offset = self.resolve_incremental_linking_thunk_if_requested(offset);
+        let result = self.find_frames_impl(offset, None, &LookupOptions::default(), &mut |name| {
+            name.to_string()
+        });
+        self.enforce_memory_budget();
+        self.apply_non_utf8_handling_to_frames(result?)
+    }
+
+    /// Like [`Context::find_frames_at`], but the function names in the result are stored
+    /// in `arena` instead of being allocated for each frame. This is meant for
+    /// symbolicating large batches of addresses.
+    pub fn find_frames_in<'r>(
+        &self,
+        probe: Rva32,
+        arena: &'r StringArena,
+    ) -> Result<Option<FunctionFrames<'_, &'r str>>> {
+        let offset = match Rva(probe.0).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let offset = self.resolve_incremental_linking_thunk_if_requested(offset);
+        let result = self.find_frames_impl(offset, None, &LookupOptions::default(), &mut |name| {
+            arena.alloc(name)
+        });
+        self.enforce_memory_budget();
+        self.apply_non_utf8_handling_to_frames(result?)
+    }
+
+    /// Like [`Context::find_frames_at`], with options that only apply to this lookup.
+    pub fn find_frames_with_options(
+        &self,
+        probe: Rva32,
+        options: &LookupOptions,
+    ) -> Result<Option<FunctionFrames<'_>>> {
+        let probe = probe.0;
+        let probe = if options.return_address {
+            match probe.checked_sub(1) {
+                Some(probe) => probe,
+                None => return Ok(None),
+            }
+        } else if options.inclusive_end && self.is_function_end(probe)? {
+            probe - 1
+        } else {
+            probe
+        };
         let offset = match Rva(probe).to_internal_offset(self.address_map) {
             Some(offset) => offset,
             None => return Ok(None),
         };
+        let offset = self.resolve_incremental_linking_thunk_if_requested(offset);
+        let result = self.find_frames_impl(offset, None, options, &mut |name| name.to_string());
+        self.enforce_memory_budget();
+        self.apply_non_utf8_handling_to_frames(result?)
+    }
+
+    /// Whether `rva` is the end address of the function which contains the byte
+    /// before it.
+    fn is_function_end(&self, rva: u32) -> Result<bool> {
+        let last_byte = match rva.checked_sub(1) {
+            Some(last_byte) => last_byte,
+            None => return Ok(false),
+        };
+        Ok(match self.find_function_at_rva(last_byte, false)? {
+            Some(function) => function.end_rva == Some(rva),
+            None => false,
+        })
+    }
+
+    /// Find the frames of all addresses in `range`, for example a basic block. The
+    /// result has one entry for every distinct inline stack and line location in the
+    /// range, in ascending address order, i.e. for every function, line record and
+    /// inline range which intersects the range. The frames of each entry are looked up
+    /// at the first address in the range where they apply.
+    ///
+    /// This is useful for binary-diff and coverage tools, which work with address
+    /// ranges instead of single instruction addresses.
+    pub fn find_frames_covering(&self, range: Range<Rva32>) -> Result<Vec<FunctionFrames<'_>>> {
+        let range = range.start.0..range.end.0;
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+        let rva_list = self.full_rva_list();
+        let first = rva_list.partition_point(|rva| *rva <= range.start);
+        let last = rva_list.partition_point(|rva| *rva < range.end);
+        let mut probes = Vec::new();
+        for &probe in std::iter::once(&range.start).chain(&rva_list[first..last]) {
+            probes.push(probe);
+            let function = match self.find_function_at_rva(probe, false)? {
+                Some(function) => function,
+                None => continue,
+            };
+            let size = match function.end_rva {
+                Some(end_rva) if end_rva > function.start_rva => end_rva - function.start_rva,
+                _ => continue,
+            };
+            probes.extend(
+                self.frame_boundaries(function.start_rva, size)?
+                    .into_iter()
+                    .filter(|rva| range.contains(rva)),
+            );
+        }
+        probes.sort_unstable();
+        probes.dedup();
+
+        let mut seen_locations = HashSet::new();
+        let mut result = Vec::new();
+        for probe in probes {
+            let function_frames = match self.find_frames_at(Rva32(probe))? {
+                Some(function_frames) => function_frames,
+                None => continue,
+            };
+            let location: Vec<_> = function_frames
+                .frames
+                .iter()
+                .map(|frame| {
+                    (
+                        frame.start_rva,
+                        frame.function.clone(),
+                        frame.file.clone(),
+                        frame.line,
+                    )
+                })
+                .collect();
+            if seen_locations.insert(location) {
+                result.push(function_frames);
+            }
+        }
+        Ok(result)
+    }
+
+    /// The rvas in the function at `start_rva` at which the frames can change: the
+    /// starts of the function's line records, and the starts and ends of its inline
+    /// ranges. Sorted, without duplicates.
+    fn frame_boundaries(&self, start_rva: u32, size: u32) -> Result<Vec<u32>> {
+        let mut boundaries = vec![start_rva];
+        let offset = match Rva(start_rva).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(boundaries),
+        };
+        let to_rva = |section_offset: u32| {
+            section_offset
+                .checked_sub(offset.offset)
+                .filter(|delta| *delta < size)
+                .map(|delta| start_rva + delta)
+        };
+
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            procedure_cache,
+            function_line_cache,
+            extended_module_cache,
+            ..
+        } = &mut *cache;
+        let (module_index, module_info, proc) = match self.lookup_function(offset, module_cache) {
+            Some(PublicOrProcedureSymbol::Procedure(module_index, module_info, _, proc, _)) => {
+                (module_index, module_info, Some(proc))
+            }
+            Some(PublicOrProcedureSymbol::Public(module_index, Some(module_info), _)) => {
+                (module_index, module_info, None)
+            }
+            _ => return Ok(boundaries),
+        };
+        let ExtendedModuleInfo {
+            inlinees, lines, ..
+        } = self.get_extended_module_info(extended_module_cache, module_index, module_info)?;
+
+        let function_lines = function_line_cache
+            .entry(offset)
+            .or_default()
+            .get_lines(offset, Some(size), lines)
+            .map_err(|e| self.module_error(module_index, "reading the procedure's lines", e))?;
+        boundaries.extend(
+            function_lines
+                .iter()
+                .filter_map(|line| to_rva(line.start_offset)),
+        );
+
+        if let Some(proc) = proc {
+            let inline_ranges = procedure_cache
+                .entry(proc.offset)
+                .or_default()
+                .get_inline_ranges(module_info, proc, inlinees, &mut Vec::new())
+                .map_err(|e| {
+                    self.module_error(module_index, "computing the procedure's inline ranges", e)
+                })?;
+            for range in inline_ranges {
+                boundaries.extend(to_rva(range.start_offset));
+                boundaries.extend(to_rva(range.end_offset));
+            }
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        Ok(boundaries)
+    }
+
+    /// Find the function which contains the provided address, and return a handle to
+    /// it. This is the first half of [`Context::find_frames_at`]; the second half is
+    /// [`Context::frames_for`].
+    ///
+    /// This is useful when many addresses fall into the same function, for example
+    /// with profiler samples: The search for the section contribution, the module and
+    /// the procedure only has to be done once per function.
+    pub fn lookup_function_handle(&self, probe: Rva32) -> Option<FunctionHandle> {
+        let offset = Rva(probe.0).to_internal_offset(self.address_map)?;
+        let offset = self.resolve_incremental_linking_thunk_if_requested(offset);
+        let mut cache = self.cache.borrow_mut();
+        let (module_index, symbol, start_offset, end_offset) =
+            match self.lookup_function(offset, &mut cache.module_cache)? {
+                PublicOrProcedureSymbol::Public(module_index, _, global_function_index) => {
+                    let func = &self.global_functions[global_function_index];
+                    let end_offset = match self.global_functions.get(global_function_index + 1) {
+                        Some(next_entry)
+                            if next_entry.start_offset.section == func.start_offset.section =>
+                        {
+                            Some(next_entry.start_offset.offset - func.start_offset.offset)
+                        }
+                        _ => None,
+                    };
+                    let end_offset = self
+                        .cap_public_function_size(end_offset)
+                        .and_then(|size| func.start_offset.offset.checked_add(size));
+                    (
+                        module_index,
+                        FunctionHandleSymbol::Public(global_function_index),
+                        func.start_offset,
+                        end_offset,
+                    )
+                }
+                PublicOrProcedureSymbol::Procedure(
+                    module_index,
+                    _,
+                    procedure_index,
+                    proc,
+                    end_offset,
+                ) => (
+                    module_index,
+                    FunctionHandleSymbol::Procedure(procedure_index),
+                    proc.offset,
+                    Some(end_offset),
+                ),
+            };
+        cache.memory_budget.touch(module_index, start_offset);
+        drop(cache);
+        self.enforce_memory_budget();
+        let start_rva = start_offset.to_rva(self.address_map)?.0;
+        let end_rva = end_offset.and_then(|end| start_rva.checked_add(end - start_offset.offset));
+        Some(FunctionHandle {
+            module_index,
+            symbol,
+            start_offset,
+            end_offset,
+            start_rva,
+            end_rva,
+        })
+    }
+
+    /// Like [`Context::find_frames_at`], for an address inside the function of `handle`,
+    /// which was returned by [`Context::lookup_function_handle`]. Returns `Ok(None)` if
+    /// `probe` is outside of that function.
+    pub fn frames_for(
+        &self,
+        handle: &FunctionHandle,
+        probe: Rva32,
+    ) -> Result<Option<FunctionFrames<'_>>> {
+        let offset = match Rva(probe.0).to_internal_offset(self.address_map) {
+            Some(offset) if handle.contains(offset) => offset,
+            _ => return Ok(None),
+        };
+        let result = self.find_frames_impl(
+            offset,
+            Some(handle),
+            &LookupOptions::default(),
+            &mut |name| name.to_string(),
+        );
+        self.enforce_memory_budget();
+        self.apply_non_utf8_handling_to_frames(result?)
+    }
+
+    /// Get the function of a handle from the module cache. This reads the module
+    /// again if it was evicted from the cache in the meantime.
+    fn resolve_function_handle<'m>(
+        &self,
+        handle: &FunctionHandle,
+        module_cache: &'m mut BasicModuleInfoCache<'a, 's>,
+    ) -> Option<PublicOrProcedureSymbol<'a, 's, 'm>> {
+        let basic_module_info =
+            module_cache.get_basic_module_info(self.type_formatter.modules(), handle.module_index);
+        match handle.symbol {
+            FunctionHandleSymbol::Public(global_function_index) => {
+                Some(PublicOrProcedureSymbol::Public(
+                    handle.module_index,
+                    basic_module_info.map(|info| info.module_info),
+                    global_function_index,
+                ))
+            }
+            FunctionHandleSymbol::Procedure(procedure_index) => {
+                let basic_module_info = basic_module_info?;
+                let procedures = &basic_module_info.procedures;
+                Some(PublicOrProcedureSymbol::Procedure(
+                    handle.module_index,
+                    basic_module_info.module_info,
+                    procedure_index,
+                    procedures.get(procedure_index)?,
+                    self.procedure_end_offset(procedures, procedure_index),
+                ))
+            }
+        }
+    }
 
+    /// `make_name` creates the function names of the frames.
+    fn find_frames_impl<S>(
+        &self,
+        offset: PdbInternalSectionOffset,
+        handle: Option<&FunctionHandle>,
+        options: &LookupOptions,
+        make_name: &mut dyn FnMut(&str) -> S,
+    ) -> Result<Option<FunctionFrames<'a, S>>> {
+        if handle.is_none() {
+            if let Some(trampoline) = self.lookup_linker_trampoline(offset) {
+                return Ok(self.trampoline_frames(&trampoline, offset, make_name));
+            }
+        }
         let mut cache = self.cache.borrow_mut();
         let ContextCache {
             module_cache,
@@ -461,10 +4686,18 @@ impl<'a, 's> Context<'a, 's> {
             function_line_cache,
             extended_module_cache,
             inline_name_cache,
+            memory_budget,
             ..
         } = &mut *cache;
 
-        let func = match self.lookup_function(offset, module_cache) {
+        let func = match handle {
+            Some(handle) => self.resolve_function_handle(handle, module_cache),
+            None => self.lookup_function(offset, module_cache),
+        };
+        let func = match func {
+            Some(PublicOrProcedureSymbol::Public(..)) if !options.nearest_symbol_fallback => {
+                return Ok(None)
+            }
             Some(func) => func,
             None => return Ok(None),
         };
@@ -476,11 +4709,20 @@ impl<'a, 's> Context<'a, 's> {
         //    and no file / line info.
         //  - Some PDBs have public symbols and modules, but the modules only have file /
         //    line info and no procedures.
-        let (module_index, module_info, func_offset, func_size, func_name, proc_stuff) = match func
-        {
+        let mut provenance;
+        let (
+            module_index,
+            module_info,
+            func_offset,
+            func_size,
+            func_name,
+            raw_func_name,
+            mut proc_stuff,
+        ) = match func {
             PublicOrProcedureSymbol::Public(module_index, module_info, global_function_index) => {
                 let func = &self.global_functions[global_function_index];
-                let func_name = func.name.map(|name| name.to_string().to_string());
+                provenance = func.provenance();
+                let func_name = func.name.map(|name| make_name(&name.to_string()));
                 // Get the function size from the address of the next entry in the global function list.
                 let size = match self.global_functions.get(global_function_index + 1) {
                     Some(next_entry)
@@ -490,52 +4732,84 @@ impl<'a, 's> Context<'a, 's> {
                     }
                     _ => None,
                 };
+                let size = self.cap_public_function_size(size);
                 (
                     module_index,
                     module_info,
                     func.start_offset,
                     size,
                     func_name,
+                    func.name,
                     None,
                 )
             }
-            PublicOrProcedureSymbol::Procedure(module_index, module_info, proc) => {
+            PublicOrProcedureSymbol::Procedure(module_index, module_info, _, proc, end_offset) => {
+                provenance = match proc.is_thunk {
+                    true => Provenance::Thunk,
+                    false => Provenance::ProcedureWithoutLines,
+                };
                 let proc_extended_info = procedure_cache.entry(proc.offset).or_default();
-                let func_name = proc_extended_info
-                    .get_name(
-                        proc,
-                        &self.type_formatter,
-                        &self.global_functions,
-                        module_index,
-                    )
-                    .map(String::from);
+                let func_name = if options.demangle {
+                    let start = self.start_phase();
+                    let func_name = proc_extended_info
+                        .get_name(
+                            proc,
+                            &self.type_formatter,
+                            &self.global_functions,
+                            module_index,
+                            module_info,
+                            self.flags,
+                        )
+                        .map(&mut *make_name);
+                    self.end_phase(start, |t| &mut t.formatting);
+                    func_name
+                } else {
+                    Some(make_name(&proc.name.to_string()))
+                };
                 (
                     module_index,
                     Some(module_info),
                     proc.offset,
-                    Some(proc.len),
+                    Some(end_offset - proc.offset.offset),
                     func_name,
+                    Some(proc.name),
                     Some((proc, proc_extended_info)),
                 )
             }
         };
+        memory_budget.touch(module_index, func_offset);
 
-        let extended_module_info = match module_info {
-            Some(module_info) => Some(
-                extended_module_cache
-                    .entry(module_index)
-                    .or_insert_with(|| self.compute_extended_module_info(module_info))
-                    .as_mut()
-                    .map_err(|err| mem::replace(err, Error::ExtendedModuleInfoUnsuccessful))?,
-            ),
-            None => None,
-        };
+        let start = self.start_phase();
+        let extended_module_info = module_info.and_then(|module_info| {
+            let result =
+                self.get_extended_module_info(extended_module_cache, module_index, module_info);
+            self.degrade(
+                module_index,
+                "reading the module's line information",
+                result,
+            )
+        });
 
-        let (file, line) = if let Some(ExtendedModuleInfo { line_program, .. }) =
-            &extended_module_info
+        let mut line_end = None;
+        let mut column = None;
+        let mut column_end = None;
+        let mut line_distance = None;
+        let (file_index, raw_file, file_checksum, line) = if let Some(ExtendedModuleInfo {
+            lines: module_lines,
+            ..
+        }) = &extended_module_info
         {
             let function_line_info = function_line_cache.entry(func_offset).or_default();
-            let lines = function_line_info.get_lines(func_offset, line_program)?;
+            let lines = self
+                .degrade(
+                    module_index,
+                    "reading the procedure's lines",
+                    function_line_info.get_lines(func_offset, func_size, module_lines),
+                )
+                .unwrap_or_default();
+            if provenance == Provenance::ProcedureWithoutLines && !lines.is_empty() {
+                provenance = Provenance::ProcedureWithLines;
+            }
             let search = match lines.binary_search_by_key(&offset.offset, |li| li.start_offset) {
                 Err(0) => None,
                 Ok(i) => Some(i),
@@ -544,47 +4818,108 @@ impl<'a, 's> Context<'a, 's> {
             match search {
                 Some(index) => {
                     let line_info = &lines[index];
+                    let line = self.line_number(line_info.line_start);
+                    if line.is_some() {
+                        line_end = Some(line_info.line_end.max(line_info.line_start));
+                        // Compilers write 0 if they don't know the column.
+                        column = line_info.column_start.filter(|column| *column != 0);
+                        column_end = line_info.column_end.filter(|column| *column != 0);
+                        line_distance = Some(offset.offset - line_info.start_offset);
+                    }
                     (
-                        self.resolve_filename(line_program, line_info.file_index),
-                        Some(line_info.line_start),
+                        Some(line_info.file_index),
+                        self.resolve_raw_filename(module_index, module_lines, line_info.file_index),
+                        self.resolve_file_checksum(module_lines, line_info.file_index),
+                        line,
                     )
                 }
-                None => (None, None),
+                None => (None, None, None, None),
             }
         } else {
-            (None, None)
+            (None, None, None, None)
         };
+        self.end_phase(start, |t| &mut t.line_program_parse);
 
-        let start_rva = match func_offset.to_rva(self.address_map) {
-            Some(rva) => rva.0,
+        let function_start_rva = match self.rva_or_report(func_offset) {
+            Some(rva) => rva,
             None => return Ok(None),
         };
-        let end_rva = func_size.and_then(|size| start_rva.checked_add(size));
+        let function_end_rva = func_size.and_then(|size| function_start_rva.checked_add(size));
+
+        let frame_info = match (&mut proc_stuff, module_info) {
+            (Some((proc, proc_extended_info)), Some(module_info)) => {
+                proc_extended_info.get_frame_info(module_info, proc)
+            }
+            _ => None,
+        };
 
+        // Only names which came out of the type formatter have a layout.
+        let function_name_layout = match &proc_stuff {
+            Some((_, proc_extended_info)) if options.demangle => proc_extended_info
+                .name
+                .as_ref()
+                .and_then(Option::as_ref)
+                .and_then(|(_, layout)| layout.clone()),
+            _ => None,
+        };
+        let language = extended_module_info.as_ref().and_then(|info| info.language);
         let frame = Frame {
             function: func_name,
-            file,
-            start_rva,
-            end_rva,
+            raw_function: raw_func_name,
+            function_name_layout,
+            file: self.file_name(raw_file),
+            file_index,
+            raw_file,
+            file_checksum,
+            start_rva: function_start_rva,
+            end_rva: function_end_rva,
+            offset: offset.offset - func_offset.offset,
             line,
+            line_end,
+            column,
+            column_end,
+            line_distance,
+            language,
         };
 
         // Ordered outside to inside, until just before the end of this function.
         let mut frames = vec![frame];
+        let max_inline_depth = options
+            .max_inline_depth()
+            .min(self.max_inline_depth.unwrap_or(usize::MAX));
 
-        if let (Some((proc, proc_extended_info)), Some(extended_module_info)) =
-            (proc_stuff, extended_module_info)
+        if let (Some((proc, proc_extended_info)), Some(extended_module_info), true) =
+            (proc_stuff, extended_module_info, max_inline_depth > 0)
         {
             let ExtendedModuleInfo {
                 inlinees,
-                line_program,
+                lines,
                 module_info,
                 ..
             } = extended_module_info;
-            let mut inline_ranges =
-                proc_extended_info.get_inline_ranges(module_info, proc, inlinees)?;
+            let start = self.start_phase();
+            let mut missing_inlinees = Vec::new();
+            let mut inline_ranges = self
+                .degrade(
+                    module_index,
+                    "computing the procedure's inline ranges",
+                    proc_extended_info.get_inline_ranges(
+                        *module_info,
+                        proc,
+                        inlinees,
+                        &mut missing_inlinees,
+                    ),
+                )
+                .unwrap_or_default();
+            self.end_phase(start, |t| &mut t.inline_computation);
+            for inlinee in missing_inlinees {
+                self.report(|| Diagnostic::MissingInlineRanges {
+                    module_index,
+                    inlinee: inlinee.0,
+                });
+            }
 
-            loop {
+            while frames.len() <= max_inline_depth {
                 let current_depth = (frames.len() - 1) as u16;
 
                 // Look up (offset.offset, current_depth) in inline_ranges.
@@ -609,26 +4944,33 @@ impl<'a, 's> Context<'a, 's> {
                     Err(_) => break,
                 };
 
-                let function = inline_name_cache
+                let start = self.start_phase();
+                let (function, function_name_layout) = match inline_name_cache
                     .entry(inline_range.inlinee)
                     .or_insert_with(|| {
                         self.type_formatter
-                            .format_id(module_index, inline_range.inlinee)
-                    })
-                    .as_ref()
-                    .ok()
-                    .cloned();
-                let file = inline_range
-                    .file_index
-                    .and_then(|file_index| self.resolve_filename(line_program, file_index));
-                let line = inline_range.line_start;
+                            .format_id_with_layout(module_index, inline_range.inlinee)
+                    }) {
+                    Ok((name, layout)) => (Some(make_name(name)), layout.clone()),
+                    Err(_) => (None, None),
+                };
+                self.end_phase(start, |t| &mut t.formatting);
+                let file_index = inline_range.file_index;
+                let raw_file = file_index.and_then(|file_index| {
+                    self.resolve_raw_filename(module_index, lines, file_index)
+                });
+                let file_checksum =
+                    file_index.and_then(|file_index| self.resolve_file_checksum(lines, file_index));
+                let line = inline_range
+                    .line_start
+                    .and_then(|line| self.line_number(line));
 
                 let start_internal = PdbInternalSectionOffset {
                     offset: inline_range.start_offset,
                     section: func_offset.section,
                 };
-                let start_rva = match start_internal.to_rva(self.address_map) {
-                    Some(rva) => rva.0,
+                let start_rva = match self.rva_or_report(start_internal) {
+                    Some(rva) => rva,
                     None => return Ok(None),
                 };
                 let end_rva =
@@ -636,10 +4978,23 @@ impl<'a, 's> Context<'a, 's> {
 
                 frames.push(Frame {
                     function,
-                    file,
+                    raw_function: self
+                        .type_formatter
+                        .raw_id_name(module_index, inline_range.inlinee),
+                    function_name_layout,
+                    file: self.file_name(raw_file),
+                    file_index,
+                    raw_file,
+                    file_checksum,
                     start_rva,
                     end_rva,
+                    offset: offset.offset - inline_range.start_offset,
                     line,
+                    line_end: None,
+                    column: None,
+                    column_end: None,
+                    line_distance: None,
+                    language: None,
                 });
 
                 inline_ranges = remainder;
@@ -648,8 +5003,20 @@ impl<'a, 's> Context<'a, 's> {
             // Now order from inside to outside.
             frames.reverse();
         }
+        self.report_unresolved_types();
 
-        Ok(Some(frames))
+        Ok(Some(FunctionFrames {
+            start_rva: function_start_rva,
+            end_rva: function_end_rva,
+            frames,
+            is_optimized: frame_info.and_then(|info| info.is_optimized()),
+            frame_flags: frame_info.and_then(|info| info.frame_flags),
+            other_folded_functions: self.count_other_folded_functions(func_offset),
+            unverified: self.debug_id_match.is_unverified(),
+            provenance,
+            function_id: self.function_id(function_start_rva),
+            start_offset: Some(func_offset),
+        }))
     }
 
     fn compute_full_rva_list(&self, module_cache: &mut BasicModuleInfoCache<'a, 's>) -> Vec<u32> {
@@ -660,6 +5027,7 @@ impl<'a, 's> Context<'a, 's> {
             }
         }
         for module_index in 0..self.type_formatter.modules().len() {
+            self.report_progress(module_index);
             if let Some(BasicModuleInfo { procedures, .. }) =
                 module_cache.get_basic_module_info(self.type_formatter.modules(), module_index)
             {
@@ -670,134 +5038,682 @@ impl<'a, 's> Context<'a, 's> {
                 }
             }
         }
-        list.sort_unstable();
-        list.dedup();
-        list
+        self.report_progress(self.type_formatter.modules().len());
+        list.sort_unstable();
+        list.dedup();
+        list
+    }
+
+    fn lookup_function<'m>(
+        &self,
+        offset: PdbInternalSectionOffset,
+        module_cache: &'m mut BasicModuleInfoCache<'a, 's>,
+    ) -> Option<PublicOrProcedureSymbol<'a, 's, 'm>> {
+        // If the requested address is not present in any section contribution, we
+        // don't know which module it belongs to.
+        let sc = self.lookup_section_contribution(offset)?;
+        let start = self.start_phase();
+        module_cache.get_basic_module_info(self.type_formatter.modules(), sc.module_index);
+        self.end_phase(start, |t| &mut t.module_symbol_walk);
+        self.report_module_problems(module_cache);
+        let basic_module_info =
+            module_cache.get_basic_module_info(self.type_formatter.modules(), sc.module_index);
+
+        let module_info = if let Some(BasicModuleInfo {
+            procedures,
+            module_info,
+            ..
+        }) = basic_module_info
+        {
+            // Find the last procedure which starts at or before the offset. Searching by
+            // the end offset could land on an earlier procedure whose length overlaps
+            // the next one, e.g. because it includes a jump table.
+            let procedure_index = procedures
+                .partition_point(|p| {
+                    (p.offset.section, p.offset.offset) <= (offset.section, offset.offset)
+                })
+                .checked_sub(1);
+            if let Some(procedure_index) = procedure_index {
+                let proc = &procedures[procedure_index];
+                let end_offset = self.procedure_end_offset(procedures, procedure_index);
+                if proc.offset.section == offset.section && offset.offset < end_offset {
+                    // Found a procedure at the requested offset.
+                    if self.flags.contains(ContextFlags::CROSS_CHECK_PUBLICS) {
+                        self.cross_check_procedure(offset, sc, proc);
+                    }
+                    return Some(PublicOrProcedureSymbol::Procedure(
+                        sc.module_index,
+                        *module_info,
+                        procedure_index,
+                        proc,
+                        end_offset,
+                    ));
+                }
+            }
+            Some(*module_info)
+        } else {
+            None
+        };
+
+        // No procedure was found at this offset in the module that the section
+        // contribution pointed us at.
+        // This is not uncommon.
+        // Fall back to the public symbols.
+        let global_function_index = self.lookup_public_function(offset, sc)?;
+        Some(PublicOrProcedureSymbol::Public(
+            sc.module_index,
+            module_info,
+            global_function_index,
+        ))
+    }
+
+    /// The end offset of `procedures[index]`. This is the end of the procedure's
+    /// code according to its symbol, or with
+    /// [`ContextFlags::CORRECT_PROCEDURE_ENDS`], the start of the next function or
+    /// the end of the section contribution, whichever comes first.
+    fn procedure_end_offset(&self, procedures: &[ProcedureSymbolFunction], index: usize) -> u32 {
+        let proc = &procedures[index];
+        let end_offset = proc.offset.offset.saturating_add(proc.len);
+        if !self.flags.contains(ContextFlags::CORRECT_PROCEDURE_ENDS) {
+            return end_offset;
+        }
+        let start = (proc.offset.section, proc.offset.offset);
+        let mut limit = match self.lookup_section_contribution(proc.offset) {
+            Some(sc) => sc.end_offset,
+            None => return end_offset,
+        };
+        let next_procedure = procedures[index + 1..]
+            .iter()
+            .map(|p| (p.offset.section, p.offset.offset))
+            .find(|next| *next > start);
+        let next_public_index = self
+            .global_functions
+            .partition_point(|p| (p.start_offset.section, p.start_offset.offset) <= start);
+        let next_public = self
+            .global_functions
+            .get(next_public_index)
+            .map(|p| (p.start_offset.section, p.start_offset.offset));
+        for (section, offset) in next_procedure.into_iter().chain(next_public) {
+            if section == proc.offset.section {
+                limit = limit.min(offset);
+            }
+        }
+        if limit > proc.offset.offset {
+            limit
+        } else {
+            end_offset
+        }
+    }
+
+    /// Find the entry of `global_functions` which covers `offset`, i.e. the last one
+    /// which starts at or before `offset` in the section contribution `sc`.
+    fn lookup_public_function(
+        &self,
+        offset: PdbInternalSectionOffset,
+        sc: &ModuleSectionContribution,
+    ) -> Option<usize> {
+        let last_global_function_starting_lte_address = match self
+            .global_functions
+            .binary_search_by_key(&(offset.section, offset.offset), |p| {
+                (p.start_offset.section, p.start_offset.offset)
+            }) {
+            Err(0) => return None,
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let fun = &self.global_functions[last_global_function_starting_lte_address];
+        debug_assert!(
+            fun.start_offset.section < offset.section
+                || (fun.start_offset.section == offset.section
+                    && fun.start_offset.offset <= offset.offset)
+        );
+        if fun.start_offset.section != offset.section {
+            return None;
+        }
+        // Ignore symbols outside the section contribution.
+        if fun.start_offset.offset < sc.start_offset {
+            return None;
+        }
+        if !fun.is_code && self.flags.contains(ContextFlags::FUNCTION_PUBLICS_ONLY) {
+            return None;
+        }
+        if let Some(max_size) = self.max_public_function_size {
+            if offset.offset - fun.start_offset.offset >= max_size {
+                return None;
+            }
+        }
+        Some(last_global_function_starting_lte_address)
+    }
+
+    /// Apply [`Context::set_max_public_function_size`] to the estimated size of a
+    /// function which is only known from a public symbol.
+    fn cap_public_function_size(&self, size: Option<u32>) -> Option<u32> {
+        match (size, self.max_public_function_size) {
+            (Some(size), Some(max_size)) => Some(size.min(max_size)),
+            (None, max_size) => max_size,
+            (size, None) => size,
+        }
+    }
+
+    /// Compare the procedure `proc`, which was found for `offset`, with the answer
+    /// that the public symbols give for `offset`, and report any disagreement.
+    fn cross_check_procedure(
+        &self,
+        offset: PdbInternalSectionOffset,
+        sc: &ModuleSectionContribution,
+        proc: &ProcedureSymbolFunction,
+    ) {
+        let public = match self.lookup_public_function(offset, sc) {
+            Some(index) => &self.global_functions[index],
+            None => return,
+        };
+        let public_name = match public.name {
+            Some(name) => name,
+            None => return,
+        };
+        if public.start_offset.offset < proc.offset.offset {
+            // The procedure has no public symbol of its own.
+            return;
+        }
+        if public.start_offset.offset == proc.offset.offset
+            && self
+                .public_names_at_offset(proc.offset)
+                .any(|name| public_name_matches(&name.to_string(), &proc.name.to_string()))
+        {
+            return;
+        }
+        let (rva, procedure_start_rva, public_start_rva) = match (
+            offset.to_rva(self.address_map),
+            proc.offset.to_rva(self.address_map),
+            public.start_offset.to_rva(self.address_map),
+        ) {
+            (Some(rva), Some(procedure_start), Some(public_start)) => {
+                (rva.0, procedure_start.0, public_start.0)
+            }
+            _ => return,
+        };
+        self.report(|| Diagnostic::PublicSymbolMismatch {
+            rva,
+            procedure_name: proc.name.to_string().into_owned(),
+            procedure_start_rva,
+            public_name: public_name.to_string().into_owned(),
+            public_start_rva,
+        });
+    }
+
+    fn lookup_section_contribution(
+        &self,
+        offset: PdbInternalSectionOffset,
+    ) -> Option<&ModuleSectionContribution> {
+        let sc_index = self
+            .section_contributions
+            .binary_search_by(|sc| {
+                if sc.section_index < offset.section {
+                    Ordering::Less
+                } else if sc.section_index > offset.section {
+                    Ordering::Greater
+                } else if sc.end_offset <= offset.offset {
+                    Ordering::Less
+                } else if sc.start_offset > offset.offset {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .ok()?;
+        Some(&self.section_contributions[sc_index])
+    }
+
+    /// Update the cache sizes for the modules and procedures which were used since
+    /// the last call, and drop the caches of the least-recently-used modules until
+    /// the cached information fits into the memory budget again.
+    fn enforce_memory_budget(&self) {
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            function_line_cache,
+            procedure_cache,
+            extended_module_cache,
+            memory_budget,
+            ..
+        } = &mut *cache;
+        let limit = match memory_budget.limit {
+            Some(limit) => limit,
+            None => {
+                memory_budget.touched.clear();
+                return;
+            }
+        };
+
+        memory_budget.current_use += 1;
+        let current_use = memory_budget.current_use;
+        for (module_index, func_offset) in mem::take(&mut memory_budget.touched) {
+            let module_size = module_cache
+                .cache
+                .get(&module_index)
+                .and_then(Option::as_ref)
+                .map_or(0, |module| vec_size(&module.procedures))
+                + extended_module_cache
+                    .get(&module_index)
+                    .and_then(|module| module.as_ref().ok())
+                    .map_or(0, |module| btree_map_size(&module.inlinees));
+            let module_usage = memory_budget.modules.entry(module_index).or_default();
+            module_usage.last_use = current_use;
+            memory_budget.total_size += module_size;
+            memory_budget.total_size -= mem::replace(&mut module_usage.size, module_size);
+
+            if let Some(function_line_info) = function_line_cache.get_mut(&func_offset) {
+                let size = function_line_info.heap_size();
+                memory_budget.total_size += size;
+                memory_budget.total_size -=
+                    mem::replace(&mut function_line_info.accounted_size, size);
+            }
+            if let Some(procedure_info) = procedure_cache.get_mut(&func_offset) {
+                let size = procedure_info.heap_size();
+                memory_budget.total_size += size;
+                memory_budget.total_size -= mem::replace(&mut procedure_info.accounted_size, size);
+            }
+        }
+
+        while memory_budget.total_size > limit {
+            let least_recently_used = memory_budget
+                .modules
+                .iter()
+                .filter(|(_, module_usage)| module_usage.last_use != current_use)
+                .min_by_key(|(_, module_usage)| module_usage.last_use)
+                .map(|(module_index, _)| *module_index);
+            let module_index = match least_recently_used {
+                Some(module_index) => module_index,
+                None => break,
+            };
+
+            let module_usage = memory_budget.modules.remove(&module_index).unwrap();
+            let mut freed_size = module_usage.size;
+            module_cache.cache.remove(&module_index);
+            extended_module_cache.remove(&module_index);
+            let mut belongs_to_module = |offset: &PdbInternalSectionOffset, size: usize| {
+                let in_module = self
+                    .lookup_section_contribution(*offset)
+                    .is_some_and(|sc| sc.module_index == module_index);
+                if in_module {
+                    freed_size += size;
+                }
+                in_module
+            };
+            function_line_cache
+                .retain(|offset, info| !belongs_to_module(offset, info.accounted_size));
+            procedure_cache.retain(|offset, info| !belongs_to_module(offset, info.accounted_size));
+            memory_budget.total_size -= freed_size;
+        }
+    }
+
+    /// The extended information of a module, from this context's cache, from the
+    /// shared module cache, or computed now. A failure is only returned once, later
+    /// calls return [`Error::ExtendedModuleInfoUnsuccessful`].
+    fn get_extended_module_info<'c>(
+        &self,
+        extended_module_cache: &'c mut BTreeMap<usize, Result<Rc<ExtendedModuleInfo<'a, 's>>>>,
+        module_index: usize,
+        module_info: ModuleData<'a, 's>,
+    ) -> Result<&'c ExtendedModuleInfo<'a, 's>> {
+        extended_module_cache
+            .entry(module_index)
+            .or_insert_with(|| {
+                let shared_modules = self.shared_module_cache.map(|cache| &cache.modules);
+                // Contexts with and without COMPACT_MODULES can share the cache, so
+                // only take the module if it comes from the same kind of data.
+                if let Some(info) = shared_modules
+                    .and_then(|m| m.borrow().get(&module_index).cloned())
+                    .filter(|info| info.module_info.is_compact() == module_info.is_compact())
+                {
+                    return Ok(info);
+                }
+                let info = Rc::new(self.compute_extended_module_info(module_index, module_info)?);
+                if let Some(shared_modules) = shared_modules {
+                    shared_modules
+                        .borrow_mut()
+                        .insert(module_index, info.clone());
+                }
+                Ok(info)
+            })
+            .as_mut()
+            .map(|info| &**info)
+            .map_err(|err| mem::replace(err, Error::ExtendedModuleInfoUnsuccessful))
+    }
+
+    /// The module's stream, for methods which walk the module's records. Compact
+    /// modules read the stream again, and it stays in memory from then on.
+    fn module_stream(
+        &self,
+        module_index: usize,
+        module_info: ModuleData<'a, 's>,
+    ) -> Result<&'a ModuleInfo<'s>> {
+        match module_info {
+            ModuleData::Stream(module_info) => Ok(module_info),
+            ModuleData::Compact(_) => {
+                let module = &self.type_formatter.modules()[module_index];
+                self.module_info_provider
+                    .get_module_info(module_index, module)?
+                    .ok_or(Error::ModuleInfoNotFound(module_index))
+            }
+        }
+    }
+
+    /// Like `get_extended_module_info`, but with the module's stream, its inlinees
+    /// and its line program also for compact modules. For these, the information
+    /// isn't cached.
+    fn get_stream_module_info(
+        &self,
+        extended_module_cache: &mut BTreeMap<usize, Result<Rc<ExtendedModuleInfo<'a, 's>>>>,
+        module_index: usize,
+        module_info: ModuleData<'a, 's>,
+    ) -> Result<Rc<ExtendedModuleInfo<'a, 's>>> {
+        if let ModuleData::Compact(_) = module_info {
+            let module_info = self.module_stream(module_index, module_info)?;
+            let info =
+                self.compute_extended_module_info(module_index, ModuleData::Stream(module_info))?;
+            return Ok(Rc::new(info));
+        }
+        self.get_extended_module_info(extended_module_cache, module_index, module_info)?;
+        match extended_module_cache.get(&module_index) {
+            Some(Ok(info)) => Ok(info.clone()),
+            _ => Err(Error::ExtendedModuleInfoUnsuccessful),
+        }
+    }
+
+    fn compute_extended_module_info(
+        &self,
+        module_index: usize,
+        module_info: ModuleData<'a, 's>,
+    ) -> Result<ExtendedModuleInfo<'a, 's>> {
+        let module_info = match module_info {
+            ModuleData::Stream(module_info) => module_info,
+            ModuleData::Compact(compact_module) => {
+                // The inline ranges of the procedures are already computed.
+                return Ok(ExtendedModuleInfo {
+                    module_info,
+                    inlinees: BTreeMap::new(),
+                    lines: ModuleLines::Compact(compact_module),
+                    language: compact_module.language(),
+                });
+            }
+        };
+        let lines = match module_info.line_program() {
+            Ok(line_program) => ModuleLines::C13(line_program),
+            Err(pdb::Error::UnimplementedFeature(_)) => {
+                // The module has C11 lines, which the pdb crate can't read.
+                let module = &self.type_formatter.modules()[module_index];
+                let legacy_lines = self
+                    .module_info_provider
+                    .get_legacy_line_data(module_index, module)
+                    .and_then(|data| data.map(LegacyLineProgram::parse).transpose())
+                    .map_err(|e| {
+                        self.module_error(module_index, "reading the C11 lines", e.into())
+                    })?;
+                ModuleLines::C11(legacy_lines.unwrap_or_default())
+            }
+            Err(e) => {
+                return Err(self.module_error(module_index, "reading the line program", e.into()))
+            }
+        };
+
+        let inlinees: BTreeMap<IdIndex, Inlinee> = module_info
+            .inlinees()
+            .and_then(|inlinees| inlinees.map(|i| Ok((i.index(), i))).collect())
+            .map_err(|e| self.module_error(module_index, "reading the inlinees", e.into()))?;
+
+        Ok(ExtendedModuleInfo {
+            module_info: ModuleData::Stream(module_info),
+            inlinees,
+            lines,
+            language: compute_module_language(module_info).ok().flatten(),
+        })
+    }
+
+    /// Add the section contribution and module problems to `issues`, see
+    /// [`ContextPdbData::validate`].
+    fn collect_validation_issues(&self, issues: &mut Vec<ValidationIssue>) -> Result<()> {
+        let mut section_contribution_iter = self
+            .debug_info
+            .section_contributions()
+            .map_err(Error::stream("DBI section contributions"))?;
+        // (section, start offset, end offset, module index)
+        let mut previous: Option<(u16, u32, u32, usize)> = None;
+        let mut reported_unordered = false;
+        let mut reported_sections = Vec::new();
+        while let Some(sc) = section_contribution_iter
+            .next()
+            .map_err(Error::stream("DBI section contributions"))?
+        {
+            if sc.size == 0 {
+                continue;
+            }
+            let (section, start) = (sc.offset.section, sc.offset.offset);
+            if get_section(section, &self.sections).is_none()
+                && !reported_sections.contains(&section)
+            {
+                reported_sections.push(section);
+                issues.push(ValidationIssue::ContributionOutsideSections {
+                    section,
+                    offset: start,
+                    module_index: sc.module,
+                });
+            }
+            if let Some((prev_section, prev_start, prev_end, prev_module)) = previous {
+                if (section, start) < (prev_section, prev_start) {
+                    if !reported_unordered {
+                        reported_unordered = true;
+                        issues.push(ValidationIssue::UnorderedContributions {
+                            section,
+                            offset: start,
+                            module_index: sc.module,
+                        });
+                    }
+                } else if section == prev_section && start < prev_end {
+                    issues.push(ValidationIssue::OverlappingContributions {
+                        section,
+                        offset: start,
+                        module_index: sc.module,
+                        other_module_index: prev_module,
+                    });
+                }
+            }
+            previous = Some((section, start, start.saturating_add(sc.size), sc.module));
+        }
+
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            extended_module_cache,
+            ..
+        } = &mut *cache;
+        let modules = self.type_formatter.modules();
+        let unreadable_module =
+            |module_index: usize, error: Error| ValidationIssue::UnreadableModule {
+                module_index,
+                module_name: modules[module_index].module_name().into_owned(),
+                error: error.to_string(),
+            };
+        for module_index in 0..modules.len() {
+            self.start_module(module_index)?;
+            module_cache.get_basic_module_info(modules, module_index);
+            for (module_index, error) in module_cache.unreadable_modules.drain(..) {
+                issues.push(unreadable_module(module_index, error));
+            }
+            for (module_index, symbol_index, error) in module_cache.malformed_symbols.drain(..) {
+                issues.push(ValidationIssue::MalformedSymbol {
+                    module_index,
+                    symbol_offset: symbol_index.0,
+                    error: error.to_string(),
+                });
+            }
+            let BasicModuleInfo {
+                procedures,
+                module_info,
+                ..
+            } = match module_cache.get_basic_module_info(modules, module_index) {
+                Some(basic_module_info) => basic_module_info,
+                None => continue,
+            };
+            // Validation checks the module's records, also for compact modules.
+            let info = match self.get_stream_module_info(
+                extended_module_cache,
+                module_index,
+                *module_info,
+            ) {
+                Ok(info) => info,
+                Err(error) => {
+                    issues.push(unreadable_module(module_index, error));
+                    continue;
+                }
+            };
+            let ExtendedModuleInfo {
+                module_info,
+                inlinees,
+                lines,
+                ..
+            } = &*info;
+            let module_info = self.module_stream(module_index, *module_info)?;
+            for proc in procedures {
+                let start_rva = self.rva_for_offset(proc.offset).map(u32::from);
+                match lines.lines_for_symbol(proc.offset, Some(proc.len)) {
+                    Ok(proc_lines) if proc_lines.is_empty() => {
+                        issues.push(ValidationIssue::ProcedureWithoutLines {
+                            module_index,
+                            name: proc.name.to_string().into_owned(),
+                            start_rva,
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(error) => issues.push(unreadable_module(module_index, error)),
+                }
+                let mut missing_inlinees = Vec::new();
+                if let Err(error) = compute_procedure_inline_ranges(
+                    module_info,
+                    proc,
+                    inlinees,
+                    &mut missing_inlinees,
+                ) {
+                    issues.push(unreadable_module(module_index, error));
+                }
+                for inlinee in missing_inlinees {
+                    issues.push(ValidationIssue::InlineSiteWithoutRanges {
+                        module_index,
+                        procedure_start_rva: start_rva,
+                        inlinee: inlinee.0,
+                    });
+                }
+            }
+        }
+        Ok(())
     }
 
-    fn lookup_function<'m>(
-        &self,
-        offset: PdbInternalSectionOffset,
-        module_cache: &'m mut BasicModuleInfoCache<'a, 's>,
-    ) -> Option<PublicOrProcedureSymbol<'a, 's, 'm>> {
-        let sc_index = match self.section_contributions.binary_search_by(|sc| {
-            if sc.section_index < offset.section {
-                Ordering::Less
-            } else if sc.section_index > offset.section {
-                Ordering::Greater
-            } else if sc.end_offset <= offset.offset {
-                Ordering::Less
-            } else if sc.start_offset > offset.offset {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
-            }
-        }) {
-            Ok(sc_index) => sc_index,
-            Err(_) => {
-                // The requested address is not present in any section contribution.
-                return None;
-            }
-        };
-
-        let sc = &self.section_contributions[sc_index];
-        let basic_module_info =
-            module_cache.get_basic_module_info(self.type_formatter.modules(), sc.module_index);
+    /// Wrap `source` in an [`Error::Module`], to say which module the error happened in.
+    fn module_error(&self, module_index: usize, operation: &'static str, source: Error) -> Error {
+        let module_name = self
+            .type_formatter
+            .modules()
+            .get(module_index)
+            .map(|module| module.module_name().into_owned())
+            .unwrap_or_default();
+        Error::Module {
+            module_index,
+            module_name,
+            operation,
+            source: Box::new(source),
+        }
+    }
 
-        let module_info = if let Some(BasicModuleInfo {
-            procedures,
-            module_info,
-        }) = basic_module_info
-        {
-            if let Ok(procedure_index) = procedures.binary_search_by(|p| {
-                if p.offset.section < offset.section {
-                    Ordering::Less
-                } else if p.offset.section > offset.section {
-                    Ordering::Greater
-                } else if p.offset.offset + p.len <= offset.offset {
-                    Ordering::Less
-                } else if p.offset.offset > offset.offset {
-                    Ordering::Greater
-                } else {
-                    Ordering::Equal
-                }
-            }) {
-                // Found a procedure at the requested offset.
-                return Some(PublicOrProcedureSymbol::Procedure(
-                    sc.module_index,
-                    module_info,
-                    &procedures[procedure_index],
-                ));
-            }
-            Some(*module_info)
-        } else {
+    /// The line number to report for `line`, see [`ContextFlags::KEEP_LINE_ZERO`].
+    fn line_number(&self, line: u32) -> Option<u32> {
+        if line == 0 && !self.flags.contains(ContextFlags::KEEP_LINE_ZERO) {
             None
-        };
-
-        // No procedure was found at this offset in the module that the section
-        // contribution pointed us at.
-        // This is not uncommon.
-        // Fall back to the public symbols.
+        } else {
+            Some(line)
+        }
+    }
 
-        let last_global_function_starting_lte_address = match self
-            .global_functions
-            .binary_search_by_key(&(offset.section, offset.offset), |p| {
-                (p.start_offset.section, p.start_offset.offset)
-            }) {
-            Err(0) => return None,
-            Ok(i) => i,
-            Err(i) => i - 1,
-        };
-        let fun = &self.global_functions[last_global_function_starting_lte_address];
-        debug_assert!(
-            fun.start_offset.section < offset.section
-                || (fun.start_offset.section == offset.section
-                    && fun.start_offset.offset <= offset.offset)
-        );
-        if fun.start_offset.section != offset.section {
+    fn resolve_file_checksum(
+        &self,
+        lines: &ModuleLines<'a>,
+        file_index: FileIndex,
+    ) -> Option<FileChecksum<'a>> {
+        if !self.flags.contains(ContextFlags::FILE_CHECKSUMS) {
             return None;
         }
-        // Ignore symbols outside the section contribution.
-        if fun.start_offset.offset < sc.start_offset {
+        lines.file_checksum(file_index)
+    }
+
+    /// The file name for [`Frame::file`], unless the context was created with
+    /// [`ContextFlags::RAW_FILE_NAMES`].
+    fn file_name(&self, raw_file: Option<RawString<'a>>) -> Option<Cow<'a, str>> {
+        if self.flags.contains(ContextFlags::RAW_FILE_NAMES) {
             return None;
         }
-
-        Some(PublicOrProcedureSymbol::Public(
-            sc.module_index,
-            module_info,
-            last_global_function_starting_lte_address,
-        ))
+        raw_file.map(|name| name.to_string())
     }
 
-    fn compute_extended_module_info(
+    /// Without a string table, [`Diagnostic::MissingStringTable`] is reported once.
+    /// Otherwise, each file index which can't be resolved is reported once as
+    /// [`Diagnostic::UnresolvedFileName`].
+    fn resolve_raw_filename(
         &self,
-        module_info: &'a ModuleInfo<'s>,
-    ) -> Result<ExtendedModuleInfo<'a, 's>> {
-        let line_program = module_info.line_program()?;
-
-        let inlinees: BTreeMap<IdIndex, Inlinee> = module_info
-            .inlinees()?
-            .map(|i| Ok((i.index(), i)))
-            .collect()?;
-
-        Ok(ExtendedModuleInfo {
-            module_info,
-            inlinees,
-            line_program,
-        })
+        module_index: usize,
+        lines: &ModuleLines<'a>,
+        file_index: FileIndex,
+    ) -> Option<RawString<'a>> {
+        let name = lines.file_name(file_index, self.string_table);
+        if name.is_none() {
+            if self.string_table.is_none() {
+                if !self.missing_string_table_reported.replace(true) {
+                    self.report(|| Diagnostic::MissingStringTable);
+                }
+            } else if self
+                .unresolved_file_names_reported
+                .borrow_mut()
+                .insert((module_index, file_index.0))
+            {
+                self.report(|| Diagnostic::UnresolvedFileName {
+                    module_index,
+                    file_index: file_index.0,
+                });
+            }
+        }
+        name
     }
 
-    fn resolve_filename(
+    /// Turn an error from reading optional information for a lookup into `None`,
+    /// and report it as [`Diagnostic::DegradedModule`], so that the lookup can
+    /// still return the function. Errors which were cached by an earlier lookup
+    /// are not reported again.
+    fn degrade<T>(
         &self,
-        line_program: &LineProgram,
-        file_index: FileIndex,
-    ) -> Option<Cow<'a, str>> {
-        if let Some(string_table) = self.string_table {
-            if let Ok(file_info) = line_program.get_file_info(file_index) {
-                return file_info.name.to_string_lossy(string_table).ok();
+        module_index: usize,
+        operation: &'static str,
+        result: Result<T>,
+    ) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(
+                Error::ExtendedModuleInfoUnsuccessful
+                | Error::ProcedureLinesUnsuccessful
+                | Error::ProcedureInlineRangesUnsuccessful,
+            ) => None,
+            Err(error) => {
+                self.report(|| Diagnostic::DegradedModule {
+                    module_index,
+                    module_name: self
+                        .type_formatter
+                        .modules()
+                        .get(module_index)
+                        .map(|module| module.module_name().into_owned())
+                        .unwrap_or_default(),
+                    operation,
+                    error: error.to_string(),
+                });
+                None
             }
         }
-        None
     }
 }
 
@@ -806,23 +5722,136 @@ impl<'a, 's> Context<'a, 's> {
 pub struct FunctionIter<'c, 'a, 's> {
     context: &'c Context<'a, 's>,
     full_rva_list: Rc<Vec<u32>>,
+    /// The index of the next entry to be returned from the front.
     cur_index: usize,
+    /// One past the index of the next entry to be returned from the back.
+    end_index: usize,
+}
+
+impl<'c, 'a, 's> FunctionIter<'c, 'a, 's> {
+    /// Move the front of the iterator to the first function which starts at or after
+    /// `rva`, so that the next call to `next()` returns that function.
+    ///
+    /// This can move the iterator both forwards and backwards, but never past the
+    /// entries which have already been consumed from the back.
+    pub fn skip_to_rva(&mut self, rva: Rva32) {
+        let rva = rva.0;
+        let index = self
+            .full_rva_list
+            .partition_point(|&start_rva| start_rva < rva);
+        self.cur_index = index.min(self.end_index);
+    }
+
+    /// The function at `full_rva_list[index]`, with an estimated end address if its
+    /// end address isn't known, see [`Function::estimated_end_rva`].
+    fn function_at_index(&self, index: usize) -> Option<Function> {
+        let rva = self.full_rva_list[index];
+        let mut function = self.context.find_function_at_rva(rva, false).ok()??;
+        if function.estimated_end_rva.is_none() {
+            function.estimated_end_rva = self.estimate_end_rva(function.start_rva);
+        }
+        Some(function)
+    }
+
+    /// The start of the next function after `start_rva` in the same section, or the
+    /// end of the section.
+    fn estimate_end_rva(&self, start_rva: u32) -> Option<u32> {
+        let section = self.context.offset_for_rva(Rva32(start_rva))?.section;
+        let next_index = self.full_rva_list.partition_point(|&rva| rva <= start_rva);
+        if let Some(&next_rva) = self.full_rva_list.get(next_index) {
+            if self
+                .context
+                .offset_for_rva(Rva32(next_rva))
+                .map(|offset| offset.section)
+                == Some(section)
+            {
+                return Some(next_rva);
+            }
+        }
+        let header = self
+            .context
+            .sections
+            .get(usize::from(section).checked_sub(1)?)?;
+        self.context
+            .rva_for_offset(PdbInternalSectionOffset {
+                section,
+                offset: header.virtual_size,
+            })
+            .map(u32::from)
+    }
 }
 
 impl<'c, 'a, 's> Iterator for FunctionIter<'c, 'a, 's> {
     type Item = Function;
 
     fn next(&mut self) -> Option<Function> {
-        loop {
-            if self.cur_index >= self.full_rva_list.len() {
-                return None;
-            }
-            let rva = self.full_rva_list[self.cur_index];
+        while self.cur_index < self.end_index {
+            let index = self.cur_index;
             self.cur_index += 1;
-            if let Ok(Some(fun)) = self.context.find_function(rva) {
+            if let Some(fun) = self.function_at_index(index) {
                 return Some(fun);
             }
         }
+        None
+    }
+}
+
+impl<'c, 'a, 's> DoubleEndedIterator for FunctionIter<'c, 'a, 's> {
+    fn next_back(&mut self) -> Option<Function> {
+        while self.cur_index < self.end_index {
+            self.end_index -= 1;
+            if let Some(fun) = self.function_at_index(self.end_index) {
+                return Some(fun);
+            }
+        }
+        None
+    }
+}
+
+/// An iterator over the public symbols in a [`Context`], from
+/// [`Context::public_symbols`]. The iteration stops after the first error.
+pub struct PublicSymbolIter<'c, 'a, 's> {
+    context: &'c Context<'a, 's>,
+    symbol_iter: Option<SymbolIter<'a>>,
+}
+
+impl<'c, 'a, 's> Iterator for PublicSymbolIter<'c, 'a, 's> {
+    type Item = Result<PublicSymbolEntry>;
+
+    fn next(&mut self) -> Option<Result<PublicSymbolEntry>> {
+        loop {
+            let symbol = match self.symbol_iter.as_mut()?.next() {
+                Ok(Some(symbol)) => symbol,
+                Ok(None) => {
+                    self.symbol_iter = None;
+                    return None;
+                }
+                Err(err) => {
+                    self.symbol_iter = None;
+                    return Some(Err(Error::stream("global symbols")(err)));
+                }
+            };
+            if let S_PUB32 | S_PUB32_ST = symbol.raw_kind() {
+                if let Ok(SymbolData::Public(public)) = symbol.parse() {
+                    let rva = match public.offset.section {
+                        0 => None,
+                        _ => self.context.rva_for_offset(public.offset).map(u32::from),
+                    };
+                    return Some(Ok(PublicSymbolEntry {
+                        name: public.name.to_string().into_owned(),
+                        rva,
+                        code: public.code,
+                        function: public.function,
+                        managed: public.managed,
+                        msil: public.msil,
+                        in_executable_section: is_executable_section(
+                            public.offset.section,
+                            &self.context.sections,
+                        ),
+                    }));
+                }
+            }
+        }
     }
 }
 
@@ -830,13 +5859,94 @@ struct ContextCache<'a, 's> {
     module_cache: BasicModuleInfoCache<'a, 's>,
     function_line_cache: HashMap<PdbInternalSectionOffset, FunctionLineInfo>,
     procedure_cache: HashMap<PdbInternalSectionOffset, ExtendedProcedureInfo>,
-    extended_module_cache: BTreeMap<usize, Result<ExtendedModuleInfo<'a, 's>>>,
-    inline_name_cache: BTreeMap<IdIndex, Result<String>>,
+    extended_module_cache: BTreeMap<usize, Result<Rc<ExtendedModuleInfo<'a, 's>>>>,
+    inline_name_cache: BTreeMap<IdIndex, Result<(String, Option<FunctionNameLayout>)>>,
     full_rva_list: Option<Rc<Vec<u32>>>,
+    frames_cache: FramesCache<'a>,
+    memory_budget: MemoryBudget,
+    trampolines: Option<Vec<TrampolineRecord>>,
+}
+
+/// The results of [`Context::find_frames_at`] by probe address, see
+/// [`Context::set_frames_cache_capacity`].
+///
+/// This approximates a least-recently-used cache with two generations: new and
+/// reused results go into `recent`, and when `recent` is half full, it replaces
+/// `older`, whose entries are dropped.
+#[derive(Default)]
+struct FramesCache<'a> {
+    capacity: usize,
+    recent: HashMap<u32, Option<FunctionFrames<'a>>>,
+    older: HashMap<u32, Option<FunctionFrames<'a>>>,
+}
+
+impl<'a> FramesCache<'a> {
+    fn get(&mut self, probe: u32) -> Option<Option<FunctionFrames<'a>>> {
+        if let Some(frames) = self.recent.get(&probe) {
+            return Some(frames.clone());
+        }
+        let frames = self.older.remove(&probe)?;
+        self.insert(probe, frames.clone());
+        Some(frames)
+    }
+
+    fn insert(&mut self, probe: u32, frames: Option<FunctionFrames<'a>>) {
+        if self.recent.len() >= (self.capacity / 2).max(1) {
+            self.older = mem::take(&mut self.recent);
+        }
+        self.recent.insert(probe, frames);
+    }
+
+    fn clear(&mut self) {
+        self.recent.clear();
+        self.older.clear();
+    }
+}
+
+/// Keeps track of the size of the evictable caches and of when each module was last
+/// used, for [`Context::set_memory_budget`]. Sizes are only tracked while a budget
+/// is set.
+#[derive(Default)]
+struct MemoryBudget {
+    limit: Option<usize>,
+    /// The accounted size of all module and procedure caches.
+    total_size: usize,
+    /// Incremented once per lookup.
+    current_use: u64,
+    modules: HashMap<usize, ModuleMemoryUsage>,
+    /// The (module index, function offset) pairs that were used by the current
+    /// lookup and whose sizes haven't been accounted yet.
+    touched: Vec<(usize, PdbInternalSectionOffset)>,
+}
+
+impl MemoryBudget {
+    fn touch(&mut self, module_index: usize, func_offset: PdbInternalSectionOffset) {
+        if self.limit.is_some() {
+            self.touched.push((module_index, func_offset));
+        }
+    }
+}
+
+#[derive(Default)]
+struct ModuleMemoryUsage {
+    last_use: u64,
+    /// The accounted size of the module's procedure list and inlinee table.
+    size: usize,
 }
 
 struct BasicModuleInfoCache<'a, 's> {
     cache: HashMap<usize, Option<BasicModuleInfo<'a, 's>>>,
+    /// The modules which failed to load and haven't been reported yet, see
+    /// [`Diagnostic::UnreadableModule`].
+    unreadable_modules: Vec<(usize, Error)>,
+    /// The symbol records which were skipped and haven't been reported yet, see
+    /// [`Diagnostic::MalformedSymbol`].
+    malformed_symbols: Vec<(usize, SymbolIndex, Error)>,
+    /// The procedure lists from an imported cache snapshot, which spare walking the
+    /// symbols of these modules, see [`Context::import_cache`].
+    imported_procedures: HashMap<usize, ModuleProcedures>,
+    /// Whether the context has [`ContextFlags::COMPACT_MODULES`].
+    compact_modules: bool,
     module_info_provider: &'a dyn ModuleProvider<'s>,
 }
 
@@ -849,41 +5959,156 @@ impl<'a, 's> BasicModuleInfoCache<'a, 's> {
         // TODO: 2021 edition
         let module_info_provider = self.module_info_provider;
 
+        let unreadable_modules = &mut self.unreadable_modules;
+        let malformed_symbols = &mut self.malformed_symbols;
+        let imported_procedures = &self.imported_procedures;
+        let compact_modules = self.compact_modules;
         self.cache
             .entry(module_index)
             .or_insert_with(|| {
-                let module = modules.get(module_index as usize)?;
-                let module_info = module_info_provider
-                    .get_module_info(module_index, module)
-                    .ok()??;
-                BasicModuleInfo::try_from_module_info(module_info).ok()
+                let module = modules.get(module_index)?;
+                if compact_modules {
+                    if let Some(compact_module) =
+                        module_info_provider.get_compact_module(module_index, module)
+                    {
+                        return Some(BasicModuleInfo {
+                            module_info: ModuleData::Compact(compact_module),
+                            procedures: compact_module.procedures(),
+                            symbols_size: 0,
+                        });
+                    }
+                }
+                // Modules without a symbol stream are normal, so only errors are
+                // reported.
+                let result = match module_info_provider.get_module_info(module_index, module) {
+                    Ok(Some(module_info)) => {
+                        if let Some(imported) = imported_procedures.get(&module_index) {
+                            // If the names can't be read, the snapshot doesn't fit this
+                            // module after all, so fall back to walking its symbols.
+                            if let Some(basic_module_info) =
+                                BasicModuleInfo::from_imported_procedures(module_info, imported)
+                            {
+                                return Some(basic_module_info);
+                            }
+                        }
+                        let mut skipped_symbols = Vec::new();
+                        let result = BasicModuleInfo::try_from_module_info(
+                            module_info,
+                            &mut skipped_symbols,
+                            || {
+                                module_info_provider
+                                    .get_legacy_line_data(module_index, module)
+                                    .ok()
+                                    .flatten()
+                                    .and_then(|data| LegacyLineProgram::parse(data).ok())
+                            },
+                        );
+                        malformed_symbols.extend(
+                            skipped_symbols
+                                .into_iter()
+                                .map(|(symbol_index, err)| (module_index, symbol_index, err)),
+                        );
+                        result
+                    }
+                    Ok(None) => return None,
+                    Err(err) => Err(err.into()),
+                };
+                match result {
+                    Ok(basic_module_info) => Some(basic_module_info),
+                    Err(err) => {
+                        unreadable_modules.push((module_index, err));
+                        None
+                    }
+                }
             })
             .as_ref()
     }
 }
 
+/// Where the information of a module comes from: its stream, or the tables which
+/// were extracted for [`ContextFlags::COMPACT_MODULES`].
+#[derive(Clone, Copy)]
+enum ModuleData<'a, 's> {
+    Stream(&'a ModuleInfo<'s>),
+    Compact(&'a CompactModule),
+}
+
+impl<'a, 's> ModuleData<'a, 's> {
+    fn is_compact(&self) -> bool {
+        matches!(self, ModuleData::Compact(_))
+    }
+
+    fn language(&self) -> Option<Language> {
+        match self {
+            ModuleData::Stream(module_info) => compute_module_language(module_info).ok().flatten(),
+            ModuleData::Compact(compact_module) => compact_module.language(),
+        }
+    }
+}
+
 struct BasicModuleInfo<'a, 's> {
-    module_info: &'a ModuleInfo<'s>,
+    module_info: ModuleData<'a, 's>,
     procedures: Vec<ProcedureSymbolFunction<'a>>,
+    /// The size of the module's symbol data, in bytes. The pdb crate doesn't expose
+    /// the size of the module stream, so we compute it while iterating the symbols.
+    symbols_size: usize,
 }
 
 impl<'a, 's> BasicModuleInfo<'a, 's> {
+    /// Records which can't be parsed are skipped and added to `malformed_symbols`,
+    /// so that one bad record doesn't lose all the procedures of the module.
+    /// `read_legacy_lines` is only called for modules with C11 lines, if the lengths
+    /// of procedures have to be recovered from their lines.
     pub fn try_from_module_info(
         module_info: &'a ModuleInfo<'s>,
+        malformed_symbols: &mut Vec<(SymbolIndex, Error)>,
+        read_legacy_lines: impl FnOnce() -> Option<LegacyLineProgram<'a>>,
     ) -> Result<BasicModuleInfo<'a, 's>> {
         let mut symbols_iter = module_info.symbols()?;
         let mut functions = Vec::new();
-        while let Some(symbol) = symbols_iter.next()? {
+        let mut zero_length_functions = Vec::new();
+        let mut symbols_size = 0;
+        loop {
+            let symbol = match symbols_iter.next() {
+                Ok(Some(symbol)) => symbol,
+                Ok(None) => break,
+                Err(err) => {
+                    // The record's length is broken, so we can't find the next record.
+                    malformed_symbols.push((SymbolIndex(symbols_size as u32), err.into()));
+                    break;
+                }
+            };
+            // Each record is prefixed with a u16 length.
+            symbols_size = symbol.index().0 as usize + symbol.raw_bytes().len() + 2;
+            if let S_GMANPROC | S_LMANPROC = symbol.raw_kind() {
+                // Managed procedures, in mixed-mode (C++/CLI) PDBs.
+                if let Some(proc) = ManagedProcedureSymbol::parse(symbol.raw_bytes()) {
+                    if proc.len != 0 {
+                        functions.push(ProcedureSymbolFunction {
+                            offset: proc.offset,
+                            len: proc.len,
+                            name: proc.name,
+                            symbol_index: symbol.index(),
+                            end_symbol_index: proc.end,
+                            type_index: TypeIndex(0),
+                            managed_token: Some(proc.token),
+                            is_thunk: false,
+                        });
+                    }
+                }
+                continue;
+            }
             if let S_LPROC32 | S_LPROC32_ST | S_GPROC32 | S_GPROC32_ST | S_LPROC32_ID
             | S_GPROC32_ID | S_LPROC32_DPC | S_LPROC32_DPC_ID | S_THUNK32 | S_THUNK32_ST
             | S_SEPCODE = symbol.raw_kind()
             {
                 match symbol.parse() {
                     Ok(SymbolData::Procedure(proc)) => {
-                        if proc.len == 0 {
-                            continue;
-                        }
-
+                        let functions = if proc.len == 0 {
+                            &mut zero_length_functions
+                        } else {
+                            &mut functions
+                        };
                         functions.push(ProcedureSymbolFunction {
                             offset: proc.offset,
                             len: proc.len,
@@ -891,6 +6116,8 @@ impl<'a, 's> BasicModuleInfo<'a, 's> {
                             symbol_index: symbol.index(),
                             end_symbol_index: proc.end,
                             type_index: proc.type_index,
+                            managed_token: None,
+                            is_thunk: false,
                         });
                     }
                     Ok(SymbolData::SeparatedCode(data)) => {
@@ -901,20 +6128,22 @@ impl<'a, 's> BasicModuleInfo<'a, 's> {
                         // SeparatedCode references another procedure with data.parent_offset.
                         // Usually the SeparatedCode symbol comes right after the referenced symbol.
                         // Take the name and type_index from the referenced procedure.
-                        let (name, type_index) = match functions.last() {
+                        let (name, type_index, managed_token) = match functions.last() {
                             Some(proc) if proc.offset == data.parent_offset => {
-                                (proc.name, proc.type_index)
+                                (proc.name, proc.type_index, proc.managed_token)
                             }
                             _ => continue,
                         };
 
                         functions.push(ProcedureSymbolFunction {
                             offset: data.offset,
-                            len: data.len as u32,
+                            len: data.len,
                             name,
                             symbol_index: symbol.index(),
                             end_symbol_index: data.end,
                             type_index,
+                            managed_token,
+                            is_thunk: false,
                         });
                     }
                     Ok(SymbolData::Thunk(thunk)) => {
@@ -922,32 +6151,212 @@ impl<'a, 's> BasicModuleInfo<'a, 's> {
                             continue;
                         }
 
-                        // Treat thunks as procedures. This isn't perfectly accurate but it
-                        // doesn't cause any harm.
-                        functions.push(ProcedureSymbolFunction {
-                            offset: thunk.offset,
-                            len: thunk.len as u32,
-                            name: thunk.name,
-                            symbol_index: symbol.index(),
-                            end_symbol_index: thunk.end,
-                            type_index: TypeIndex(0),
-                        });
-                    }
-                    _ => {}
-                }
-            }
+                        // Treat thunks as procedures. This isn't perfectly accurate but it
+                        // doesn't cause any harm.
+                        functions.push(ProcedureSymbolFunction {
+                            offset: thunk.offset,
+                            len: thunk.len as u32,
+                            name: thunk.name,
+                            symbol_index: symbol.index(),
+                            end_symbol_index: thunk.end,
+                            type_index: TypeIndex(0),
+                            managed_token: None,
+                            is_thunk: true,
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(err) => malformed_symbols.push((symbol.index(), err.into())),
+                }
+            }
+        }
+        // Procedures in section 0 have no address. They can't be found by lookups,
+        // and they would be listed by the function iterators with bogus rvas.
+        functions.retain(|p| p.offset.section != 0);
+        // Sort and de-duplicate, so that we can use binary search during lookup.
+        functions.sort_unstable_by_key(|p| (p.offset.section, p.offset.offset));
+        functions.dedup_by_key(|p| p.offset);
+        if !zero_length_functions.is_empty() {
+            let line_program = module_info.line_program();
+            let legacy_lines = match &line_program {
+                // The module has C11 lines, which the pdb crate can't read.
+                Err(pdb::Error::UnimplementedFeature(_)) => read_legacy_lines(),
+                _ => None,
+            };
+            recover_procedure_lengths(&mut functions, zero_length_functions, |offset, len| match (
+                &line_program,
+                &legacy_lines,
+            ) {
+                (Ok(line_program), _) => {
+                    let mut lines = line_program.lines_for_symbol(offset);
+                    lines_end(std::iter::from_fn(|| lines.next().ok().flatten()))
+                }
+                (Err(_), Some(legacy_lines)) => {
+                    lines_end(legacy_lines.lines_for_symbol(offset, len))
+                }
+                (Err(_), None) => None,
+            });
+        }
+
+        Ok(BasicModuleInfo {
+            module_info: ModuleData::Stream(module_info),
+            procedures: functions,
+            symbols_size,
+        })
+    }
+
+    /// Build the procedure list from an imported cache snapshot, reading only the
+    /// records with the procedure names. Returns `None` if a name can't be read.
+    fn from_imported_procedures(
+        module_info: &'a ModuleInfo<'s>,
+        imported: &ModuleProcedures,
+    ) -> Option<BasicModuleInfo<'a, 's>> {
+        let procedures = imported
+            .procedures
+            .iter()
+            .map(|proc| {
+                Some(ProcedureSymbolFunction {
+                    offset: proc.offset,
+                    len: proc.len,
+                    name: procedure_name_at(module_info, proc.name_symbol_index)?,
+                    symbol_index: proc.symbol_index,
+                    end_symbol_index: proc.end_symbol_index,
+                    type_index: proc.type_index,
+                    managed_token: proc.managed_token,
+                    is_thunk: proc.is_thunk,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(BasicModuleInfo {
+            module_info: ModuleData::Stream(module_info),
+            procedures,
+            symbols_size: imported.symbols_size,
+        })
+    }
+
+    /// The procedure list in the form which is stored in cache snapshots, see
+    /// [`Context::export_cache`].
+    fn to_module_procedures(&self) -> ModuleProcedures {
+        let procedures = self
+            .procedures
+            .iter()
+            .map(|proc| SnapshotProcedure {
+                offset: proc.offset,
+                len: proc.len,
+                symbol_index: proc.symbol_index,
+                end_symbol_index: proc.end_symbol_index,
+                name_symbol_index: self.name_symbol_index(proc),
+                type_index: proc.type_index,
+                managed_token: proc.managed_token,
+                is_thunk: proc.is_thunk,
+            })
+            .collect();
+        ModuleProcedures {
+            symbols_size: self.symbols_size,
+            procedures,
+        }
+    }
+
+    /// The index of the record which has the name of the procedure. Separated code
+    /// takes the name of its parent procedure.
+    fn name_symbol_index(&self, proc: &ProcedureSymbolFunction<'a>) -> SymbolIndex {
+        let module_info = match self.module_info {
+            ModuleData::Stream(module_info) => module_info,
+            ModuleData::Compact(compact_module) => return compact_module.name_symbol_index(proc),
+        };
+        let parent_offset = match module_info
+            .symbols_at(proc.symbol_index)
+            .ok()
+            .and_then(|mut symbols| symbols.next().ok().flatten())
+            .and_then(|symbol| symbol.parse().ok())
+        {
+            Some(SymbolData::SeparatedCode(data)) => data.parent_offset,
+            _ => return proc.symbol_index,
+        };
+        match self
+            .procedures
+            .binary_search_by_key(&(parent_offset.section, parent_offset.offset), |p| {
+                (p.offset.section, p.offset.offset)
+            }) {
+            Ok(parent_index) => self.procedures[parent_index].symbol_index,
+            Err(_) => proc.symbol_index,
+        }
+    }
+}
+
+/// Read the name of the procedure, thunk or managed procedure record at `index`.
+fn procedure_name_at<'a>(
+    module_info: &'a ModuleInfo<'_>,
+    index: SymbolIndex,
+) -> Option<RawString<'a>> {
+    let symbol = module_info.symbols_at(index).ok()?.next().ok()??;
+    if let S_GMANPROC | S_LMANPROC = symbol.raw_kind() {
+        return Some(ManagedProcedureSymbol::parse(symbol.raw_bytes())?.name);
+    }
+    match symbol.parse().ok()? {
+        SymbolData::Procedure(proc) => Some(proc.name),
+        SymbolData::Thunk(thunk) => Some(thunk.name),
+        _ => None,
+    }
+}
+
+/// Some toolchains emit procedure records with a length of 0, even though the
+/// procedure has code and line records. Recover the length from the end of the
+/// procedure's line records, or else from the start of the module's next procedure
+/// in the same section, and add the procedures to the sorted `functions`.
+/// Procedures whose length can't be recovered are left out, and so are the ones
+/// at the address of a procedure with a length.
+///
+/// `lines_end` returns the end of the line records of the procedure at the given
+/// offset. It also gets the distance to the next procedure, if there is one, for
+/// line tables which aren't grouped by procedure.
+fn recover_procedure_lengths<'a>(
+    functions: &mut Vec<ProcedureSymbolFunction<'a>>,
+    zero_length_functions: Vec<ProcedureSymbolFunction<'a>>,
+    mut lines_end: impl FnMut(PdbInternalSectionOffset, Option<u32>) -> Option<u32>,
+) {
+    let mut recovered = Vec::new();
+    for mut proc in zero_length_functions {
+        if proc.offset.section == 0 {
+            continue;
         }
-        // Sort and de-duplicate, so that we can use binary search during lookup.
+        let next_index = functions.partition_point(|p| p.offset <= proc.offset);
+        if next_index > 0 && functions[next_index - 1].offset == proc.offset {
+            continue;
+        }
+        let next_offset = functions
+            .get(next_index)
+            .filter(|next| next.offset.section == proc.offset.section)
+            .map(|next| next.offset.offset);
+        let lines_end = lines_end(
+            proc.offset,
+            next_offset.map(|next_offset| next_offset - proc.offset.offset),
+        );
+        let end = match (lines_end, next_offset) {
+            (Some(lines_end), Some(next_offset)) => lines_end.min(next_offset),
+            (Some(end), None) | (None, Some(end)) => end,
+            (None, None) => continue,
+        };
+        if end > proc.offset.offset {
+            proc.len = end - proc.offset.offset;
+            recovered.push(proc);
+        }
+    }
+    if !recovered.is_empty() {
+        functions.extend(recovered);
         functions.sort_unstable_by_key(|p| (p.offset.section, p.offset.offset));
         functions.dedup_by_key(|p| p.offset);
-
-        Ok(BasicModuleInfo {
-            module_info,
-            procedures: functions,
-        })
     }
 }
 
+/// The end of the code which is covered by `lines`. Lines whose end overflows are
+/// ignored.
+fn lines_end(lines: impl IntoIterator<Item = LineInfo>) -> Option<u32> {
+    lines
+        .into_iter()
+        .filter_map(|line| line.offset.offset.checked_add(line.length.unwrap_or(0)))
+        .max()
+}
+
 /// The order of the fields matters for the lexicographical sort.
 #[derive(Debug, Clone, PartialOrd, PartialEq, Eq, Ord)]
 pub struct ModuleSectionContribution {
@@ -968,7 +6377,8 @@ fn compute_section_contributions(
     placeholder_functions: &mut Vec<PublicSymbolFunctionOrPlaceholder>,
 ) -> Result<Vec<ModuleSectionContribution>> {
     let mut section_contribution_iter = debug_info
-        .section_contributions()?
+        .section_contributions()
+        .map_err(Error::stream("DBI section contributions"))?
         .filter(|sc| Ok(sc.size != 0 && is_executable_section(sc.offset.section, sections)));
     let mut section_contributions = Vec::new();
 
@@ -1017,6 +6427,7 @@ fn compute_section_contributions(
                 placeholder_functions.push(PublicSymbolFunctionOrPlaceholder {
                     start_offset: sc.offset,
                     name: None,
+                    is_code: true,
                 });
             }
         }
@@ -1044,13 +6455,111 @@ fn compute_section_contributions(
     Ok(section_contributions)
 }
 
+/// Whether the mangled name of a public symbol can be the name of the procedure
+/// `procedure_name`. Only the qualified name is compared, not the signature. Names
+/// which can't be compared without a demangler, such as operators, constructors and
+/// templates, are assumed to match.
+fn public_name_matches(public_name: &str, procedure_name: &str) -> bool {
+    if public_name == procedure_name {
+        return true;
+    }
+    if let Some(mangled) = public_name.strip_prefix('?') {
+        // ?name@scope1@scope2@@signature
+        if mangled.starts_with('?') || mangled.starts_with('$') {
+            return true;
+        }
+        let qualified_name = match mangled.find("@@") {
+            Some(end) => &mangled[..end],
+            None => return true,
+        };
+        let parts: Vec<&str> = qualified_name.split('@').collect();
+        if parts.iter().any(|part| {
+            part.is_empty() || part.starts_with(|c: char| c == '?' || c.is_ascii_digit())
+        }) {
+            // Back-references and nested special names.
+            return true;
+        }
+        let undecorated = parts.into_iter().rev().collect::<Vec<_>>().join("::");
+        return undecorated == procedure_name;
+    }
+    // C names: _name for cdecl, _name@N for stdcall, @name@N for fastcall.
+    let without_suffix = match public_name.rfind('@') {
+        Some(at) if at > 0 && public_name[at + 1..].bytes().all(|b| b.is_ascii_digit()) => {
+            &public_name[..at]
+        }
+        _ => public_name,
+    };
+    without_suffix == procedure_name
+        || without_suffix
+            .strip_prefix(|c: char| c == '_' || c == '@')
+            .is_some_and(|name| name == procedure_name)
+}
+
 /// section_index is a 1-based index from PdbInternalSectionOffset.
 fn get_section(section_index: u16, sections: &[ImageSectionHeader]) -> Option<&ImageSectionHeader> {
     if section_index == 0 {
         None
     } else {
-        sections.get((section_index - 1) as usize)
+        sections.get(usize::from(section_index - 1))
+    }
+}
+
+/// A grouped section from an S_COFFGROUP symbol of the linker module, e.g.
+/// `.CRT$XCU`. The pdb crate doesn't parse these.
+struct CoffGroup {
+    name: String,
+    offset: PdbInternalSectionOffset,
+    size: u32,
+}
+
+fn parse_coff_group(data: &[u8]) -> Option<CoffGroup> {
+    // kind: u16, size: u32, characteristics: u32, offset: u32, section: u16,
+    // name: null-terminated string
+    let u32_at = |pos: usize| {
+        let bytes = data.get(pos..pos + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let size = u32_at(2)?;
+    let offset = u32_at(10)?;
+    let section = data.get(14..16)?;
+    let name = data.get(16..)?.split(|b| *b == 0).next()?;
+    Some(CoffGroup {
+        name: String::from_utf8_lossy(name).into_owned(),
+        offset: PdbInternalSectionOffset {
+            offset,
+            section: u16::from_le_bytes([section[0], section[1]]),
+        },
+        size,
+    })
+}
+
+/// Whether the grouped section holds entries of the C++ initializer table, as
+/// opposed to the table's start and end markers.
+fn is_initializer_table(group_name: &str) -> bool {
+    group_name.starts_with(".CRT$XC") && group_name != ".CRT$XCA" && group_name != ".CRT$XCZ"
+}
+
+/// Returns the kind and the variable name of a compiler-generated initializer or
+/// destructor, e.g. ``ns::`dynamic initializer for 'gFoo''``. The namespace comes
+/// before the marker.
+fn parse_initializer_name(name: &str) -> Option<(InitializerKind, String)> {
+    for (kind, marker) in [
+        (
+            InitializerKind::DynamicInitializer,
+            "`dynamic initializer for '",
+        ),
+        (
+            InitializerKind::AtexitDestructor,
+            "`dynamic atexit destructor for '",
+        ),
+    ] {
+        if let Some(marker_start) = name.find(marker) {
+            let scope = &name[..marker_start];
+            let variable = name[marker_start + marker.len()..].trim_end_matches('\'');
+            return Some((kind, format!("{}{}", scope, variable)));
+        }
     }
+    None
 }
 
 /// section_index is a 1-based index from PdbInternalSectionOffset.
@@ -1073,6 +6582,19 @@ struct PublicSymbolFunctionOrPlaceholder<'s> {
     /// The symbol name of the public symbol. This is the mangled ("decorated") function signature.
     /// None if this is a placeholder.
     name: Option<RawString<'s>>,
+    /// Whether the public symbol is marked as code or as a function. Always true for
+    /// placeholders.
+    is_code: bool,
+}
+
+impl PublicSymbolFunctionOrPlaceholder<'_> {
+    /// Placeholders and data symbols are only the nearest symbol, not the function.
+    fn provenance(&self) -> Provenance {
+        match self.name {
+            Some(_) if self.is_code => Provenance::PublicSymbol,
+            _ => Provenance::NearestSymbol,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -1096,38 +6618,108 @@ struct ProcedureSymbolFunction<'a> {
     /// The type of this procedure, or 0. This is needed to get the arguments for the
     /// function signature.
     type_index: TypeIndex,
+    /// The metadata token of the method, if this is a managed procedure.
+    managed_token: Option<u32>,
+    /// Whether this comes from an S_THUNK32 record instead of a procedure record.
+    is_thunk: bool,
+}
+
+/// The parts of an S_GMANPROC / S_LMANPROC record that we need. The pdb crate doesn't
+/// parse these.
+struct ManagedProcedureSymbol<'t> {
+    end: SymbolIndex,
+    len: u32,
+    token: u32,
+    offset: PdbInternalSectionOffset,
+    name: RawString<'t>,
+}
+
+impl<'t> ManagedProcedureSymbol<'t> {
+    fn parse(raw_bytes: &'t [u8]) -> Option<Self> {
+        // The layout is:
+        // kind: u16, parent: u32, end: u32, next: u32, len: u32, debug start: u32,
+        // debug end: u32, token: u32, offset: u32, section: u16, flags: u8,
+        // return register: u16, name: null-terminated string
+        let u32_at = |pos: usize| {
+            let bytes = raw_bytes.get(pos..pos + 4)?;
+            Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+        let section = raw_bytes.get(34..36)?;
+        let name = raw_bytes.get(39..)?;
+        let name = name.split(|b| *b == 0).next().unwrap_or(&[]);
+        Some(Self {
+            end: SymbolIndex(u32_at(6)?),
+            len: u32_at(14)?,
+            token: u32_at(26)?,
+            offset: PdbInternalSectionOffset {
+                offset: u32_at(30)?,
+                section: u16::from_le_bytes([section[0], section[1]]),
+            },
+            name: RawString::from(name),
+        })
+    }
 }
 
 enum PublicOrProcedureSymbol<'a, 's, 'm> {
-    Public(usize, Option<&'a ModuleInfo<'s>>, usize),
-    Procedure(usize, &'a ModuleInfo<'s>, &'m ProcedureSymbolFunction<'a>),
+    Public(usize, Option<ModuleData<'a, 's>>, usize),
+    Procedure(
+        usize,
+        ModuleData<'a, 's>,
+        usize,
+        &'m ProcedureSymbolFunction<'a>,
+        /// The end offset of the procedure, see [`Context::procedure_end_offset`].
+        u32,
+    ),
 }
 
 #[derive(Default)]
 struct FunctionLineInfo {
     lines: Option<Result<Vec<CachedLineInfo>>>,
+    /// The size which was last added to the memory budget for this entry.
+    accounted_size: usize,
 }
 
 impl FunctionLineInfo {
+    fn heap_size(&self) -> usize {
+        match &self.lines {
+            Some(Ok(lines)) => vec_size(lines),
+            _ => 0,
+        }
+    }
+
     fn get_lines(
         &mut self,
         function_offset: PdbInternalSectionOffset,
-        line_program: &LineProgram,
+        function_len: Option<u32>,
+        module_lines: &ModuleLines,
     ) -> Result<&[CachedLineInfo]> {
         let lines = self
             .lines
             .get_or_insert_with(|| {
-                let mut iterator = line_program.lines_for_symbol(function_offset);
-                let mut lines = Vec::new();
-                let mut next_item = iterator.next()?;
-                while let Some(line_info) = next_item {
-                    next_item = iterator.next()?;
-                    lines.push(CachedLineInfo {
+                let mut lines: Vec<CachedLineInfo> = module_lines
+                    .lines_for_symbol(function_offset, function_len)?
+                    .into_iter()
+                    .map(|line_info| CachedLineInfo {
                         start_offset: line_info.offset.offset,
                         file_index: line_info.file_index,
                         line_start: line_info.line_start,
-                    });
-                }
+                        line_end: line_info.line_end,
+                        column_start: line_info.column_start,
+                        column_end: line_info.column_end,
+                    })
+                    .collect();
+                // Some compilers emit several records at the same offset, where all
+                // but the last one are empty, e.g. for the line of a macro and then
+                // for the line of the code that it expands to. Keep only the last
+                // one, so that the binary search in the lookups is deterministic.
+                lines.sort_by_key(|line| line.start_offset);
+                lines.dedup_by(|next, previous| {
+                    if next.start_offset != previous.start_offset {
+                        return false;
+                    }
+                    mem::swap(next, previous);
+                    true
+                });
                 Ok(lines)
             })
             .as_mut()
@@ -1138,63 +6730,416 @@ impl FunctionLineInfo {
 
 #[derive(Default)]
 struct ExtendedProcedureInfo {
-    name: Option<Option<String>>,
+    name: Option<Option<(String, Option<FunctionNameLayout>)>>,
     inline_ranges: Option<Result<Vec<InlineRange>>>,
+    frame_info: Option<Option<ProcedureFrameInfo>>,
+    /// The size which was last added to the memory budget for this entry.
+    accounted_size: usize,
 }
 
 impl ExtendedProcedureInfo {
+    fn heap_size(&self) -> usize {
+        let name_size = match &self.name {
+            Some(Some((name, layout))) => {
+                name.capacity() + layout.as_ref().map_or(0, FunctionNameLayout::heap_size)
+            }
+            _ => 0,
+        };
+        let inline_ranges_size = match &self.inline_ranges {
+            Some(Ok(inline_ranges)) => vec_size(inline_ranges),
+            _ => 0,
+        };
+        name_size + inline_ranges_size
+    }
+
     fn get_name(
         &mut self,
         proc: &ProcedureSymbolFunction,
         type_formatter: &TypeFormatter,
         global_functions: &[PublicSymbolFunctionOrPlaceholder],
         module_index: usize,
+        module_info: ModuleData,
+        flags: ContextFlags,
     ) -> Option<&str> {
+        self.get_name_with_layout(
+            proc,
+            type_formatter,
+            global_functions,
+            module_index,
+            module_info,
+            flags,
+        )
+        .map(|(name, _)| name)
+    }
+
+    /// Like `get_name`, and also return where the return type, scope, name and
+    /// arguments are in the name, if they're known from the type records.
+    fn get_name_with_layout(
+        &mut self,
+        proc: &ProcedureSymbolFunction,
+        type_formatter: &TypeFormatter,
+        global_functions: &[PublicSymbolFunctionOrPlaceholder],
+        module_index: usize,
+        module_info: ModuleData,
+        flags: ContextFlags,
+    ) -> Option<(&str, Option<&FunctionNameLayout>)> {
         self.name
             .get_or_insert_with(|| {
-                if proc.type_index == TypeIndex(0) && !proc.name.as_bytes().starts_with(&[b'?']) {
-                    // We have no type, so proc.name might be an argument-less string.
-                    // If we have a public symbol at this address which is a decorated name
-                    // (starts with a '?'), prefer to use that because it'll usually include
-                    // the arguments.
+                if let Some(token) = proc.managed_token {
+                    // Resolving the token to a method name needs the assembly's
+                    // metadata, which isn't in the PDB. The name is usually present.
+                    if proc.name.as_bytes().is_empty() {
+                        return Some((format!("<managed method 0x{:08x}>", token), None));
+                    }
+                    return Some((proc.name.to_string().into_owned(), None));
+                }
+                let prefer_public = if flags.contains(ContextFlags::PREFER_PROCEDURE_NAMES) {
+                    false
+                } else if flags.contains(ContextFlags::PREFER_DECORATED_PUBLIC_NAMES) {
+                    true
+                } else {
+                    proc.type_index == TypeIndex(0) && !proc.name.as_bytes().starts_with(b"?")
+                };
+                if prefer_public {
+                    // By default, we only get here if we have no type, so proc.name might
+                    // be an argument-less string. If we have a public symbol at this
+                    // address which is a decorated name (starts with a '?'), prefer to use
+                    // that because it'll usually include the arguments.
                     if let Ok(public_fun_index) = global_functions
                         .binary_search_by_key(&(proc.offset.section, proc.offset.offset), |f| {
                             (f.start_offset.section, f.start_offset.offset)
                         })
                     {
                         if let Some(name) = global_functions[public_fun_index].name {
-                            if name.as_bytes().starts_with(&[b'?']) {
-                                return Some(name.to_string().to_string());
+                            if name.as_bytes().starts_with(b"?") {
+                                return Some((name.to_string().to_string(), None));
                             }
                         }
                     }
                 }
+                if flags.contains(ContextFlags::PARAMETER_NAMES) && proc.type_index != TypeIndex(0)
+                {
+                    let parameter_names = match module_info {
+                        ModuleData::Stream(module_info) => {
+                            compute_parameter_names(module_info, proc).ok()
+                        }
+                        ModuleData::Compact(compact_module) => compact_module.parameter_names(proc),
+                    };
+                    if let Some(parameter_names) = parameter_names {
+                        let parameter_names: Vec<&str> =
+                            parameter_names.iter().map(String::as_str).collect();
+                        return type_formatter
+                            .format_function_with_layout(
+                                &proc.name.to_string(),
+                                module_index,
+                                proc.type_index,
+                                &parameter_names,
+                            )
+                            .ok();
+                    }
+                }
                 type_formatter
-                    .format_function(&proc.name.to_string(), module_index, proc.type_index)
+                    .format_function_with_layout(
+                        &proc.name.to_string(),
+                        module_index,
+                        proc.type_index,
+                        &[],
+                    )
                     .ok()
             })
-            .as_deref()
+            .as_ref()
+            .map(|(name, layout)| (name.as_str(), layout.as_ref()))
     }
 
-    fn get_inline_ranges(
+    fn get_frame_info(
         &mut self,
-        module_info: &ModuleInfo,
+        module_info: ModuleData,
+        proc: &ProcedureSymbolFunction,
+    ) -> Option<ProcedureFrameInfo> {
+        *self.frame_info.get_or_insert_with(|| match module_info {
+            ModuleData::Stream(module_info) => compute_procedure_frame_info(module_info, proc).ok(),
+            ModuleData::Compact(compact_module) => compact_module.frame_info(proc),
+        })
+    }
+
+    /// If the inline ranges are computed by this call, the inlinees whose ranges had
+    /// to be derived from their callees are added to `missing_inlinees`. The inline
+    /// ranges of compact modules aren't copied into the cache.
+    fn get_inline_ranges<'r>(
+        &'r mut self,
+        module_info: ModuleData<'r, '_>,
         proc: &ProcedureSymbolFunction,
         inlinees: &BTreeMap<IdIndex, Inlinee>,
-    ) -> Result<&[InlineRange]> {
+        missing_inlinees: &mut Vec<IdIndex>,
+    ) -> Result<&'r [InlineRange]> {
+        let module_info = match module_info {
+            ModuleData::Stream(module_info) => module_info,
+            ModuleData::Compact(compact_module) => return Ok(compact_module.inline_ranges(proc)),
+        };
         let inline_ranges = self
             .inline_ranges
-            .get_or_insert_with(|| compute_procedure_inline_ranges(module_info, proc, inlinees))
+            .get_or_insert_with(|| {
+                compute_procedure_inline_ranges(module_info, proc, inlinees, missing_inlinees)
+            })
             .as_mut()
             .map_err(|e| mem::replace(e, Error::ProcedureInlineRangesUnsuccessful))?;
         Ok(inline_ranges)
     }
 }
 
+/// Information about how a procedure was compiled.
+#[derive(Clone, Copy)]
+struct ProcedureFrameInfo {
+    /// The flags from the procedure's S_FRAMEPROC record, if it has one.
+    frame_flags: Option<FrameFlags>,
+    /// The procedure's S_FRAMEPROC record, if it has one.
+    frame_proc: Option<FrameProcRecord>,
+    /// The "optimized debug info" flag of the procedure symbol.
+    has_optimized_debug_info: bool,
+    /// The offsets of the end of the prologue and of the start of the epilogue,
+    /// relative to the start of the procedure, if the procedure symbol has them.
+    debug_range: Option<(u32, u32)>,
+}
+
+impl ProcedureFrameInfo {
+    fn is_optimized(&self) -> Option<bool> {
+        if self.has_optimized_debug_info {
+            return Some(true);
+        }
+        self.frame_flags
+            .map(|flags| flags.contains(FrameFlags::OPTIMIZED_FOR_SPEED))
+    }
+}
+
+fn compute_procedure_frame_info(
+    module_info: &ModuleInfo,
+    proc: &ProcedureSymbolFunction,
+) -> Result<ProcedureFrameInfo> {
+    let mut symbols_iter = module_info.symbols_at(proc.symbol_index)?;
+    let (has_optimized_debug_info, debug_range) =
+        match symbols_iter.next()?.map(|symbol| symbol.parse()) {
+            // Some compilers, e.g. clang, always write zero for both offsets.
+            Some(Ok(SymbolData::Procedure(p)))
+                if p.dbg_end_offset != 0
+                    && p.dbg_start_offset <= p.dbg_end_offset
+                    && p.dbg_end_offset < p.len =>
+            {
+                (
+                    p.flags.optdbginfo,
+                    Some((p.dbg_start_offset, p.dbg_end_offset)),
+                )
+            }
+            Some(Ok(SymbolData::Procedure(p))) => (p.flags.optdbginfo, None),
+            _ => (false, None),
+        };
+    let mut frame_flags = None;
+    let mut frame_proc = None;
+    while let Some(symbol) = symbols_iter.next()? {
+        if symbol.index() >= proc.end_symbol_index {
+            break;
+        }
+        match symbol.raw_kind() {
+            S_FRAMEPROC => {
+                if let Some(record) = parse_frame_proc(symbol.raw_bytes()) {
+                    frame_flags = Some(FrameFlags::from_bits_truncate(record.flags));
+                    frame_proc = Some(record);
+                }
+                break;
+            }
+            S_LPROC32 | S_LPROC32_ST | S_GPROC32 | S_GPROC32_ST | S_LPROC32_ID | S_GPROC32_ID
+            | S_LPROC32_DPC | S_LPROC32_DPC_ID => {
+                // The S_FRAMEPROC of a nested procedure is not ours.
+                if let Ok(SymbolData::Procedure(p)) = symbol.parse() {
+                    symbols_iter.skip_to(p.end)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(ProcedureFrameInfo {
+        frame_flags,
+        frame_proc,
+        has_optimized_debug_info,
+        debug_range,
+    })
+}
+
+/// The fields of an `S_FRAMEPROC` record.
+#[derive(Clone, Copy)]
+struct FrameProcRecord {
+    frame_size: u32,
+    padding_size: u32,
+    padding_offset: u32,
+    saved_registers_size: u32,
+    exception_handler: PdbInternalSectionOffset,
+    flags: u32,
+}
+
+/// Parse an `S_FRAMEPROC` record. The pdb crate doesn't parse these. The layout is:
+/// kind: u16, frame size: u32, padding size: u32, padding offset: u32,
+/// saved registers size: u32, exception handler offset: u32,
+/// exception handler section: u16, flags: u32
+fn parse_frame_proc(data: &[u8]) -> Option<FrameProcRecord> {
+    let u32_at = |pos: usize| -> Option<u32> {
+        let bytes = data.get(pos..pos + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let section = data.get(22..24)?;
+    Some(FrameProcRecord {
+        frame_size: u32_at(2)?,
+        padding_size: u32_at(6)?,
+        padding_offset: u32_at(10)?,
+        saved_registers_size: u32_at(14)?,
+        exception_handler: PdbInternalSectionOffset {
+            offset: u32_at(18)?,
+            section: u16::from_le_bytes([section[0], section[1]]),
+        },
+        flags: u32_at(24)?,
+    })
+}
+
+/// Returns the names of the procedure's parameters, in order.
+///
+/// Parameter names are not part of the procedure's type. Instead, they are found in
+/// the variable records in the outermost scope of the procedure: S_LOCAL records with
+/// the "is parameter" flag in optimized code, and S_REGREL32 / S_BPREL32 records in
+/// unoptimized code. The latter don't say whether they describe a parameter or a local
+/// variable, but the compiler emits the parameters first, so the formatter only uses
+/// as many of them as the function type has arguments.
+/// The implicit `this` parameter of member functions is skipped, and unnamed
+/// parameters (`__formal`) are returned as empty strings.
+fn compute_parameter_names(
+    module_info: &ModuleInfo,
+    proc: &ProcedureSymbolFunction,
+) -> Result<Vec<String>> {
+    let mut param_locals = Vec::new();
+    let mut frame_relatives = Vec::new();
+    let mut symbols_iter = module_info.symbols_at(proc.symbol_index)?;
+    let _proc_sym = symbols_iter.next()?;
+    while let Some(symbol) = symbols_iter.next()? {
+        if symbol.index() >= proc.end_symbol_index {
+            break;
+        }
+        match symbol.raw_kind() {
+            S_LOCAL => {
+                if let Ok(SymbolData::Local(local)) = symbol.parse() {
+                    if local.flags.isparam {
+                        param_locals.push(local.name);
+                    }
+                }
+            }
+            S_REGREL32 => {
+                if let Ok(SymbolData::RegisterRelative(regrel)) = symbol.parse() {
+                    frame_relatives.push(regrel.name);
+                }
+            }
+            S_BPREL32 => {
+                // The pdb crate doesn't parse these. The layout is:
+                // kind: u16, offset: i32, type: u32, name: null-terminated string
+                let name = symbol.raw_bytes().get(10..).unwrap_or(&[]);
+                let name = name.split(|b| *b == 0).next().unwrap_or(&[]);
+                frame_relatives.push(RawString::from(name));
+            }
+            S_LPROC32 | S_LPROC32_ST | S_GPROC32 | S_GPROC32_ST | S_LPROC32_ID | S_GPROC32_ID
+            | S_LPROC32_DPC | S_LPROC32_DPC_ID | S_BLOCK32 | S_BLOCK32_ST | S_INLINESITE
+            | S_INLINESITE2 => {
+                // Skip nested scopes, they don't contain parameters.
+                match symbol.parse() {
+                    Ok(SymbolData::Procedure(p)) => {
+                        symbols_iter.skip_to(p.end)?;
+                    }
+                    Ok(SymbolData::Block(b)) => {
+                        symbols_iter.skip_to(b.end)?;
+                    }
+                    Ok(SymbolData::InlineSite(site)) => {
+                        symbols_iter.skip_to(site.end)?;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let names = if param_locals.is_empty() {
+        frame_relatives
+    } else {
+        param_locals
+    };
+    let names = match names.split_first() {
+        Some((first, rest)) if first.as_bytes() == b"this" => rest,
+        _ => &names[..],
+    };
+    Ok(names
+        .iter()
+        .map(|name| match name.as_bytes() {
+            b"__formal" => String::new(),
+            _ => name.to_string().into_owned(),
+        })
+        .collect())
+}
+
+/// The number of bytes covered by the union of the `(start, end)` ranges.
+fn merged_range_length(mut ranges: Vec<(u32, u32)>) -> u32 {
+    ranges.sort_unstable();
+    let mut length = 0;
+    let mut covered_end = 0;
+    for (start, end) in ranges {
+        let start = start.max(covered_end);
+        if end > start {
+            length += end - start;
+            covered_end = end;
+        }
+    }
+    length
+}
+
+/// For each inline site in the procedure, add the size of its code and one call site
+/// to the inlinee's entry in `sizes`.
+fn add_inline_site_sizes(
+    module_info: &ModuleInfo,
+    proc: &ProcedureSymbolFunction,
+    inlinees: &BTreeMap<IdIndex, Inlinee>,
+    sizes: &mut HashMap<IdIndex, (u64, usize)>,
+) -> Result<()> {
+    let mut symbols_iter = module_info.symbols_at(proc.symbol_index)?;
+    let _proc_sym = symbols_iter.next()?;
+    while let Some(symbol) = symbols_iter.next()? {
+        if symbol.index() >= proc.end_symbol_index {
+            break;
+        }
+        if let S_LPROC32 | S_LPROC32_ST | S_GPROC32 | S_GPROC32_ST | S_LPROC32_ID | S_GPROC32_ID
+        | S_LPROC32_DPC | S_LPROC32_DPC_ID | S_INLINESITE | S_INLINESITE2 = symbol.raw_kind()
+        {
+            match symbol.parse() {
+                Ok(SymbolData::Procedure(p)) => {
+                    // This is a nested procedure. Skip it.
+                    symbols_iter.skip_to(p.end)?;
+                }
+                Ok(SymbolData::InlineSite(site)) => {
+                    let mut size = 0;
+                    if let Some(inlinee) = inlinees.get(&site.inlinee) {
+                        let mut iter = inlinee.lines(proc.offset, &site);
+                        while let Ok(Some(line_info)) = iter.next() {
+                            size += u64::from(line_info.length.unwrap_or(0));
+                        }
+                    }
+                    let entry = sizes.entry(site.inlinee).or_default();
+                    entry.0 += size;
+                    entry.1 += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
 fn compute_procedure_inline_ranges(
     module_info: &ModuleInfo,
     proc: &ProcedureSymbolFunction,
     inlinees: &BTreeMap<IdIndex, Inlinee>,
+    missing_inlinees: &mut Vec<IdIndex>,
 ) -> Result<Vec<InlineRange>> {
     let mut lines = Vec::new();
     let mut symbols_iter = module_info.symbols_at(proc.symbol_index)?;
@@ -1219,6 +7164,7 @@ fn compute_procedure_inline_ranges(
                         site,
                         0,
                         &mut lines,
+                        missing_inlinees,
                     )?;
                 }
                 _ => {}
@@ -1250,6 +7196,7 @@ fn process_inlinee_symbols(
     site: InlineSiteSymbol,
     call_depth: u16,
     lines: &mut Vec<InlineRange>,
+    missing_inlinees: &mut Vec<IdIndex>,
 ) -> Result<RangeSet2<u32>> {
     let mut ranges = RangeSet2::empty();
     let mut file_index = None;
@@ -1300,6 +7247,7 @@ fn process_inlinee_symbols(
                         site,
                         call_depth + 1,
                         lines,
+                        missing_inlinees,
                     )?;
                 }
                 _ => {}
@@ -1309,6 +7257,7 @@ fn process_inlinee_symbols(
 
     if !ranges.is_superset(&callee_ranges) {
         // Workaround bad debug info.
+        missing_inlinees.push(site.inlinee);
         let missing_ranges: RangeSet2<u32> = &callee_ranges - &ranges;
         for range in missing_ranges.iter() {
             let (start_offset, end_offset) = match range {
@@ -1332,10 +7281,107 @@ fn process_inlinee_symbols(
     Ok(ranges)
 }
 
+/// The line program of a module, as returned by [`Context::line_program`].
+/// Dereferences to the pdb crate's [`LineProgram`].
+///
+/// This keeps the cached information of the module alive, even if the module is
+/// released from the context's cache in the meantime.
+pub struct ModuleLineProgram<'a, 's> {
+    info: Rc<ExtendedModuleInfo<'a, 's>>,
+}
+
+impl<'a, 's> std::ops::Deref for ModuleLineProgram<'a, 's> {
+    type Target = LineProgram<'a>;
+
+    fn deref(&self) -> &LineProgram<'a> {
+        match &self.info.lines {
+            ModuleLines::C13(line_program) => line_program,
+            _ => unreachable!("only created for C13 line programs"),
+        }
+    }
+}
+
 struct ExtendedModuleInfo<'a, 's> {
-    module_info: &'a ModuleInfo<'s>,
+    module_info: ModuleData<'a, 's>,
     inlinees: BTreeMap<IdIndex, Inlinee<'a>>,
-    line_program: LineProgram<'a>,
+    lines: ModuleLines<'a>,
+    language: Option<Language>,
+}
+
+/// The line information of a module, in the current C13 format or in the legacy
+/// C11 format, or extracted for [`ContextFlags::COMPACT_MODULES`].
+enum ModuleLines<'a> {
+    C13(LineProgram<'a>),
+    C11(LegacyLineProgram<'a>),
+    Compact(&'a CompactModule),
+}
+
+impl<'a> ModuleLines<'a> {
+    /// The lines of the procedure at `offset`. `len` is only used for C11 lines, in
+    /// which the lines aren't grouped by procedure.
+    fn lines_for_symbol(
+        &self,
+        offset: PdbInternalSectionOffset,
+        len: Option<u32>,
+    ) -> Result<Vec<LineInfo>> {
+        match self {
+            ModuleLines::C13(line_program) => {
+                Ok(line_program.lines_for_symbol(offset).collect()?)
+            }
+            ModuleLines::C11(legacy_lines) => Ok(legacy_lines.lines_for_symbol(offset, len)),
+            ModuleLines::Compact(compact_module) => Ok(compact_module.lines_for_symbol(offset)),
+        }
+    }
+
+    fn file_name(
+        &self,
+        file_index: FileIndex,
+        string_table: Option<&'a StringTable<'_>>,
+    ) -> Option<RawString<'a>> {
+        match self {
+            ModuleLines::C13(line_program) => {
+                let file_info = line_program.get_file_info(file_index).ok()?;
+                file_info.name.to_raw_string(string_table?).ok()
+            }
+            ModuleLines::C11(legacy_lines) => legacy_lines.file_name(file_index),
+            ModuleLines::Compact(compact_module) => {
+                compact_module.file_name(file_index, string_table)
+            }
+        }
+    }
+
+    /// C11 lines have no checksums.
+    fn file_checksum(&self, file_index: FileIndex) -> Option<FileChecksum<'a>> {
+        match self {
+            ModuleLines::C13(line_program) => {
+                Some(line_program.get_file_info(file_index).ok()?.checksum)
+            }
+            ModuleLines::C11(_) => None,
+            ModuleLines::Compact(compact_module) => compact_module.file_checksum(file_index),
+        }
+    }
+}
+
+/// Returns the language from the module's compile flags record. This record is
+/// usually the second symbol in the module, after S_OBJNAME.
+fn compute_module_language(module_info: &ModuleInfo) -> Result<Option<Language>> {
+    let mut symbols_iter = module_info.symbols()?;
+    while let Some(symbol) = symbols_iter.next()? {
+        // The pdb crate fails to parse language codes that it doesn't know, such as
+        // the one for Rust, so read the code from the raw bytes. The layout is:
+        // S_COMPILE: kind: u16, machine: u8, language: u8, ...
+        // S_COMPILE2 / S_COMPILE3: kind: u16, flags: u32 with the language in the
+        // low byte, ...
+        let code = match symbol.raw_kind() {
+            S_COMPILE => symbol.raw_bytes().get(3),
+            S_COMPILE2 | S_COMPILE2_ST | S_COMPILE3 => symbol.raw_bytes().get(2),
+            S_LPROC32 | S_LPROC32_ST | S_GPROC32 | S_GPROC32_ST | S_LPROC32_ID | S_GPROC32_ID
+            | S_LPROC32_DPC | S_LPROC32_DPC_ID => return Ok(None),
+            _ => continue,
+        };
+        return Ok(code.map(|code| Language::from_code(*code)));
+    }
+    Ok(None)
 }
 
 #[derive(Clone, Debug)]
@@ -1343,6 +7389,9 @@ struct CachedLineInfo {
     pub start_offset: u32,
     pub file_index: FileIndex,
     pub line_start: u32,
+    pub line_end: u32,
+    pub column_start: Option<u32>,
+    pub column_end: Option<u32>,
 }
 
 struct HexNum<N: LowerHex>(pub N);
@@ -1384,3 +7433,111 @@ impl std::fmt::Debug for InlineRange {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn procedure(offset: u32, len: u32) -> ProcedureSymbolFunction<'static> {
+        ProcedureSymbolFunction {
+            offset: PdbInternalSectionOffset { section: 1, offset },
+            len,
+            name: RawString::from("f"),
+            symbol_index: SymbolIndex(0),
+            end_symbol_index: SymbolIndex(0),
+            type_index: TypeIndex(0),
+            managed_token: None,
+            is_thunk: false,
+        }
+    }
+
+    fn line(offset: u32, length: Option<u32>) -> LineInfo {
+        LineInfo {
+            offset: PdbInternalSectionOffset { section: 1, offset },
+            length,
+            file_index: FileIndex(0),
+            line_start: 1,
+            line_end: 1,
+            column_start: None,
+            column_end: None,
+            kind: pdb::LineInfoKind::Statement,
+        }
+    }
+
+    #[test]
+    fn recover_zero_procedure_lengths() {
+        let mut functions = vec![procedure(0x100, 0x10), procedure(0x200, 0x10)];
+        let zero_length_functions = vec![
+            // The lines end before the next procedure.
+            procedure(0x120, 0),
+            // The lines run into the next procedure.
+            procedure(0x180, 0),
+            // No lines, and no procedure after it.
+            procedure(0x300, 0),
+            // Already has a procedure with a length.
+            procedure(0x100, 0),
+        ];
+        let mut next_procedure_distances = Vec::new();
+        recover_procedure_lengths(&mut functions, zero_length_functions, |offset, len| {
+            next_procedure_distances.push((offset.offset, len));
+            match offset.offset {
+                0x120 => lines_end(vec![line(0x120, Some(0x8)), line(0x128, Some(0x10))]),
+                0x180 => lines_end(vec![line(0x180, Some(0x100))]),
+                _ => None,
+            }
+        });
+        let lengths: Vec<_> = functions.iter().map(|p| (p.offset.offset, p.len)).collect();
+        assert_eq!(
+            lengths,
+            vec![(0x100, 0x10), (0x120, 0x18), (0x180, 0x80), (0x200, 0x10)]
+        );
+        assert_eq!(
+            next_procedure_distances,
+            vec![(0x120, Some(0xe0)), (0x180, Some(0x80)), (0x300, None)]
+        );
+    }
+
+    #[test]
+    fn lines_end_ignores_overflowing_lines() {
+        assert_eq!(lines_end(vec![]), None);
+        assert_eq!(lines_end(vec![line(0x10, None)]), Some(0x10));
+        assert_eq!(
+            lines_end(vec![line(0x10, Some(0x20)), line(u32::MAX, Some(2))]),
+            Some(0x30)
+        );
+    }
+
+    #[test]
+    fn parse_managed_procedure_symbol() {
+        let mut record = Vec::new();
+        record.extend_from_slice(&0x112au16.to_le_bytes()); // S_GMANPROC
+        record.extend_from_slice(&0u32.to_le_bytes()); // parent
+        record.extend_from_slice(&0x1234u32.to_le_bytes()); // end
+        record.extend_from_slice(&0u32.to_le_bytes()); // next
+        record.extend_from_slice(&0x40u32.to_le_bytes()); // len
+        record.extend_from_slice(&0x4u32.to_le_bytes()); // debug start
+        record.extend_from_slice(&0x3cu32.to_le_bytes()); // debug end
+        record.extend_from_slice(&0x0600_0001u32.to_le_bytes()); // token
+        record.extend_from_slice(&0x2000u32.to_le_bytes()); // offset
+        record.extend_from_slice(&1u16.to_le_bytes()); // section
+        record.push(0); // flags
+        record.extend_from_slice(&0u16.to_le_bytes()); // return register
+        record.extend_from_slice(b"Program.Main\0");
+
+        let proc = ManagedProcedureSymbol::parse(&record).unwrap();
+        assert_eq!(proc.end, SymbolIndex(0x1234));
+        assert_eq!(proc.len, 0x40);
+        assert_eq!(proc.token, 0x0600_0001);
+        assert_eq!(
+            proc.offset,
+            PdbInternalSectionOffset {
+                section: 1,
+                offset: 0x2000
+            }
+        );
+        assert_eq!(proc.name, RawString::from("Program.Main"));
+
+        // A record which is cut off before the name is rejected.
+        assert!(ManagedProcedureSymbol::parse(&record[..36]).is_none());
+    }
+}
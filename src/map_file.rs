@@ -0,0 +1,93 @@
+use std::io::{self, Write};
+
+use pdb::Rva;
+
+use crate::{Context, FunctionFilter};
+
+impl<'a, 's> Context<'a, 's> {
+    /// Write a listing in the style of an MSVC linker map file: first the sections of
+    /// the image, then every function with its address, size and the object file it
+    /// came from.
+    ///
+    /// This is useful for tracking code size when the original map files aren't
+    /// available. Unlike a real map file, only functions are listed, and the names
+    /// are the same as the ones returned by [`Context::functions`].
+    pub fn write_map_file<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_map_file_matching(writer, &FunctionFilter::default())
+    }
+
+    /// Like [`Context::write_map_file`], but only lists the functions which are
+    /// returned by [`Context::functions_matching`] for `filter`. All sections are
+    /// still listed.
+    pub fn write_map_file_matching<W: Write>(
+        &self,
+        mut writer: W,
+        filter: &FunctionFilter,
+    ) -> io::Result<()> {
+        writeln!(
+            writer,
+            " Start         Length     Name                   Class"
+        )?;
+        for (section_index_zero_based, section) in self.sections.iter().enumerate() {
+            let class = if section.characteristics.execute() {
+                "CODE"
+            } else {
+                "DATA"
+            };
+            writeln!(
+                writer,
+                " {:04x}:{:08x} {:08x}H {:<23} {}",
+                section_index_zero_based + 1,
+                0,
+                section.virtual_size,
+                section.name(),
+                class
+            )?;
+        }
+        writeln!(writer)?;
+
+        writeln!(
+            writer,
+            "  Address         Size       Publics by Value              Rva        Lib:Object"
+        )?;
+        writeln!(writer)?;
+        for function in self.functions_matching(filter) {
+            let offset = match Rva(function.start_rva).to_internal_offset(self.address_map) {
+                Some(offset) => offset,
+                None => continue,
+            };
+            let size = function
+                .end_rva
+                .map_or(0, |end_rva| end_rva.saturating_sub(function.start_rva));
+            let object = self
+                .lookup_section_contribution(offset)
+                .and_then(|sc| self.type_formatter.modules().get(sc.module_index))
+                .map(|module| {
+                    let object = file_name(&module.module_name()).to_string();
+                    let library = module.object_file_name();
+                    if library == module.module_name() {
+                        object
+                    } else {
+                        format!("{}:{}", file_name(&library), object)
+                    }
+                })
+                .unwrap_or_else(|| "<unknown>".to_string());
+            writeln!(
+                writer,
+                " {:04x}:{:08x}  {:08x}H  {:<29} {:08x} f {}",
+                offset.section,
+                offset.offset,
+                size,
+                function.name.as_deref().unwrap_or("<unknown>"),
+                function.start_rva,
+                object
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The part of a Windows or Unix path after the last separator.
+fn file_name(path: &str) -> &str {
+    path.rsplit(['\\', '/']).next().unwrap_or(path)
+}
@@ -0,0 +1,224 @@
+use std::mem;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use pdb::{PdbInternalSectionOffset, Source};
+
+use crate::{
+    BasicModuleInfo, Context, ContextFlags, ContextPdbData, Error, ExtendedProcedureInfo, Function,
+    FunctionIter, FunctionNameLayout, Result, TypeFormatterFlags,
+};
+
+/// The formatted procedure names of the modules which one thread worked on.
+type NamesWithLayouts = Vec<(
+    PdbInternalSectionOffset,
+    Option<(String, Option<FunctionNameLayout>)>,
+)>;
+
+/// List all functions of a PDB, like [`Context::functions`], on the threads of the
+/// rayon thread pool.
+///
+/// A [`Context`] can't be shared between threads, so every thread calls `open` to
+/// get its own copy of the PDB's source and creates its own context from it. The
+/// threads then take the modules one at a time, read their symbols and format the
+/// names of their functions, so that the work is balanced even if a few modules
+/// contain most of the functions. The results are merged into one list, sorted by
+/// address.
+///
+/// The ids of the returned functions are assigned in address order, see
+/// [`Function::id`]. If any thread fails to open the PDB, the first error is
+/// returned.
+pub fn par_functions<'s, S, F>(
+    open: F,
+    formatter_flags: TypeFormatterFlags,
+    context_flags: ContextFlags,
+) -> Result<Vec<Function>>
+where
+    S: Source<'s> + 's,
+    F: Fn() -> std::io::Result<S> + Sync,
+{
+    let next_task = AtomicUsize::new(0);
+    let results = rayon::broadcast(|_| -> Result<(Vec<Function>, Vec<u32>)> {
+        let source = open().map_err(|e| Error::PdbError(e.into()))?;
+        let data = ContextPdbData::open(source)?;
+        let context = data.make_context_with_flags(formatter_flags, context_flags)?;
+        let mut tasks = context.public_function_rvas_by_module();
+        let module_count = tasks.len() - 1;
+        let mut functions = Vec::new();
+        let mut rvas = Vec::new();
+        loop {
+            let task = next_task.fetch_add(1, Ordering::Relaxed);
+            let mut task_rvas = match tasks.get_mut(task) {
+                Some(task_rvas) => mem::take(task_rvas),
+                None => break,
+            };
+            if task < module_count {
+                task_rvas.extend(context.procedure_rvas(task));
+            }
+            task_rvas.sort_unstable();
+            task_rvas.dedup();
+            functions.extend(context.functions_at_rvas(task_rvas.clone()));
+            rvas.extend(task_rvas);
+            if task < module_count {
+                context.release_module(task);
+            }
+        }
+        Ok((functions, rvas))
+    });
+
+    let mut functions = Vec::new();
+    let mut all_rvas = Vec::new();
+    for result in results {
+        let (thread_functions, thread_rvas) = result?;
+        functions.extend(thread_functions);
+        all_rvas.extend(thread_rvas);
+    }
+    // A procedure and a public symbol at the same address can be attributed to
+    // different modules, so the same function can come from two tasks.
+    functions.sort_by_key(|function| function.start_rva);
+    functions.dedup_by_key(|function| function.start_rva);
+    all_rvas.sort_unstable();
+    all_rvas.dedup();
+    for (id, function) in functions.iter_mut().enumerate() {
+        function.id = id as u32;
+        // The threads only estimated the end from the functions of their own module,
+        // so the next function in the whole list can start earlier.
+        if function.end_rva.is_none() {
+            let next_index = all_rvas.partition_point(|&rva| rva <= function.start_rva);
+            if let (Some(&next_rva), Some(estimated_end_rva)) =
+                (all_rvas.get(next_index), function.estimated_end_rva)
+            {
+                function.estimated_end_rva = Some(estimated_end_rva.min(next_rva));
+            }
+        }
+    }
+    Ok(functions)
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Format the names of all procedures on the threads of the rayon thread pool,
+    /// and store them in this context's caches, so that the lookups which follow,
+    /// e.g. a full dump with [`Context::functions`], don't format them again.
+    /// Formatting the signatures can take most of the time of a full dump of a PDB
+    /// with many templates.
+    ///
+    /// The type and id streams which the formatting reads can't be shared between
+    /// threads, so every thread calls `open` to get its own copy of the PDB's source,
+    /// which has to be the same PDB as the one of this context. The threads format
+    /// the names with this context's [`ContextFlags`] and [`TypeFormatterFlags`].
+    /// Names which this context has already formatted are kept.
+    ///
+    /// Returns [`Error::GuidMismatch`] if `open` returns a different PDB.
+    pub fn par_format_names<'t, S, F>(&self, open: F) -> Result<()>
+    where
+        S: Source<'t> + 't,
+        F: Fn() -> std::io::Result<S> + Sync,
+    {
+        let formatter_flags = self.type_formatter.flags();
+        let context_flags = self.flags;
+        let expected_identity = self.pdb_identity;
+        let next_module = AtomicUsize::new(0);
+        let results = rayon::broadcast(|_| -> Result<NamesWithLayouts> {
+            let source = open().map_err(|e| Error::PdbError(e.into()))?;
+            let data = ContextPdbData::open(source)?;
+            let context = data.make_context_with_flags(formatter_flags, context_flags)?;
+            if let (Some((expected, _)), Some((actual, _))) =
+                (expected_identity, context.pdb_identity)
+            {
+                if expected != actual {
+                    return Err(Error::GuidMismatch { expected, actual });
+                }
+            }
+            let modules = context.type_formatter.modules();
+            let mut names = Vec::new();
+            loop {
+                let module_index = next_module.fetch_add(1, Ordering::Relaxed);
+                if module_index >= modules.len() {
+                    break;
+                }
+                let mut cache = context.cache.borrow_mut();
+                if let Some(BasicModuleInfo {
+                    module_info,
+                    procedures,
+                    ..
+                }) = cache
+                    .module_cache
+                    .get_basic_module_info(modules, module_index)
+                {
+                    for proc in procedures {
+                        let name = ExtendedProcedureInfo::default()
+                            .get_name_with_layout(
+                                proc,
+                                &context.type_formatter,
+                                &context.global_functions,
+                                module_index,
+                                *module_info,
+                                context_flags,
+                            )
+                            .map(|(name, layout)| (String::from(name), layout.cloned()));
+                        names.push((proc.offset, name));
+                    }
+                }
+                drop(cache);
+                context.release_module(module_index);
+            }
+            Ok(names)
+        });
+
+        let mut cache = self.cache.borrow_mut();
+        for result in results {
+            for (offset, name) in result? {
+                let info = cache.procedure_cache.entry(offset).or_default();
+                info.name.get_or_insert(name);
+            }
+        }
+        Ok(())
+    }
+
+    /// The start addresses of the public functions, grouped by the module whose
+    /// section contribution contains them. The last entry has the ones which can't
+    /// be attributed to a module.
+    fn public_function_rvas_by_module(&self) -> Vec<Vec<u32>> {
+        let mut rvas_by_module = vec![Vec::new(); self.type_formatter.modules().len() + 1];
+        for func in &self.global_functions {
+            let offset = func.start_offset;
+            let rva = match offset.to_rva(self.address_map) {
+                Some(rva) => rva.0,
+                None => continue,
+            };
+            let module_index = self
+                .lookup_section_contribution(offset)
+                .map(|sc| sc.module_index)
+                .filter(|&module_index| module_index < rvas_by_module.len() - 1)
+                .unwrap_or(rvas_by_module.len() - 1);
+            rvas_by_module[module_index].push(rva);
+        }
+        rvas_by_module
+    }
+
+    /// The start addresses of the procedures in the module.
+    fn procedure_rvas(&self, module_index: usize) -> Vec<u32> {
+        let mut cache = self.cache.borrow_mut();
+        match cache
+            .module_cache
+            .get_basic_module_info(self.type_formatter.modules(), module_index)
+        {
+            Some(BasicModuleInfo { procedures, .. }) => procedures
+                .iter()
+                .filter_map(|proc| Some(proc.offset.to_rva(self.address_map)?.0))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Iterate over the functions at the sorted addresses in `rvas`.
+    fn functions_at_rvas(&self, rvas: Vec<u32>) -> FunctionIter<'_, 'a, 's> {
+        let end_index = rvas.len();
+        FunctionIter {
+            context: self,
+            full_rva_list: Rc::new(rvas),
+            cur_index: 0,
+            end_index,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::convert::TryFrom;
+
+use object::read::{Object, ObjectSection};
+use object::SectionKind;
+
+use crate::Error;
+
+/// The named entry points from the export table of a PE image (.exe or .dll).
+///
+/// This can be attached to a [`Context`](crate::Context) with
+/// [`Context::set_pe_exports`](crate::Context::set_pe_exports), so that addresses
+/// which can't be found in the PDB are still resolved to the nearest preceding
+/// export. This is useful for stripped PDBs and for PDBs which only partially match
+/// the image.
+#[derive(Debug, Clone, Default)]
+pub struct PeExports {
+    /// Sorted by rva, without duplicate rvas.
+    exports: Vec<PeExport>,
+    /// The rva ranges of the executable sections of the image, sorted.
+    code_ranges: Vec<(u32, u32)>,
+    /// The preferred load address of the image.
+    image_base: u64,
+}
+
+#[derive(Debug, Clone)]
+struct PeExport {
+    rva: u32,
+    name: String,
+}
+
+impl PeExports {
+    /// Parse the export table from the bytes of a PE image file.
+    ///
+    /// Exports which forward to other DLLs are skipped, because their code isn't in
+    /// this image.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let file = object::File::parse(data).map_err(Error::PeExports)?;
+        let image_base = file.relative_address_base();
+
+        let mut code_ranges: Vec<(u32, u32)> = file
+            .sections()
+            .filter(|section| section.kind() == SectionKind::Text)
+            .filter_map(|section| {
+                let start = u32::try_from(section.address().wrapping_sub(image_base)).ok()?;
+                let size = u32::try_from(section.size()).unwrap_or(u32::MAX);
+                Some((start, start.saturating_add(size)))
+            })
+            .collect();
+        code_ranges.sort_unstable();
+
+        let mut exports: Vec<PeExport> = file
+            .exports()
+            .map_err(Error::PeExports)?
+            .into_iter()
+            .filter_map(|export| {
+                Some(PeExport {
+                    rva: u32::try_from(export.address().wrapping_sub(image_base)).ok()?,
+                    name: String::from_utf8_lossy(export.name()).into_owned(),
+                })
+            })
+            .collect();
+        // If there are several names for the same address, keep the first one in
+        // the export table.
+        exports.sort_by_key(|export| export.rva);
+        exports.dedup_by_key(|export| export.rva);
+
+        Ok(Self {
+            exports,
+            code_ranges,
+            image_base,
+        })
+    }
+
+    /// The preferred load address of the image, from its optional header. This can
+    /// be used to convert virtual addresses to rvas, see
+    /// [`Va64::to_rva`](crate::Va64::to_rva).
+    pub fn image_base(&self) -> u64 {
+        self.image_base
+    }
+
+    /// The number of distinct export addresses.
+    pub fn len(&self) -> usize {
+        self.exports.len()
+    }
+
+    /// Whether there are no exports.
+    pub fn is_empty(&self) -> bool {
+        self.exports.is_empty()
+    }
+
+    /// Find the export which starts at or before `rva`, in the same executable section.
+    /// Returns the export's name and start rva, and the start rva of the next export
+    /// in the same section, or the section end.
+    pub(crate) fn lookup(&self, rva: u32) -> Option<(&str, u32, Option<u32>)> {
+        let code_range = self
+            .code_ranges
+            .iter()
+            .find(|(start, end)| *start <= rva && rva < *end)?;
+        let index = match self.exports.binary_search_by_key(&rva, |export| export.rva) {
+            Err(0) => return None,
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let export = &self.exports[index];
+        if export.rva < code_range.0 {
+            return None;
+        }
+        let end_rva = match self.exports.get(index + 1) {
+            Some(next) if next.rva < code_range.1 => next.rva,
+            _ => code_range.1,
+        };
+        Some((&export.name, export.rva, Some(end_rva)))
+    }
+}
@@ -0,0 +1,162 @@
+use std::io::{self, Write};
+
+use crate::{Context, Frame, FunctionFrames, Rva32};
+
+/// The symbol table of a library in the compact format of the Firefox Profiler, as
+/// returned by [`Context::compact_symbol_table`].
+///
+/// The name of the symbol at `addr[i]` is `buffer[index[i]..index[i + 1]]`, so
+/// `index` has one more element than `addr`. A symbol extends up to the address of
+/// the next symbol.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactSymbolTable {
+    /// The start rvas of the symbols, in ascending order.
+    pub addr: Vec<u32>,
+    /// The offsets of the symbol names in `buffer`.
+    pub index: Vec<u32>,
+    /// The UTF-8 symbol names, without separators.
+    pub buffer: Vec<u8>,
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Create the symbol table of this PDB in the compact format which the Firefox
+    /// Profiler and samply use for native symbols. Every function is listed once,
+    /// with the name returned by [`Context::functions`].
+    pub fn compact_symbol_table(&self) -> CompactSymbolTable {
+        let mut table = CompactSymbolTable::default();
+        for function in self.functions() {
+            if table.addr.last() == Some(&function.start_rva) {
+                continue;
+            }
+            table.addr.push(function.start_rva);
+            table.index.push(table.buffer.len() as u32);
+            if let Some(name) = &function.name {
+                table.buffer.extend_from_slice(name.as_bytes());
+            }
+        }
+        table.index.push(table.buffer.len() as u32);
+        table
+    }
+
+    /// The debug ID of this PDB in the form which symbol servers and the Firefox
+    /// Profiler use: the GUID as uppercase hex digits without dashes, followed by the
+    /// age in hex. `None` if the PDB's identity is unknown.
+    pub fn debug_id(&self) -> Option<String> {
+        self.pdb_identity
+            .map(|(guid, age)| format!("{:X}{:X}", guid.simple(), age))
+    }
+
+    /// Look up `addresses` and write the result as JSON in the format of the
+    /// Firefox Profiler's symbolication API (version 5), with a single stack of
+    /// frames in `module_name`. Resolved frames contain the function with its
+    /// offset and size, the file and line, and the inlined functions at the address.
+    /// Addresses which can't be resolved, or whose lookup fails, only contain the
+    /// module offset.
+    pub fn write_symbolication_json<W: Write>(
+        &self,
+        module_name: &str,
+        addresses: &[Rva32],
+        mut writer: W,
+    ) -> io::Result<()> {
+        let module = json_string(module_name);
+        write!(writer, r#"{{"results":[{{"stacks":[["#)?;
+        for (frame_index, &Rva32(address)) in addresses.iter().enumerate() {
+            if frame_index != 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                r#"{{"frame":{},"module_offset":"{:#x}","module":{}"#,
+                frame_index, address, module
+            )?;
+            if let Ok(Some(function_frames)) = self.find_frames_at(Rva32(address)) {
+                write_function_frames(&mut writer, address, &function_frames)?;
+            }
+            write!(writer, "}}")?;
+        }
+        let found_module = match self.debug_id() {
+            Some(debug_id) => format!("{}/{}", module_name, debug_id),
+            None => module_name.to_string(),
+        };
+        writeln!(
+            writer,
+            r#"]],"found_modules":{{{}:true}}}}]}}"#,
+            json_string(&found_module)
+        )
+    }
+}
+
+/// Write the members of a resolved frame in the symbolication API format. The
+/// outermost frame is the function; the others are listed in `inlines`, from inside
+/// to outside.
+fn write_function_frames<W: Write>(
+    writer: &mut W,
+    address: u32,
+    function_frames: &FunctionFrames,
+) -> io::Result<()> {
+    let (outer, inlines) = match function_frames.frames.split_last() {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+    if let Some(function) = &outer.function {
+        write!(writer, r#","function":{}"#, json_string(function))?;
+    }
+    // With resolved incremental linking thunks, the function can start after the
+    // address.
+    if let Some(function_offset) = address.checked_sub(function_frames.start_rva) {
+        write!(writer, r#","function_offset":"{:#x}""#, function_offset)?;
+    }
+    if let Some(end_rva) = function_frames.end_rva {
+        write!(
+            writer,
+            r#","function_size":"{:#x}""#,
+            end_rva.saturating_sub(function_frames.start_rva)
+        )?;
+    }
+    write_location(writer, outer)?;
+    if !inlines.is_empty() {
+        write!(writer, r#","inlines":["#)?;
+        for (inline_index, frame) in inlines.iter().enumerate() {
+            if inline_index != 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "{{")?;
+            let function = frame.function.as_deref().unwrap_or("");
+            write!(writer, r#""function":{}"#, json_string(function))?;
+            write_location(writer, frame)?;
+            write!(writer, "}}")?;
+        }
+        write!(writer, "]")?;
+    }
+    Ok(())
+}
+
+/// Write the `file` and `line` members of a frame, if they are known.
+fn write_location<W: Write>(writer: &mut W, frame: &Frame) -> io::Result<()> {
+    if let Some(file) = &frame.file {
+        write!(writer, r#","file":{}"#, json_string(file))?;
+    }
+    if let Some(line) = frame.line {
+        write!(writer, r#","line":{}"#, line)?;
+    }
+    Ok(())
+}
+
+/// Quote and escape `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,113 @@
+use pdb::Rva;
+
+use crate::{Context, ContextCache, ExtendedModuleInfo, PublicOrProcedureSymbol, Result, Rva32};
+
+/// The position of an address within the procedure which contains it, see
+/// [`Context::procedure_body`].
+struct ProcedureBody {
+    /// The offset of the address from the start of the procedure.
+    offset: u32,
+    /// The offset of the first instruction after the prologue.
+    body_start: u32,
+    /// The offset of the first instruction of the epilogue.
+    body_end: u32,
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Whether the provided address is in the prologue of the procedure which
+    /// contains it, i.e. before the stack frame is set up. Debuggers use this to
+    /// decide whether the locals of the procedure have valid values yet at a stop
+    /// location.
+    ///
+    /// The end of the prologue is taken from the procedure symbol. If the symbol
+    /// doesn't have it, as in PDBs from clang, the prologue is assumed to be the
+    /// first line record of the procedure, which compilers attribute to the line of
+    /// the function's opening brace.
+    ///
+    /// Returns `None` if the address isn't in a procedure, e.g. if it is only covered
+    /// by a public symbol, or if neither source has the information.
+    pub fn is_in_prologue(&self, probe: Rva32) -> Result<Option<bool>> {
+        let probe = probe.0;
+        Ok(self
+            .procedure_body(probe)?
+            .map(|body| body.offset < body.body_start))
+    }
+
+    /// Whether the provided address is in the epilogue of the procedure which
+    /// contains it, i.e. after the stack frame has started to be torn down, where
+    /// the locals may no longer be valid.
+    ///
+    /// The start of the epilogue is taken from the procedure symbol, or if the
+    /// symbol doesn't have it, the epilogue is assumed to be the last line record of
+    /// the procedure, for the function's closing brace. Only the final epilogue is
+    /// known; the early returns of a procedure with several epilogues are not
+    /// detected.
+    ///
+    /// Returns `None` if the address isn't in a procedure, e.g. if it is only covered
+    /// by a public symbol, or if neither source has the information.
+    pub fn is_in_epilogue(&self, probe: Rva32) -> Result<Option<bool>> {
+        let probe = probe.0;
+        Ok(self
+            .procedure_body(probe)?
+            .map(|body| body.offset >= body.body_end))
+    }
+
+    /// Find the procedure which contains `probe`, and the part of it between the
+    /// prologue and the epilogue.
+    fn procedure_body(&self, probe: u32) -> Result<Option<ProcedureBody>> {
+        let offset = match Rva(probe).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            procedure_cache,
+            function_line_cache,
+            extended_module_cache,
+            ..
+        } = &mut *cache;
+        let (module_index, module_info, proc, end_offset) =
+            match self.lookup_function(offset, module_cache) {
+                Some(PublicOrProcedureSymbol::Procedure(
+                    module_index,
+                    module_info,
+                    _,
+                    proc,
+                    end_offset,
+                )) => (module_index, module_info, proc, end_offset),
+                _ => return Ok(None),
+            };
+        let probe_offset = offset.offset - proc.offset.offset;
+        let debug_range = procedure_cache
+            .entry(proc.offset)
+            .or_default()
+            .get_frame_info(module_info, proc)
+            .and_then(|info| info.debug_range);
+        if let Some((body_start, body_end)) = debug_range {
+            return Ok(Some(ProcedureBody {
+                offset: probe_offset,
+                body_start,
+                body_end,
+            }));
+        }
+
+        let ExtendedModuleInfo { lines, .. } =
+            self.get_extended_module_info(extended_module_cache, module_index, module_info)?;
+        let function_lines = function_line_cache
+            .entry(proc.offset)
+            .or_default()
+            .get_lines(proc.offset, Some(end_offset - proc.offset.offset), lines)
+            .map_err(|e| self.module_error(module_index, "reading the procedure's lines", e))?;
+        // Without a line in between, the lines can't be told apart from the body.
+        let (second, last) = match function_lines {
+            [_, second, .., last] => (second, last),
+            _ => return Ok(None),
+        };
+        Ok(Some(ProcedureBody {
+            offset: probe_offset,
+            body_start: second.start_offset.saturating_sub(proc.offset.offset),
+            body_end: last.start_offset.saturating_sub(proc.offset.offset),
+        }))
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use pdb::{Source, SourceSlice, SourceView};
+
+/// Reads byte ranges of a file which isn't available locally, for [`RangeSource`].
+///
+/// For a PDB on a symbol server, an implementation sends an HTTP `GET` request with
+/// a `Range: bytes=<offset>-<offset + len - 1>` header. This crate doesn't depend on
+/// an HTTP client, so the implementation is up to the caller.
+pub trait RangeReader: fmt::Debug {
+    /// Read the bytes at `offset` into `buf`, and return the number of bytes that
+    /// were read. This can be less than `buf.len()` only at the end of the file.
+    fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+/// A [`Source`] which reads the pages of a PDB on demand with a [`RangeReader`],
+/// and keeps the most recently used blocks in a cache.
+///
+/// Symbolicating a few addresses only touches a small part of a PDB: the stream
+/// directory, the DBI stream, the public symbols and the streams of the modules
+/// which contain the addresses. With this source, only those parts are downloaded.
+///
+/// The file is read in blocks of a fixed size, which should be a multiple of the
+/// MSF page size (usually 4096 bytes). Adjacent missing blocks are read with a
+/// single request.
+pub struct RangeSource<R: RangeReader> {
+    reader: R,
+    block_size: usize,
+    capacity: usize,
+    blocks: HashMap<u64, CachedBlock>,
+    clock: u64,
+    fetched_bytes: u64,
+    request_count: u64,
+}
+
+struct CachedBlock {
+    data: Vec<u8>,
+    last_used: u64,
+}
+
+impl<R: RangeReader> RangeSource<R> {
+    /// Create a source with blocks of 64 KiB and a cache of 16 MiB.
+    pub fn new(reader: R) -> Self {
+        Self::with_cache_size(reader, 64 * 1024, 256)
+    }
+
+    /// Create a source which reads blocks of `block_size` bytes and caches up to
+    /// `capacity` blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` or `capacity` is 0.
+    pub fn with_cache_size(reader: R, block_size: usize, capacity: usize) -> Self {
+        assert!(block_size != 0, "block_size must not be 0");
+        assert!(capacity != 0, "capacity must not be 0");
+        RangeSource {
+            reader,
+            block_size,
+            capacity,
+            blocks: HashMap::new(),
+            clock: 0,
+            fetched_bytes: 0,
+            request_count: 0,
+        }
+    }
+
+    /// The number of bytes which were read from the reader so far.
+    pub fn fetched_bytes(&self) -> u64 {
+        self.fetched_bytes
+    }
+
+    /// The number of calls to [`RangeReader::read_range`] so far.
+    pub fn request_count(&self) -> u64 {
+        self.request_count
+    }
+
+    /// Return the reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Make sure that the blocks `first..=last` are cached, reading runs of missing
+    /// blocks with one request each.
+    fn fetch_blocks(&mut self, first: u64, last: u64) -> io::Result<()> {
+        let mut block = first;
+        while block <= last {
+            if self.blocks.contains_key(&block) {
+                block += 1;
+                continue;
+            }
+            let mut run_end = block;
+            while run_end < last && !self.blocks.contains_key(&(run_end + 1)) {
+                run_end += 1;
+            }
+            let block_count = (run_end - block + 1) as usize;
+            let mut data = vec![0; block_count * self.block_size];
+            let offset = block * self.block_size as u64;
+            let len = self.reader.read_range(offset, &mut data)?;
+            self.request_count += 1;
+            self.fetched_bytes += len as u64;
+            data.truncate(len);
+            for (i, chunk) in data.chunks(self.block_size).enumerate() {
+                self.insert_block(block + i as u64, chunk.to_vec());
+            }
+            block = run_end + 1;
+        }
+        Ok(())
+    }
+
+    fn insert_block(&mut self, block: u64, data: Vec<u8>) {
+        if self.blocks.len() >= self.capacity {
+            let least_recently_used = self
+                .blocks
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(block, _)| *block);
+            if let Some(least_recently_used) = least_recently_used {
+                self.blocks.remove(&least_recently_used);
+            }
+        }
+        self.clock += 1;
+        self.blocks.insert(
+            block,
+            CachedBlock {
+                data,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Append the bytes of `slice` to `out`.
+    fn read_slice(&mut self, slice: &SourceSlice, out: &mut Vec<u8>) -> io::Result<()> {
+        if slice.size == 0 {
+            return Ok(());
+        }
+        let block_size = self.block_size as u64;
+        let end = slice.offset + slice.size as u64;
+        let first = slice.offset / block_size;
+        let last = (end - 1) / block_size;
+        let mut offset = slice.offset;
+        let mut block = first;
+        while offset < end {
+            // Slices can be larger than the cache, so fetch at most `capacity` blocks
+            // at once, and the rest once they are needed.
+            if !self.blocks.contains_key(&block) {
+                self.fetch_blocks(block, last.min(block + self.capacity as u64 - 1))?;
+            }
+            self.clock += 1;
+            let cached = self.blocks.get_mut(&block).unwrap();
+            cached.last_used = self.clock;
+            let start_in_block = (offset - block * block_size) as usize;
+            let end_in_block = ((end - block * block_size) as usize).min(self.block_size);
+            if cached.data.len() < end_in_block {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the requested range is beyond the end of the file",
+                ));
+            }
+            out.extend_from_slice(&cached.data[start_in_block..end_in_block]);
+            offset = block * block_size + end_in_block as u64;
+            block += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<R: RangeReader> fmt::Debug for RangeSource<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeSource")
+            .field("reader", &self.reader)
+            .field("block_size", &self.block_size)
+            .field("cached_blocks", &self.blocks.len())
+            .finish()
+    }
+}
+
+impl<'s, R: RangeReader + 's> Source<'s> for RangeSource<R> {
+    fn view(&mut self, slices: &[SourceSlice]) -> io::Result<Box<dyn SourceView<'s>>> {
+        let len = slices.iter().map(|slice| slice.size).sum();
+        let mut bytes = Vec::with_capacity(len);
+        for slice in slices {
+            self.read_slice(slice, &mut bytes)?;
+        }
+        Ok(Box::new(RangeView { bytes }))
+    }
+}
+
+struct RangeView {
+    bytes: Vec<u8>,
+}
+
+impl fmt::Debug for RangeView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RangeView({} bytes)", self.bytes.len())
+    }
+}
+
+impl SourceView<'_> for RangeView {
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes
+    }
+}
@@ -0,0 +1,101 @@
+/// The fixed stream index of the PDB information stream.
+pub const PDB_INFO_STREAM_INDEX: u16 = 1;
+/// The fixed stream index of the DBI stream.
+pub const DBI_STREAM_INDEX: u16 = 3;
+
+/// The stream index which stands for "no stream".
+const NO_STREAM: u16 = 0xffff;
+
+/// The size of the DBI stream header, which is followed by the module list.
+const DBI_HEADER_SIZE: usize = 64;
+
+/// The feature code in the PDB information stream of PDBs which were linked with
+/// /DEBUG:FASTLINK ("MINI").
+pub const FEATURE_MINIMAL_DEBUG_INFO: u32 = 0x494e_494d;
+
+/// The parts of a module's entry in the DBI module list that describe its stream.
+pub struct DbiModuleEntry {
+    stream: u16,
+    pub symbols_size: usize,
+    /// The size of the legacy C11 line information.
+    pub lines_size: usize,
+    pub c13_lines_size: usize,
+}
+
+impl DbiModuleEntry {
+    /// The index of the module's stream, if it has one.
+    pub fn stream(&self) -> Option<u16> {
+        match self.stream {
+            NO_STREAM => None,
+            stream => Some(stream),
+        }
+    }
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The version from the header of the PDB information stream `info`.
+pub fn pdb_info_version(info: &[u8]) -> Option<u32> {
+    u32_at(info, 0)
+}
+
+/// The version from the header of the DBI stream `dbi`. The first field of the
+/// header is a signature.
+pub fn dbi_version(dbi: &[u8]) -> Option<u32> {
+    u32_at(dbi, 4)
+}
+
+/// Returns the entries of the module list of the DBI stream `dbi`, in module index
+/// order.
+pub fn dbi_module_entries(dbi: &[u8]) -> Option<Vec<DbiModuleEntry>> {
+    // The module list follows the DBI header. Each entry is:
+    // opened: u32, section contribution: [u8; 28], flags: u16, stream: u16,
+    // symbols size: u32, C11 lines size: u32, C13 lines size: u32, file count: u16,
+    // padding: u16, file names offset: u32, source file: u32, pdb file: u32,
+    // module name: null-terminated string, object name: null-terminated string,
+    // padding to a multiple of 4 bytes
+    let module_list_end = DBI_HEADER_SIZE + u32_at(dbi, 24)? as usize;
+    let module_list = dbi.get(..module_list_end)?;
+    let mut entries = Vec::new();
+    let mut entry = DBI_HEADER_SIZE;
+    while entry + 64 <= module_list_end {
+        let stream = module_list.get(entry + 34..entry + 36)?;
+        entries.push(DbiModuleEntry {
+            stream: u16::from_le_bytes([stream[0], stream[1]]),
+            symbols_size: u32_at(module_list, entry + 36)? as usize,
+            lines_size: u32_at(module_list, entry + 40)? as usize,
+            c13_lines_size: u32_at(module_list, entry + 44)? as usize,
+        });
+        let mut pos = entry + 64;
+        for _ in 0..2 {
+            let name_len = module_list.get(pos..)?.iter().position(|b| *b == 0)?;
+            pos += name_len + 1;
+        }
+        entry = (pos + 3) & !3;
+    }
+    Some(entries)
+}
+
+/// Returns the feature codes at the end of the PDB information stream `info`, whose
+/// stream name data ends at `names_end`.
+pub fn pdb_info_features(info: &[u8], names_end: usize) -> Option<Vec<u32>> {
+    // The stream names are followed by a hash table from name offsets to stream
+    // indexes: size: u32, capacity: u32, present bits: (word count: u32, words),
+    // deleted bits: (word count: u32, words), entries: [(u32, u32); size]
+    let size = u32_at(info, names_end)? as usize;
+    let present_words = u32_at(info, names_end + 8)? as usize;
+    let deleted_words_pos = names_end + 12 + present_words * 4;
+    let deleted_words = u32_at(info, deleted_words_pos)? as usize;
+    let entries_end = deleted_words_pos + 4 + deleted_words * 4 + size * 8;
+    // The table is followed by one u32 which is always 0, and then the features.
+    let features = info.get(entries_end + 4..)?;
+    Some(
+        features
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect(),
+    )
+}
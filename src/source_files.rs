@@ -0,0 +1,114 @@
+use pdb::{FileIndex, Rva};
+
+use crate::{Context, ContextCache, ExtendedModuleInfo, PublicOrProcedureSymbol, Result, Rva32};
+
+/// A source file which is referenced by the line records of a function, as returned
+/// by [`Context::function_source_files`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionSourceFile {
+    /// The file name. `None` if the name couldn't be read, e.g. because the PDB has
+    /// no string table.
+    pub file: Option<String>,
+    /// The number of line records of the function in this file.
+    pub line_records: usize,
+    /// The number of distinct line numbers of these records.
+    pub lines: usize,
+    /// The number of bytes of code which the records cover.
+    pub bytes: u32,
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// List the source files which are referenced by the line records of the
+    /// function which contains the provided address, in the order in which they
+    /// first appear in the function's code, with the number of records, lines and
+    /// bytes in each file.
+    ///
+    /// A function's line records aren't always in the file of its definition:
+    /// functions from headers which were inlined without inline site records, and
+    /// code from macros, are attributed to the header. Ownership attribution tools
+    /// can use this to split a function's code between files. The files of inline
+    /// sites are not included, see [`Context::inline_ranges_for_function`].
+    ///
+    /// Returns an empty list if no function contains the address, or if the function
+    /// has no line records.
+    pub fn function_source_files(&self, probe: Rva32) -> Result<Vec<FunctionSourceFile>> {
+        let probe = probe.0;
+        let function = match self.find_function_at_rva(probe, false)? {
+            Some(function) => function,
+            None => return Ok(Vec::new()),
+        };
+        let offset = match Rva(function.start_rva).to_internal_offset(self.address_map) {
+            Some(offset) => offset,
+            None => return Ok(Vec::new()),
+        };
+        let size = function
+            .end_rva
+            .map(|end_rva| end_rva.saturating_sub(function.start_rva));
+
+        let mut cache = self.cache.borrow_mut();
+        let ContextCache {
+            module_cache,
+            function_line_cache,
+            extended_module_cache,
+            ..
+        } = &mut *cache;
+        let (module_index, module_info) = match self.lookup_function(offset, module_cache) {
+            Some(PublicOrProcedureSymbol::Procedure(module_index, module_info, ..)) => {
+                (module_index, module_info)
+            }
+            Some(PublicOrProcedureSymbol::Public(module_index, Some(module_info), _)) => {
+                (module_index, module_info)
+            }
+            _ => return Ok(Vec::new()),
+        };
+        let ExtendedModuleInfo { lines, .. } =
+            self.get_extended_module_info(extended_module_cache, module_index, module_info)?;
+        let function_lines = function_line_cache
+            .entry(offset)
+            .or_default()
+            .get_lines(offset, size, lines)
+            .map_err(|e| self.module_error(module_index, "reading the procedure's lines", e))?;
+
+        let end_offset = size.map(|size| offset.offset.saturating_add(size));
+        let mut files: Vec<(FileIndex, FunctionSourceFile, Vec<u32>)> = Vec::new();
+        for (i, line) in function_lines.iter().enumerate() {
+            let line_end_offset = function_lines
+                .get(i + 1)
+                .map(|next| next.start_offset)
+                .or(end_offset)
+                .unwrap_or(line.start_offset);
+            let index = match files
+                .iter()
+                .position(|(index, ..)| *index == line.file_index)
+            {
+                Some(index) => index,
+                None => {
+                    let file = self
+                        .resolve_raw_filename(module_index, lines, line.file_index)
+                        .map(|name| name.to_string().into_owned());
+                    let source_file = FunctionSourceFile {
+                        file,
+                        line_records: 0,
+                        lines: 0,
+                        bytes: 0,
+                    };
+                    files.push((line.file_index, source_file, Vec::new()));
+                    files.len() - 1
+                }
+            };
+            let (_, source_file, line_numbers) = &mut files[index];
+            source_file.line_records += 1;
+            source_file.bytes += line_end_offset.saturating_sub(line.start_offset);
+            if !line_numbers.contains(&line.line_start) {
+                line_numbers.push(line.line_start);
+            }
+        }
+        Ok(files
+            .into_iter()
+            .map(|(_, source_file, line_numbers)| FunctionSourceFile {
+                lines: line_numbers.len(),
+                ..source_file
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{Context, Frame, LookupOptions, Provenance, Rva32};
+
+/// One frame of a symbolized call stack, as returned by [`Context::symbolize_stack`].
+#[derive(Clone)]
+pub struct StackFrame<'a> {
+    /// The index of the address in the stack which this frame belongs to.
+    pub stack_index: usize,
+    /// The address from the stack, as passed in.
+    pub address: u32,
+    /// The address which was looked up: `address` for the first entry, and
+    /// `address - 1` for the return addresses.
+    pub lookup_address: u32,
+    /// Whether this is an inlined frame. The frames of each address are ordered from
+    /// the innermost inlined function to the outer function, and only the last one
+    /// isn't inlined.
+    pub inlined: bool,
+    /// What kind of symbol the outer function was found in, see
+    /// [`FunctionFrames::provenance`](crate::FunctionFrames::provenance). `None` if the
+    /// address couldn't be symbolized.
+    pub provenance: Option<Provenance>,
+    /// The frame, or `None` if the address couldn't be symbolized, or if its lookup
+    /// failed. Such addresses still get an entry, so that the stack keeps its shape.
+    pub frame: Option<Frame<'a>>,
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Symbolize a call stack from a stack walk. The first address is the
+    /// instruction pointer of the innermost frame and is looked up as it is; the
+    /// others are return addresses, so the byte before them is looked up, see
+    /// [`LookupOptions::return_address`].
+    ///
+    /// The result is a flat list with the frames of all addresses in stack order,
+    /// including the inlined frames, with the index of the address that each frame
+    /// belongs to. Addresses which can't be symbolized get one entry without a frame,
+    /// and so do the ones whose lookup fails, e.g. because a module can't be read, so
+    /// that one bad address doesn't lose the rest of the stack.
+    pub fn symbolize_stack(&self, addresses: &[Rva32]) -> Vec<StackFrame<'_>> {
+        let mut result = Vec::with_capacity(addresses.len());
+        for (stack_index, &Rva32(address)) in addresses.iter().enumerate() {
+            let is_return_address = stack_index != 0;
+            let options = LookupOptions {
+                return_address: is_return_address,
+                ..Default::default()
+            };
+            let lookup_address = match is_return_address {
+                true => address.saturating_sub(1),
+                false => address,
+            };
+            let function_frames = match self.find_frames_with_options(Rva32(address), &options) {
+                Ok(Some(function_frames)) => function_frames,
+                Ok(None) | Err(_) => {
+                    result.push(StackFrame {
+                        stack_index,
+                        address,
+                        lookup_address,
+                        inlined: false,
+                        provenance: None,
+                        frame: None,
+                    });
+                    continue;
+                }
+            };
+            let frame_count = function_frames.frames.len();
+            for (index, frame) in function_frames.frames.into_iter().enumerate() {
+                result.push(StackFrame {
+                    stack_index,
+                    address,
+                    lookup_address,
+                    inlined: index + 1 != frame_count,
+                    provenance: Some(function_frames.provenance),
+                    frame: Some(frame),
+                });
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+
+/// A parsed symbol path, in the syntax of the `_NT_SYMBOL_PATH` environment variable
+/// which is used by the native Windows debugging tools, for example
+/// `cache*C:\Symbols;srv*C:\Symbols*https://msdl.microsoft.com/download/symbols;D:\MyBuild`.
+///
+/// The path consists of `;`-separated entries. Each entry is one of:
+///
+///  - `srv*[store*...]url` or `symsrv*dll*[store*...]url`: A symbol server. The
+///    downstream stores are local directories which are searched first, and which
+///    receive the files that are downloaded from the server. An empty store, as in
+///    `srv**url`, stands for the default downstream store.
+///  - `cache*[dir]`: Files which are found in any of the following entries are copied
+///    to `dir`, which is also searched before those entries. An empty `dir` stands for
+///    the default downstream store.
+///  - Anything else is a local directory.
+///
+/// Use [`SymbolPath::locations`] to get the ordered list of places to look in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolPath {
+    entries: Vec<SymbolPathEntry>,
+}
+
+/// One `;`-separated entry of a [`SymbolPath`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolPathEntry {
+    /// A local directory.
+    Directory(PathBuf),
+    /// A symbol server, from a `srv*` or `symsrv*` entry.
+    Server {
+        /// The downstream stores, in search order. `None` stands for the default
+        /// downstream store.
+        downstream_stores: Vec<Option<PathBuf>>,
+        /// The URL of the server, or the path of a symbol store on a network share.
+        url: String,
+    },
+    /// A `cache*` entry. `None` stands for the default downstream store.
+    Cache(Option<PathBuf>),
+}
+
+/// A place to look for symbol files, from [`SymbolPath::locations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolLocation {
+    /// A local directory.
+    Local {
+        /// The directory to look in.
+        path: PathBuf,
+        /// The directories into which files found here should be copied.
+        cache_dirs: Vec<PathBuf>,
+    },
+    /// A symbol server.
+    Remote {
+        /// The URL of the server, or the path of a symbol store on a network share.
+        url: String,
+        /// The directories into which downloaded files should be stored, with the
+        /// symbol store layout.
+        cache_dirs: Vec<PathBuf>,
+    },
+}
+
+impl SymbolPath {
+    /// Parse a symbol path. Empty entries are ignored. This never fails; anything that
+    /// isn't recognized as a server or cache entry is treated as a directory.
+    pub fn parse(symbol_path: &str) -> Self {
+        let entries = symbol_path
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(parse_entry)
+            .collect();
+        Self { entries }
+    }
+
+    /// Read the symbol path from the `_NT_ALT_SYMBOL_PATH` and `_NT_SYMBOL_PATH`
+    /// environment variables, in that order, like the Windows debugging tools do.
+    /// Returns `None` if neither variable is set.
+    pub fn from_env() -> Option<Self> {
+        let parts: Vec<String> = ["_NT_ALT_SYMBOL_PATH", "_NT_SYMBOL_PATH"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .collect();
+        if parts.is_empty() {
+            return None;
+        }
+        Some(Self::parse(&parts.join(";")))
+    }
+
+    /// The entries of this symbol path, in order.
+    pub fn entries(&self) -> &[SymbolPathEntry] {
+        &self.entries
+    }
+
+    /// The places to look for symbol files, in the order in which they should be
+    /// tried. Each downstream store of a server and each `cache*` directory becomes
+    /// its own local location, in front of the locations it caches.
+    ///
+    /// `default_store` is used for the entries which refer to the default downstream
+    /// store.
+    pub fn locations(&self, default_store: &Path) -> Vec<SymbolLocation> {
+        let resolve =
+            |dir: &Option<PathBuf>| dir.clone().unwrap_or_else(|| default_store.to_path_buf());
+        let mut locations = Vec::new();
+        // The directories of the cache* entries seen so far. They apply to all
+        // entries to their right.
+        let mut cache_dirs: Vec<PathBuf> = Vec::new();
+        for entry in &self.entries {
+            match entry {
+                SymbolPathEntry::Directory(path) => locations.push(SymbolLocation::Local {
+                    path: path.clone(),
+                    cache_dirs: cache_dirs.clone(),
+                }),
+                SymbolPathEntry::Cache(dir) => {
+                    let dir = resolve(dir);
+                    locations.push(SymbolLocation::Local {
+                        path: dir.clone(),
+                        cache_dirs: cache_dirs.clone(),
+                    });
+                    cache_dirs.push(dir);
+                }
+                SymbolPathEntry::Server {
+                    downstream_stores,
+                    url,
+                } => {
+                    let mut server_cache_dirs = cache_dirs.clone();
+                    for store in downstream_stores {
+                        let store = resolve(store);
+                        locations.push(SymbolLocation::Local {
+                            path: store.clone(),
+                            cache_dirs: server_cache_dirs.clone(),
+                        });
+                        server_cache_dirs.push(store);
+                    }
+                    locations.push(SymbolLocation::Remote {
+                        url: url.clone(),
+                        cache_dirs: server_cache_dirs,
+                    });
+                }
+            }
+        }
+        locations
+    }
+}
+
+fn parse_entry(entry: &str) -> SymbolPathEntry {
+    let mut parts = entry.split('*');
+    let keyword = parts.next().unwrap_or("");
+    if keyword.eq_ignore_ascii_case("cache") {
+        return SymbolPathEntry::Cache(optional_path(parts.next().unwrap_or("")));
+    }
+    let mut parts: Vec<&str> = if keyword.eq_ignore_ascii_case("srv") {
+        parts.collect()
+    } else if keyword.eq_ignore_ascii_case("symsrv") {
+        // The next part is the name of the symbol server DLL, usually symsrv.dll.
+        parts.skip(1).collect()
+    } else {
+        return SymbolPathEntry::Directory(PathBuf::from(entry));
+    };
+    let url = parts.pop().unwrap_or("").to_string();
+    // "srv*url" uses no downstream store; "srv**url" uses the default one.
+    let downstream_stores = parts.into_iter().map(optional_path).collect();
+    SymbolPathEntry::Server {
+        downstream_stores,
+        url,
+    }
+}
+
+fn optional_path(path: &str) -> Option<PathBuf> {
+    match path.trim() {
+        "" => None,
+        path => Some(PathBuf::from(path)),
+    }
+}
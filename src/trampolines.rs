@@ -0,0 +1,182 @@
+use pdb::{PdbInternalSectionOffset, Rva, TrampolineSymbol, TrampolineType};
+
+use crate::{Context, Rva32};
+
+/// The kind of a trampoline, see [`Trampoline`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TrampolineKind {
+    /// A jump thunk in the incremental linking table (ILT), which jumps to the
+    /// current location of an incrementally linked function.
+    IncrementalLinking,
+    /// A branch island, which extends the range of a branch instruction whose
+    /// target is too far away, e.g. on arm64.
+    BranchIsland,
+    /// A trampoline of a kind which this crate doesn't know about.
+    Unknown,
+}
+
+/// A trampoline which the linker inserted, from an `S_TRAMPOLINE` record of the
+/// `* Linker *` module. Returned by [`Context::trampoline_at`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Trampoline {
+    /// The kind of trampoline.
+    pub kind: TrampolineKind,
+    /// The start address of the trampoline, as a relative address (rva).
+    pub start_rva: u32,
+    /// The end address of the trampoline, exclusive.
+    pub end_rva: u32,
+    /// The address that the trampoline jumps to. `None` if the target can't be
+    /// translated to an rva.
+    pub target_rva: Option<u32>,
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Find the trampoline which contains the provided address.
+    ///
+    /// Lookups of addresses inside trampolines return a function and frames with the
+    /// provenance [`Provenance::Trampoline`](crate::Provenance::Trampoline), instead
+    /// of the public symbol which covers them. This method gives the trampoline's
+    /// target, which can be looked up to find the function that the code is actually
+    /// calling.
+    ///
+    /// The trampolines are read from the linker's module on first use.
+    pub fn trampoline_at(&self, probe: Rva32) -> Option<Trampoline> {
+        let offset = Rva(probe.0).to_internal_offset(self.address_map)?;
+        let trampoline = self.lookup_trampoline(offset)?;
+        let start_rva = trampoline.start_offset.to_rva(self.address_map)?.0;
+        Some(Trampoline {
+            kind: trampoline.kind,
+            start_rva,
+            end_rva: start_rva + u32::from(trampoline.size),
+            target_rva: trampoline.target.to_rva(self.address_map).map(|rva| rva.0),
+        })
+    }
+}
+
+/// A trampoline record from the linker's module, e.g. a jump thunk in the
+/// incremental linking table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TrampolineRecord {
+    pub(crate) kind: TrampolineKind,
+    pub(crate) start_offset: PdbInternalSectionOffset,
+    pub(crate) size: u16,
+    pub(crate) target: PdbInternalSectionOffset,
+}
+
+/// Convert the `S_TRAMPOLINE` records of the linker's module into records sorted by
+/// address, for [`find_trampoline`]. Empty trampolines are left out.
+pub(crate) fn trampoline_records(
+    symbols: impl IntoIterator<Item = TrampolineSymbol>,
+) -> Vec<TrampolineRecord> {
+    let mut trampolines: Vec<_> = symbols
+        .into_iter()
+        .filter(|trampoline| trampoline.size != 0)
+        .map(|trampoline| TrampolineRecord {
+            kind: match trampoline.tramp_type {
+                TrampolineType::Incremental => TrampolineKind::IncrementalLinking,
+                TrampolineType::BranchIsland => TrampolineKind::BranchIsland,
+                _ => TrampolineKind::Unknown,
+            },
+            start_offset: trampoline.thunk,
+            size: trampoline.size,
+            target: trampoline.target,
+        })
+        .collect();
+    trampolines.sort_unstable_by_key(|t| (t.start_offset.section, t.start_offset.offset));
+    trampolines
+}
+
+/// Find the trampoline which contains `offset` in the sorted `trampolines`.
+pub(crate) fn find_trampoline(
+    trampolines: &[TrampolineRecord],
+    offset: PdbInternalSectionOffset,
+) -> Option<TrampolineRecord> {
+    let index = match trampolines.binary_search_by_key(&(offset.section, offset.offset), |t| {
+        (t.start_offset.section, t.start_offset.offset)
+    }) {
+        Err(0) => return None,
+        Ok(i) => i,
+        Err(i) => i - 1,
+    };
+    let trampoline = trampolines[index];
+    if trampoline.start_offset.section != offset.section
+        || offset.offset - trampoline.start_offset.offset >= u32::from(trampoline.size)
+    {
+        return None;
+    }
+    Some(trampoline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(section: u16, offset: u32) -> PdbInternalSectionOffset {
+        PdbInternalSectionOffset { section, offset }
+    }
+
+    fn symbol(tramp_type: TrampolineType, thunk: u32, size: u16, target: u32) -> TrampolineSymbol {
+        TrampolineSymbol {
+            tramp_type,
+            size,
+            thunk: offset(1, thunk),
+            target: offset(1, target),
+        }
+    }
+
+    #[test]
+    fn records_are_sorted_and_empty_ones_dropped() {
+        let records = trampoline_records(vec![
+            symbol(TrampolineType::BranchIsland, 0x20, 8, 0x9000),
+            symbol(TrampolineType::Incremental, 0x10, 5, 0x4000),
+            symbol(TrampolineType::Incremental, 0x18, 0, 0x5000),
+            symbol(TrampolineType::Unknown, 0x30, 4, 0x6000),
+        ]);
+        assert_eq!(
+            records,
+            vec![
+                TrampolineRecord {
+                    kind: TrampolineKind::IncrementalLinking,
+                    start_offset: offset(1, 0x10),
+                    size: 5,
+                    target: offset(1, 0x4000),
+                },
+                TrampolineRecord {
+                    kind: TrampolineKind::BranchIsland,
+                    start_offset: offset(1, 0x20),
+                    size: 8,
+                    target: offset(1, 0x9000),
+                },
+                TrampolineRecord {
+                    kind: TrampolineKind::Unknown,
+                    start_offset: offset(1, 0x30),
+                    size: 4,
+                    target: offset(1, 0x6000),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn find_trampoline_by_offset() {
+        let records = trampoline_records(vec![
+            symbol(TrampolineType::Incremental, 0x10, 5, 0x4000),
+            symbol(TrampolineType::BranchIsland, 0x20, 8, 0x9000),
+        ]);
+        let find = |section, offset_in_section| {
+            find_trampoline(&records, offset(section, offset_in_section))
+                .map(|trampoline| trampoline.target.offset)
+        };
+        assert_eq!(find(1, 0x0f), None);
+        assert_eq!(find(1, 0x10), Some(0x4000));
+        assert_eq!(find(1, 0x14), Some(0x4000));
+        // Between the two trampolines.
+        assert_eq!(find(1, 0x15), None);
+        assert_eq!(find(1, 0x27), Some(0x9000));
+        assert_eq!(find(1, 0x28), None);
+        // Same offset in another section.
+        assert_eq!(find(2, 0x10), None);
+        assert_eq!(find_trampoline(&[], offset(1, 0x10)), None);
+    }
+}
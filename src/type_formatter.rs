@@ -1,4 +1,7 @@
+use crate::constants::CV_CALL_THISCALL;
 use crate::error::Error;
+use crate::function_name::FunctionNameLayout;
+use crate::{compute_module_language, CompactModule, Language};
 use bitflags::bitflags;
 use pdb::{
     ArgumentList, ArrayType, ClassKind, ClassType, CrossModuleExports, CrossModuleImports,
@@ -9,12 +12,12 @@ use pdb::{
     Variant,
 };
 use range_collections::{RangeSet, RangeSet2};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::Write;
 use std::mem;
-use std::ops::Bound;
+use std::ops::{Bound, Range};
 
 type Result<V> = std::result::Result<V, Error>;
 
@@ -38,6 +41,30 @@ bitflags! {
 
         /// Do not print a functions argument types.
         const NO_ARGUMENTS = 0b100000;
+
+        /// Print anonymous namespaces as "(anonymous namespace)", regardless of whether the
+        /// PDB spells them as "`anonymous namespace'" (MSVC) or "(anonymous namespace)"
+        /// (clang-cl). This makes names from different toolchains group together.
+        const NORMALIZE_ANONYMOUS_NAMESPACES = 0b1000000;
+
+        /// Print lambdas as "{lambda}" instead of "<lambda_1>" (MSVC) or
+        /// "`lambda at file.cpp:12:5'" (clang-cl). The numbering and source locations in
+        /// lambda names are not stable across builds, so this makes it possible to group
+        /// functions from different builds by name.
+        const NORMALIZE_LAMBDAS = 0b10000000;
+
+        /// Do not print the "const" qualifier after the arguments of const member
+        /// functions, so that the names match tools which leave out qualifiers. Other
+        /// qualifiers, such as "volatile", "&&" or "__ptr64", are never printed.
+        const NO_MEMBER_FUNCTION_QUALIFIERS = 0b100000000;
+
+        /// Print the signatures of functions from modules whose source language is
+        /// Rust in Rust style, e.g. "fn alloc::alloc::alloc(u64) -> u8*", with the
+        /// return type after the arguments. The return type is only printed if
+        /// NO_FUNCTION_RETURN isn't set and the function returns something. The
+        /// argument and return types keep their names from the PDB. Checking the
+        /// language reads the module's symbols.
+        const RUST_SIGNATURES = 0b1000000000;
     }
 }
 
@@ -63,6 +90,85 @@ pub trait ModuleProvider<'s> {
         module_index: usize,
         module: &Module,
     ) -> std::result::Result<Option<&ModuleInfo<'s>>, pdb::Error>;
+
+    /// Get the module's procedures, lines and inline ranges without its stream, for
+    /// contexts with [`ContextFlags::COMPACT_MODULES`](crate::ContextFlags::COMPACT_MODULES).
+    /// Returns `None` if the module has no stream or can't be compacted, in which
+    /// case the stream is read with [`ModuleProvider::get_module_info`].
+    ///
+    /// The default implementation returns `None`. Implementations can create the
+    /// compact module with [`CompactModule::new`] from a stream which they drop
+    /// afterwards.
+    fn get_compact_module(&self, _module_index: usize, _module: &Module) -> Option<&CompactModule> {
+        None
+    }
+
+    /// Get the module's line information in the legacy C11 format, which was written
+    /// by compilers up to Visual C++ 7 and which [`ModuleInfo`] can't read. This is
+    /// the part of the module stream which follows the symbols. Returns `None` if the
+    /// module has no C11 line information.
+    ///
+    /// The default implementation returns `None`, so that frames from such modules
+    /// have no file and line information.
+    fn get_legacy_line_data(
+        &self,
+        _module_index: usize,
+        _module: &Module,
+    ) -> std::result::Result<Option<&[u8]>, pdb::Error> {
+        Ok(None)
+    }
+
+    /// Whether the module has debug information, i.e. symbols or line information.
+    /// This is used by [`Context::modules`](crate::Context::modules).
+    ///
+    /// The default implementation checks whether
+    /// [`ModuleProvider::get_module_info`] returns the module's stream, so it reads
+    /// the stream.
+    fn has_debug_info(
+        &self,
+        module_index: usize,
+        module: &Module,
+    ) -> std::result::Result<bool, pdb::Error> {
+        Ok(self.get_module_info(module_index, module)?.is_some())
+    }
+
+    /// Which kinds of debug information the module has. This is used by
+    /// [`Context::modules`](crate::Context::modules) and
+    /// [`Context::debug_info_summary`](crate::Context::debug_info_summary).
+    ///
+    /// The default implementation reads the module's stream with
+    /// [`ModuleProvider::get_module_info`] and checks whether it has any symbols and
+    /// any C13 line information.
+    fn module_debug_info(
+        &self,
+        module_index: usize,
+        module: &Module,
+    ) -> std::result::Result<ModuleDebugInfo, pdb::Error> {
+        let module_info = match self.get_module_info(module_index, module)? {
+            Some(module_info) => module_info,
+            None => return Ok(ModuleDebugInfo::default()),
+        };
+        let has_symbols = module_info.symbols()?.next()?.is_some();
+        let has_lines = match module_info.line_program() {
+            Ok(line_program) => line_program.lines().next()?.is_some(),
+            Err(pdb::Error::UnimplementedFeature(_)) => true,
+            Err(e) => return Err(e),
+        };
+        Ok(ModuleDebugInfo {
+            has_symbols,
+            has_lines,
+        })
+    }
+}
+
+/// Which kinds of debug information a module has, see
+/// [`ModuleProvider::module_debug_info`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ModuleDebugInfo {
+    /// Whether the module has symbol records, e.g. procedures.
+    pub has_symbols: bool,
+    /// Whether the module has line information.
+    pub has_lines: bool,
 }
 
 /// Allows printing function signatures, for example for use in stack traces.
@@ -84,6 +190,7 @@ pub struct TypeFormatter<'a, 's> {
     cache: RefCell<TypeFormatterCache<'a>>,
     ptr_size: u64,
     flags: TypeFormatterFlags,
+    max_name_length: Cell<Option<usize>>,
 }
 
 struct TypeFormatterCache<'a> {
@@ -94,6 +201,12 @@ struct TypeFormatterCache<'a> {
     module_name_map: Option<HashMap<String, usize>>,
     module_imports: HashMap<usize, Result<CrossModuleImports<'a>>>,
     module_exports: HashMap<usize, Result<CrossModuleExports>>,
+    /// module_index -> whether the module's language is Rust, for
+    /// [`TypeFormatterFlags::RUST_SIGNATURES`]
+    rust_modules: HashMap<usize, bool>,
+    /// The (module index, record index) pairs of the records which were replaced with
+    /// placeholders since the last call to [`TypeFormatter::take_unresolved_indexes`].
+    unresolved_indexes: Vec<(usize, u32)>,
 }
 
 // 'a: Lifetime of the thing that owns the various streams.
@@ -157,9 +270,12 @@ impl<'a, 's> TypeFormatter<'a, 's> {
                 module_name_map: None,
                 module_imports: HashMap::new(),
                 module_exports: HashMap::new(),
+                rust_modules: HashMap::new(),
+                unresolved_indexes: Vec::new(),
             }),
             ptr_size,
             flags,
+            max_name_length: Cell::new(None),
         })
     }
 
@@ -168,6 +284,40 @@ impl<'a, 's> TypeFormatter<'a, 's> {
         &self.modules
     }
 
+    /// The flags that this type formatter was created with.
+    pub fn flags(&self) -> TypeFormatterFlags {
+        self.flags
+    }
+
+    /// Limit the length of the formatted names, in bytes. Longer names, which are
+    /// common for template-heavy code, are cut off and end with `...#` and a hash of
+    /// the full name, so that different names stay different. `None`, the default,
+    /// means no limit.
+    ///
+    /// This applies to the names of procedures and of inlined functions. It takes
+    /// `&self` so that it can be changed on the formatter of a [`Context`](crate::Context);
+    /// use [`Context::set_max_name_length`](crate::Context::set_max_name_length) for
+    /// that, which also drops the names that the context has already formatted.
+    pub fn set_max_name_length(&self, max_name_length: Option<usize>) {
+        self.max_name_length.set(max_name_length);
+    }
+
+    /// The limit which was set with [`TypeFormatter::set_max_name_length`].
+    pub fn max_name_length(&self) -> Option<usize> {
+        self.max_name_length.get()
+    }
+
+    /// Returns the indexes of the type and id records which couldn't be found while
+    /// formatting, since the last call of this method, and forgets them. Each index
+    /// is paired with the index of the module whose name was being formatted. Such
+    /// records are usually in a type server or in the object files of a
+    /// `/DEBUG:FASTLINK` PDB. The formatted names contain a `<missing type 0x...>`
+    /// placeholder for each of them, or lack the argument list if the function type
+    /// is missing.
+    pub fn take_unresolved_indexes(&self) -> Vec<(usize, u32)> {
+        mem::take(&mut self.cache.borrow_mut().unresolved_indexes)
+    }
+
     fn for_module<F, R>(&self, module_index: usize, f: F) -> R
     where
         F: FnOnce(&mut TypeFormatterForModule<'_, 'a, 's>) -> R,
@@ -190,6 +340,23 @@ impl<'a, 's> TypeFormatter<'a, 's> {
         self.for_module(module_index, |tf| tf.get_type_size(index))
     }
 
+    /// Get the number of bytes that the arguments of the function type at
+    /// `function_type_index` take up on the stack, with each argument rounded up to
+    /// the pointer size. The `this` pointer of methods counts too, unless the method
+    /// uses `__thiscall`. On x86, this is the `N` in the `_name@N` and `@name@N`
+    /// decorations of `__stdcall` and `__fastcall` functions.
+    ///
+    /// Returns `None` for variadic functions and if the type isn't a function type.
+    pub fn stack_parameter_size(
+        &self,
+        module_index: usize,
+        function_type_index: TypeIndex,
+    ) -> Result<Option<u64>> {
+        self.for_module(module_index, |tf| {
+            tf.stack_parameter_size(function_type_index)
+        })
+    }
+
     /// Return a string with the function or method signature, including return type (if
     /// requested), namespace and/or class qualifiers, and arguments.
     /// If the TypeIndex is 0, then only the raw name is emitted. In that case, the
@@ -224,11 +391,68 @@ impl<'a, 's> TypeFormatter<'a, 's> {
         module_index: usize,
         function_type_index: TypeIndex,
     ) -> Result<()> {
+        if self.needs_post_processing() {
+            let mut s = String::new();
+            self.for_module(module_index, |tf| {
+                tf.emit_function(&mut s, name, function_type_index)
+            })?;
+            return self.write_post_processed_name(w, &s);
+        }
         self.for_module(module_index, |tf| {
             tf.emit_function(w, name, function_type_index)
         })
     }
 
+    /// Like [`TypeFormatter::format_function`], but prints the supplied argument names
+    /// after the argument types, e.g. `foo(int count, char* name)`.
+    /// `argument_names` is matched up with the arguments in order. If there are fewer
+    /// names than arguments, the remaining arguments are printed without a name. Empty
+    /// names are skipped.
+    pub fn format_function_with_argument_names(
+        &self,
+        name: &str,
+        module_index: usize,
+        function_type_index: TypeIndex,
+        argument_names: &[&str],
+    ) -> Result<String> {
+        let mut s = String::new();
+        self.emit_function_with_argument_names(
+            &mut s,
+            name,
+            module_index,
+            function_type_index,
+            argument_names,
+        )?;
+        Ok(s)
+    }
+
+    /// Like [`TypeFormatter::emit_function`], but prints the supplied argument names
+    /// after the argument types. See [`TypeFormatter::format_function_with_argument_names`].
+    pub fn emit_function_with_argument_names(
+        &self,
+        w: &mut impl Write,
+        name: &str,
+        module_index: usize,
+        function_type_index: TypeIndex,
+        argument_names: &[&str],
+    ) -> Result<()> {
+        if self.needs_post_processing() {
+            let mut s = String::new();
+            self.for_module(module_index, |tf| {
+                tf.emit_function_with_argument_names(
+                    &mut s,
+                    name,
+                    function_type_index,
+                    argument_names,
+                )
+            })?;
+            return self.write_post_processed_name(w, &s);
+        }
+        self.for_module(module_index, |tf| {
+            tf.emit_function_with_argument_names(w, name, function_type_index, argument_names)
+        })
+    }
+
     /// Return a string with the function or method signature, including return type (if
     /// requested), namespace and/or class qualifiers, and arguments.
     /// This method is used for inlined functions.
@@ -240,6 +464,59 @@ impl<'a, 's> TypeFormatter<'a, 's> {
         Ok(s)
     }
 
+    /// The name of the function in the id record `id_index`, as it is stored in the
+    /// PDB, without its scope and arguments. `None` if the record isn't a function.
+    pub(crate) fn raw_id_name(
+        &self,
+        module_index: usize,
+        id_index: IdIndex,
+    ) -> Option<RawString<'a>> {
+        match self.for_module(module_index, |tf| tf.parse_id_index(id_index)) {
+            Ok(IdData::Function(f)) => Some(f.name),
+            Ok(IdData::MemberFunction(m)) => Some(m.name),
+            _ => None,
+        }
+    }
+
+    /// Like [`TypeFormatter::format_id`], and also return the positions of the parts
+    /// of the name. The layout is `None` if the id isn't a function, or if the name
+    /// was truncated to the maximum name length.
+    pub(crate) fn format_id_with_layout(
+        &self,
+        module_index: usize,
+        id_index: IdIndex,
+    ) -> Result<(String, Option<FunctionNameLayout>)> {
+        let mut s = String::new();
+        let marks = self.for_module(module_index, |tf| {
+            tf.emit_id_with_marks(&mut CountingWriter::new(&mut s), id_index)
+        })?;
+        let layout = marks.map(|marks| marks.into_layout(&s));
+        self.post_process_with_layout(s, layout)
+    }
+
+    /// Like [`TypeFormatter::format_function_with_argument_names`], and also return the
+    /// positions of the parts of the name. The layout is `None` if the name was
+    /// truncated to the maximum name length.
+    pub(crate) fn format_function_with_layout(
+        &self,
+        name: &str,
+        module_index: usize,
+        function_type_index: TypeIndex,
+        argument_names: &[&str],
+    ) -> Result<(String, Option<FunctionNameLayout>)> {
+        let mut s = String::new();
+        let marks = self.for_module(module_index, |tf| {
+            tf.emit_function_with_marks(
+                &mut CountingWriter::new(&mut s),
+                name,
+                function_type_index,
+                argument_names,
+            )
+        })?;
+        let layout = marks.into_layout(&s);
+        self.post_process_with_layout(s, Some(layout))
+    }
+
     /// Write out the function or method signature, including return type (if requested),
     /// namespace and/or class qualifiers, and arguments.
     /// This method is used for inlined functions.
@@ -251,8 +528,91 @@ impl<'a, 's> TypeFormatter<'a, 's> {
         module_index: usize,
         id_index: IdIndex,
     ) -> Result<()> {
+        if self.needs_post_processing() {
+            let mut s = String::new();
+            self.for_module(module_index, |tf| tf.emit_id(&mut s, id_index))?;
+            return self.write_post_processed_name(w, &s);
+        }
         self.for_module(module_index, |tf| tf.emit_id(w, id_index))
     }
+
+    fn needs_post_processing(&self) -> bool {
+        self.needs_name_normalization() || self.max_name_length.get().is_some()
+    }
+
+    /// Write out `name` after applying the `NORMALIZE_*` flags and the maximum name
+    /// length.
+    fn write_post_processed_name(&self, w: &mut impl Write, name: &str) -> Result<()> {
+        let normalized;
+        let name = if self.needs_name_normalization() {
+            let mut s = String::new();
+            write_normalized_name(&mut s, name, self.flags)?;
+            normalized = s;
+            &normalized
+        } else {
+            name
+        };
+        match self.max_name_length.get() {
+            Some(max_name_length) if name.len() > max_name_length => {
+                write_truncated_name(w, name, max_name_length)
+            }
+            _ => Ok(w.write_str(name)?),
+        }
+    }
+
+    /// Like `write_post_processed_name`, and move the positions of `layout` along with
+    /// the rewritten parts. Each part is normalized on its own.
+    fn post_process_with_layout(
+        &self,
+        name: String,
+        layout: Option<FunctionNameLayout>,
+    ) -> Result<(String, Option<FunctionNameLayout>)> {
+        let (name, layout) = match layout {
+            Some(layout) if self.needs_name_normalization() => {
+                let mut boundaries = layout.boundaries();
+                boundaries.push(0);
+                boundaries.push(name.len());
+                boundaries.sort_unstable();
+                boundaries.dedup();
+                let mut normalized = String::with_capacity(name.len());
+                // (old position, new position) for each boundary, sorted.
+                let mut positions = Vec::with_capacity(boundaries.len());
+                for pair in boundaries.windows(2) {
+                    positions.push((pair[0], normalized.len()));
+                    write_normalized_name(&mut normalized, &name[pair[0]..pair[1]], self.flags)?;
+                }
+                positions.push((name.len(), normalized.len()));
+                let layout = layout.map_positions(|position| {
+                    match positions.binary_search_by_key(&position, |(old, _)| *old) {
+                        Ok(index) => positions[index].1,
+                        Err(_) => position,
+                    }
+                });
+                (normalized, Some(layout))
+            }
+            _ if self.needs_name_normalization() => {
+                let mut normalized = String::with_capacity(name.len());
+                write_normalized_name(&mut normalized, &name, self.flags)?;
+                (normalized, None)
+            }
+            layout => (name, layout),
+        };
+        match self.max_name_length.get() {
+            Some(max_name_length) if name.len() > max_name_length => {
+                let mut truncated = String::new();
+                write_truncated_name(&mut truncated, &name, max_name_length)?;
+                Ok((truncated, None))
+            }
+            _ => Ok((name, layout)),
+        }
+    }
+
+    fn needs_name_normalization(&self) -> bool {
+        self.flags.intersects(
+            TypeFormatterFlags::NORMALIZE_ANONYMOUS_NAMESPACES
+                | TypeFormatterFlags::NORMALIZE_LAMBDAS,
+        )
+    }
 }
 
 impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
@@ -264,6 +624,37 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
             0
         }
     }
+    /// Get the number of bytes that the arguments of the function type at
+    /// `function_type_index` take up on the stack.
+    pub fn stack_parameter_size(&mut self, function_type_index: TypeIndex) -> Result<Option<u64>> {
+        let (argument_list, this_type) = match self.parse_type_index(function_type_index)? {
+            TypeData::MemberFunction(t) => {
+                let this_type = match t.attributes.calling_convention() {
+                    CV_CALL_THISCALL => None,
+                    _ => t.this_pointer_type,
+                };
+                (t.argument_list, this_type)
+            }
+            TypeData::Procedure(t) => (t.argument_list, None),
+            _ => return Ok(None),
+        };
+        let arguments = match self.parse_type_index(argument_list)? {
+            TypeData::ArgumentList(list) => list.arguments,
+            _ => return Err(Error::ArgumentTypeNotArgumentList),
+        };
+        let slot_size = self.ptr_size;
+        let mut size = 0;
+        for argument in this_type.into_iter().chain(arguments) {
+            // The `...` of variadic functions is stored as an argument without type.
+            if argument == TypeIndex(0) {
+                return Ok(None);
+            }
+            let argument_size = self.get_type_size(argument);
+            size += argument_size.div_ceil(slot_size) * slot_size;
+        }
+        Ok(Some(size))
+    }
+
     /// Write out the function or method signature, including return type (if requested),
     /// namespace and/or class qualifiers, and arguments.
     /// If the TypeIndex is 0, then only the raw name is emitted. In that case, the
@@ -276,87 +667,232 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         name: &str,
         function_type_index: TypeIndex,
     ) -> Result<()> {
+        self.emit_function_with_argument_names(w, name, function_type_index, &[])
+    }
+
+    /// Like `emit_function`, but prints the supplied argument names after the
+    /// argument types.
+    pub fn emit_function_with_argument_names(
+        &mut self,
+        w: &mut impl Write,
+        name: &str,
+        function_type_index: TypeIndex,
+        argument_names: &[&str],
+    ) -> Result<()> {
+        self.emit_function_with_marks(
+            &mut CountingWriter::new(w),
+            name,
+            function_type_index,
+            argument_names,
+        )?;
+        Ok(())
+    }
+
+    /// Like `emit_function_with_argument_names`, and return the positions of the
+    /// parts of the name.
+    fn emit_function_with_marks<W: Write>(
+        &mut self,
+        w: &mut CountingWriter<'_, W>,
+        name: &str,
+        function_type_index: TypeIndex,
+        argument_names: &[&str],
+    ) -> Result<NameMarks> {
         if function_type_index == TypeIndex(0) {
-            return self.emit_name_str(w, name);
+            self.emit_name_str(w, name)?;
+            return Ok(NameMarks::name_only(0..w.len));
         }
 
-        match self.parse_type_index(function_type_index)? {
+        let rust_style = self.uses_rust_signatures();
+        let type_data = match self.parse_function_type_index(function_type_index)? {
+            Some(type_data) => type_data,
+            None => {
+                self.emit_name_str(w, name)?;
+                return Ok(NameMarks::name_only(0..w.len));
+            }
+        };
+        let mut marks = NameMarks::name_only(0..0);
+        match type_data {
             TypeData::MemberFunction(t) => {
-                if t.this_pointer_type.is_none() {
-                    self.maybe_emit_static(w)?;
+                if rust_style {
+                    write!(w, "fn ")?;
+                } else {
+                    if t.this_pointer_type.is_none() {
+                        self.maybe_emit_static(w)?;
+                    }
+                    self.maybe_emit_return_type(w, Some(t.return_type), t.attributes)?;
                 }
-                self.maybe_emit_return_type(w, Some(t.return_type), t.attributes)?;
+                marks.qualified.start = w.len;
                 self.emit_name_str(w, name)?;
-                self.emit_method_args(w, t, true)?;
+                marks.qualified.end = w.len;
+                self.emit_method_args(w, t, true, argument_names)?;
+                marks.arguments = Some(marks.qualified.end..w.len);
+                if rust_style {
+                    marks.return_type = self.emit_rust_return_type_with_marks(
+                        w,
+                        Some(t.return_type),
+                        t.attributes,
+                    )?;
+                }
             }
             TypeData::Procedure(t) => {
-                self.maybe_emit_return_type(w, t.return_type, t.attributes)?;
+                if rust_style {
+                    write!(w, "fn ")?;
+                } else {
+                    self.maybe_emit_return_type(w, t.return_type, t.attributes)?;
+                }
+                marks.qualified.start = w.len;
                 self.emit_name_str(w, name)?;
+                marks.qualified.end = w.len;
 
                 if !self.has_flags(TypeFormatterFlags::NO_ARGUMENTS) {
                     write!(w, "(")?;
-                    self.emit_type_index(w, t.argument_list)?;
+                    if argument_names.is_empty() {
+                        self.emit_type_index(w, t.argument_list)?;
+                    } else {
+                        match self.parse_type_index(t.argument_list)? {
+                            TypeData::ArgumentList(list) => {
+                                self.emit_arg_list(w, list, false, argument_names)?
+                            }
+                            _ => return Err(Error::ArgumentTypeNotArgumentList),
+                        }
+                    }
                     write!(w, ")")?;
                 }
+                marks.arguments = Some(marks.qualified.end..w.len);
+                if rust_style {
+                    marks.return_type =
+                        self.emit_rust_return_type_with_marks(w, t.return_type, t.attributes)?;
+                }
             }
             _ => {
                 write!(w, "{}", name)?;
+                return Ok(NameMarks::name_only(0..w.len));
             }
         }
-        Ok(())
+        if !rust_style {
+            marks.return_type = Some(0..marks.qualified.start);
+        }
+        Ok(marks)
     }
 
     /// Write out the function or method signature, including return type (if requested),
     /// namespace and/or class qualifiers, and arguments.
     /// This method is used for inlined functions.
     pub fn emit_id(&mut self, w: &mut impl Write, id_index: IdIndex) -> Result<()> {
+        self.emit_id_with_marks(&mut CountingWriter::new(w), id_index)?;
+        Ok(())
+    }
+
+    /// Like `emit_id`, and return the positions of the parts of the name, if the id is
+    /// a function.
+    fn emit_id_with_marks<W: Write>(
+        &mut self,
+        w: &mut CountingWriter<'_, W>,
+        id_index: IdIndex,
+    ) -> Result<Option<NameMarks>> {
         let id_data = match self.parse_id_index(id_index) {
             Ok(id_data) => id_data,
             Err(Error::PdbError(pdb::Error::UnimplementedTypeKind(t))) => {
                 write!(w, "<unimplemented type kind 0x{:x}>", t)?;
-                return Ok(());
+                return Ok(None);
             }
             Err(Error::PdbError(pdb::Error::TypeNotFound(type_index))) => {
-                write!(w, "<missing type 0x{:x}>", type_index)?;
-                return Ok(());
+                self.emit_unresolved(w, type_index)?;
+                return Ok(None);
             }
             Err(e) => return Err(e),
         };
+        let mut marks = NameMarks::name_only(0..0);
         match id_data {
             IdData::MemberFunction(m) => {
-                let t = match self.parse_type_index(m.function_type)? {
-                    TypeData::MemberFunction(t) => t,
-                    _ => return Err(Error::MemberFunctionIdIsNotMemberFunctionType),
+                let t = match self.parse_function_type_index(m.function_type)? {
+                    Some(TypeData::MemberFunction(t)) => t,
+                    Some(_) => return Err(Error::MemberFunctionIdIsNotMemberFunctionType),
+                    None => {
+                        self.emit_type_index(w, m.parent)?;
+                        write!(w, "::")?;
+                        let name_start = w.len;
+                        self.emit_name_str(w, &m.name.to_string())?;
+                        return Ok(Some(NameMarks {
+                            name_start: Some(name_start),
+                            ..NameMarks::name_only(0..w.len)
+                        }));
+                    }
                 };
 
-                if t.this_pointer_type.is_none() {
-                    self.maybe_emit_static(w)?;
+                let rust_style = self.uses_rust_signatures();
+                if rust_style {
+                    write!(w, "fn ")?;
+                } else {
+                    if t.this_pointer_type.is_none() {
+                        self.maybe_emit_static(w)?;
+                    }
+                    self.maybe_emit_return_type(w, Some(t.return_type), t.attributes)?;
                 }
-                self.maybe_emit_return_type(w, Some(t.return_type), t.attributes)?;
+                marks.qualified.start = w.len;
                 self.emit_type_index(w, m.parent)?;
                 write!(w, "::")?;
+                marks.name_start = Some(w.len);
                 self.emit_name_str(w, &m.name.to_string())?;
-                self.emit_method_args(w, t, true)?;
+                marks.qualified.end = w.len;
+                self.emit_method_args(w, t, true, &[])?;
+                marks.arguments = Some(marks.qualified.end..w.len);
+                if rust_style {
+                    marks.return_type = self.emit_rust_return_type_with_marks(
+                        w,
+                        Some(t.return_type),
+                        t.attributes,
+                    )?;
+                } else {
+                    marks.return_type = Some(0..marks.qualified.start);
+                }
             }
             IdData::Function(f) => {
-                let t = match self.parse_type_index(f.function_type)? {
-                    TypeData::Procedure(t) => t,
-                    _ => return Err(Error::FunctionIdIsNotProcedureType),
+                let t = match self.parse_function_type_index(f.function_type)? {
+                    Some(TypeData::Procedure(t)) => t,
+                    Some(_) => return Err(Error::FunctionIdIsNotProcedureType),
+                    None => {
+                        if let Some(scope) = f.scope {
+                            self.emit_id_with_marks(w, scope)?;
+                            write!(w, "::")?;
+                        }
+                        let name_start = w.len;
+                        self.emit_name_str(w, &f.name.to_string())?;
+                        return Ok(Some(NameMarks {
+                            name_start: Some(name_start),
+                            ..NameMarks::name_only(0..w.len)
+                        }));
+                    }
                 };
 
-                self.maybe_emit_return_type(w, t.return_type, t.attributes)?;
+                let rust_style = self.uses_rust_signatures();
+                if rust_style {
+                    write!(w, "fn ")?;
+                } else {
+                    self.maybe_emit_return_type(w, t.return_type, t.attributes)?;
+                }
+                marks.qualified.start = w.len;
                 if let Some(scope) = f.scope {
-                    self.emit_id(w, scope)?;
+                    self.emit_id_with_marks(w, scope)?;
                     write!(w, "::")?;
                 }
 
+                marks.name_start = Some(w.len);
                 self.emit_name_str(w, &f.name.to_string())?;
+                marks.qualified.end = w.len;
 
                 if !self.has_flags(TypeFormatterFlags::NO_ARGUMENTS) {
                     write!(w, "(")?;
                     self.emit_type_index(w, t.argument_list)?;
                     write!(w, ")")?;
                 }
+                marks.arguments = Some(marks.qualified.end..w.len);
+                if rust_style {
+                    marks.return_type =
+                        self.emit_rust_return_type_with_marks(w, t.return_type, t.attributes)?;
+                } else {
+                    marks.return_type = Some(0..marks.qualified.start);
+                }
             }
             IdData::String(s) => {
                 let name = s.name.to_string();
@@ -366,6 +902,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
                 } else {
                     write!(w, "{}", name)?;
                 }
+                return Ok(None);
             }
             IdData::StringList(s) => {
                 write!(w, "\"")?;
@@ -376,10 +913,14 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
                     self.emit_type_index(w, *type_index)?;
                 }
                 write!(w, "\"")?;
+                return Ok(None);
+            }
+            other => {
+                write!(w, "<unhandled id scope {:?}>::", other)?;
+                return Ok(None);
             }
-            other => write!(w, "<unhandled id scope {:?}>::", other)?,
         }
-        Ok(())
+        Ok(Some(marks))
     }
 
     /// Checks whether the given name declares an anonymous namespace.
@@ -388,7 +929,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
     /// identifier of the namespace. Demanglers usually resolve this as "anonymous namespace".
     fn is_anonymous_namespace(name: &str) -> bool {
         name.strip_prefix("?A0x")
-            .map_or(false, |rest| u32::from_str_radix(rest, 16).is_ok())
+            .is_some_and(|rest| u32::from_str_radix(rest, 16).is_ok())
     }
 
     fn resolve_index<I>(&mut self, index: I) -> Result<I>
@@ -616,6 +1157,73 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         Ok(())
     }
 
+    /// Whether function signatures from this module are printed in Rust style, see
+    /// [`TypeFormatterFlags::RUST_SIGNATURES`].
+    fn uses_rust_signatures(&mut self) -> bool {
+        if !self.has_flags(TypeFormatterFlags::RUST_SIGNATURES) {
+            return false;
+        }
+        let module_index = self.module_index;
+        let module_provider = self.module_provider;
+        let module = self.modules.get(module_index);
+        *self
+            .cache
+            .rust_modules
+            .entry(module_index)
+            .or_insert_with(|| {
+                let module_info = module.and_then(|module| {
+                    module_provider
+                        .get_module_info(module_index, module)
+                        .ok()
+                        .flatten()
+                });
+                module_info.is_some_and(|module_info| {
+                    matches!(
+                        compute_module_language(module_info),
+                        Ok(Some(Language::Rust))
+                    )
+                })
+            })
+    }
+
+    /// Write the return type in Rust style, after the arguments: " -> ret". Nothing
+    /// is written for functions which return void.
+    fn maybe_emit_rust_return_type(
+        &mut self,
+        w: &mut impl Write,
+        type_index: Option<TypeIndex>,
+        attrs: FunctionAttributes,
+    ) -> Result<()> {
+        if self.has_flags(TypeFormatterFlags::NO_FUNCTION_RETURN) || attrs.is_constructor() {
+            return Ok(());
+        }
+        let index = match type_index {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        if let TypeData::Primitive(PrimitiveType {
+            kind: PrimitiveKind::Void | PrimitiveKind::NoType,
+            indirection: None,
+        }) = self.parse_type_index(index)?
+        {
+            return Ok(());
+        }
+        write!(w, " -> ")?;
+        self.emit_type_index(w, index)
+    }
+
+    /// Like `maybe_emit_rust_return_type`, and return the position of the return type.
+    fn emit_rust_return_type_with_marks<W: Write>(
+        &mut self,
+        w: &mut CountingWriter<'_, W>,
+        type_index: Option<TypeIndex>,
+        attrs: FunctionAttributes,
+    ) -> Result<Option<Range<usize>>> {
+        let start = w.len;
+        self.maybe_emit_rust_return_type(w, type_index, attrs)?;
+        Ok((w.len > start).then(|| start + " -> ".len()..w.len))
+    }
+
     fn emit_name_str(&mut self, w: &mut impl Write, name: &str) -> Result<()> {
         if name.is_empty() {
             write!(w, "<name omitted>")?;
@@ -692,6 +1300,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         w: &mut impl Write,
         method_type: MemberFunctionType,
         allow_emit_const: bool,
+        argument_names: &[&str],
     ) -> Result<()> {
         if self.has_flags(TypeFormatterFlags::NO_ARGUMENTS) {
             return Ok(());
@@ -720,13 +1329,18 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         write!(w, "(")?;
         if let Some(first_arg) = extra_first_arg {
             self.emit_type_index(w, first_arg)?;
-            self.emit_arg_list(w, args_list, true)?;
+            self.emit_argument_name(w, argument_names.first())?;
+            let remaining_names = argument_names.get(1..).unwrap_or(&[]);
+            self.emit_arg_list(w, args_list, true, remaining_names)?;
         } else {
-            self.emit_arg_list(w, args_list, false)?;
+            self.emit_arg_list(w, args_list, false, argument_names)?;
         }
         write!(w, ")")?;
 
-        if is_const_method && allow_emit_const {
+        if is_const_method
+            && allow_emit_const
+            && !self.has_flags(TypeFormatterFlags::NO_MEMBER_FUNCTION_QUALIFIERS)
+        {
             write!(w, " const")?;
         }
 
@@ -795,7 +1409,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         self.emit_type_index(w, fun.class_type)?;
         self.emit_attributes(w, attributes, false, false)?;
         write!(w, ")")?;
-        self.emit_method_args(w, fun, false)?;
+        self.emit_method_args(w, fun, false, &[])?;
         Ok(())
     }
 
@@ -938,8 +1552,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         let mut iter = dimensions_as_bytes.into_iter().peekable();
         while let Some(current_level_byte_size) = iter.next() {
             let next_level_byte_size = *iter.peek().unwrap_or(&base_size);
-            if next_level_byte_size != 0 {
-                let element_count = current_level_byte_size / next_level_byte_size;
+            if let Some(element_count) = current_level_byte_size.checked_div(next_level_byte_size) {
                 write!(w, "[{}]", element_count)?;
             } else {
                 // The base size can be zero: struct A{}; void foo(A x[10])
@@ -985,7 +1598,9 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         w: &mut impl Write,
         list: ArgumentList,
         comma_before_first: bool,
+        argument_names: &[&str],
     ) -> Result<()> {
+        let mut names = argument_names.iter();
         if let Some((first, args)) = list.arguments.split_first() {
             if comma_before_first {
                 write!(w, ",")?;
@@ -994,17 +1609,27 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
                 }
             }
             self.emit_type_index(w, *first)?;
+            self.emit_argument_name(w, names.next())?;
             for index in args.iter() {
                 write!(w, ",")?;
                 if self.has_flags(TypeFormatterFlags::SPACE_AFTER_COMMA) {
                     write!(w, " ")?;
                 }
                 self.emit_type_index(w, *index)?;
+                self.emit_argument_name(w, names.next())?;
             }
         }
         Ok(())
     }
 
+    fn emit_argument_name(&mut self, w: &mut impl Write, name: Option<&&str>) -> Result<()> {
+        match name {
+            Some(name) if !name.is_empty() => write!(w, " {}", name)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
     fn emit_primitive(
         &mut self,
         w: &mut impl Write,
@@ -1076,6 +1701,32 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         Ok(())
     }
 
+    /// Write the placeholder for a type or id record which couldn't be found, and
+    /// remember its index for [`TypeFormatter::take_unresolved_indexes`].
+    fn emit_unresolved(&mut self, w: &mut impl Write, index: u32) -> Result<()> {
+        self.cache
+            .unresolved_indexes
+            .push((self.module_index, index));
+        write!(w, "<missing type 0x{:x}>", index)?;
+        Ok(())
+    }
+
+    /// Parse the type of a function, or return `None` and remember its index for
+    /// [`TypeFormatter::take_unresolved_indexes`] if it can't be found, so that the
+    /// function can still be named without its signature.
+    fn parse_function_type_index(&mut self, index: TypeIndex) -> Result<Option<TypeData<'a>>> {
+        match self.parse_type_index(index) {
+            Ok(type_data) => Ok(Some(type_data)),
+            Err(Error::PdbError(pdb::Error::TypeNotFound(type_index))) => {
+                self.cache
+                    .unresolved_indexes
+                    .push((self.module_index, type_index));
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     fn emit_named(&mut self, w: &mut impl Write, base: &str, name: RawString) -> Result<()> {
         if self.has_flags(TypeFormatterFlags::NAME_ONLY) {
             write!(w, "{}", name)?
@@ -1094,8 +1745,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
                 Ok(())
             }
             Err(Error::PdbError(pdb::Error::TypeNotFound(type_index))) => {
-                write!(w, "<missing type 0x{:x}>", type_index)?;
-                Ok(())
+                self.emit_unresolved(w, type_index)
             }
             Err(e) => Err(e),
         }
@@ -1105,8 +1755,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
         match self.emit_type_inner(w, type_data) {
             Ok(()) => Ok(()),
             Err(Error::PdbError(pdb::Error::TypeNotFound(type_index))) => {
-                write!(w, "<missing type 0x{:x}>", type_index)?;
-                Ok(())
+                self.emit_unresolved(w, type_index)
             }
             Err(e) => Err(e),
         }
@@ -1119,7 +1768,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
             TypeData::MemberFunction(t) => {
                 self.maybe_emit_return_type(w, Some(t.return_type), t.attributes)?;
                 write!(w, "()")?;
-                self.emit_method_args(w, t, false)?;
+                self.emit_method_args(w, t, false, &[])?;
             }
             TypeData::Procedure(t) => {
                 self.maybe_emit_return_type(w, t.return_type, t.attributes)?;
@@ -1127,7 +1776,7 @@ impl<'cache, 'a, 's> TypeFormatterForModule<'cache, 'a, 's> {
                 self.emit_type_index(w, t.argument_list)?;
                 write!(w, "")?;
             }
-            TypeData::ArgumentList(t) => self.emit_arg_list(w, t, false)?,
+            TypeData::ArgumentList(t) => self.emit_arg_list(w, t, false, &[])?,
             TypeData::Pointer(t) => self.emit_ptr(w, t, false)?,
             TypeData::Array(t) => self.emit_array(w, t)?,
             TypeData::Union(t) => self.emit_named(w, "union", t.name)?,
@@ -1258,19 +1907,15 @@ impl<'a> TypeSizeCache<'a> {
     ) -> Option<(RawString<'a>, u64)> {
         if let Ok(type_data) = item.parse() {
             match type_data {
-                TypeData::Class(t) => {
-                    if !t.properties.forward_reference() {
-                        let name = t.unique_name.unwrap_or(t.name);
-                        self.forward_ref_sizes.insert(name, t.size);
-                        return Some((name, t.size));
-                    }
+                TypeData::Class(t) if !t.properties.forward_reference() => {
+                    let name = t.unique_name.unwrap_or(t.name);
+                    self.forward_ref_sizes.insert(name, t.size);
+                    return Some((name, t.size));
                 }
-                TypeData::Union(t) => {
-                    if !t.properties.forward_reference() {
-                        let name = t.unique_name.unwrap_or(t.name);
-                        self.forward_ref_sizes.insert(name, t.size);
-                        return Some((name, t.size));
-                    }
+                TypeData::Union(t) if !t.properties.forward_reference() => {
+                    let name = t.unique_name.unwrap_or(t.name);
+                    self.forward_ref_sizes.insert(name, t.size);
+                    return Some((name, t.size));
                 }
                 _ => {}
             }
@@ -1278,3 +1923,167 @@ impl<'a> TypeSizeCache<'a> {
         None
     }
 }
+
+/// A writer which counts the bytes written to it, so that the positions of the parts
+/// of a function name can be recorded while the name is written.
+struct CountingWriter<'w, W> {
+    inner: &'w mut W,
+    len: usize,
+}
+
+impl<'w, W: Write> CountingWriter<'w, W> {
+    fn new(inner: &'w mut W) -> Self {
+        Self { inner, len: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.len += s.len();
+        self.inner.write_str(s)
+    }
+}
+
+/// The positions of the parts of a function name which were recorded while the name
+/// was written, see [`FunctionNameLayout::new`].
+struct NameMarks {
+    return_type: Option<Range<usize>>,
+    qualified: Range<usize>,
+    name_start: Option<usize>,
+    arguments: Option<Range<usize>>,
+}
+
+impl NameMarks {
+    /// The marks of a name which was written without a signature.
+    fn name_only(qualified: Range<usize>) -> Self {
+        Self {
+            return_type: None,
+            qualified,
+            name_start: None,
+            arguments: None,
+        }
+    }
+
+    fn into_layout(self, full_name: &str) -> FunctionNameLayout {
+        FunctionNameLayout::new(
+            full_name,
+            self.return_type,
+            self.qualified,
+            self.name_start,
+            self.arguments,
+        )
+    }
+}
+
+/// Write out `name`, with anonymous namespaces and lambdas rewritten according to
+/// the `NORMALIZE_*` flags. This is done as a post-processing step because these
+/// names can come from many places: procedure symbol names, class names from the
+/// type records, and the ID records of inlinees.
+fn write_normalized_name(w: &mut impl Write, name: &str, flags: TypeFormatterFlags) -> Result<()> {
+    let normalize_namespaces = flags.contains(TypeFormatterFlags::NORMALIZE_ANONYMOUS_NAMESPACES);
+    let normalize_lambdas = flags.contains(TypeFormatterFlags::NORMALIZE_LAMBDAS);
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        if normalize_namespaces {
+            if let Some(r) = rest
+                .strip_prefix("`anonymous namespace'")
+                .or_else(|| rest.strip_prefix("(anonymous namespace)"))
+            {
+                w.write_str("(anonymous namespace)")?;
+                rest = r;
+                continue;
+            }
+        }
+        if normalize_lambdas {
+            if let Some(r) = rest.strip_prefix("`lambda at ") {
+                if let Some(end) = r.find('\'') {
+                    w.write_str("{lambda}")?;
+                    rest = &r[end + 1..];
+                    continue;
+                }
+            }
+            if let Some(r) = rest.strip_prefix("<lambda_") {
+                let id_len = r.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(r.len());
+                if id_len != 0 {
+                    if let Some(r) = r[id_len..].strip_prefix('>') {
+                        w.write_str("{lambda}")?;
+                        rest = r;
+                        continue;
+                    }
+                }
+            }
+        }
+        w.write_char(c)?;
+        rest = &rest[c.len_utf8()..];
+    }
+    Ok(())
+}
+
+/// Write the first bytes of `name`, followed by `...#` and the 32-bit FNV-1a hash of
+/// the whole name in hex, with at most `max_length` bytes in total. The hash suffix
+/// is always written, even if it is longer than `max_length`.
+fn write_truncated_name(w: &mut impl Write, name: &str, max_length: usize) -> Result<()> {
+    let hash = name.bytes().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    let suffix = format!("...#{:08x}", hash);
+    let mut prefix_length = max_length.saturating_sub(suffix.len());
+    while !name.is_char_boundary(prefix_length) {
+        prefix_length -= 1;
+    }
+    w.write_str(&name[..prefix_length])?;
+    w.write_str(&suffix)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(name: &str) -> String {
+        let mut s = String::new();
+        write_normalized_name(&mut s, name, TypeFormatterFlags::NORMALIZE_LAMBDAS).unwrap();
+        s
+    }
+
+    #[test]
+    fn normalize_msvc_lambdas() {
+        assert_eq!(
+            normalized("Foo::<lambda_1f3a9c>::operator()"),
+            "Foo::{lambda}::operator()"
+        );
+        assert_eq!(
+            normalized("run<<lambda_0>,<lambda_ab12> >"),
+            "run<{lambda},{lambda} >"
+        );
+        // Only hexadecimal ids followed by '>' are lambdas.
+        assert_eq!(normalized("<lambda_x>"), "<lambda_x>");
+        assert_eq!(normalized("<lambda_>"), "<lambda_>");
+        assert_eq!(normalized("<lambda_12"), "<lambda_12");
+    }
+
+    #[test]
+    fn normalize_clang_lambdas() {
+        assert_eq!(
+            normalized("Foo::`lambda at c:\\src\\foo.cpp:12:5'::operator()"),
+            "Foo::{lambda}::operator()"
+        );
+        // Without the closing quote, the name is left alone.
+        assert_eq!(
+            normalized("`lambda at foo.cpp:1:2"),
+            "`lambda at foo.cpp:1:2"
+        );
+    }
+
+    #[test]
+    fn normalize_only_with_flags() {
+        let mut s = String::new();
+        write_normalized_name(
+            &mut s,
+            "`anonymous namespace'::<lambda_1>",
+            TypeFormatterFlags::NORMALIZE_ANONYMOUS_NAMESPACES,
+        )
+        .unwrap();
+        assert_eq!(s, "(anonymous namespace)::<lambda_1>");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{Context, Provenance};
+
+/// The address range of a `RUNTIME_FUNCTION` entry from the exception directory
+/// (`.pdata`) of a PE image, see [`Context::check_unwind_info`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RuntimeFunction {
+    /// The start address of the code which the entry describes.
+    pub begin_rva: u32,
+    /// The end address of the code which the entry describes, exclusive.
+    pub end_rva: u32,
+}
+
+/// A disagreement between the unwind information of an image and the procedures in
+/// the PDB, found by [`Context::check_unwind_info`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnwindIssue {
+    /// No runtime function entry overlaps the procedure. This is expected for leaf
+    /// functions which don't touch the stack pointer or save registers; for all
+    /// other functions, stack walks through them will fail.
+    MissingUnwindInfo {
+        /// The procedure name.
+        name: Option<String>,
+        /// The start address of the procedure.
+        start_rva: u32,
+        /// The end address of the procedure.
+        end_rva: u32,
+    },
+    /// The runtime function entries which overlap the procedure don't start at its
+    /// start address or don't end at its end address.
+    MismatchedRange {
+        /// The procedure name.
+        name: Option<String>,
+        /// The start address of the procedure.
+        start_rva: u32,
+        /// The end address of the procedure.
+        end_rva: u32,
+        /// The lowest start address of the overlapping entries.
+        unwind_begin_rva: u32,
+        /// The highest end address of the overlapping entries.
+        unwind_end_rva: u32,
+    },
+    /// A runtime function entry whose start address isn't in any function of the
+    /// PDB.
+    UnwindInfoWithoutFunction(RuntimeFunction),
+}
+
+impl<'a, 's> Context<'a, 's> {
+    /// Correlate the runtime function entries of the image's exception directory
+    /// with the procedures in the PDB, and list the procedures with missing or
+    /// mismatched unwind information, and the entries without a function. Missing
+    /// or wrong unwind information is a common cause of broken stack walks on x64
+    /// and arm64.
+    ///
+    /// This crate doesn't read PE files, so the caller has to supply the entries.
+    /// A procedure can be covered by several chained entries; they are compared as
+    /// a whole. Functions which are only known from public symbols are not checked,
+    /// because their sizes are only estimates.
+    ///
+    /// This reads the symbols of all modules, so it is slow for large PDBs.
+    pub fn check_unwind_info(&self, runtime_functions: &[RuntimeFunction]) -> Vec<UnwindIssue> {
+        let mut runtime_functions = runtime_functions.to_vec();
+        runtime_functions.sort_unstable_by_key(|entry| (entry.begin_rva, entry.end_rva));
+        // The highest end address of the entries up to each index, for finding the
+        // entries which start before a procedure and overlap it.
+        let max_end_rvas: Vec<u32> = runtime_functions
+            .iter()
+            .scan(0, |max_end_rva, entry| {
+                *max_end_rva = entry.end_rva.max(*max_end_rva);
+                Some(*max_end_rva)
+            })
+            .collect();
+
+        let mut issues = Vec::new();
+        for function in self.functions() {
+            if !matches!(
+                function.provenance,
+                Provenance::Procedure | Provenance::Thunk
+            ) {
+                continue;
+            }
+            let (start_rva, end_rva) = match function.end_rva {
+                Some(end_rva) if end_rva > function.start_rva => (function.start_rva, end_rva),
+                _ => continue,
+            };
+            let first = max_end_rvas.partition_point(|max_end_rva| *max_end_rva <= start_rva);
+            let last = runtime_functions.partition_point(|entry| entry.begin_rva < end_rva);
+            let overlapping = runtime_functions[first..last.max(first)]
+                .iter()
+                .filter(|entry| entry.end_rva > start_rva);
+            let range = overlapping.fold(None, |range: Option<(u32, u32)>, entry| {
+                Some(match range {
+                    Some((begin, end)) => (begin.min(entry.begin_rva), end.max(entry.end_rva)),
+                    None => (entry.begin_rva, entry.end_rva),
+                })
+            });
+            match range {
+                None => issues.push(UnwindIssue::MissingUnwindInfo {
+                    name: function.name,
+                    start_rva,
+                    end_rva,
+                }),
+                Some((unwind_begin_rva, unwind_end_rva))
+                    if (unwind_begin_rva, unwind_end_rva) != (start_rva, end_rva) =>
+                {
+                    issues.push(UnwindIssue::MismatchedRange {
+                        name: function.name,
+                        start_rva,
+                        end_rva,
+                        unwind_begin_rva,
+                        unwind_end_rva,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        for entry in runtime_functions {
+            if let Ok(None) = self.find_function_at_rva(entry.begin_rva, false) {
+                issues.push(UnwindIssue::UnwindInfoWithoutFunction(entry));
+            }
+        }
+        issues
+    }
+}
@@ -0,0 +1,2985 @@
+use std::{
+    cell::RefCell,
+    convert::TryInto,
+    error::Error,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+use pdb_addr2line::{
+    maybe_owned::MaybeOwned, parse_function_name, pdb, pdb::FallibleIterator, CacheSnapshot,
+    CancellationToken, Capabilities, Context, ContextFlags, ContextPdbData, Diagnostic, FileOffset,
+    FrameBaseRegister, FrameFlags, FrameTemplate, FunctionFilter, FunctionNameParts,
+    FunctionSourceFile, GeneratedFunctionKind, HelperFunctionKind, InitializerKind,
+    InlinedCodeSize, Language, LookupOptions, ModuleProvider, NonUtf8Handling, PaddingGap,
+    Progress, Provenance, RuntimeFunction, Rva32, StringArena, StringInterner, TypeFormatter,
+    UnwindIssue, Va64, ValidationIssue,
+};
+
+/// Returns the full path to the specified fixture.
+fn fixture<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut full_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    full_path.push("tests");
+    full_path.push("fixtures");
+
+    let path = path.as_ref();
+    full_path.push(path);
+
+    assert!(
+        full_path.exists(),
+        "Fixture does not exist: {}",
+        path.display()
+    );
+
+    full_path
+}
+
+#[test]
+fn test_function_iter_seek_and_reverse() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let forward: Vec<u32> = context.functions().map(|f| f.start_rva).collect();
+    let mut backward: Vec<u32> = context.functions().rev().map(|f| f.start_rva).collect();
+    backward.reverse();
+    assert_eq!(forward, backward);
+
+    let mut iter = context.functions();
+    iter.skip_to_rva(Rva32(0x1180));
+    assert_eq!(iter.next().map(|f| f.start_rva), Some(0x1180));
+    iter.skip_to_rva(Rva32(0x1121));
+    assert_eq!(iter.next().map(|f| f.start_rva), Some(0x1180));
+    iter.skip_to_rva(Rva32(0x1000));
+    assert_eq!(iter.next().map(|f| f.start_rva), Some(0x1000));
+
+    let last = forward.last().copied();
+    let mut iter = context.functions();
+    assert_eq!(iter.next_back().map(|f| f.start_rva), last);
+    iter.skip_to_rva(Rva32(u32::MAX));
+    assert!(iter.next().is_none());
+
+    // Every function has an end address or an estimate of it.
+    for function in context.functions() {
+        let estimated_end_rva = function.estimated_end_rva.unwrap();
+        assert!(estimated_end_rva > function.start_rva);
+        if let Some(end_rva) = function.end_rva {
+            assert_eq!(estimated_end_rva, end_rva);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_offsets() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let probe = 0x1014;
+    let function_frames = context.find_frames_at(Rva32(probe))?.unwrap();
+    assert_eq!(function_frames.frames.len(), 4);
+    for frame in &function_frames.frames {
+        assert_eq!(frame.start_rva + frame.offset, probe);
+        if let Some(end_rva) = frame.end_rva {
+            assert!(probe < end_rva);
+        }
+    }
+    let outer_frame = function_frames.frames.last().unwrap();
+    assert_eq!(outer_frame.start_rva, function_frames.start_rva);
+    assert_eq!(outer_frame.offset, 4);
+
+    Ok(())
+}
+
+#[test]
+fn test_file_checksums() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+
+    let context = data.make_context()?;
+    let function_frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert!(function_frames
+        .frames
+        .iter()
+        .all(|frame| frame.file_checksum.is_none()));
+
+    let context = data.make_context_with_flags(Default::default(), ContextFlags::FILE_CHECKSUMS)?;
+    let function_frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert!(function_frames
+        .frames
+        .iter()
+        .all(|frame| frame.file_checksum.is_some()));
+
+    Ok(())
+}
+
+#[test]
+fn test_parameter_names() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+
+    let context = data.make_context()?;
+    assert_eq!(
+        context.find_function_at(Rva32(0x1000))?.unwrap().name.as_deref(),
+        Some("google_breakpad::CrashGenerationClient::RequestDump(_EXCEPTION_POINTERS*, MDRawAssertionInfo*)")
+    );
+
+    let context =
+        data.make_context_with_flags(Default::default(), ContextFlags::PARAMETER_NAMES)?;
+    assert_eq!(
+        context.find_function_at(Rva32(0x1000))?.unwrap().name.as_deref(),
+        Some("google_breakpad::CrashGenerationClient::RequestDump(_EXCEPTION_POINTERS* ex_info, MDRawAssertionInfo* assert_info)")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_memory_usage() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let before = context.memory_usage();
+    assert_eq!(before.module_streams, 0);
+    assert_eq!(before.line_tables, 0);
+
+    context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let after = context.memory_usage();
+    assert!(after.module_streams > 0);
+    assert!(after.line_tables > 0);
+    assert!(after.names > 0);
+    assert!(after.inline_ranges > 0);
+    assert!(after.total() > before.total());
+
+    Ok(())
+}
+
+#[test]
+fn test_memory_budget() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let unlimited_context = data.make_context()?;
+    let mut budgeted_context = data.make_context()?;
+    budgeted_context.set_memory_budget(Some(0));
+    assert_eq!(budgeted_context.memory_budget(), Some(0));
+
+    let addresses: Vec<u32> = unlimited_context.functions().map(|f| f.start_rva).collect();
+    for &address in &addresses {
+        let expected = unlimited_context.find_frames_at(Rva32(address))?;
+        let actual = budgeted_context.find_frames_at(Rva32(address))?;
+        let summarize = |frames: Option<pdb_addr2line::FunctionFrames>| {
+            frames.map(|frames| {
+                frames
+                    .frames
+                    .iter()
+                    .map(|frame| (frame.function.clone(), frame.line))
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(summarize(actual), summarize(expected));
+    }
+
+    let unlimited = unlimited_context.memory_usage();
+    let budgeted = budgeted_context.memory_usage();
+    assert!(budgeted.procedures < unlimited.procedures);
+    assert!(budgeted.line_tables < unlimited.line_tables);
+
+    // Handle lookups are subject to the budget too.
+    let mut handle_context = data.make_context()?;
+    handle_context.set_memory_budget(Some(0));
+    let handles: Vec<_> = addresses
+        .iter()
+        .map(|&address| handle_context.lookup_function_handle(Rva32(address)))
+        .collect();
+    assert!(handle_context.memory_usage().procedures <= budgeted.procedures);
+    for (&address, handle) in addresses.iter().zip(&handles) {
+        let frames = match handle {
+            Some(handle) => handle_context.frames_for(handle, Rva32(address))?,
+            None => None,
+        };
+        assert_eq!(
+            frames.map(|frames| frames.frames.len()),
+            unlimited_context
+                .find_frames_at(Rva32(address))?
+                .map(|frames| frames.frames.len())
+        );
+    }
+    let handle_budgeted = handle_context.memory_usage();
+    assert!(handle_budgeted.procedures < unlimited.procedures);
+    assert!(handle_budgeted.line_tables < unlimited.line_tables);
+
+    Ok(())
+}
+
+#[test]
+fn test_lookup_by_section_offset() -> Result<(), Box<dyn Error>> {
+    let mut pdb = pdb::PDB::open(std::fs::File::open(fixture("mozglue.pdb"))?)?;
+    let offset = pdb::Rva(0x1014)
+        .to_internal_offset(&pdb.address_map()?)
+        .unwrap();
+
+    let data = ContextPdbData::try_from_pdb(pdb)?;
+    let context = data.make_context()?;
+    let by_rva = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let by_offset = context.find_frames_at_offset(offset)?.unwrap();
+    assert_eq!(by_offset.start_rva, by_rva.start_rva);
+    assert_eq!(by_offset.frames.len(), by_rva.frames.len());
+    assert_eq!(
+        by_offset.frames[0].function.as_deref(),
+        by_rva.frames[0].function.as_deref()
+    );
+
+    let function = context.find_function_at_offset(offset)?.unwrap();
+    assert_eq!(function.start_rva, by_rva.start_rva);
+
+    Ok(())
+}
+
+#[test]
+fn test_address_translation() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    // crash.pdb has no OMAP tables, so original and final rvas are the same.
+    assert_eq!(context.original_rva_for_rva(Rva32(0x1014)), Some(0x1014));
+    assert_eq!(context.rva_for_original_rva(0x1014), Some(Rva32(0x1014)));
+
+    let offset = context.offset_for_rva(Rva32(0x1014)).unwrap();
+    assert_eq!(context.rva_for_offset(offset), Some(Rva32(0x1014)));
+    // The address map is the one that the context uses.
+    assert_eq!(
+        pdb::Rva(0x1014).to_internal_offset(context.address_map()),
+        Some(offset)
+    );
+    assert_eq!(offset.to_rva(context.address_map()), Some(pdb::Rva(0x1014)));
+
+    // Lookup results have the start address in both forms.
+    let function = context.find_function_at(Rva32(0x1014))?.unwrap();
+    let start_offset = function.start_offset.unwrap();
+    assert_eq!(
+        context.rva_for_offset(start_offset),
+        Some(Rva32(function.start_rva))
+    );
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(frames.start_offset, Some(start_offset));
+    // Public symbols too.
+    let frames = context.find_frames_at(Rva32(0x3800))?.unwrap();
+    assert_eq!(
+        frames.start_offset,
+        context.offset_for_rva(Rva32(frames.start_rva))
+    );
+
+    // Typed addresses.
+    let rva = Va64(0x4000_1014).to_rva(0x4000_0000).unwrap();
+    assert_eq!(rva, Rva32(0x1014));
+    assert_eq!(rva.to_va(0x4000_0000), Va64(0x4000_1014));
+    assert_eq!(Va64(0x1000).to_rva(0x4000_0000), None);
+    assert_eq!(
+        context.find_function_at(rva)?.unwrap().start_rva,
+        function.start_rva
+    );
+    assert_eq!(
+        context.find_frames_at(rva)?.unwrap().start_offset,
+        Some(start_offset)
+    );
+    let file_offset = context.file_offset_for_rva(rva).unwrap();
+    assert_ne!(file_offset, FileOffset(0x1014));
+    assert_eq!(context.rva_for_file_offset(file_offset), Some(rva));
+    assert_eq!(context.rva_for_file_offset(FileOffset(0)), None);
+    assert_eq!(context.rva_for_file_offset(FileOffset(u32::MAX)), None);
+
+    // The deprecated u32 entry points forward to the typed ones.
+    #[allow(deprecated)]
+    {
+        assert_eq!(
+            context.find_function(0x1014)?.unwrap().start_rva,
+            function.start_rva
+        );
+        assert_eq!(
+            context.find_frames(0x1014)?.unwrap().start_offset,
+            Some(start_offset)
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_optimization_info() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let frames = context.find_frames_at(Rva32(0x1000))?.unwrap();
+    assert_eq!(frames.is_optimized, Some(true));
+    let frame_flags = frames.frame_flags.unwrap();
+    assert!(frame_flags.contains(FrameFlags::OPTIMIZED_FOR_SPEED | FrameFlags::GS_CHECK));
+    assert!(!frame_flags.contains(FrameFlags::HAS_INLINE_ASM));
+
+    Ok(())
+}
+
+#[test]
+fn test_incremental_linking_thunks() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+    context.set_flags(ContextFlags::RESOLVE_INCREMENTAL_LINKING_THUNKS);
+
+    // crash.pdb was not linked incrementally, so nothing is redirected.
+    assert_eq!(
+        context.incremental_linking_thunk_target(Rva32(0x1000)),
+        None
+    );
+    assert_eq!(context.trampoline_at(Rva32(0x1000)), None);
+    let function = context.find_function_at(Rva32(0x1010))?.unwrap();
+    assert_eq!(function.start_rva, 0x1000);
+    assert_eq!(function.provenance, Provenance::Procedure);
+
+    Ok(())
+}
+
+#[test]
+fn test_folded_functions() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+
+    let mut names = context.folded_function_names(Rva32(0x2eb6))?;
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "??1bad_alloc@std@@UAE@XZ".to_string(),
+            "??1bad_array_new_length@std@@UAE@XZ".to_string(),
+        ]
+    );
+    assert_eq!(context.folded_function_names(Rva32(0x1000))?.len(), 1);
+
+    let function = context.find_function_at(Rva32(0x2eb6))?.unwrap();
+    assert_eq!(function.other_folded_functions, 0);
+
+    context.set_flags(ContextFlags::FOLDED_FUNCTION_COUNTS);
+    let function = context.find_function_at(Rva32(0x2eb6))?.unwrap();
+    assert_eq!(function.other_folded_functions, 1);
+    let frames = context.find_frames_at(Rva32(0x2ec7))?.unwrap();
+    assert_eq!(frames.other_folded_functions, 5);
+
+    Ok(())
+}
+
+#[test]
+fn test_portable_pdb_detection() -> Result<(), Box<dyn Error>> {
+    let mut portable_pdb = b"BSJB".to_vec();
+    portable_pdb.resize(4096, 0);
+    let result = ContextPdbData::open(std::io::Cursor::new(portable_pdb));
+    assert!(matches!(result, Err(pdb_addr2line::Error::PortablePdb)));
+
+    let data = ContextPdbData::open(std::fs::File::open(fixture("crash.pdb"))?)?;
+    let context = data.make_context()?;
+    assert!(context.find_function_at(Rva32(0x1000))?.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_debug_id_verification() -> Result<(), Box<dyn Error>> {
+    use pdb_addr2line::{uuid::Uuid, AgeMismatchPolicy, DebugIdMatch};
+
+    let mut pdb = pdb::PDB::open(std::fs::File::open(fixture("crash.pdb"))?)?;
+    let guid = pdb.pdb_information()?.guid;
+    let age = pdb.debug_information()?.age().unwrap();
+    let mut data = ContextPdbData::try_from_pdb(pdb)?;
+
+    assert!(matches!(
+        data.verify_debug_id(Uuid::nil(), age, AgeMismatchPolicy::BestEffort),
+        Err(pdb_addr2line::Error::GuidMismatch { .. })
+    ));
+    assert!(matches!(
+        data.verify_debug_id(guid, age + 1, AgeMismatchPolicy::Reject),
+        Err(pdb_addr2line::Error::AgeMismatch { .. })
+    ));
+    assert_eq!(
+        data.verify_debug_id(guid, age, AgeMismatchPolicy::Reject)?,
+        DebugIdMatch::Verified
+    );
+    let context = data.make_context()?;
+    assert!(!context.find_frames_at(Rva32(0x1000))?.unwrap().unverified);
+
+    assert_eq!(
+        data.verify_debug_id(guid, age + 1, AgeMismatchPolicy::BestEffort)?,
+        DebugIdMatch::AgeMismatch {
+            expected: age + 1,
+            actual: age
+        }
+    );
+    let context = data.make_context()?;
+    assert!(context.debug_id_match().is_unverified());
+    assert!(context.find_frames_at(Rva32(0x1000))?.unwrap().unverified);
+    assert!(context.find_function_at(Rva32(0x1000))?.unwrap().unverified);
+
+    Ok(())
+}
+
+#[test]
+fn test_map_file() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let mut map = Vec::new();
+    context.write_map_file(&mut map)?;
+    let map = String::from_utf8(map)?;
+    assert!(map
+        .lines()
+        .any(|line| line.contains(".text") && line.ends_with("CODE")));
+    let request_dump = map
+        .lines()
+        .find(|line| line.contains("RequestDump"))
+        .unwrap();
+    assert!(request_dump.starts_with(" 0001:00000000  00000114H"));
+
+    Ok(())
+}
+
+#[test]
+fn test_absolute_symbols() -> Result<(), Box<dyn Error>> {
+    for name in ["crash.pdb", "mozglue.pdb"] {
+        let file = std::fs::File::open(fixture(name))?;
+        let mut pdb = pdb::PDB::open(file)?;
+
+        // Neither fixture has public symbols in section 0, so there are no absolute
+        // symbols to report.
+        let global_symbols = pdb.global_symbols()?;
+        let mut symbols = global_symbols.iter();
+        while let Some(symbol) = symbols.next()? {
+            if let Ok(pdb::SymbolData::Public(public)) = symbol.parse() {
+                assert_ne!(public.offset.section, 0);
+            }
+        }
+
+        let data = ContextPdbData::try_from_pdb(pdb)?;
+        let context = data.make_context()?;
+        assert_eq!(context.absolute_symbols(), &[]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_all_inlinees() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let inlinees = context.all_inlinees()?;
+    assert!(!inlinees.is_empty());
+    assert!(inlinees
+        .windows(2)
+        .all(|pair| pair[0].inlined_bytes >= pair[1].inlined_bytes));
+
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let innermost = frames.frames[0].function.clone();
+    let summary = inlinees
+        .iter()
+        .find(|summary| summary.name == innermost)
+        .unwrap();
+    assert!(summary.call_sites >= 1);
+    assert!(summary.inlined_bytes > 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_functions_in_file() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let functions = context.functions_in_file(
+        "C:/Projects/breakpad-tools/deps/breakpad/src/client/windows/common/../crash_generation/crash_generation_client.cc",
+    )?;
+    assert_eq!(functions.len(), 1);
+    let function = &functions[0];
+    assert_eq!(
+        function.name.as_deref(),
+        Some("google_breakpad::CrashGenerationClient::RequestDump(_EXCEPTION_POINTERS*, MDRawAssertionInfo*)")
+    );
+    assert_eq!((function.start_rva, function.end_rva), (0x1000, 0x1114));
+    assert_eq!(function.ranges, vec![(0x1000, 0x1114)]);
+
+    assert!(context.functions_in_file("c:\\nonexistent.cc")?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_module_for_address() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let (_, module_name) = context.module_for_address(Rva32(0x1010)).unwrap();
+    assert!(module_name.ends_with("crash_generation_client.obj"));
+    assert!(context.module_for_address(Rva32(0xffff_0000)).is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_non_utf8_handling() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+    context.set_non_utf8_handling(NonUtf8Handling::Error, NonUtf8Handling::Error);
+
+    // All names in the fixture are valid UTF-8, so the strict mode changes nothing.
+    let frames = context.find_frames_at(Rva32(0x1010))?.unwrap();
+    let frame = &frames.frames[0];
+    assert_eq!(
+        frame.raw_function.map(|name| name.to_string()).as_deref(),
+        Some("google_breakpad::CrashGenerationClient::RequestDump")
+    );
+    assert!(frame.file.is_some());
+    assert_eq!(
+        frame.file_path(),
+        frame.file.as_deref().map(std::path::PathBuf::from)
+    );
+    assert!(context
+        .find_function_at(Rva32(0x1010))?
+        .unwrap()
+        .name
+        .is_some());
+
+    // Patch the procedure name "RequestDump" in a copy of the fixture, keeping its
+    // length.
+    let patched_pdb = |replacement: &[u8]| -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut bytes = std::fs::read(fixture("crash.pdb"))?;
+        let name = b"google_breakpad::CrashGenerationClient::RequestDump\0";
+        let mut patched_count = 0;
+        for pos in 0..bytes.len() - name.len() {
+            if bytes[pos..].starts_with(name) {
+                let start = pos + name.len() - 12;
+                bytes[start..start + 3].copy_from_slice(replacement);
+                patched_count += 1;
+            }
+        }
+        assert_ne!(patched_count, 0);
+        Ok(bytes)
+    };
+
+    // A name which really contains U+FFFD is valid UTF-8.
+    let bytes = patched_pdb("\u{fffd}".as_bytes())?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(std::io::Cursor::new(bytes))?)?;
+    let mut context = data.make_context()?;
+    context.set_non_utf8_handling(NonUtf8Handling::Error, NonUtf8Handling::Error);
+    let frames = context.find_frames_at(Rva32(0x1010))?.unwrap();
+    assert_eq!(
+        frames.frames[0].function.as_deref(),
+        Some("google_breakpad::CrashGenerationClient::\u{fffd}uestDump(_EXCEPTION_POINTERS*, MDRawAssertionInfo*)")
+    );
+    assert!(context
+        .find_function_at(Rva32(0x1010))?
+        .unwrap()
+        .name
+        .is_some());
+
+    // Invalid bytes are only available raw.
+    let bytes = patched_pdb(b"\xffeq")?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(std::io::Cursor::new(bytes))?)?;
+    let mut context = data.make_context()?;
+    context.set_non_utf8_handling(NonUtf8Handling::Error, NonUtf8Handling::Error);
+    assert!(matches!(
+        context.find_frames_at(Rva32(0x1010)),
+        Err(pdb_addr2line::Error::InvalidUtf8("symbol name", _))
+    ));
+    assert!(matches!(
+        context.find_function_at(Rva32(0x1010)),
+        Err(pdb_addr2line::Error::InvalidUtf8("symbol name", _))
+    ));
+    context.set_non_utf8_handling(NonUtf8Handling::Raw, NonUtf8Handling::Raw);
+    let frames = context.find_frames_at(Rva32(0x1010))?.unwrap();
+    assert_eq!(frames.frames[0].function, None);
+    assert_eq!(
+        frames.frames[0].raw_function.map(|name| name.as_bytes()),
+        Some(&b"google_breakpad::CrashGenerationClient::\xffequestDump"[..])
+    );
+    assert_eq!(context.find_function_at(Rva32(0x1010))?.unwrap().name, None);
+    context.set_non_utf8_handling(NonUtf8Handling::Lossy, NonUtf8Handling::Lossy);
+    assert_eq!(
+        context
+            .find_function_at(Rva32(0x1010))?
+            .unwrap()
+            .name
+            .as_deref(),
+        Some("google_breakpad::CrashGenerationClient::\u{fffd}equestDump(_EXCEPTION_POINTERS*, MDRawAssertionInfo*)")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_timings() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context_with_flags(Default::default(), ContextFlags::TIMINGS)?;
+    assert_eq!(context.timings().stream_parse, data.timings().stream_parse);
+    assert_eq!(context.timings().formatting, Duration::ZERO);
+
+    context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let timings = context.timings();
+    assert!(timings.module_symbol_walk > Duration::ZERO);
+    assert!(timings.formatting > Duration::ZERO);
+    assert!(timings.total() >= timings.publics_scan + timings.formatting);
+
+    Ok(())
+}
+
+#[test]
+fn test_function_handle() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let handle = context.lookup_function_handle(Rva32(0x1014)).unwrap();
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(handle.start_rva(), frames.start_rva);
+    assert_eq!(handle.end_rva(), frames.end_rva);
+
+    let end_rva = handle.end_rva().unwrap();
+    for probe in handle.start_rva()..end_rva {
+        let expected = context.find_frames_at(Rva32(probe))?.unwrap();
+        let actual = context.frames_for(&handle, Rva32(probe))?.unwrap();
+        assert_eq!(actual.frames.len(), expected.frames.len());
+        for (actual, expected) in actual.frames.iter().zip(&expected.frames) {
+            assert_eq!(actual.function, expected.function);
+            assert_eq!(actual.line, expected.line);
+        }
+    }
+    assert!(context.frames_for(&handle, Rva32(end_rva))?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_language() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let (outer, inlined) = frames.frames.split_last().unwrap();
+    assert_eq!(outer.language, Some(Language::Cpp));
+    assert!(!inlined.is_empty());
+    assert!(inlined.iter().all(|frame| frame.language.is_none()));
+
+    Ok(())
+}
+
+#[test]
+fn test_functions_matching() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let all_count = context.functions().count();
+
+    let filter = FunctionFilter {
+        module_patterns: vec!["*/CRASH_GENERATION_CLIENT.obj".to_string()],
+        ..Default::default()
+    };
+    let functions: Vec<_> = context.functions_matching(&filter).collect();
+    assert!(!functions.is_empty());
+    assert!(functions.len() < all_count);
+    assert!(functions.iter().any(|f| f.start_rva == 0x1000));
+    for function in &functions {
+        let (_, module_name) = context
+            .module_for_address(Rva32(function.start_rva))
+            .unwrap();
+        assert!(module_name.ends_with("crash_generation_client.obj"));
+    }
+
+    let filter = FunctionFilter {
+        languages: vec![Language::Rust],
+        ..Default::default()
+    };
+    assert_eq!(context.functions_matching(&filter).count(), 0);
+    assert_eq!(
+        context
+            .functions_matching(&FunctionFilter::default())
+            .count(),
+        all_count
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_functions_matching_shards() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let all_rvas: Vec<u32> = context.functions().map(|f| f.start_rva).collect();
+
+    // Each thread uses its own ContextPdbData.
+    let shard_count = 3;
+    let mut shard_rvas: Vec<u32> = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..shard_count)
+            .map(|shard_index| {
+                scope.spawn(move || {
+                    let file = std::fs::File::open(fixture("mozglue.pdb")).unwrap();
+                    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file).unwrap()).unwrap();
+                    let context = data.make_context().unwrap();
+                    let filter = FunctionFilter {
+                        shard: Some((shard_index, shard_count)),
+                        ..Default::default()
+                    };
+                    let rvas: Vec<u32> = context
+                        .functions_matching(&filter)
+                        .map(|f| f.start_rva)
+                        .collect();
+                    assert!(!rvas.is_empty());
+                    rvas
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect()
+    });
+    shard_rvas.sort_unstable();
+    assert_eq!(shard_rvas, all_rvas);
+
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "shard index 3 is out of range for 3 shards")]
+fn test_functions_matching_shard_out_of_range() {
+    let file = std::fs::File::open(fixture("mozglue.pdb")).unwrap();
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file).unwrap()).unwrap();
+    let context = data.make_context().unwrap();
+    let filter = FunctionFilter {
+        shard: Some((3, 3)),
+        ..Default::default()
+    };
+    let _ = context.functions_matching(&filter);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_functions() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let expected: Vec<_> = context.functions().collect();
+
+    let functions = pdb_addr2line::par_functions(
+        || std::fs::File::open(fixture("mozglue.pdb")),
+        Default::default(),
+        ContextFlags::default(),
+    )?;
+    assert_eq!(functions.len(), expected.len());
+    for (id, (function, expected)) in functions.iter().zip(&expected).enumerate() {
+        assert_eq!(function.start_rva, expected.start_rva);
+        assert_eq!(function.end_rva, expected.end_rva);
+        assert_eq!(function.estimated_end_rva, expected.estimated_end_rva);
+        assert_eq!(function.name, expected.name);
+        assert_eq!(function.id, id as u32);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_format_names() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let plain_context = data.make_context()?;
+    let expected: Vec<_> = plain_context.functions().map(|f| f.name).collect();
+
+    let context = data.make_context()?;
+    assert_eq!(context.memory_usage().names, 0);
+    context.par_format_names(|| std::fs::File::open(fixture("mozglue.pdb")))?;
+    assert!(context.memory_usage().names > 0);
+    let names: Vec<_> = context.functions().map(|f| f.name).collect();
+    assert_eq!(names, expected);
+
+    // The names must come from the same PDB.
+    let result = context.par_format_names(|| std::fs::File::open(fixture("crash.pdb")));
+    assert!(matches!(
+        result,
+        Err(pdb_addr2line::Error::GuidMismatch { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_raw_file_names() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let cpuid_h = "/builds/worker/fetches/clang/lib/clang/12.0.1/include/cpuid.h";
+    let sse_cpp = "/builds/worker/checkouts/gecko/mozglue/build/SSE.cpp";
+
+    let context = data.make_context()?;
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let innermost = &frames.frames[0];
+    assert_eq!(innermost.file_index, Some(pdb::FileIndex(0x18)));
+    assert_eq!(innermost.raw_file.unwrap().as_bytes(), cpuid_h.as_bytes());
+    assert_eq!(innermost.file.as_deref(), Some(cpuid_h));
+    let outer = frames.frames.last().unwrap();
+    assert_eq!(outer.file_index, Some(pdb::FileIndex(0)));
+    assert_eq!(outer.raw_file.unwrap().as_bytes(), sse_cpp.as_bytes());
+
+    // With RAW_FILE_NAMES, only the index and the raw bytes are filled in.
+    let context = data.make_context_with_flags(Default::default(), ContextFlags::RAW_FILE_NAMES)?;
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let innermost = &frames.frames[0];
+    assert_eq!(innermost.file, None);
+    assert_eq!(innermost.file_index, Some(pdb::FileIndex(0x18)));
+    assert_eq!(innermost.raw_file.unwrap().as_bytes(), cpuid_h.as_bytes());
+    assert_eq!(innermost.file_name().as_deref(), Some(cpuid_h));
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_template() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let frames = context.find_frames_at(Rva32(0x1010))?.unwrap();
+    let frame = &frames.frames[0];
+
+    let template = FrameTemplate::parse("{line} (+{offset:#x}) {rva:#010x} {end_rva:X} {{}}")?;
+    assert_eq!(template.format(frame), "323 (+0x10) 0x00001010 1114 {}");
+    let template = FrameTemplate::parse("[{line:5}] [{function:4}]")?;
+    let function_start = "[  323] [google_breakpad::";
+    assert!(template.format(frame).starts_with(function_start));
+
+    assert!(FrameTemplate::parse("{function").is_err());
+    assert!(FrameTemplate::parse("{nonexistent}").is_err());
+    assert!(FrameTemplate::parse("{file:x}").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_lookup_options() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let all_frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    assert_eq!(all_frames.len(), 4);
+
+    let options = LookupOptions {
+        inline_frames: false,
+        ..Default::default()
+    };
+    let frames = context
+        .find_frames_with_options(Rva32(0x1014), &options)?
+        .unwrap();
+    assert_eq!(frames.frames.len(), 1);
+    assert_eq!(frames.frames[0].function, all_frames[3].function);
+
+    let options = LookupOptions {
+        max_inline_depth: Some(1),
+        ..Default::default()
+    };
+    let frames = context
+        .find_frames_with_options(Rva32(0x1014), &options)?
+        .unwrap();
+    assert_eq!(frames.frames.len(), 2);
+    assert_eq!(frames.frames[0].function, all_frames[2].function);
+
+    let mut limited_context = data.make_context()?;
+    limited_context.set_max_inline_depth(Some(1));
+    let frames = limited_context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(frames.frames.len(), 2);
+    assert_eq!(frames.frames[0].function, all_frames[2].function);
+    let options = LookupOptions {
+        max_inline_depth: Some(2),
+        ..Default::default()
+    };
+    let frames = limited_context
+        .find_frames_with_options(Rva32(0x1014), &options)?
+        .unwrap();
+    assert_eq!(frames.frames.len(), 2);
+
+    let options = LookupOptions {
+        return_address: true,
+        ..Default::default()
+    };
+    let frames = context
+        .find_frames_with_options(Rva32(0x1015), &options)?
+        .unwrap();
+    assert_eq!(frames.frames.len(), 4);
+    assert_eq!(frames.frames[0].offset, all_frames[0].offset);
+
+    // _GLOBAL__sub_I_SSE.cpp ends at 0x115c, and no function covers that address.
+    assert!(context.find_frames_at(Rva32(0x115c))?.is_none());
+    let options = LookupOptions {
+        inclusive_end: true,
+        ..Default::default()
+    };
+    let frames = context
+        .find_frames_with_options(Rva32(0x115c), &options)?
+        .unwrap();
+    assert_eq!(frames.start_rva, 0x1010);
+    let frames = context
+        .find_frames_with_options(Rva32(0x1014), &options)?
+        .unwrap();
+    assert_eq!(frames.frames.len(), 4);
+    assert_eq!(frames.frames[0].offset, all_frames[0].offset);
+
+    let options = LookupOptions {
+        demangle: false,
+        ..Default::default()
+    };
+    let frames = context
+        .find_frames_with_options(Rva32(0x1014), &options)?
+        .unwrap();
+    assert_eq!(
+        frames.frames[3].function.as_deref(),
+        Some("_GLOBAL__sub_I_SSE.cpp")
+    );
+
+    // This address is only covered by a public symbol.
+    assert!(context.find_frames_at(Rva32(0x1d460))?.is_some());
+    let options = LookupOptions {
+        nearest_symbol_fallback: false,
+        ..Default::default()
+    };
+    assert!(context
+        .find_frames_with_options(Rva32(0x1d460), &options)?
+        .is_none());
+
+    Ok(())
+}
+
+/// A module provider for which reading any module fails.
+struct FailingModuleProvider;
+
+impl<'s> ModuleProvider<'s> for FailingModuleProvider {
+    fn get_module_info(
+        &self,
+        _module_index: usize,
+        _module: &pdb::Module,
+    ) -> Result<Option<&pdb::ModuleInfo<'s>>, pdb::Error> {
+        Err(pdb::Error::UnimplementedFeature("reading modules"))
+    }
+}
+
+#[test]
+fn test_too_many_sections() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let mut pdb = pdb::PDB::open(file)?;
+    let mut sections = pdb.sections()?.unwrap_or_default();
+    let address_map = pdb.address_map()?;
+    let global_symbols = pdb.global_symbols()?;
+    let debug_info = pdb.debug_information()?;
+    let type_info = pdb.type_information()?;
+    let id_info = pdb.id_information()?;
+    let modules = debug_info.modules()?.collect::<Vec<_>>()?;
+    let type_formatter = TypeFormatter::new_from_parts(
+        &data,
+        modules,
+        &debug_info,
+        &type_info,
+        &id_info,
+        None,
+        Default::default(),
+    )?;
+
+    // Section indexes are 16-bit, so the 65536th section can't be addressed.
+    sections.resize(0x10000, Default::default());
+    let result = Context::new_from_parts(
+        &data,
+        &sections,
+        &address_map,
+        &global_symbols,
+        None,
+        &debug_info,
+        MaybeOwned::Owned(type_formatter),
+    );
+    assert!(matches!(
+        result,
+        Err(pdb_addr2line::Error::TooManySections(0x10000))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_unresolved_types_shared_formatter() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let mut pdb = pdb::PDB::open(file)?;
+    let sections = pdb.sections()?.unwrap_or_default();
+    let address_map = pdb.address_map()?;
+    let global_symbols = pdb.global_symbols()?;
+    let debug_info = pdb.debug_information()?;
+    let type_info = pdb.type_information()?;
+    let id_info = pdb.id_information()?;
+    let modules = debug_info.modules()?.collect::<Vec<_>>()?;
+    let type_formatter = TypeFormatter::new_from_parts(
+        &data,
+        modules,
+        &debug_info,
+        &type_info,
+        &id_info,
+        None,
+        Default::default(),
+    )?;
+    let mut context = Context::new_from_parts(
+        &data,
+        &sections,
+        &address_map,
+        &global_symbols,
+        None,
+        &debug_info,
+        MaybeOwned::Borrowed(&type_formatter),
+    )?;
+
+    let diagnostics: Rc<RefCell<Vec<Diagnostic>>> = Rc::default();
+    let sink = diagnostics.clone();
+    context.set_diagnostics_callback(Some(Box::new(move |diagnostic| {
+        sink.borrow_mut().push(diagnostic.clone())
+    })));
+
+    // Another user of the shared formatter runs into a missing record in module 4.
+    type_formatter.format_id(4, pdb::IdIndex(0xfffff))?;
+    // The next lookup of the context reports it, for the module it came from.
+    let (module_index, _) = context.module_for_address(Rva32(0x1010)).unwrap();
+    assert_eq!(module_index, 2);
+    context.find_frames_at(Rva32(0x1010))?;
+
+    let diagnostics = diagnostics.borrow();
+    let unresolved: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| matches!(diagnostic, Diagnostic::UnresolvedTypes { .. }))
+        .collect();
+    assert_eq!(unresolved.len(), 1);
+    assert!(matches!(
+        unresolved[0],
+        Diagnostic::UnresolvedTypes { module_index: 4, indexes } if indexes == &[0xfffff]
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_missing_string_table() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let mut pdb = pdb::PDB::open(file)?;
+    let sections = pdb.sections()?.unwrap_or_default();
+    let address_map = pdb.address_map()?;
+    let global_symbols = pdb.global_symbols()?;
+    let debug_info = pdb.debug_information()?;
+    let type_info = pdb.type_information()?;
+    let id_info = pdb.id_information()?;
+    let modules = debug_info.modules()?.collect::<Vec<_>>()?;
+    let type_formatter = TypeFormatter::new_from_parts(
+        &data,
+        modules,
+        &debug_info,
+        &type_info,
+        &id_info,
+        None,
+        Default::default(),
+    )?;
+    let mut context = Context::new_from_parts(
+        &data,
+        &sections,
+        &address_map,
+        &global_symbols,
+        None,
+        &debug_info,
+        MaybeOwned::Owned(type_formatter),
+    )?;
+
+    let diagnostics: Rc<RefCell<Vec<Diagnostic>>> = Rc::default();
+    let sink = diagnostics.clone();
+    context.set_diagnostics_callback(Some(Box::new(move |diagnostic| {
+        sink.borrow_mut().push(diagnostic.clone())
+    })));
+
+    // Without the string table, the frames keep their names and lines, but have
+    // no file names.
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(frames.frames.len(), 4);
+    assert!(frames.frames.iter().all(|frame| frame.function.is_some()));
+    assert!(frames.frames.iter().all(|frame| frame.file.is_none()));
+    let expected_context = data.make_context()?;
+    let expected = expected_context.find_frames_at(Rva32(0x1014))?.unwrap();
+    for (frame, expected) in frames.frames.iter().zip(&expected.frames) {
+        assert_eq!(frame.line, expected.line);
+    }
+    context.find_frames_at(Rva32(0x1000))?;
+
+    let diagnostics = diagnostics.borrow();
+    assert_eq!(diagnostics.len(), 1);
+    assert!(matches!(diagnostics[0], Diagnostic::MissingStringTable));
+
+    Ok(())
+}
+
+#[test]
+fn test_diagnostics_callback() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let mut pdb = pdb::PDB::open(file)?;
+    let sections = pdb.sections()?.unwrap_or_default();
+    let address_map = pdb.address_map()?;
+    let global_symbols = pdb.global_symbols()?;
+    let debug_info = pdb.debug_information()?;
+    let type_info = pdb.type_information()?;
+    let id_info = pdb.id_information()?;
+    let string_table = pdb.string_table().ok();
+    let modules = debug_info.modules()?.collect::<Vec<_>>()?;
+    let type_formatter = TypeFormatter::new_from_parts(
+        &FailingModuleProvider,
+        modules,
+        &debug_info,
+        &type_info,
+        &id_info,
+        string_table.as_ref(),
+        Default::default(),
+    )?;
+    let mut context = Context::new_from_parts(
+        &FailingModuleProvider,
+        &sections,
+        &address_map,
+        &global_symbols,
+        string_table.as_ref(),
+        &debug_info,
+        MaybeOwned::Owned(type_formatter),
+    )?;
+
+    let diagnostics: Rc<RefCell<Vec<Diagnostic>>> = Rc::default();
+    let sink = diagnostics.clone();
+    context.set_diagnostics_callback(Some(Box::new(move |diagnostic| {
+        sink.borrow_mut().push(diagnostic.clone())
+    })));
+
+    // Without the module, the lookup falls back to the public symbol.
+    let frames = context.find_frames_at(Rva32(0x1010))?.unwrap();
+    assert_eq!(frames.frames[0].line, None);
+    context.find_frames_at(Rva32(0x1010))?;
+
+    let diagnostics = diagnostics.borrow();
+    assert_eq!(diagnostics.len(), 1);
+    match &diagnostics[0] {
+        Diagnostic::UnreadableModule { module_name, .. } => {
+            assert!(module_name.ends_with("crash_generation_client.obj"))
+        }
+        other => panic!("Unexpected diagnostic {:?}", other),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_find_frames_in_arena() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let arena = StringArena::new();
+
+    let expected = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let frames = context.find_frames_in(Rva32(0x1014), &arena)?.unwrap();
+    assert_eq!(frames.frames.len(), expected.frames.len());
+    for (frame, expected) in frames.frames.iter().zip(&expected.frames) {
+        assert_eq!(frame.function, expected.function.as_deref());
+        assert_eq!(frame.file, expected.file);
+    }
+    let string_count = arena.len();
+    assert_eq!(string_count, 4);
+
+    // Looking up the same function again doesn't add any strings.
+    context.find_frames_in(Rva32(0x1015), &arena)?.unwrap();
+    assert_eq!(arena.len(), string_count);
+
+    Ok(())
+}
+
+#[test]
+fn test_find_frames_interned() -> Result<(), Box<dyn Error>> {
+    let interner = StringInterner::new();
+    let mut names = Vec::new();
+    for _ in 0..2 {
+        let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+        let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+        let context = data.make_context()?;
+
+        let expected = context.find_frames_at(Rva32(0x1014))?.unwrap();
+        let frames = context
+            .find_frames_interned(Rva32(0x1014), &interner)?
+            .unwrap();
+        assert_eq!(frames.frames.len(), expected.frames.len());
+        for (frame, expected) in frames.frames.iter().zip(&expected.frames) {
+            assert_eq!(frame.function.as_deref(), expected.function.as_deref());
+            assert_eq!(frame.file, expected.file);
+        }
+        names.push(frames.frames[0].function.clone().unwrap());
+    }
+
+    // The second PDB reuses the names of the first one.
+    assert_eq!(interner.len(), 4);
+    assert!(std::sync::Arc::ptr_eq(&names[0], &names[1]));
+
+    Ok(())
+}
+
+#[test]
+fn test_named_streams() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+
+    let names = data.stream_names()?;
+    assert!(names.iter().any(|name| name == "/names"));
+
+    let stream = data
+        .named_stream("/names")?
+        .expect("the /names stream exists");
+    // The string table starts with its signature, 0xEFFEEFFE.
+    assert_eq!(&stream[..4], &[0xfe, 0xef, 0xfe, 0xef]);
+
+    assert!(data.named_stream("/no-such-stream")?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_cross_check_publics() -> Result<(), Box<dyn Error>> {
+    for name in ["crash.pdb", "mozglue.pdb"] {
+        let file = std::fs::File::open(fixture(name))?;
+        let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+        let mut context =
+            data.make_context_with_flags(Default::default(), ContextFlags::CROSS_CHECK_PUBLICS)?;
+        let mismatches = Rc::new(RefCell::new(0));
+        let sink = mismatches.clone();
+        context.set_diagnostics_callback(Some(Box::new(move |diagnostic| {
+            if let Diagnostic::PublicSymbolMismatch { .. } = diagnostic {
+                *sink.borrow_mut() += 1;
+            }
+        })));
+
+        let start_rvas: Vec<u32> = context.functions().map(|f| f.start_rva).collect();
+        for rva in start_rvas {
+            context.find_frames_at(Rva32(rva))?;
+        }
+        // The procedures and the public symbols of the fixtures agree everywhere.
+        assert_eq!(*mismatches.borrow(), 0, "{}", name);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cache_snapshot() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let expected = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let bytes = context.export_cache()?.to_bytes();
+
+    let snapshot = CacheSnapshot::from_bytes(&bytes)?;
+    assert!(!snapshot.is_empty());
+    let context = data.make_context()?;
+    context.import_cache(&snapshot)?;
+    // The snapshot has the procedure list of the module, and a context which hasn't
+    // read the module yet passes it on.
+    assert_eq!(context.export_cache()?.to_bytes(), bytes);
+    assert!(context.memory_usage().line_tables > 0);
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(frames.frames.len(), expected.frames.len());
+    for (frame, expected_frame) in frames.frames.iter().zip(&expected.frames) {
+        assert_eq!(frame.function, expected_frame.function);
+        assert_eq!(frame.file, expected_frame.file);
+        assert_eq!(frame.line, expected_frame.line);
+    }
+    let summary =
+        |function: pdb_addr2line::Function| (function.start_rva, function.end_rva, function.name);
+    let functions: Vec<_> = context.functions().map(summary).collect();
+    let expected_functions: Vec<_> = data.make_context()?.functions().map(summary).collect();
+    assert_eq!(functions, expected_functions);
+
+    // Snapshots are only accepted with the same flags and by the same PDB.
+    let context =
+        data.make_context_with_flags(Default::default(), ContextFlags::PARAMETER_NAMES)?;
+    assert!(context.import_cache(&snapshot).is_err());
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let other_data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    assert!(other_data.make_context()?.import_cache(&snapshot).is_err());
+
+    assert!(CacheSnapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_capabilities() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    assert_eq!(
+        data.capabilities()?,
+        Capabilities {
+            pdb_version: 20000404,
+            dbi_version: 19990903,
+            has_line_info: true,
+            has_ipi: true,
+            publics_only: false,
+            is_fastlink: false,
+        }
+    );
+
+    let mut file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    assert!(!pdb_addr2line::is_portable_pdb(&mut file));
+
+    Ok(())
+}
+
+#[test]
+fn test_compact_modules() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let flags = ContextFlags::FILE_CHECKSUMS | ContextFlags::PARAMETER_NAMES;
+    let compact_context =
+        data.make_context_with_flags(Default::default(), flags | ContextFlags::COMPACT_MODULES)?;
+    let context = data.make_context_with_flags(Default::default(), flags)?;
+
+    // The compact context reads the module first, so the stream isn't kept.
+    compact_context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let usage = compact_context.memory_usage();
+    assert_eq!(usage.module_streams, 0);
+    assert!(usage.compact_modules > 0);
+
+    let summarize = |frames: Option<pdb_addr2line::FunctionFrames>| {
+        frames.map(|frames| {
+            let outer = (frames.start_rva, frames.end_rva, frames.is_optimized);
+            let frames: Vec<_> = frames
+                .frames
+                .into_iter()
+                .map(|frame| {
+                    (
+                        frame.function,
+                        frame.raw_function.map(|name| name.as_bytes().to_vec()),
+                        frame.file.map(|file| file.into_owned()),
+                        frame.file_index,
+                        frame
+                            .file_checksum
+                            .map(|checksum| format!("{:?}", checksum)),
+                        frame.start_rva,
+                        frame.end_rva,
+                        frame.line,
+                        frame.line_end,
+                    )
+                })
+                .collect();
+            (outer, frames)
+        })
+    };
+    let functions: Vec<_> = context.functions().collect();
+    assert!(!functions.is_empty());
+    for function in &functions {
+        let end_rva = function.end_rva.unwrap_or(function.start_rva + 1);
+        for address in [function.start_rva, (function.start_rva + end_rva) / 2] {
+            assert_eq!(
+                summarize(compact_context.find_frames_at(Rva32(address))?),
+                summarize(context.find_frames_at(Rva32(address))?),
+                "frames at 0x{:x}",
+                address
+            );
+        }
+    }
+    let summary =
+        |function: pdb_addr2line::Function| (function.start_rva, function.end_rva, function.name);
+    let compact_functions: Vec<_> = compact_context.functions().map(summary).collect();
+    assert_eq!(
+        compact_functions,
+        functions.into_iter().map(summary).collect::<Vec<_>>()
+    );
+
+    // A new context uses the modules which were already compacted.
+    let other_context =
+        data.make_context_with_flags(Default::default(), flags | ContextFlags::COMPACT_MODULES)?;
+    let frames = other_context.find_frames_at(Rva32(0x1014))?;
+    assert_eq!(
+        summarize(frames),
+        summarize(context.find_frames_at(Rva32(0x1014))?)
+    );
+    assert_eq!(other_context.memory_usage().module_streams, 0);
+
+    // Methods which walk the module's records read the stream.
+    let (module_index, _) = compact_context.module_for_address(Rva32(0x1014)).unwrap();
+    assert!(compact_context.line_program(module_index)?.is_some());
+    assert_eq!(
+        compact_context.all_inlinees()?.len(),
+        context.all_inlinees()?.len()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_release_module() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let (module_index, _) = context.module_for_address(Rva32(0x1014)).unwrap();
+    let usage = context.memory_usage();
+    assert!(context.release_module(module_index));
+    assert!(!context.release_module(module_index));
+    assert!(context.memory_usage().procedures < usage.procedures);
+
+    // The derived information is kept, and the module is parsed again on demand.
+    let frames_again = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(frames_again.frames.len(), frames.frames.len());
+    assert_eq!(frames_again.frames[0].function, frames.frames[0].function);
+    assert!(context.find_function_at(Rva32(0x1030))?.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_size_breakdown() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let breakdown = context.size_breakdown();
+    assert_eq!(breakdown.functions.len(), context.functions().count());
+    assert!(breakdown
+        .functions
+        .windows(2)
+        .all(|w| w[0].start_rva < w[1].start_rva));
+    assert!(breakdown.modules.windows(2).all(|w| w[0].size >= w[1].size));
+    for module in &breakdown.modules {
+        assert!(module.function_bytes <= module.size, "{:?}", module);
+    }
+
+    let function = breakdown
+        .functions
+        .iter()
+        .find(|function| function.start_rva == 0x1010)
+        .unwrap();
+    let expected = context.find_function_at(Rva32(0x1010))?.unwrap();
+    assert_eq!(
+        function.start_rva + function.size,
+        expected.end_rva.unwrap()
+    );
+    assert_eq!(function.name, expected.name);
+
+    let gaps = context.padding_gaps();
+    assert!(gaps.windows(2).all(|w| w[0].start_rva < w[1].start_rva));
+    assert!(gaps.contains(&PaddingGap {
+        module_index: breakdown.functions[0].module_index.unwrap(),
+        start_rva: 0x100d,
+        size: 3,
+    }));
+    for module in &breakdown.modules {
+        let padding_bytes: u64 = gaps
+            .iter()
+            .filter(|gap| gap.module_index == module.module_index)
+            .map(|gap| u64::from(gap.size))
+            .sum();
+        assert_eq!(module.padding_bytes, padding_bytes);
+        assert!(module.padding_bytes <= module.size, "{:?}", module);
+        assert!(
+            module.function_bytes + module.padding_bytes <= module.size,
+            "{:?}",
+            module
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_static_initializers() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let initializers = context.static_initializers()?;
+    let xcu: Vec<_> = initializers
+        .table_contributions
+        .iter()
+        .filter(|contribution| contribution.section_name == ".CRT$XCU")
+        .collect();
+    assert_eq!(xcu.len(), 7);
+    assert!(xcu.iter().all(|contribution| contribution.size == 8));
+    assert!(xcu[0].module_name.ends_with("SSE.obj"));
+
+    let destructor = initializers
+        .functions
+        .iter()
+        .find(|function| function.variable == "mozilla::baseprofiler::PSAutoLock::gPSMutex")
+        .unwrap();
+    assert_eq!(destructor.kind, InitializerKind::AtexitDestructor);
+    assert!(destructor
+        .module_name
+        .ends_with("Unified_cpp_mozglue_baseprofiler0.obj"));
+    let initializer = initializers
+        .functions
+        .iter()
+        .find(|function| function.kind == InitializerKind::DynamicInitializer)
+        .unwrap();
+    assert_eq!(initializer.variable, "std::_Fac_tidy_reg");
+    assert_eq!(
+        initializer.name,
+        "std::`dynamic initializer for '_Fac_tidy_reg''"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_progress_and_cancellation() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+
+    let reports = Rc::new(RefCell::new(Vec::new()));
+    let reports_in_callback = reports.clone();
+    context.set_progress_callback(Some(Box::new(move |progress: Progress| {
+        reports_in_callback.borrow_mut().push(progress)
+    })));
+    context.all_inlinees()?;
+    {
+        let reports = reports.borrow();
+        let module_count = reports[0].module_count;
+        assert_eq!(reports.len(), module_count + 1);
+        assert_eq!(reports[0].modules_processed, 0);
+        assert_eq!(reports[module_count].modules_processed, module_count);
+    }
+
+    let token = CancellationToken::new();
+    context.set_cancellation_token(Some(token.clone()));
+    assert!(context.functions_in_file("nonexistent.cpp")?.is_empty());
+    token.cancel();
+    assert!(matches!(
+        context.functions_in_file("nonexistent.cpp"),
+        Err(pdb_addr2line::Error::Cancelled)
+    ));
+    // Lookups aren't affected.
+    assert!(context.find_frames_at(Rva32(0x1014))?.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_modules() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let modules = context.modules()?;
+    assert!(modules
+        .iter()
+        .enumerate()
+        .all(|(index, module)| module.module_index == index));
+    let (module_index, module_name) = context.module_for_address(Rva32(0x1014)).unwrap();
+    assert_eq!(modules[module_index].name, module_name);
+    assert!(modules[module_index].has_debug_info);
+    assert!(modules[module_index].has_symbols && modules[module_index].has_lines);
+    let linker = modules
+        .iter()
+        .find(|module| module.name == "* Linker *")
+        .unwrap();
+    assert!(linker.has_debug_info);
+    // The linker's module has symbols, e.g. for the sections, but no lines.
+    assert!(linker.has_symbols && !linker.has_lines);
+    let import = modules
+        .iter()
+        .find(|module| module.name == "Import:ADVAPI32.dll")
+        .unwrap();
+    assert!(import.object_file_name.ends_with("advapi32.lib"));
+    let resources = modules
+        .iter()
+        .find(|module| {
+            module
+                .name
+                .ends_with("internal .obj file created from .res files")
+        })
+        .unwrap();
+    assert!(!resources.has_debug_info);
+    assert!(!resources.has_symbols && !resources.has_lines);
+
+    let summary = context.debug_info_summary()?;
+    assert_eq!(summary.module_count, modules.len());
+    assert_eq!(
+        summary.stripped_modules,
+        modules
+            .iter()
+            .filter(|module| !module.has_debug_info)
+            .count()
+    );
+    assert!(summary.stripped_modules > 0);
+    assert!(summary.modules_with_lines < summary.modules_with_symbols);
+
+    // No module streams were read.
+    assert_eq!(context.memory_usage().module_streams, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_public_symbols() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let symbols = context
+        .public_symbols()
+        .collect::<Result<Vec<_>, pdb_addr2line::Error>>()?;
+    let code_count = symbols
+        .iter()
+        .filter(|symbol| symbol.in_executable_section)
+        .count();
+    assert!(code_count > 0);
+    assert!(code_count < symbols.len());
+
+    let function = context.find_function_at(Rva32(0x1000))?.unwrap();
+    let public = symbols
+        .iter()
+        .find(|symbol| symbol.rva == Some(0x1000))
+        .unwrap();
+    assert!(public.in_executable_section);
+    assert!(public.function);
+    assert_eq!(public.name, "?xgetbv@mozilla@@YA_KI@Z");
+
+    #[cfg(feature = "demangle")]
+    assert_eq!(
+        public
+            .demangled_name(pdb_addr2line::msvc_demangler::DemangleFlags::NAME_ONLY)
+            .as_deref(),
+        Some("mozilla::xgetbv")
+    );
+    assert!(function.name.unwrap().starts_with("mozilla::xgetbv("));
+
+    Ok(())
+}
+
+#[test]
+fn test_function_publics_only() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+
+    // A public symbol in .text which is neither marked as code nor as a function.
+    let function = context.find_function_at(Rva32(0x94bc7))?.unwrap();
+    assert_eq!(
+        function.name.as_deref(),
+        Some("__imp_load_CoCreateInstance")
+    );
+    let all_count = context.functions().count();
+
+    context.set_flags(ContextFlags::FUNCTION_PUBLICS_ONLY);
+    assert!(context.find_function_at(Rva32(0x94bc7))?.is_none());
+    assert!(context.find_function_at(Rva32(0x1000))?.is_some());
+    assert!(context.functions().count() < all_count);
+
+    Ok(())
+}
+
+#[test]
+fn test_correct_procedure_ends() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+
+    // mozilla::xgetbv is followed by three bytes of padding.
+    let function = context.find_function_at(Rva32(0x1000))?.unwrap();
+    assert_eq!(function.end_rva, Some(0x100d));
+    assert_eq!(
+        context.find_function_at(Rva32(0x100f))?.unwrap().provenance,
+        Provenance::PublicSymbol
+    );
+
+    context.set_flags(ContextFlags::CORRECT_PROCEDURE_ENDS);
+    let function = context.find_function_at(Rva32(0x100f))?.unwrap();
+    assert_eq!(
+        function.name.as_deref(),
+        Some("mozilla::xgetbv(unsigned int)")
+    );
+    assert_eq!(function.provenance, Provenance::Procedure);
+    assert_eq!(
+        (function.start_rva, function.end_rva),
+        (0x1000, Some(0x1010))
+    );
+    let frames = context.find_frames_at(Rva32(0x100f))?.unwrap();
+    assert_eq!(frames.end_rva, Some(0x1010));
+    // Listing the functions of a file gives the same range.
+    let file = frames.frames.last().unwrap().file.clone().unwrap();
+    let in_file = context.functions_in_file(&file)?;
+    let function = in_file.iter().find(|f| f.start_rva == 0x1000).unwrap();
+    assert_eq!(function.end_rva, 0x1010);
+    assert_eq!(
+        context.find_frames_at(Rva32(0x1014))?.unwrap().frames.len(),
+        4
+    );
+
+    // The functions of the module don't overlap.
+    let functions: Vec<_> = context.functions().take(50).collect();
+    for pair in functions.windows(2) {
+        if let Some(end_rva) = pair[0].end_rva {
+            assert!(end_rva <= pair[1].start_rva);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_max_public_function_size() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+
+    // 0x1d460 is only covered by a public symbol.
+    let function = context.find_function_at(Rva32(0x1d460))?.unwrap();
+    assert_eq!(function.provenance, Provenance::PublicSymbol);
+    let start_rva = function.start_rva;
+    let size = function.end_rva.unwrap() - start_rva;
+    assert!(size > 2);
+    let probe = start_rva + size / 2;
+
+    let max_size = probe - start_rva;
+    context.set_max_public_function_size(Some(max_size));
+    assert!(context.find_function_at(Rva32(probe))?.is_none());
+    assert!(context.find_frames_at(Rva32(probe))?.is_none());
+    let function = context.find_function_at(Rva32(start_rva))?.unwrap();
+    assert_eq!(function.end_rva, Some(probe));
+    assert!(context.find_frames_at(Rva32(probe - 1))?.is_some());
+    // Procedures are not affected.
+    assert_eq!(
+        context.find_frames_at(Rva32(0x1014))?.unwrap().frames.len(),
+        4
+    );
+
+    context.set_max_public_function_size(None);
+    assert!(context.find_function_at(Rva32(probe))?.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_name_preference() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+
+    let context = data.make_context()?;
+    let function = context.find_function_at(Rva32(0x1000))?.unwrap();
+    assert_eq!(
+        function.name.as_deref(),
+        Some("mozilla::xgetbv(unsigned int)")
+    );
+
+    let context = data.make_context_with_flags(
+        Default::default(),
+        ContextFlags::PREFER_DECORATED_PUBLIC_NAMES,
+    )?;
+    let function = context.find_function_at(Rva32(0x1000))?.unwrap();
+    assert_eq!(function.name.as_deref(), Some("?xgetbv@mozilla@@YA_KI@Z"));
+
+    let context = data.make_context_with_flags(
+        Default::default(),
+        ContextFlags::PREFER_PROCEDURE_NAMES | ContextFlags::PREFER_DECORATED_PUBLIC_NAMES,
+    )?;
+    let function = context.find_function_at(Rva32(0x1000))?.unwrap();
+    assert_eq!(
+        function.name.as_deref(),
+        Some("mozilla::xgetbv(unsigned int)")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_shared_module_cache() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let plain_context = data.make_context()?;
+    let expected = plain_context.find_frames_at(Rva32(0x1014))?.unwrap();
+
+    let cache = data.make_shared_module_cache();
+    assert!(cache.is_empty());
+    let context = cache.make_context(Default::default(), ContextFlags::default())?;
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(cache.len(), 1);
+
+    // A second context finds the module's information in the cache.
+    let other_context = cache.make_context(
+        pdb_addr2line::TypeFormatterFlags::SPACE_AFTER_COMMA,
+        ContextFlags::default(),
+    )?;
+    let other_frames = other_context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(cache.len(), 1);
+
+    for frames in [&frames, &other_frames] {
+        assert_eq!(frames.frames.len(), expected.frames.len());
+        for (frame, expected_frame) in frames.frames.iter().zip(&expected.frames) {
+            assert_eq!(frame.file, expected_frame.file);
+            assert_eq!(frame.line, expected_frame.line);
+        }
+    }
+    assert_eq!(frames.frames[0].function, expected.frames[0].function);
+
+    Ok(())
+}
+
+#[test]
+fn test_line_zero() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+
+    // The outer function and one of the inlined functions have line 0 here.
+    let context = data.make_context()?;
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[2].line, None);
+    assert_eq!(frames[3].line, None);
+    assert!(frames[0].line.is_some());
+
+    let context = data.make_context_with_flags(Default::default(), ContextFlags::KEEP_LINE_ZERO)?;
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    assert_eq!(frames[2].line, Some(0));
+    assert_eq!(frames[3].line, Some(0));
+
+    Ok(())
+}
+
+#[test]
+fn test_validate() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let report = data.validate()?;
+    assert!(!report.is_clean());
+    assert!(report
+        .issues
+        .iter()
+        .all(|issue| matches!(issue, ValidationIssue::ProcedureWithoutLines { .. })));
+    assert!(report
+        .issues
+        .contains(&ValidationIssue::ProcedureWithoutLines {
+            module_index: 8,
+            name: "_GLOBAL__sub_I_msvc_raise_wrappers.cpp".to_string(),
+            start_rva: Some(0x17f20),
+        }));
+
+    Ok(())
+}
+
+#[test]
+fn test_stack_parameter_size() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    // @__security_check_cookie@4
+    assert_eq!(context.stack_parameter_size(Rva32(0x2a6e))?, Some(4));
+    // ___scrt_unhandled_exception_filter@4
+    assert_eq!(context.stack_parameter_size(Rva32(0x3435))?, Some(4));
+    // A __thiscall method with two pointer arguments. The this pointer is passed in
+    // a register.
+    assert_eq!(context.stack_parameter_size(Rva32(0x1000))?, Some(8));
+    // An import thunk, which has no type information.
+    assert_eq!(context.stack_parameter_size(Rva32(0x37ec))?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_malformed_symbol() -> Result<(), Box<dyn Error>> {
+    // Remove the null terminator of the name of the procedure at 0x1000, so that its
+    // record can't be parsed.
+    let mut bytes = std::fs::read(fixture("crash.pdb"))?;
+    let name = b"google_breakpad::CrashGenerationClient::RequestDump\0";
+    let name_start = bytes
+        .windows(name.len())
+        .position(|window| window == name)
+        .unwrap();
+    // The name follows the 35 byte procedure header, and the record is 0x5a bytes
+    // long, not counting the length field.
+    let record_end = name_start - 37 + 0x5a;
+    bytes[name_start + name.len() - 1..record_end].fill(b'x');
+
+    let pdb = pdb::PDB::open(std::io::Cursor::new(bytes))?;
+    let data = ContextPdbData::try_from_pdb(pdb)?;
+    let mut context = data.make_context()?;
+    let diagnostics: Rc<RefCell<Vec<Diagnostic>>> = Rc::default();
+    let sink = diagnostics.clone();
+    context.set_diagnostics_callback(Some(Box::new(move |diagnostic| {
+        sink.borrow_mut().push(diagnostic.clone())
+    })));
+
+    // The skipped procedure falls back to the public symbol.
+    let function = context.find_function_at(Rva32(0x1000))?.unwrap();
+    assert_eq!(
+        function.name.as_deref(),
+        Some("?RequestDump@CrashGenerationClient@google_breakpad@@QAE_NPAU_EXCEPTION_POINTERS@@PAUMDRawAssertionInfo@@@Z")
+    );
+    // The other procedures of the module are still there.
+    let function = context.find_function_at(Rva32(0x1180))?.unwrap();
+    assert_eq!(
+        function.name.as_deref(),
+        Some("std::basic_string<wchar_t,std::char_traits<wchar_t>,std::allocator<wchar_t> >::assign(wchar_t const* const, const unsigned int)")
+    );
+
+    let diagnostics = diagnostics.borrow();
+    assert_eq!(diagnostics.len(), 1);
+    match &diagnostics[0] {
+        Diagnostic::MalformedSymbol { module_index, .. } => assert_eq!(*module_index, 2),
+        diagnostic => panic!("unexpected diagnostic {:?}", diagnostic),
+    }
+
+    let report = data.validate()?;
+    assert!(report.issues.iter().any(|issue| matches!(
+        issue,
+        ValidationIssue::MalformedSymbol {
+            module_index: 2,
+            ..
+        }
+    )));
+
+    Ok(())
+}
+
+#[test]
+fn test_export_gsym() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let gsym = context.export_gsym(0x1_8000_0000)?;
+
+    let u32_at = |pos: usize| u32::from_le_bytes(gsym[pos..pos + 4].try_into().unwrap());
+    assert_eq!(u32_at(0), 0x4753_594d);
+    assert_eq!(&gsym[4..6], &[1, 0]);
+    assert_eq!(gsym[7], 16);
+    assert_eq!(
+        u64::from_le_bytes(gsym[8..16].try_into().unwrap()),
+        0x1_8000_1000
+    );
+    let mut start_rvas: Vec<u32> = context.functions().map(|f| f.start_rva).collect();
+    start_rvas.dedup();
+    assert_eq!(u32_at(16) as usize, start_rvas.len());
+
+    let strtab_offset = u32_at(20) as usize;
+    let strtab_size = u32_at(24) as usize;
+    let strtab = &gsym[strtab_offset..strtab_offset + strtab_size];
+    assert!(strtab
+        .windows(b"\0mozilla::xgetbv(unsigned int)\0".len())
+        .any(|w| w == b"\0mozilla::xgetbv(unsigned int)\0"));
+
+    Ok(())
+}
+
+#[test]
+fn test_profiler_symbols() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let table = context.compact_symbol_table();
+    assert_eq!(table.index.len(), table.addr.len() + 1);
+    assert!(table.addr.windows(2).all(|w| w[0] < w[1]));
+    let position = table.addr.binary_search(&0x1000).unwrap();
+    let name = &table.buffer[table.index[position] as usize..table.index[position + 1] as usize];
+    assert_eq!(name, b"mozilla::xgetbv(unsigned int)");
+
+    let debug_id = context.debug_id().unwrap();
+    assert_eq!(debug_id.len(), 33);
+    let mut json = Vec::new();
+    context.write_symbolication_json(
+        "mozglue.pdb",
+        &[Rva32(0x1014), Rva32(0xffff_fff0)],
+        &mut json,
+    )?;
+    let json = String::from_utf8(json)?;
+    assert!(json.starts_with(r#"{"results":[{"stacks":[[{"frame":0,"module_offset":"0x1014","module":"mozglue.pdb","function":"#));
+    assert!(json.contains(r#","inlines":[{"function":"#));
+    assert!(json.contains(r#"{"frame":1,"module_offset":"0xfffffff0","module":"mozglue.pdb"}"#));
+    assert!(json.ends_with(&format!(
+        "]],\"found_modules\":{{\"mozglue.pdb/{}\":true}}}}]}}\n",
+        debug_id
+    )));
+
+    Ok(())
+}
+
+#[test]
+fn test_dbghelp_style_lookups() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let symbol = context.symbol_from_address(Rva32(0x1004))?.unwrap();
+    assert_eq!(
+        symbol.name.as_deref(),
+        Some("mozilla::xgetbv(unsigned int)")
+    );
+    assert_eq!(symbol.address, 0x1000);
+    assert_eq!(symbol.size, Some(13));
+    assert_eq!(symbol.displacement, 4);
+
+    let line = context.line_from_address(Rva32(0x1180))?.unwrap();
+    assert_eq!(
+        line.file,
+        "/builds/worker/checkouts/gecko/memory/build/mozjemalloc.cpp"
+    );
+    assert_eq!(line.line, 2229);
+    assert_eq!(line.address, 0x117e);
+    assert_eq!(line.displacement, 2);
+
+    // The outer line at this address is line 0.
+    assert_eq!(context.line_from_address(Rva32(0x1014))?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_line_distance() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    // The line record of line 2229 starts at 0x117e.
+    let frames = context.find_frames_at(Rva32(0x1180))?.unwrap();
+    let outer = frames.frames.last().unwrap();
+    assert_eq!(outer.line, Some(2229));
+    assert_eq!(outer.line_distance, Some(2));
+    let frames = context.find_frames_at(Rva32(0x117e))?.unwrap();
+    assert_eq!(frames.frames.last().unwrap().line_distance, Some(0));
+
+    // Inlined frames and frames without a line have no distance.
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert!(frames
+        .frames
+        .iter()
+        .all(|frame| frame.line_distance.is_none()));
+
+    Ok(())
+}
+
+#[test]
+fn test_helper_function_kind() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    assert_eq!(
+        context.helper_function_kind(Rva32(0x92e90))?,
+        Some(HelperFunctionKind::StackProbe)
+    );
+    assert_eq!(
+        context.helper_function_kind(Rva32(0x92f14))?,
+        Some(HelperFunctionKind::SecurityCheck)
+    );
+    assert_eq!(
+        context.helper_function_kind(Rva32(0x943f0))?,
+        Some(HelperFunctionKind::ControlFlowGuard)
+    );
+    assert_eq!(
+        context.helper_function_kind(Rva32(0x948e0))?,
+        Some(HelperFunctionKind::MemoryRoutine)
+    );
+    assert_eq!(context.helper_function_kind(Rva32(0x1000))?, None);
+
+    assert_eq!(
+        HelperFunctionKind::from_function_name("@__security_check_cookie@4"),
+        Some(HelperFunctionKind::SecurityCheck)
+    );
+    assert_eq!(
+        HelperFunctionKind::from_function_name("__guard_dispatch_icall_fptr"),
+        Some(HelperFunctionKind::ControlFlowGuard)
+    );
+    assert_eq!(
+        HelperFunctionKind::from_function_name("_alloca_probe_16"),
+        Some(HelperFunctionKind::StackProbe)
+    );
+    assert_eq!(HelperFunctionKind::from_function_name("std::memcpy"), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_find_frames_covering() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    assert!(context
+        .find_frames_covering(Rva32(0x1004)..Rva32(0x1004))?
+        .is_empty());
+
+    let covering = context.find_frames_covering(Rva32(0x1004)..Rva32(0x1020))?;
+    assert_eq!(covering.len(), 3);
+    assert_eq!(covering[0].start_rva, 0x1000);
+    assert_eq!(covering[0].frames.len(), 1);
+    assert_eq!(covering[0].frames[0].line, Some(191));
+    assert_eq!(covering[1].start_rva, 0x1010);
+    assert_eq!(covering[1].frames.len(), 4);
+    assert_eq!(covering[1].frames[0].line, Some(286));
+    assert_eq!(covering[2].frames.len(), 3);
+    assert_eq!(covering[2].frames[0].start_rva, 0x101d);
+    assert_eq!(covering[2].frames[0].line, Some(34));
+
+    // Each entry matches the lookup of a single address.
+    let single = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(single.frames.len(), covering[1].frames.len());
+    assert_eq!(single.frames[0].function, covering[1].frames[0].function);
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_layout() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    // google_breakpad::CrashGenerationClient::RequestDump
+    let layout = context.frame_layout(Rva32(0x1000))?.unwrap();
+    assert_eq!(layout.frame_size, 12);
+    assert_eq!(layout.saved_registers_size, 12);
+    assert_eq!(layout.exception_handler_rva, None);
+    assert_eq!(
+        layout.local_base_register,
+        Some(FrameBaseRegister::StackPointer)
+    );
+    assert!(layout.flags.contains(FrameFlags::SECURITY_CHECKS));
+
+    // google_breakpad::ExceptionHandler::Initialize has a C++ exception handler.
+    let layout = context.frame_layout(Rva32(0x1420))?.unwrap();
+    assert_eq!(layout.frame_size, 48);
+    assert_eq!(layout.exception_handler_rva, Some(0x3808));
+    assert_eq!(
+        layout.parameter_base_register,
+        Some(FrameBaseRegister::FramePointer)
+    );
+    assert!(layout.flags.contains(FrameFlags::HAS_EH));
+
+    // memcpy is a six-byte jump to the import, without an S_FRAMEPROC record.
+    assert_eq!(context.frame_layout(Rva32(0x37f2))?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_check_unwind_info() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let mut runtime_functions = Vec::new();
+    for function in context.functions() {
+        let end_rva = match (function.provenance, function.end_rva) {
+            (Provenance::Procedure | Provenance::Thunk, Some(end_rva)) => end_rva,
+            _ => continue,
+        };
+        match function.start_rva {
+            // mozilla::xgetbv has no entry.
+            0x1000 => {}
+            // _GLOBAL__sub_I_SSE.cpp has an entry which is too short.
+            0x1010 => runtime_functions.push(RuntimeFunction {
+                begin_rva: 0x1010,
+                end_rva: end_rva - 4,
+            }),
+            // arena_t::SplitRun has two chained entries.
+            0x1160 => {
+                runtime_functions.push(RuntimeFunction {
+                    begin_rva: 0x1160,
+                    end_rva: 0x1200,
+                });
+                runtime_functions.push(RuntimeFunction {
+                    begin_rva: 0x1200,
+                    end_rva,
+                });
+            }
+            start_rva => runtime_functions.push(RuntimeFunction {
+                begin_rva: start_rva,
+                end_rva,
+            }),
+        }
+    }
+    let orphan = RuntimeFunction {
+        begin_rva: 0xfff0_0000,
+        end_rva: 0xfff0_0010,
+    };
+    runtime_functions.push(orphan);
+
+    let issues = context.check_unwind_info(&runtime_functions);
+    assert_eq!(
+        issues,
+        vec![
+            UnwindIssue::MissingUnwindInfo {
+                name: Some("mozilla::xgetbv(unsigned int)".to_string()),
+                start_rva: 0x1000,
+                end_rva: 0x100d,
+            },
+            UnwindIssue::MismatchedRange {
+                name: Some("_GLOBAL__sub_I_SSE.cpp()".to_string()),
+                start_rva: 0x1010,
+                end_rva: 0x115c,
+                unwind_begin_rva: 0x1010,
+                unwind_end_rva: 0x1158,
+            },
+            UnwindIssue::UnwindInfoWithoutFunction(orphan),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_symbol_source() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let source = |probe| -> Result<Provenance, Box<dyn Error>> {
+        Ok(context.find_frames_at(Rva32(probe))?.unwrap().provenance)
+    };
+    assert_eq!(source(0x1000)?, Provenance::ProcedureWithLines);
+    // __security_check_cookie is written in assembly.
+    assert_eq!(source(0x2a6e)?, Provenance::ProcedureWithoutLines);
+    // __CxxFrameHandler3 jumps to the imported function.
+    assert_eq!(source(0x3726)?, Provenance::Thunk);
+    // A section contribution without any symbols.
+    assert_eq!(source(0x3800)?, Provenance::NearestSymbol);
+
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    assert_eq!(
+        context.find_frames_at(Rva32(0x1d460))?.unwrap().provenance,
+        Provenance::PublicSymbol
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_function_size_metrics() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let metrics = context.function_size_metrics(Rva32(0x1000))?.unwrap();
+    assert_eq!(metrics.total_size, 13);
+    assert_eq!(metrics.self_size, 13);
+    assert!(metrics.inlinees.is_empty());
+
+    let metrics = context.function_size_metrics(Rva32(0x1200))?.unwrap();
+    assert_eq!(
+        metrics.name.as_deref(),
+        Some("arena_t::SplitRun(arena_run_t*, unsigned long long, bool, bool)")
+    );
+    assert_eq!(metrics.start_rva, 0x1160);
+    assert_eq!(metrics.total_size, 918);
+    assert_eq!(metrics.self_size, 747);
+    assert_eq!(
+        metrics.inlinees[0],
+        InlinedCodeSize {
+            name: Some("pages_commit(void*, unsigned long long)".to_string()),
+            bytes: 137,
+        }
+    );
+    assert!(metrics
+        .inlinees
+        .windows(2)
+        .all(|w| w[0].bytes >= w[1].bytes));
+
+    Ok(())
+}
+
+#[test]
+fn test_frames_cache() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context_with_flags(Default::default(), ContextFlags::TIMINGS)?;
+    context.set_frames_cache_capacity(4);
+
+    let first = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let timings = context.timings();
+    // The second lookup is answered from the cache, without any lookup phases.
+    let second = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(context.timings().total(), timings.total());
+    assert_eq!(second.frames.len(), first.frames.len());
+    for (a, b) in first.frames.iter().zip(&second.frames) {
+        assert_eq!(a.function, b.function);
+        assert_eq!(a.file, b.file);
+        assert_eq!(a.line, b.line);
+    }
+    assert_eq!(first.frames[2].line, None);
+
+    // Results are evicted when the cache is full, and they are still correct
+    // afterwards.
+    for probe in (0x1000..0x1200).step_by(8) {
+        context.find_frames_at(Rva32(probe))?;
+    }
+    assert_eq!(
+        context.find_frames_at(Rva32(0x1014))?.unwrap().frames.len(),
+        4
+    );
+
+    // Changing the flags drops the cached results.
+    context.set_flags(ContextFlags::TIMINGS | ContextFlags::KEEP_LINE_ZERO);
+    assert_eq!(
+        context.find_frames_at(Rva32(0x1014))?.unwrap().frames[2].line,
+        Some(0)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_inlinee_name() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let (module_index, _) = context.module_for_address(Rva32(0x1014)).unwrap();
+
+    // Collect the inlinees of the module's inline sites with the pdb crate.
+    let mut pdb = pdb::PDB::open(std::fs::File::open(fixture("mozglue.pdb"))?)?;
+    let debug_info = pdb.debug_information()?;
+    let module = debug_info.modules()?.nth(module_index)?.unwrap();
+    let module_info = pdb.module_info(&module)?.unwrap();
+    let mut inlinees = Vec::new();
+    let mut symbols = module_info.symbols()?;
+    while let Some(symbol) = symbols.next()? {
+        if let Ok(pdb::SymbolData::InlineSite(site)) = symbol.parse() {
+            inlinees.push(site.inlinee);
+        }
+    }
+    assert!(!inlinees.is_empty());
+
+    let names = inlinees
+        .iter()
+        .map(|inlinee| context.inlinee_name(module_index, *inlinee))
+        .collect::<Result<Vec<_>, _>>()?;
+    // The names are the same as the ones of the inlined frames.
+    for frame in &context.find_frames_at(Rva32(0x1014))?.unwrap().frames[..3] {
+        assert!(names.contains(frame.function.as_ref().unwrap()));
+    }
+    assert!(names.contains(&"mozilla::xgetbv(unsigned int)".to_string()));
+    // The raw names of inlined frames come from the id records, without the scope.
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    assert_eq!(
+        frames[1]
+            .raw_function
+            .map(|name| name.to_string())
+            .as_deref(),
+        Some("has_cpuid_bits")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_function_name_parts() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    // All of these names were formatted from the type records, so their parts are
+    // known without parsing the names.
+    assert!(frames
+        .iter()
+        .all(|frame| frame.function_name_layout.is_some()));
+    let parts = frames[1].function_name_parts().unwrap();
+    assert_eq!(parts.return_type, None);
+    assert_eq!(parts.scope, vec!["`anonymous namespace'"]);
+    assert_eq!(parts.name, "has_cpuid_bits");
+    assert_eq!(
+        parts.arguments,
+        Some("(unsigned int, `anonymous namespace'::CPUIDRegister, unsigned int)")
+    );
+    let parts = frames[2].function_name_parts().unwrap();
+    assert_eq!(
+        parts.path().collect::<Vec<_>>(),
+        vec![
+            "mozilla",
+            "sse_private",
+            "`dynamic initializer for 'mmx_enabled'"
+        ]
+    );
+    assert_eq!(parts.arguments, Some("()"));
+    let parts = frames[3].function_name_parts().unwrap();
+    assert_eq!(parts.name, "_GLOBAL__sub_I_SSE.cpp");
+    assert_eq!(parts.arguments, Some("()"));
+
+    let parts = parse_function_name("void __cdecl A::B<C::D>::E(int) const");
+    assert_eq!(
+        parts,
+        FunctionNameParts {
+            return_type: Some("void __cdecl"),
+            scope: vec!["A", "B<C::D>"],
+            name: "E",
+            arguments: Some("(int) const"),
+        }
+    );
+    let parts = parse_function_name("std::operator<<<std::char_traits<char> >(char const*)");
+    assert_eq!(parts.scope, vec!["std"]);
+    assert_eq!(parts.name, "operator<<<std::char_traits<char> >");
+    let parts = parse_function_name("A::operator()(int)");
+    assert_eq!(parts.name, "operator()");
+    assert_eq!(parts.arguments, Some("(int)"));
+    let parts = parse_function_name("ns::`dynamic initializer for '`anonymous namespace'::x''");
+    assert_eq!(parts.scope, vec!["ns"]);
+    assert_eq!(
+        parts.name,
+        "`dynamic initializer for '`anonymous namespace'::x''"
+    );
+    let parts = parse_function_name("memcpy");
+    assert_eq!(parts.scope, Vec::<&str>::new());
+    assert_eq!(parts.name, "memcpy");
+    assert_eq!(parts.arguments, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_line_program() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let (module_index, _) = context.module_for_address(Rva32(0x1014)).unwrap();
+    let line_program = context.line_program(module_index)?.unwrap();
+    let line_count = line_program.lines().count()?;
+    assert!(line_count > 0);
+    // The line program is cached, and the lookups use the same one.
+    assert_eq!(
+        context.find_frames_at(Rva32(0x1014))?.unwrap().frames.len(),
+        4
+    );
+    let line_program = context.line_program(module_index)?.unwrap();
+    assert_eq!(line_program.lines().count()?, line_count);
+
+    assert!(context.line_program(usize::MAX)?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_line_records() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    // je_malloc_usable_size starts with an empty record for line 61 of
+    // malloc_decls.h, at the same offset as the record for line 4908.
+    for probe in [0x44c0, 0x44c4] {
+        let frames = context.find_frames_at(Rva32(probe))?.unwrap().frames;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].line, Some(4908));
+        assert!(frames[0]
+            .file
+            .as_ref()
+            .unwrap()
+            .ends_with("mozjemalloc.cpp"));
+    }
+    let line = context.line_from_address(Rva32(0x44c4))?.unwrap();
+    assert_eq!((line.line, line.address), (4908, 0x44c0));
+
+    Ok(())
+}
+
+#[test]
+fn test_generated_functions() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let generated = |probe| -> Result<_, Box<dyn Error>> {
+        Ok(context.find_function_at(Rva32(probe))?.unwrap().generated)
+    };
+    // _GLOBAL__sub_I_SSE.cpp()
+    assert_eq!(
+        generated(0x1010)?,
+        Some(GeneratedFunctionKind::DynamicInitializer)
+    );
+    // mozilla::AutoProfilerLabelData::`dynamic atexit destructor for 'sAPLMutex'()
+    assert_eq!(
+        generated(0x50660)?,
+        Some(GeneratedFunctionKind::AtexitDestructor)
+    );
+    // ??_EIFStream@mozilla@@$4PPPPPPPM@A@EAAPEAXI@Z
+    assert_eq!(
+        generated(0x58480)?,
+        Some(GeneratedFunctionKind::DeletingDestructor)
+    );
+    assert_eq!(generated(0x1000)?, None);
+    // Lambdas are quoted with a backtick too, but they are user code.
+    assert_eq!(generated(0x186b0)?, None);
+
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let function = context.find_function_at(Rva32(0x2ec7))?.unwrap();
+    assert_eq!(
+        function.name.as_deref(),
+        Some("std::bad_alloc::`scalar deleting destructor'(unsigned int)")
+    );
+    assert_eq!(
+        function.generated,
+        Some(GeneratedFunctionKind::DeletingDestructor)
+    );
+
+    assert_eq!(
+        GeneratedFunctionKind::from_function_name("[thunk]:Foo::f`adjustor{8}' (void)"),
+        Some(GeneratedFunctionKind::Thunk)
+    );
+    assert_eq!(
+        GeneratedFunctionKind::from_function_name("__ehhandler$?f@@YAXXZ"),
+        Some(GeneratedFunctionKind::ExceptionHandler)
+    );
+    assert_eq!(
+        GeneratedFunctionKind::from_function_name("`f'::`1'::catch$0"),
+        Some(GeneratedFunctionKind::ExceptionHandler)
+    );
+    assert_eq!(GeneratedFunctionKind::from_function_name("catch_up"), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_function_ids() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let first = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    let second = context.find_function_at(Rva32(0x1160))?.unwrap();
+    assert_eq!((first.function_id, second.id), (0, 1));
+    // Lookups of the same function return the same id, no matter which address in
+    // the function or which kind of lookup.
+    assert_eq!(context.find_function_at(Rva32(0x1010))?.unwrap().id, 0);
+    assert_eq!(
+        context.find_frames_at(Rva32(0x1020))?.unwrap().function_id,
+        0
+    );
+    assert_eq!(
+        context.find_frames_at(Rva32(0x1170))?.unwrap().function_id,
+        1
+    );
+    assert_eq!(context.find_function_at(Rva32(0x1000))?.unwrap().id, 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_line_ranges() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    // The fixtures have neither multi-line statements nor columns.
+    let frame = &context.find_frames_at(Rva32(0x1000))?.unwrap().frames[0];
+    assert!(frame.line.is_some());
+    assert_eq!(frame.line_end, frame.line);
+    assert_eq!((frame.column, frame.column_end), (None, None));
+    // Procedures without lines have no line range.
+    let frame = &context.find_frames_at(Rva32(0x2a6e))?.unwrap().frames[0];
+    assert_eq!((frame.line, frame.line_end), (None, None));
+
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    // Inlined frames only have a start line.
+    assert!(frames[0].line.is_some());
+    assert_eq!(frames[0].line_end, None);
+    assert_eq!(frames[3].line_end, frames[3].line);
+
+    Ok(())
+}
+
+#[test]
+fn test_max_name_length() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+
+    let full_names: Vec<Option<String>> = context
+        .find_frames_at(Rva32(0x1014))?
+        .unwrap()
+        .frames
+        .into_iter()
+        .map(|frame| frame.function)
+        .collect();
+    context.set_max_name_length(Some(40));
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    // The outer function's name is short enough.
+    assert_eq!(frames[3].function, full_names[3]);
+    let truncated = frames[1].function.as_deref().unwrap();
+    assert_eq!(truncated.len(), 40);
+    assert!(truncated.starts_with("`anonymous namespace'::has_"));
+    assert_eq!(&truncated[28..32], "...#");
+    assert!(full_names[1].as_deref().unwrap().len() > 40);
+    assert_eq!(
+        context
+            .find_function_at(Rva32(0x1000))?
+            .unwrap()
+            .name
+            .as_deref(),
+        Some("mozilla::xgetbv(unsigned int)")
+    );
+
+    context.set_max_name_length(None);
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    assert_eq!(frames[1].function, full_names[1]);
+
+    Ok(())
+}
+
+#[test]
+fn test_inline_ranges_for_function() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let ranges = context.inline_ranges_for_function(Rva32(0x1014))?;
+    assert!(!ranges.is_empty());
+    assert!(ranges
+        .windows(2)
+        .all(|pair| (pair[0].start_rva, pair[0].depth) <= (pair[1].start_rva, pair[1].depth)));
+    // The ranges which contain an address are the inlined frames at that address.
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap().frames;
+    let mut covering: Vec<_> = ranges
+        .iter()
+        .filter(|range| range.start_rva <= 0x1014 && 0x1014 < range.end_rva)
+        .collect();
+    covering.sort_by_key(|range| std::cmp::Reverse(range.depth));
+    assert_eq!(covering.len(), frames.len() - 1);
+    for (range, frame) in covering.iter().zip(&frames) {
+        assert_eq!(range.function, frame.function);
+        assert_eq!(range.start_rva, frame.start_rva);
+        assert_eq!(range.file.as_deref(), frame.file.as_deref());
+    }
+
+    // mozilla::xgetbv has no inlined functions, and public symbols have no ranges.
+    assert!(context
+        .inline_ranges_for_function(Rva32(0x1000))?
+        .is_empty());
+    assert!(context
+        .inline_ranges_for_function(Rva32(0x1d460))?
+        .is_empty());
+
+    Ok(())
+}
+
+#[cfg(feature = "range-source")]
+#[test]
+fn test_range_source() -> Result<(), Box<dyn Error>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    /// Counts the bytes which were read, like a download would.
+    #[derive(Debug)]
+    struct FileReader(std::fs::File, std::rc::Rc<std::cell::Cell<u64>>);
+
+    impl pdb_addr2line::RangeReader for FileReader {
+        fn read_range(&mut self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.seek(SeekFrom::Start(offset))?;
+            let mut len = 0;
+            while len < buf.len() {
+                match self.0.read(&mut buf[len..])? {
+                    0 => break,
+                    n => len += n,
+                }
+            }
+            self.1.set(self.1.get() + len as u64);
+            Ok(len)
+        }
+    }
+
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let file_size = file.metadata()?.len();
+    let read_bytes = std::rc::Rc::new(std::cell::Cell::new(0));
+    let reader = FileReader(file, read_bytes.clone());
+    let source = pdb_addr2line::RangeSource::with_cache_size(reader, 16 * 1024, 8);
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(source)?)?;
+    let context = data.make_context()?;
+    let frames = context.find_frames_at(Rva32(0x1014))?.unwrap();
+
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let expected_data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let expected_context = expected_data.make_context()?;
+    let expected = expected_context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(frames.frames.len(), expected.frames.len());
+    for (frame, expected) in frames.frames.iter().zip(&expected.frames) {
+        assert_eq!(frame.function, expected.function);
+        assert_eq!(frame.line, expected.line);
+    }
+    // Only the parts of the PDB which are needed for the lookup were read.
+    assert!(read_bytes.get() < file_size / 2, "{}", read_bytes.get());
+
+    Ok(())
+}
+
+#[test]
+fn test_function_source_files() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    // logalloc_init has lines from the macros in malloc_decls.h.
+    let function = context.find_function_at(Rva32(0xbf70))?.unwrap();
+    let files = context.function_source_files(Rva32(0xbf70))?;
+    assert_eq!(
+        files,
+        vec![
+            FunctionSourceFile {
+                file: Some(
+                    "/builds/worker/checkouts/gecko/memory/replace/logalloc/LogAlloc.cpp".into()
+                ),
+                line_records: 30,
+                lines: 25,
+                bytes: 449,
+            },
+            FunctionSourceFile {
+                file: Some("/builds/worker/checkouts/gecko/memory/build/malloc_decls.h".into()),
+                line_records: 5,
+                lines: 5,
+                bytes: 54,
+            },
+        ]
+    );
+    let bytes: u32 = files.iter().map(|file| file.bytes).sum();
+    assert_eq!(Some(function.start_rva + bytes), function.end_rva);
+
+    assert_eq!(context.function_source_files(Rva32(0x1014))?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_prologue_and_epilogue() -> Result<(), Box<dyn Error>> {
+    // MSVC writes the body range into the procedure symbols.
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    assert_eq!(context.is_in_prologue(Rva32(0x1000))?, Some(true));
+    assert_eq!(context.is_in_prologue(Rva32(0x1080))?, Some(false));
+    assert_eq!(context.is_in_epilogue(Rva32(0x1080))?, Some(false));
+    assert_eq!(context.is_in_epilogue(Rva32(0x1113))?, Some(true));
+    // __local_stdio_printf_options has no prologue, and its epilogue is the `ret`.
+    assert_eq!(context.is_in_prologue(Rva32(0x1360))?, Some(false));
+    assert_eq!(context.is_in_epilogue(Rva32(0x1364))?, Some(false));
+    assert_eq!(context.is_in_epilogue(Rva32(0x1365))?, Some(true));
+
+    // clang doesn't, so the first and last line records are used.
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+    assert_eq!(context.is_in_prologue(Rva32(0x1160))?, Some(true));
+    assert_eq!(context.is_in_prologue(Rva32(0x1170))?, Some(false));
+    assert_eq!(context.is_in_epilogue(Rva32(0x1170))?, Some(false));
+    assert_eq!(context.is_in_epilogue(Rva32(0x14f5))?, Some(true));
+    // mozilla::xgetbv has too few line records to tell.
+    assert_eq!(context.is_in_prologue(Rva32(0x1000))?, None);
+    // A function which is only known from a public symbol.
+    assert_eq!(context.is_in_epilogue(Rva32(0x1d460))?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_inline_site_annotations() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    let sites = context.inline_site_annotations(Rva32(0x1014))?;
+    assert!(!sites.is_empty());
+    assert_eq!(sites[0].depth, 0);
+    // Each site is followed by the sites nested in it.
+    assert!(sites
+        .windows(2)
+        .all(|pair| pair[1].depth <= pair[0].depth + 1));
+    for site in &sites {
+        assert!(site
+            .annotations
+            .iter()
+            .any(|annotation| annotation.emits_line_info()));
+    }
+    // The same functions show up in the decoded ranges.
+    let ranges = context.inline_ranges_for_function(Rva32(0x1014))?;
+    for site in &sites {
+        assert!(ranges
+            .iter()
+            .any(|range| range.function == site.function && range.depth == site.depth));
+    }
+
+    assert!(context.inline_site_annotations(Rva32(0x1000))?.is_empty());
+    assert!(context.inline_site_annotations(Rva32(0x1d460))?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_symbolize_stack() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("mozglue.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let context = data.make_context()?;
+
+    // 0x1010 is the start of _GLOBAL__sub_I_SSE.cpp, so as a return address it
+    // belongs to the code before it. 0x5 isn't in any function.
+    let stack = context.symbolize_stack(&[Rva32(0x1014), Rva32(0x1010), Rva32(0x5)]);
+    let inner = context.find_frames_at(Rva32(0x1014))?.unwrap();
+    assert_eq!(stack.len(), inner.frames.len() + 2);
+    for (frame, expected) in stack.iter().zip(&inner.frames) {
+        assert_eq!(frame.stack_index, 0);
+        assert_eq!(frame.lookup_address, 0x1014);
+        assert_eq!(frame.frame.as_ref().unwrap().function, expected.function);
+    }
+    assert_eq!(
+        stack
+            .iter()
+            .filter(|frame| frame.stack_index == 0 && !frame.inlined)
+            .count(),
+        1
+    );
+
+    let caller = &stack[inner.frames.len()];
+    assert_eq!(caller.stack_index, 1);
+    assert_eq!((caller.address, caller.lookup_address), (0x1010, 0x100f));
+    assert!(!caller.inlined);
+    let expected = context.find_frames_at(Rva32(0x100f))?.unwrap();
+    assert_eq!(caller.provenance, Some(expected.provenance));
+    assert_eq!(
+        caller.frame.as_ref().unwrap().function,
+        expected.frames.last().unwrap().function
+    );
+
+    let unknown = stack.last().unwrap();
+    assert_eq!(unknown.stack_index, 2);
+    assert!(unknown.frame.is_none());
+    assert!(unknown.provenance.is_none());
+
+    Ok(())
+}
+
+/// Build a minimal 64-bit PE image with one code section at `section_rva`, which
+/// exports `ExportA` at offset 0x100 and `ExportB` at offset 0x180 of the section.
+#[cfg(feature = "pe")]
+fn pe_image_with_exports(section_rva: u32) -> Vec<u8> {
+    fn put(image: &mut [u8], pos: usize, bytes: &[u8]) {
+        image[pos..pos + bytes.len()].copy_from_slice(bytes);
+    }
+    let mut image = vec![0u8; 0x400];
+    put(&mut image, 0, b"MZ");
+    put(&mut image, 0x3c, &0x40u32.to_le_bytes());
+    put(&mut image, 0x40, b"PE\0\0");
+    // File header: AMD64, one section, 240 bytes of optional header.
+    put(&mut image, 0x44, &0x8664u16.to_le_bytes());
+    put(&mut image, 0x46, &1u16.to_le_bytes());
+    put(&mut image, 0x54, &240u16.to_le_bytes());
+    put(&mut image, 0x56, &0x2022u16.to_le_bytes());
+    // Optional header.
+    let opt = 0x58;
+    put(&mut image, opt, &0x20bu16.to_le_bytes());
+    put(&mut image, opt + 24, &0x1_8000_0000u64.to_le_bytes());
+    put(&mut image, opt + 32, &0x1000u32.to_le_bytes());
+    put(&mut image, opt + 36, &0x200u32.to_le_bytes());
+    put(&mut image, opt + 56, &(section_rva + 0x1000).to_le_bytes());
+    put(&mut image, opt + 60, &0x200u32.to_le_bytes());
+    put(&mut image, opt + 68, &3u16.to_le_bytes());
+    put(&mut image, opt + 108, &16u32.to_le_bytes());
+    // The export directory is at the start of the section.
+    put(&mut image, opt + 112, &section_rva.to_le_bytes());
+    put(&mut image, opt + 116, &0x70u32.to_le_bytes());
+    // Section header: .text, code, execute and read.
+    let sh = opt + 240;
+    put(&mut image, sh, b".text\0\0\0");
+    put(&mut image, sh + 8, &0x200u32.to_le_bytes());
+    put(&mut image, sh + 12, &section_rva.to_le_bytes());
+    put(&mut image, sh + 16, &0x200u32.to_le_bytes());
+    put(&mut image, sh + 20, &0x200u32.to_le_bytes());
+    put(&mut image, sh + 36, &0x6000_0020u32.to_le_bytes());
+    // Section data: the export directory, its tables and names.
+    let data = 0x200;
+    let rva = |offset: u32| (section_rva + offset).to_le_bytes();
+    put(&mut image, data + 12, &rva(0x60));
+    put(&mut image, data + 16, &1u32.to_le_bytes());
+    put(&mut image, data + 20, &2u32.to_le_bytes());
+    put(&mut image, data + 24, &2u32.to_le_bytes());
+    put(&mut image, data + 28, &rva(0x28));
+    put(&mut image, data + 32, &rva(0x30));
+    put(&mut image, data + 36, &rva(0x38));
+    put(&mut image, data + 0x28, &rva(0x100));
+    put(&mut image, data + 0x2c, &rva(0x180));
+    put(&mut image, data + 0x30, &rva(0x40));
+    put(&mut image, data + 0x34, &rva(0x50));
+    put(&mut image, data + 0x38, &[0, 0, 1, 0]);
+    put(&mut image, data + 0x40, b"ExportA\0");
+    put(&mut image, data + 0x50, b"ExportB\0");
+    put(&mut image, data + 0x60, b"test.dll\0");
+    image
+}
+
+#[cfg(feature = "pe")]
+#[test]
+fn test_pe_export_fallback() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let mut context = data.make_context()?;
+
+    // The PDB doesn't cover this section, so only the exports can resolve it.
+    let section_rva = 0x10_0000;
+    assert!(context
+        .find_function_at(Rva32(section_rva + 0x190))?
+        .is_none());
+
+    let pe_exports = pdb_addr2line::PeExports::parse(&pe_image_with_exports(section_rva))?;
+    assert_eq!(pe_exports.len(), 2);
+    assert_eq!(pe_exports.image_base(), 0x1_8000_0000);
+    context.set_pe_exports(Some(pe_exports));
+
+    let function = context
+        .find_function_at(Rva32(section_rva + 0x120))?
+        .unwrap();
+    assert_eq!(function.name.as_deref(), Some("ExportA"));
+    assert_eq!(function.provenance, Provenance::PeExport);
+    assert_eq!(function.start_rva, section_rva + 0x100);
+    assert_eq!(function.end_rva, Some(section_rva + 0x180));
+
+    // The last export ends at the end of its section.
+    let function = context
+        .find_function_at(Rva32(section_rva + 0x190))?
+        .unwrap();
+    assert_eq!(function.name.as_deref(), Some("ExportB"));
+    assert_eq!(function.end_rva, Some(section_rva + 0x200));
+
+    // Addresses before the first export, or outside of the code sections, aren't
+    // resolved.
+    assert!(context
+        .find_function_at(Rva32(section_rva + 0x10))?
+        .is_none());
+    assert!(context
+        .find_function_at(Rva32(section_rva + 0x200))?
+        .is_none());
+
+    // Addresses which the PDB covers still use the PDB.
+    let function = context.find_function_at(Rva32(0x1010))?.unwrap();
+    assert_ne!(function.provenance, Provenance::PeExport);
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+use pdb_addr2line::{SymbolLocation, SymbolPath, SymbolPathEntry};
+
+#[test]
+fn test_parse_entries() {
+    let symbol_path = SymbolPath::parse(
+        r"cache*C:\Cache;SRV*C:\Store*https://example.com/symbols;;D:\Build ; srv**https://other.example.com;symsrv*symsrv.dll*https://third.example.com",
+    );
+    assert_eq!(
+        symbol_path.entries(),
+        &[
+            SymbolPathEntry::Cache(Some(PathBuf::from(r"C:\Cache"))),
+            SymbolPathEntry::Server {
+                downstream_stores: vec![Some(PathBuf::from(r"C:\Store"))],
+                url: "https://example.com/symbols".to_string(),
+            },
+            SymbolPathEntry::Directory(PathBuf::from(r"D:\Build")),
+            SymbolPathEntry::Server {
+                downstream_stores: vec![None],
+                url: "https://other.example.com".to_string(),
+            },
+            SymbolPathEntry::Server {
+                downstream_stores: vec![],
+                url: "https://third.example.com".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_locations() {
+    let symbol_path = SymbolPath::parse(r"D:\Build;cache*;srv*C:\Store*https://example.com");
+    let default_store = Path::new(r"C:\ProgramData\dbg\sym");
+    assert_eq!(
+        symbol_path.locations(default_store),
+        vec![
+            SymbolLocation::Local {
+                path: PathBuf::from(r"D:\Build"),
+                cache_dirs: vec![],
+            },
+            SymbolLocation::Local {
+                path: default_store.to_path_buf(),
+                cache_dirs: vec![],
+            },
+            SymbolLocation::Local {
+                path: PathBuf::from(r"C:\Store"),
+                cache_dirs: vec![default_store.to_path_buf()],
+            },
+            SymbolLocation::Remote {
+                url: "https://example.com".to_string(),
+                cache_dirs: vec![default_store.to_path_buf(), PathBuf::from(r"C:\Store")],
+            },
+        ]
+    );
+}
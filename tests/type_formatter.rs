@@ -66,6 +66,39 @@ fn test() -> Result<(), Box<dyn Error>> {
         "name()"
     );
 
+    let formatter_without_qualifiers = data.make_type_formatter_with_flags(
+        TypeFormatterFlags::default() | TypeFormatterFlags::NO_MEMBER_FUNCTION_QUALIFIERS,
+    )?;
+    assert_eq!(
+        formatter_without_qualifiers.format_id(2, IdIndex(0x11c2))?,
+        "std::basic_string<wchar_t,std::char_traits<wchar_t>,std::allocator<wchar_t> >::_Calculate_growth(const unsigned int)"
+    );
+
+    // The modules in this PDB are C++, so they keep the C++ style.
+    let rust_formatter = data.make_type_formatter_with_flags(
+        TypeFormatterFlags::default() | TypeFormatterFlags::RUST_SIGNATURES,
+    )?;
+    assert_eq!(
+        rust_formatter.format_id(4, IdIndex(0x80000013))?,
+        "std::allocator<wchar_t>::deallocate(wchar_t* const, const unsigned int)"
+    );
+
+    let normalizing_formatter = data.make_type_formatter_with_flags(
+        TypeFormatterFlags::default() | TypeFormatterFlags::NORMALIZE_ANONYMOUS_NAMESPACES,
+    )?;
+    assert_eq!(
+        normalizing_formatter.format_id(0, IdIndex(0x12fe))?,
+        "(anonymous namespace)::start()"
+    );
+    assert_eq!(
+        normalizing_formatter.format_function(
+            "`anonymous namespace'::crash",
+            5,
+            TypeIndex(0x225d)
+        )?,
+        "(anonymous namespace)::crash()"
+    );
+
     assert_eq!(
         formatter_without_args.format_id(4, IdIndex(0x80000013))?,
         "std::allocator<wchar_t>::deallocate"
@@ -93,3 +126,86 @@ fn test() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_rust_signatures() -> Result<(), Box<dyn Error>> {
+    // Turn the modules of crash.pdb into Rust modules, by patching the language in
+    // their S_COMPILE3 records to CV_CFL_RUST (0x15). The record starts with its
+    // kind (0x113c) and a flags field with the language in the low byte, and has the
+    // compiler name 24 bytes after the kind.
+    let mut bytes = std::fs::read(fixture("crash.pdb"))?;
+    let compiler_name = b"Microsoft (R) Optimizing Compiler";
+    let mut patched_count = 0;
+    for pos in 0..bytes.len() - 24 {
+        if bytes[pos..pos + 2] == [0x3c, 0x11] && bytes[pos + 24..].starts_with(compiler_name) {
+            bytes[pos + 2] = 0x15;
+            patched_count += 1;
+        }
+    }
+    assert_ne!(patched_count, 0);
+
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(std::io::Cursor::new(bytes))?)?;
+    let flags = (TypeFormatterFlags::default() - TypeFormatterFlags::NO_FUNCTION_RETURN)
+        | TypeFormatterFlags::RUST_SIGNATURES;
+    let formatter = data.make_type_formatter_with_flags(flags)?;
+
+    // The return type follows the arguments, and is left out for void.
+    assert_eq!(
+        formatter.format_id(2, IdIndex(0x11c2))?,
+        "fn std::basic_string<wchar_t,std::char_traits<wchar_t>,std::allocator<wchar_t> >::_Calculate_growth(const unsigned int) const -> unsigned int"
+    );
+    assert_eq!(
+        formatter.format_id(4, IdIndex(0x80000013))?,
+        "fn std::allocator<wchar_t>::deallocate(wchar_t* const, const unsigned int)"
+    );
+    assert_eq!(
+        formatter.format_function("name", 2, TypeIndex(0x13f4))?,
+        "fn name(wchar_t const* const, const unsigned int) -> std::basic_string<wchar_t,std::char_traits<wchar_t>,std::allocator<wchar_t> >&"
+    );
+
+    // NO_FUNCTION_RETURN still removes the return type.
+    let formatter = data.make_type_formatter_with_flags(
+        TypeFormatterFlags::default() | TypeFormatterFlags::RUST_SIGNATURES,
+    )?;
+    assert_eq!(
+        formatter.format_id(2, IdIndex(0x11c2))?,
+        "fn std::basic_string<wchar_t,std::char_traits<wchar_t>,std::allocator<wchar_t> >::_Calculate_growth(const unsigned int) const"
+    );
+
+    // Lookups use the same style for function names.
+    let context = data.make_context_with_flags(flags, Default::default())?;
+    assert_eq!(
+        context.find_function_at(pdb_addr2line::Rva32(0x1000))?.unwrap().name.as_deref(),
+        Some("fn google_breakpad::CrashGenerationClient::RequestDump(_EXCEPTION_POINTERS*, MDRawAssertionInfo*) -> bool")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_unresolved_indexes() -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::open(fixture("crash.pdb"))?;
+    let data = ContextPdbData::try_from_pdb(pdb::PDB::open(file)?)?;
+    let formatter = data.make_type_formatter()?;
+
+    formatter.format_id(0, IdIndex(0x12fe))?;
+    assert!(formatter.take_unresolved_indexes().is_empty());
+
+    // Missing records get a placeholder instead of failing the whole name.
+    assert_eq!(
+        formatter.format_id(0, IdIndex(0xfffff))?,
+        "<missing type 0xfffff>"
+    );
+    // A function whose type is missing is named without its signature.
+    assert_eq!(
+        formatter.format_function("main", 2, TypeIndex(0xffff0))?,
+        "main"
+    );
+    assert_eq!(
+        formatter.take_unresolved_indexes(),
+        vec![(0, 0xfffff), (2, 0xffff0)]
+    );
+    assert!(formatter.take_unresolved_indexes().is_empty());
+
+    Ok(())
+}